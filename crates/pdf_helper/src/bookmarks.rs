@@ -2,9 +2,157 @@
 
 use std::collections::BTreeMap;
 
+use genpdf::style::Color;
 use lopdf::{Dictionary, Document, Object, ObjectId};
 
-use crate::model::Section;
+use crate::model::{flatten_outline, Section};
+use crate::pdf_objects::page_top_points;
+pub use crate::pdf_objects::BookmarkDestination;
+
+/// Appearance of outline entries in the bookmark panel of PDF readers.
+///
+/// Bold and italic titles are written as the `/F` flags of an entry and the color as its `/C`
+/// entry, which readers that support them use to set, for example, part dividers apart from their
+/// sections.  The default style writes neither entry.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BookmarkStyle {
+    bold: bool,
+    italic: bool,
+    color: Option<Color>,
+}
+
+impl BookmarkStyle {
+    /// Creates a style that leaves the entries plain.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether entry titles are shown in bold.
+    pub fn is_bold(&self) -> bool {
+        self.bold
+    }
+
+    /// Returns whether entry titles are shown in italic.
+    pub fn is_italic(&self) -> bool {
+        self.italic
+    }
+
+    /// Returns the color of entry titles, if any.
+    pub fn color(&self) -> Option<Color> {
+        self.color
+    }
+
+    /// Sets whether entry titles are shown in bold and returns the updated style.
+    pub fn with_bold(mut self, bold: bool) -> Self {
+        self.bold = bold;
+        self
+    }
+
+    /// Sets whether entry titles are shown in italic and returns the updated style.
+    pub fn with_italic(mut self, italic: bool) -> Self {
+        self.italic = italic;
+        self
+    }
+
+    /// Sets the color of entry titles and returns the updated style.
+    ///
+    /// Outline colors are always RGB, so greyscale and CMYK colors are converted.
+    pub fn with_color(mut self, color: impl Into<Option<Color>>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    /// Writes the `/F` flags and `/C` color of the style into an outline item dictionary.
+    fn apply(self, dictionary: &mut Dictionary) {
+        let flags = i64::from(self.italic) | i64::from(self.bold) << 1;
+        if flags != 0 {
+            dictionary.set("F", Object::Integer(flags));
+        }
+        if let Some(color) = self.color {
+            let components = rgb_components(color)
+                .into_iter()
+                .map(Object::Real)
+                .collect();
+            dictionary.set("C", Object::Array(components));
+        }
+    }
+}
+
+/// Red, green, and blue components of `color` between `0.0` and `1.0`.
+fn rgb_components(color: Color) -> [f32; 3] {
+    let unit = |value: u8| f32::from(value) / 255.0;
+    match color {
+        Color::Rgb(red, green, blue) => [unit(red), unit(green), unit(blue)],
+        Color::Greyscale(value) => [unit(value); 3],
+        Color::Cmyk(cyan, magenta, yellow, black) => {
+            let white = 1.0 - unit(black);
+            [cyan, magenta, yellow].map(|ink| (1.0 - unit(ink)) * white)
+        }
+    }
+}
+
+/// Settings of the outline written by [`apply_section_bookmarks`].
+///
+/// The defaults jump to the whole page ([`BookmarkDestination::Fit`]), keep every entry plain, and
+/// expand the whole tree.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BookmarkOptions {
+    destination: BookmarkDestination,
+    styles: BTreeMap<usize, BookmarkStyle>,
+    default_open_level: Option<usize>,
+}
+
+impl BookmarkOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the view shown when an entry is activated.
+    pub fn destination(&self) -> BookmarkDestination {
+        self.destination
+    }
+
+    /// Returns the style of the entries at outline `level`, if one is set.
+    pub fn style(&self, level: usize) -> Option<BookmarkStyle> {
+        self.styles.get(&level).copied()
+    }
+
+    /// Returns how many levels of the outline are expanded when the document is opened, or `None`
+    /// when the whole tree is expanded.
+    pub fn default_open_level(&self) -> Option<usize> {
+        self.default_open_level
+    }
+
+    /// Sets the view shown when an entry is activated and returns the updated options.
+    pub fn with_destination(mut self, destination: BookmarkDestination) -> Self {
+        self.destination = destination;
+        self
+    }
+
+    /// Shows the entries at outline `level` in `style` and returns the updated options.
+    ///
+    /// Top-level sections are level 1 and section dividers level 0; levels without a style stay
+    /// plain.
+    pub fn with_style(mut self, level: usize, style: BookmarkStyle) -> Self {
+        self.styles.insert(level, style);
+        self
+    }
+
+    /// Limits how deep the outline tree is expanded when the document is opened and returns the
+    /// updated options.
+    ///
+    /// `Some(1)` shows only the top-level entries, `Some(2)` also their children, and so on.
+    /// Deeper parents are written with a negative `/Count`, which marks them as collapsed.  A level
+    /// also sets the catalog `/PageMode`: `Some(0)` collapses every entry and opens the document
+    /// with the bookmark panel closed (`/UseNone`), which applies to flat outlines as well, while
+    /// higher levels open it (`/UseOutlines`).  `None` expands the whole tree and leaves the page
+    /// mode to the reader.  A page mode already present in the catalog is kept.
+    pub fn with_default_open_level(mut self, level: Option<usize>) -> Self {
+        self.default_open_level = level;
+        self
+    }
+}
 
 /// Errors that can occur while embedding bookmarks into a rendered PDF document.
 #[derive(Debug)]
@@ -22,6 +170,8 @@ pub enum BookmarkError {
         /// The requested (1-indexed) page number that could not be resolved.
         page_number: usize,
     },
+    /// `merge::merge_documents` was called without any documents.
+    NoDocuments,
 }
 
 impl From<lopdf::Error> for BookmarkError {
@@ -50,6 +200,7 @@ impl std::fmt::Display for BookmarkError {
                 "Section {} refers to missing page {} for bookmark destination",
                 section_index, page_number
             ),
+            Self::NoDocuments => write!(f, "No PDF documents were given to merge"),
         }
     }
 }
@@ -58,20 +209,31 @@ impl std::error::Error for BookmarkError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Self::Parse(err) => Some(err),
-            Self::MissingCatalog | Self::InvalidCatalog | Self::MissingPage { .. } => None,
+            Self::MissingCatalog
+            | Self::InvalidCatalog
+            | Self::MissingPage { .. }
+            | Self::NoDocuments => None,
         }
     }
 }
 
-/// Applies a flat outline tree mapping sections to their starting pages.
+/// Applies an outline tree mapping sections to their starting pages.
 ///
 /// The function opens the provided PDF bytes using `lopdf`, builds an `/Outlines`
-/// dictionary, and associates each section with a `/Dest [page /Fit]` entry that
-/// targets the first page recorded for the section.
+/// dictionary, and associates each section with a destination on the first page recorded for
+/// the section.  `options` pick the view shown when an entry is activated, the styles of the
+/// entries, and how far the tree is expanded; see [`BookmarkOptions`].  The default
+/// [`BookmarkDestination::Fit`] matches the `/Dest [page /Fit]` entries of earlier releases.
+///
+/// Subsections and the headings inside section bodies become nested outline entries below their
+/// parent section, so PDF viewers show them as expandable nodes.  `section_pages` lists the start
+/// page of every entry in the reading order produced by [`flatten_outline`]; entries without a
+/// recorded page are left out and their children are attached to the closest listed ancestor.
 pub fn apply_section_bookmarks(
     pdf_bytes: &[u8],
     sections: &[Section],
     section_pages: &[Option<usize>],
+    options: &BookmarkOptions,
 ) -> Result<Vec<u8>, BookmarkError> {
     let mut document = Document::load_mem(pdf_bytes)?;
 
@@ -83,10 +245,30 @@ pub fn apply_section_bookmarks(
         return Ok(pdf_bytes.to_vec());
     }
 
+    let levels: Vec<_> = outline_entries.iter().map(|entry| entry.level).collect();
+    let parents = outline_parents(&levels);
+    let (counts, visible) = outline_counts(&parents, options.default_open_level);
+    let (links, root) = outline_links(&parents);
+
     let outlines_id = document.new_object_id();
-    link_outline_entries(outlines_id, &mut document, &mut outline_entries);
+    link_outline_entries(
+        outlines_id,
+        &mut document,
+        &mut outline_entries,
+        options,
+        &parents,
+        &links,
+        &counts,
+    )?;
 
-    insert_outlines_root(outlines_id, &mut document, &outline_entries)?;
+    insert_outlines_root(
+        outlines_id,
+        &mut document,
+        &outline_entries,
+        root,
+        visible,
+        options.default_open_level,
+    )?;
 
     let mut buffer = Vec::new();
     document.save_to(&mut buffer).map_err(BookmarkError::from)?;
@@ -98,6 +280,7 @@ struct OutlineEntry {
     page_ref: ObjectId,
     title: String,
     name: Option<String>,
+    level: usize,
 }
 
 fn collect_outline_entries(
@@ -108,7 +291,10 @@ fn collect_outline_entries(
 ) -> Result<Vec<OutlineEntry>, BookmarkError> {
     let mut entries = Vec::new();
 
-    for (index, (section, maybe_page)) in sections.iter().zip(section_pages.iter()).enumerate() {
+    let flattened = flatten_outline(sections);
+    for (index, ((level, item), maybe_page)) in
+        flattened.into_iter().zip(section_pages.iter()).enumerate()
+    {
         let Some(page_number) = *maybe_page else {
             continue;
         };
@@ -124,56 +310,164 @@ fn collect_outline_entries(
         entries.push(OutlineEntry {
             object_id: document.new_object_id(),
             page_ref,
-            title: section.title().to_string(),
-            name: section.identifier().map(|value| value.to_string()),
+            title: item.title().into_owned(),
+            name: item.identifier().map(|value| value.to_string()),
+            level,
         });
     }
 
     Ok(entries)
 }
 
+/// Resolves the parent of every entry from the nesting levels of the flattened entries.
+///
+/// An entry's parent is the closest preceding entry with a lower level, which keeps the tree
+/// intact when intermediate sections were skipped.
+fn outline_parents(levels: &[usize]) -> Vec<Option<usize>> {
+    let mut parents = Vec::with_capacity(levels.len());
+    let mut ancestors: Vec<usize> = Vec::new();
+    for (index, &level) in levels.iter().enumerate() {
+        while ancestors
+            .last()
+            .is_some_and(|&ancestor| levels[ancestor] >= level)
+        {
+            ancestors.pop();
+        }
+        parents.push(ancestors.last().copied());
+        ancestors.push(index);
+    }
+    parents
+}
+
+/// Neighbours of an outline entry, or of the outline root, as indices into the entry list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct OutlineLinks {
+    prev: Option<usize>,
+    next: Option<usize>,
+    first: Option<usize>,
+    last: Option<usize>,
+}
+
+/// Resolves the siblings and first and last children of every entry, and the first and last
+/// top-level entries below the outline root, in a single pass over `parents`.
+fn outline_links(parents: &[Option<usize>]) -> (Vec<OutlineLinks>, OutlineLinks) {
+    let mut links = vec![OutlineLinks::default(); parents.len()];
+    let mut root = OutlineLinks::default();
+    for (index, &parent) in parents.iter().enumerate() {
+        let siblings = match parent {
+            Some(parent) => &mut links[parent],
+            None => &mut root,
+        };
+        let prev = siblings.last.replace(index);
+        siblings.first.get_or_insert(index);
+        if let Some(prev) = prev {
+            links[prev].next = Some(index);
+            links[index].prev = Some(prev);
+        }
+    }
+    (links, root)
+}
+
+/// Computes the `/Count` of every entry and the number of entries visible below the outline root.
+///
+/// Entries nested fewer than `open_level` levels deep are open, with a count of the descendants
+/// they reveal; deeper entries are closed, with the negated count of the descendants they would
+/// reveal when opened.  Without an `open_level` every entry is open.
+fn outline_counts(parents: &[Option<usize>], open_level: Option<usize>) -> (Vec<i64>, i64) {
+    let mut depths = Vec::with_capacity(parents.len());
+    for parent in parents {
+        let depth = parent.map_or(1, |parent: usize| depths[parent] + 1);
+        depths.push(depth);
+    }
+    let is_open = |index: usize| open_level.map_or(true, |level| depths[index] < level);
+
+    // Children always follow their parent, so walking backwards finishes every entry's subtree
+    // before the entry itself.
+    let mut revealed = vec![0_i64; parents.len()];
+    let mut visible = 0;
+    for index in (0..parents.len()).rev() {
+        let shown = 1 + if is_open(index) { revealed[index] } else { 0 };
+        match parents[index] {
+            Some(parent) => revealed[parent] += shown,
+            None => visible += shown,
+        }
+    }
+
+    let counts = revealed
+        .iter()
+        .enumerate()
+        .map(|(index, &count)| if is_open(index) { count } else { -count })
+        .collect();
+    (counts, visible)
+}
+
 fn link_outline_entries(
     outlines_id: ObjectId,
     document: &mut Document,
     entries: &mut [OutlineEntry],
-) {
+    options: &BookmarkOptions,
+    parents: &[Option<usize>],
+    links: &[OutlineLinks],
+    counts: &[i64],
+) -> Result<(), BookmarkError> {
     for index in 0..entries.len() {
         let mut dictionary = Dictionary::new();
         dictionary.set(
             "Title",
             Object::string_literal(entries[index].title.as_str()),
         );
+        let page_top = page_top_points(document, entries[index].page_ref)?;
         dictionary.set(
             "Dest",
-            Object::Array(vec![
-                Object::Reference(entries[index].page_ref),
-                Object::Name("Fit".into()),
-            ]),
+            Object::Array(
+                options
+                    .destination
+                    .to_array(entries[index].page_ref, page_top),
+            ),
         );
-        dictionary.set("Parent", Object::Reference(outlines_id));
+        let parent_id = parents[index].map_or(outlines_id, |parent| entries[parent].object_id);
+        dictionary.set("Parent", Object::Reference(parent_id));
 
         if let Some(name) = &entries[index].name {
             dictionary.set("NM", Object::string_literal(name.as_str()));
         }
 
-        if index > 0 {
-            dictionary.set("Prev", Object::Reference(entries[index - 1].object_id));
+        if let Some(style) = options.style(entries[index].level) {
+            style.apply(&mut dictionary);
         }
 
-        if index + 1 < entries.len() {
-            dictionary.set("Next", Object::Reference(entries[index + 1].object_id));
+        let links = links[index];
+        if let Some(prev) = links.prev {
+            dictionary.set("Prev", Object::Reference(entries[prev].object_id));
+        }
+
+        if let Some(next) = links.next {
+            dictionary.set("Next", Object::Reference(entries[next].object_id));
+        }
+
+        if let (Some(first), Some(last)) = (links.first, links.last) {
+            dictionary.set("First", Object::Reference(entries[first].object_id));
+            dictionary.set("Last", Object::Reference(entries[last].object_id));
+            // A positive count opens the entry and tells viewers how many entries it reveals; a
+            // negative count keeps it closed.
+            dictionary.set("Count", Object::Integer(counts[index]));
         }
 
         document
             .objects
             .insert(entries[index].object_id, Object::Dictionary(dictionary));
     }
+
+    Ok(())
 }
 
 fn insert_outlines_root(
     outlines_id: ObjectId,
     document: &mut Document,
     entries: &[OutlineEntry],
+    root: OutlineLinks,
+    visible: i64,
+    default_open_level: Option<usize>,
 ) -> Result<(), BookmarkError> {
     let catalog_id = document
         .trailer
@@ -184,12 +478,12 @@ fn insert_outlines_root(
 
     let mut dictionary = Dictionary::new();
     dictionary.set("Type", Object::Name("Outlines".into()));
-    dictionary.set("Count", Object::Integer(entries.len() as i64));
-    if let Some(first) = entries.first() {
-        dictionary.set("First", Object::Reference(first.object_id));
+    dictionary.set("Count", Object::Integer(visible));
+    if let Some(first) = root.first {
+        dictionary.set("First", Object::Reference(entries[first].object_id));
     }
-    if let Some(last) = entries.last() {
-        dictionary.set("Last", Object::Reference(last.object_id));
+    if let Some(last) = root.last {
+        dictionary.set("Last", Object::Reference(entries[last].object_id));
     }
 
     document
@@ -204,6 +498,311 @@ fn insert_outlines_root(
         .map_err(|_| BookmarkError::InvalidCatalog)?;
 
     catalog.set("Outlines", Object::Reference(outlines_id));
+    if let Some(level) = default_open_level.filter(|_| !catalog.has(b"PageMode")) {
+        let mode = if level == 0 { "UseNone" } else { "UseOutlines" };
+        catalog.set("PageMode", Object::Name(mode.into()));
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Divider;
+    use crate::pdf_objects::POINTS_PER_MM;
+
+    #[test]
+    fn nested_entries_attach_to_closest_shallower_entry() {
+        assert_eq!(
+            outline_parents(&[1, 2, 3, 2, 1, 3]),
+            vec![None, Some(0), Some(1), Some(0), None, Some(4)]
+        );
+    }
+
+    #[test]
+    fn open_level_collapses_deeper_parents() {
+        let parents = outline_parents(&[1, 2, 3, 3, 2, 1, 2]);
+
+        let (counts, visible) = outline_counts(&parents, None);
+        assert_eq!(counts, vec![4, 2, 0, 0, 0, 1, 0]);
+        assert_eq!(visible, 7);
+
+        let (counts, visible) = outline_counts(&parents, Some(1));
+        assert_eq!(counts, vec![-2, -2, 0, 0, 0, -1, 0]);
+        assert_eq!(visible, 2);
+
+        let (counts, visible) = outline_counts(&parents, Some(2));
+        assert_eq!(counts, vec![2, -2, 0, 0, 0, 1, 0]);
+        assert_eq!(visible, 5);
+    }
+
+    #[test]
+    fn links_join_siblings_and_point_parents_at_their_children() {
+        let (links, root) = outline_links(&outline_parents(&[1, 2, 3, 2, 1]));
+        let link = |prev, next, first, last| OutlineLinks {
+            prev,
+            next,
+            first,
+            last,
+        };
+        assert_eq!(root, link(None, None, Some(0), Some(4)));
+        assert_eq!(
+            links,
+            vec![
+                link(None, Some(4), Some(1), Some(3)),
+                link(None, Some(3), Some(2), Some(2)),
+                link(None, None, None, None),
+                link(Some(1), None, None, None),
+                link(Some(0), None, None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn xyz_destination_measures_top_from_page_top() {
+        let destination = BookmarkDestination::XYZ {
+            top: Some(0.0),
+            zoom: None,
+        };
+        let array = destination.to_array((4, 0), 842.0);
+        assert!(matches!(&array[1], Object::Name(name) if name == b"XYZ"));
+        assert!(matches!(array[2], Object::Null));
+        assert!(matches!(array[3], Object::Real(top) if (f64::from(top) - 842.0).abs() < 1e-3));
+        assert!(matches!(array[4], Object::Null));
+
+        let fit = BookmarkDestination::default().to_array((4, 0), 842.0);
+        assert_eq!(fit.len(), 2);
+        assert!(matches!(&fit[1], Object::Name(name) if name == b"Fit"));
+    }
+
+    fn sample_document() -> Vec<u8> {
+        document_with_media_box([0, 0, 595, 842])
+    }
+
+    fn document_with_media_box(media_box: [i64; 4]) -> Vec<u8> {
+        let mut document = Document::with_version("1.3");
+        let pages_id = document.new_object_id();
+        let mut page = Dictionary::new();
+        page.set("Type", Object::Name("Page".into()));
+        page.set("Parent", Object::Reference(pages_id));
+        page.set(
+            "MediaBox",
+            Object::Array(media_box.map(Object::Integer).to_vec()),
+        );
+        let page_id = document.add_object(page);
+        let mut pages = Dictionary::new();
+        pages.set("Type", Object::Name("Pages".into()));
+        pages.set("Kids", Object::Array(vec![Object::Reference(page_id)]));
+        pages.set("Count", Object::Integer(1));
+        document.objects.insert(pages_id, Object::Dictionary(pages));
+
+        let mut catalog = Dictionary::new();
+        catalog.set("Type", Object::Name("Catalog".into()));
+        catalog.set("Pages", Object::Reference(pages_id));
+        let catalog_id = document.add_object(catalog);
+        document.trailer.set("Root", Object::Reference(catalog_id));
+
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).expect("save sample document");
+        bytes
+    }
+
+    #[test]
+    fn styled_levels_write_flags_and_colors() {
+        let sections =
+            vec![Section::new("Runbooks").with_divider(Divider::new("Part II: Operations"))];
+        let options = BookmarkOptions::new().with_style(
+            0,
+            BookmarkStyle::new()
+                .with_bold(true)
+                .with_italic(true)
+                .with_color(Color::Rgb(255, 0, 0)),
+        );
+        let bytes =
+            apply_section_bookmarks(&sample_document(), &sections, &[Some(1), Some(1)], &options)
+                .expect("bookmarks applied");
+
+        let document = Document::load_mem(&bytes).expect("bookmarked PDF parses");
+        let entry = |title: &[u8]| {
+            document
+                .objects
+                .values()
+                .filter_map(|object| object.as_dict().ok())
+                .find(|dictionary| {
+                    dictionary
+                        .get(b"Title")
+                        .and_then(Object::as_str)
+                        .is_ok_and(|value| value == title)
+                })
+                .expect("outline entry")
+        };
+
+        let part = entry(b"Part II: Operations");
+        assert_eq!(part.get(b"F").and_then(Object::as_i64).ok(), Some(3));
+        let color = part.get(b"C").and_then(Object::as_array).expect("color");
+        let components: Vec<_> = color
+            .iter()
+            .map(|value| value.as_float().unwrap())
+            .collect();
+        assert_eq!(components, vec![1.0, 0.0, 0.0]);
+
+        let section = entry(b"Runbooks");
+        assert!(!section.has(b"F"));
+        assert!(!section.has(b"C"));
+    }
+
+    #[test]
+    fn bookmark_colors_convert_to_rgb() {
+        assert_eq!(rgb_components(Color::Greyscale(0)), [0.0; 3]);
+        assert_eq!(rgb_components(Color::Cmyk(255, 0, 0, 0)), [0.0, 1.0, 1.0]);
+        assert_eq!(rgb_components(Color::Cmyk(0, 0, 0, 255)), [0.0; 3]);
+    }
+
+    #[test]
+    fn open_level_collapses_parents_and_sets_the_page_mode() {
+        let sections = vec![Section::new("Overview").with_subsection(Section::new("Scope"))];
+        let apply = |open_level| {
+            let bytes = apply_section_bookmarks(
+                &sample_document(),
+                &sections,
+                &[Some(1), Some(1)],
+                &BookmarkOptions::new().with_default_open_level(open_level),
+            )
+            .expect("bookmarks applied");
+            Document::load_mem(&bytes).expect("bookmarked PDF parses")
+        };
+        let overview_count = |document: &Document| {
+            document
+                .objects
+                .values()
+                .filter_map(|object| object.as_dict().ok())
+                .find(|dictionary| {
+                    dictionary
+                        .get(b"Title")
+                        .and_then(Object::as_str)
+                        .is_ok_and(|value| value == b"Overview")
+                })
+                .and_then(|dictionary| dictionary.get(b"Count").and_then(Object::as_i64).ok())
+        };
+        let page_mode = |document: &Document| {
+            document
+                .catalog()
+                .ok()
+                .and_then(|catalog| catalog.get(b"PageMode").and_then(Object::as_name).ok())
+                .map(<[u8]>::to_vec)
+        };
+
+        let expanded = apply(None);
+        assert_eq!(overview_count(&expanded), Some(1));
+        assert_eq!(page_mode(&expanded), None);
+
+        let collapsed = apply(Some(1));
+        assert_eq!(overview_count(&collapsed), Some(-1));
+        assert_eq!(page_mode(&collapsed), Some(b"UseOutlines".to_vec()));
+
+        let closed = apply(Some(0));
+        assert_eq!(overview_count(&closed), Some(-1));
+        assert_eq!(page_mode(&closed), Some(b"UseNone".to_vec()));
+    }
+
+    #[test]
+    fn destinations_measure_from_the_top_of_an_offset_media_box() {
+        let sections = vec![Section::new("Overview")];
+        let options =
+            BookmarkOptions::new().with_destination(BookmarkDestination::FitH(Some(10.0)));
+        let bytes = apply_section_bookmarks(
+            &document_with_media_box([0, 100, 595, 942]),
+            &sections,
+            &[Some(1)],
+            &options,
+        )
+        .expect("bookmarks applied");
+
+        let document = Document::load_mem(&bytes).expect("bookmarked PDF parses");
+        let destination = document
+            .objects
+            .values()
+            .filter_map(|object| object.as_dict().ok())
+            .find_map(|dictionary| dictionary.get(b"Dest").and_then(Object::as_array).ok())
+            .expect("outline destination");
+        let top = destination[2].as_float().expect("top coordinate");
+        assert!((f64::from(top) - (942.0 - 10.0 * POINTS_PER_MM)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn outline_dictionaries_link_first_last_and_count() {
+        let sections = vec![
+            Section::new("Overview")
+                .with_subsection(Section::new("Scope"))
+                .with_subsection(Section::new("Limits")),
+            Section::new("Runbooks"),
+        ];
+        let bytes = apply_section_bookmarks(
+            &sample_document(),
+            &sections,
+            &[Some(1); 4],
+            &BookmarkOptions::new(),
+        )
+        .expect("bookmarks applied");
+
+        let document = Document::load_mem(&bytes).expect("bookmarked PDF parses");
+        let id = |title: &[u8]| {
+            document
+                .objects
+                .iter()
+                .find(|(_, object)| {
+                    object
+                        .as_dict()
+                        .and_then(|dictionary| dictionary.get(b"Title"))
+                        .and_then(Object::as_str)
+                        .is_ok_and(|value| value == title)
+                })
+                .map(|(&id, _)| id)
+                .expect("outline entry")
+        };
+        let link = |dictionary: &Dictionary, key: &[u8]| {
+            dictionary.get(key).and_then(Object::as_reference).ok()
+        };
+        let count =
+            |dictionary: &Dictionary| dictionary.get(b"Count").and_then(Object::as_i64).ok();
+        let entry = |title: &[u8]| {
+            document
+                .get_dictionary(id(title))
+                .expect("entry dictionary")
+        };
+
+        let outlines_id = document
+            .catalog()
+            .and_then(|catalog| catalog.get(b"Outlines"))
+            .and_then(Object::as_reference)
+            .expect("outline root");
+        let root = document
+            .get_dictionary(outlines_id)
+            .expect("root dictionary");
+        assert_eq!(link(root, b"First"), Some(id(b"Overview")));
+        assert_eq!(link(root, b"Last"), Some(id(b"Runbooks")));
+        assert_eq!(count(root), Some(4));
+
+        let overview = entry(b"Overview");
+        assert_eq!(link(overview, b"First"), Some(id(b"Scope")));
+        assert_eq!(link(overview, b"Last"), Some(id(b"Limits")));
+        assert_eq!(count(overview), Some(2));
+        assert_eq!(link(overview, b"Prev"), None);
+        assert_eq!(link(overview, b"Next"), Some(id(b"Runbooks")));
+
+        let scope = entry(b"Scope");
+        assert_eq!(link(scope, b"Parent"), Some(id(b"Overview")));
+        assert_eq!(link(scope, b"Next"), Some(id(b"Limits")));
+        assert_eq!((link(scope, b"First"), count(scope)), (None, None));
+
+        let limits = entry(b"Limits");
+        assert_eq!(link(limits, b"Prev"), Some(id(b"Scope")));
+        assert_eq!(link(limits, b"Next"), None);
+
+        let runbooks = entry(b"Runbooks");
+        assert_eq!(link(runbooks, b"Prev"), Some(id(b"Overview")));
+        assert_eq!(link(runbooks, b"Parent"), Some(outlines_id));
+        assert_eq!((link(runbooks, b"First"), count(runbooks)), (None, None));
+    }
+}
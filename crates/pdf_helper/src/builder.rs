@@ -1,25 +1,77 @@
 //! Document construction helpers for the `pdf_helper` crate.
 
-use std::cell::RefCell;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::ops::{Bound, Range, RangeBounds};
 use std::rc::Rc;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
 
 #[cfg(feature = "bookmarks")]
 use crate::bookmarks;
-use crate::elements::CaptionedImage;
-use crate::fonts;
+use crate::calendar::utc_fields;
+use crate::diagnostics::{RenderWarning, WarningSink};
+use crate::elements::faded_color;
+use crate::elements::{
+    decode_image_from_bytes, decode_image_from_path, fill_page, image_dimensions_from_bytes,
+    image_dimensions_from_path, Callout, CaptionedImage, CodeText, DataTable, DecimalAlignedText,
+    FixedRegion, FootnoteArea, HorizontalRule, JustifiedText, KeepTogether, Legend, MarkedItem,
+    MarkedList, Spacer, TocRow, Watermark, WatermarkOptions, WrappedText,
+};
+#[cfg(feature = "encryption")]
+use crate::encryption::{self, EncryptionOptions};
+use crate::fonts::{self, FallbackFonts, FontSource, ResolvedFontSource};
+use crate::layout::{ElementKind, LayoutDump, LayoutProbe, LayoutTracker, Rect};
+#[cfg(feature = "links")]
+use crate::links;
 use crate::model::{
-    Block, Cover, HorizontalAlignment, ImageBlock, ImageSource, RichParagraph, Section,
+    continue_numbering, flatten_outline, flatten_sections, number_outline, Block, ColumnAlignment,
+    ColumnBlock, Cover, Divider, HorizontalAlignment, ImageBlock, ImageSource, LegendBlock,
+    ListBlock, Orientation, QuoteBlock, RichParagraph, Section, TableBlock, TableCell,
+    TextDirection,
+};
+#[cfg(any(
+    feature = "mixed-orientation",
+    feature = "page-labels",
+    feature = "viewer-preferences"
+))]
+use crate::pdf_objects;
+#[cfg(feature = "pdfa")]
+use crate::pdfa::{self, PdfaLevel};
+#[cfg(feature = "preview")]
+use crate::preview;
+use crate::richtext::{
+    coalesce_spans, points_to_mm, spans_to_styled_strings, Span, StyledSpan, VerticalOffset,
 };
-use genpdf::elements::{Break as LineBreak, PageBreak, Paragraph, TableLayout};
+#[cfg(feature = "tagged-pdf")]
+use crate::tagging;
+use crate::theme::Theme;
+#[cfg(feature = "fixed-timestamp")]
+use crate::timestamp;
+#[cfg(feature = "transparency")]
+use crate::transparency::{self, TranslucentColors};
+use genpdf::elements::{Break as LineBreak, LinearLayout, PageBreak, Paragraph, TableLayout};
 use genpdf::error::{Error, ErrorKind};
-use genpdf::style::{Style, StyledString};
-use genpdf::{self, Alignment, Element, Margins, Mm, PageDecorator, Position, Size};
+use genpdf::fonts::{Font, FontData, FontFamily};
+use genpdf::style::{Color, Style, StyledString};
+use genpdf::{self, Alignment, Element, Margins, Mm, PageDecorator, PaperSize, Position, Size};
 
 #[cfg(feature = "hyphenation")]
 use hyphenation::{Language, Load as _, Standard as HyphenationStandard};
 
+/// Horizontal indentation applied per nesting level to printed table of contents entries.
+const TOC_INDENT_MM: f64 = 6.0;
+const TABLE_CELL_PADDING_MM: f64 = 1.0;
+/// Horizontal space between neighbouring columns of a row.
+const ROW_COLUMN_GAP_MM: f64 = 4.0;
+/// Upper bound on the discovery passes used to settle footnotes on their pages.
+const MAX_FOOTNOTE_PASSES: usize = 4;
+
 /// Tracks the page numbers observed during a render pass so that section metadata can be derived.
 #[derive(Debug, Default)]
 struct PageTracker {
@@ -27,7 +79,39 @@ struct PageTracker {
     section_pages: Vec<Option<usize>>,
 }
 
-type PageTrackerHandle = Rc<RefCell<PageTracker>>;
+/// Handle to a [`PageTracker`] shared between the page decorator and the section markers of a
+/// render pass.
+///
+/// The tracker sits behind a mutex rather than a `RefCell` so render passes can run on any thread;
+/// it is only locked when a page starts or a section marker is rendered.
+#[derive(Clone, Debug)]
+struct PageTrackerHandle(Arc<Mutex<PageTracker>>);
+
+impl PageTrackerHandle {
+    fn new(section_count: usize) -> Self {
+        Self(Arc::new(Mutex::new(PageTracker::new(section_count))))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PageTracker> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn set_current_page(&self, page: usize) {
+        self.lock().set_current_page(page);
+    }
+
+    fn mark_section(&self, index: usize) {
+        self.lock().mark_section(index);
+    }
+
+    fn pages(&self) -> Vec<Option<usize>> {
+        self.lock().pages().to_vec()
+    }
+
+    fn current_page(&self) -> usize {
+        self.lock().current_page
+    }
+}
 
 impl PageTracker {
     fn new(section_count: usize) -> Self {
@@ -42,10 +126,13 @@ impl PageTracker {
     }
 
     fn mark_section(&mut self, index: usize) {
-        if let Some(slot) = self.section_pages.get_mut(index) {
-            if slot.is_none() {
-                *slot = Some(self.current_page);
-            }
+        // Streamed documents only learn about their outline entries while rendering.
+        if index >= self.section_pages.len() {
+            self.section_pages.resize(index + 1, None);
+        }
+        let slot = &mut self.section_pages[index];
+        if slot.is_none() {
+            *slot = Some(self.current_page);
         }
     }
 
@@ -57,11 +144,22 @@ impl PageTracker {
 /// Builder for `genpdf::Document` instances pre-configured with the crate defaults.
 #[derive(Default)]
 pub struct DocumentBuilder {
+    font_source: FontSource,
+    loaded_font: Option<(FontFamily<FontData>, ResolvedFontSource)>,
     paper_size: Option<Size>,
+    page_offset: usize,
     margins: Option<Margins>,
     header: Option<Box<HeaderFactory>>,
     footer: Option<FooterSpec>,
+    cover_footer: Option<FooterSpec>,
+    skip_first_page_header: bool,
+    skip_first_page_footer: bool,
+    page_background: Option<Color>,
+    footnotes: BTreeMap<usize, Vec<Vec<StyledSpan>>>,
     page_tracker: Option<PageTrackerHandle>,
+    layout: Option<LayoutTracker>,
+    watermarks: Vec<Watermark>,
+    warnings: Option<WarningSink>,
     #[cfg(feature = "hyphenation")]
     hyphenator: Option<HyphenationStandard>,
 }
@@ -74,6 +172,8 @@ type SharedHeaderFactory = Arc<dyn Fn(usize) -> BoxedElement>;
 
 type SharedFooterFactory = Arc<dyn Fn(usize) -> BoxedElement>;
 
+type CoverRenderer = Arc<dyn Fn(&Cover) -> BoxedElement>;
+
 impl DocumentBuilder {
     /// Creates a new builder instance with default settings.
     pub fn new() -> Self {
@@ -86,6 +186,23 @@ impl DocumentBuilder {
         self
     }
 
+    /// Selects where the document's font family is loaded from instead of the bundled fonts.
+    pub fn with_font_source(mut self, source: impl Into<FontSource>) -> Self {
+        self.font_source = source.into();
+        self
+    }
+
+    /// Uses a font family that was already loaded, together with the location it was loaded
+    /// from, instead of loading the font source again.
+    pub(crate) fn with_loaded_font(
+        mut self,
+        family: FontFamily<FontData>,
+        source: ResolvedFontSource,
+    ) -> Self {
+        self.loaded_font = Some((family, source));
+        self
+    }
+
     /// Sets the margins applied through the page decorator.
     pub fn with_margins(mut self, margins: impl Into<Margins>) -> Self {
         self.margins = Some(margins.into());
@@ -105,7 +222,22 @@ impl DocumentBuilder {
     }
 
     /// Configures a footer callback with a fixed height that is invoked for every page.
+    ///
+    /// Shorthand for [`with_body_footer`](Self::with_body_footer) and
+    /// [`with_cover_footer`](Self::with_cover_footer) with the same callback.
     pub fn with_footer<F, E>(mut self, height: impl Into<Mm>, footer: F) -> Self
+    where
+        F: Fn(usize) -> E + 'static,
+        E: Element + 'static,
+    {
+        let spec = FooterSpec::new(height, footer);
+        self.cover_footer = Some(spec.clone());
+        self.footer = Some(spec);
+        self
+    }
+
+    /// Configures the footer printed on every page after page 1.
+    pub fn with_body_footer<F, E>(mut self, height: impl Into<Mm>, footer: F) -> Self
     where
         F: Fn(usize) -> E + 'static,
         E: Element + 'static,
@@ -114,6 +246,43 @@ impl DocumentBuilder {
         self
     }
 
+    /// Configures the footer printed on page 1 only, for example a classification banner on the
+    /// cover page.
+    pub fn with_cover_footer<F, E>(mut self, height: impl Into<Mm>, footer: F) -> Self
+    where
+        F: Fn(usize) -> E + 'static,
+        E: Element + 'static,
+    {
+        self.cover_footer = Some(FooterSpec::new(height, footer));
+        self
+    }
+
+    /// Leaves the header off page 1, for example when it is a cover page.
+    pub fn skip_first_page_header(mut self, skip: bool) -> Self {
+        self.skip_first_page_header = skip;
+        self
+    }
+
+    /// Leaves the footer off page 1 and lets the body use the space it would have reserved.
+    pub fn skip_first_page_footer(mut self, skip: bool) -> Self {
+        self.skip_first_page_footer = skip;
+        self
+    }
+
+    /// Fills every page with `color` before anything else is drawn on it.
+    pub fn with_page_background(mut self, color: Color) -> Self {
+        self.page_background = Some(color);
+        self
+    }
+
+    /// Draws the watermark on every page, underneath the header, footer, and body content.
+    ///
+    /// Can be called repeatedly; watermarks are drawn in the order they were added.
+    pub fn with_watermark(mut self, watermark: Watermark) -> Self {
+        self.watermarks.push(watermark);
+        self
+    }
+
     /// Enables hyphenation using the provided hyphenation dictionary.
     #[cfg(feature = "hyphenation")]
     pub fn with_hyphenator(mut self, hyphenator: HyphenationStandard) -> Self {
@@ -127,17 +296,73 @@ impl DocumentBuilder {
         self
     }
 
+    /// Numbers the pages of the document as if `offset` pages preceded it.
+    ///
+    /// Used when a report is rendered as several documents that are joined afterwards, so headers,
+    /// footers, and trackers see page numbers of the combined output.
+    fn with_page_offset(mut self, offset: usize) -> Self {
+        self.page_offset = offset;
+        self
+    }
+
+    /// Prints the given notes at the bottom of each page, keyed by one-based page number.
+    fn with_footnotes(mut self, footnotes: BTreeMap<usize, Vec<Vec<StyledSpan>>>) -> Self {
+        self.footnotes = footnotes;
+        self
+    }
+
+    /// Installs a tracker that receives the body rectangle of every decorated page.
+    fn with_layout_tracker(mut self, layout: LayoutTracker) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Installs a sink that receives warnings, such as font fallbacks, detected while building.
+    fn with_warnings(mut self, warnings: WarningSink) -> Self {
+        self.warnings = Some(warnings);
+        self
+    }
+
     /// Builds a fully configured `genpdf::Document` instance.
     pub fn build(self) -> Result<genpdf::Document, Error> {
-        let font_family = fonts::default_font_family()?;
+        self.build_with_font_source().map(|(document, _)| document)
+    }
+
+    /// Builds the document like [`DocumentBuilder::build`] and reports where its fonts were found.
+    pub(crate) fn build_with_font_source(
+        self,
+    ) -> Result<(genpdf::Document, ResolvedFontSource), Error> {
+        let (font_family, font_source) = match self.loaded_font {
+            Some(loaded) => loaded,
+            None => self.font_source.load()?,
+        };
+        if let (Some(substitute), Some(warnings)) = (font_source.fallback_family(), &self.warnings)
+        {
+            warnings.push(RenderWarning::FontFallback {
+                requested: fonts::DEFAULT_FONT_FAMILY_NAME.to_string(),
+                substitute: substitute.to_string(),
+            });
+        }
         let mut document = genpdf::Document::new(font_family);
 
         if let Some(paper_size) = self.paper_size {
             document.set_paper_size(paper_size);
         }
 
-        let decorator =
-            ConfiguredPageDecorator::new(self.margins, self.header, self.footer, self.page_tracker);
+        let mut decorator = ConfiguredPageDecorator::new(
+            self.page_offset,
+            self.margins,
+            self.header,
+            self.footer,
+            self.page_tracker,
+            self.layout,
+            self.watermarks,
+        );
+        decorator.skip_first_page_header = self.skip_first_page_header;
+        decorator.cover_footer = self.cover_footer;
+        decorator.skip_first_page_footer = self.skip_first_page_footer;
+        decorator.page_background = self.page_background;
+        decorator.footnotes = self.footnotes;
         document.set_page_decorator(decorator);
 
         #[cfg(feature = "hyphenation")]
@@ -145,14 +370,15 @@ impl DocumentBuilder {
             document.set_hyphenator(hyphenator);
         }
 
-        Ok(document)
+        Ok((document, font_source))
     }
 }
 
 /// Definition of a footer rendered through the page decorator.
+#[derive(Clone)]
 pub struct FooterSpec {
     height: Mm,
-    factory: Box<FooterFactory>,
+    factory: Rc<FooterFactory>,
 }
 
 impl FooterSpec {
@@ -164,7 +390,7 @@ impl FooterSpec {
     {
         Self {
             height: height.into(),
-            factory: Box::new(move |page| Box::new(factory(page)) as Box<dyn Element>),
+            factory: Rc::new(move |page| Box::new(factory(page)) as Box<dyn Element>),
         }
     }
 }
@@ -174,22 +400,39 @@ struct ConfiguredPageDecorator {
     margins: Option<Margins>,
     header: Option<Box<HeaderFactory>>,
     footer: Option<FooterSpec>,
+    cover_footer: Option<FooterSpec>,
+    skip_first_page_header: bool,
+    skip_first_page_footer: bool,
+    page_background: Option<Color>,
+    footnotes: BTreeMap<usize, Vec<Vec<StyledSpan>>>,
     tracker: Option<PageTrackerHandle>,
+    layout: Option<LayoutTracker>,
+    watermarks: Vec<Watermark>,
 }
 
 impl ConfiguredPageDecorator {
     fn new(
+        page_offset: usize,
         margins: Option<Margins>,
         header: Option<Box<HeaderFactory>>,
         footer: Option<FooterSpec>,
         tracker: Option<PageTrackerHandle>,
+        layout: Option<LayoutTracker>,
+        watermarks: Vec<Watermark>,
     ) -> Self {
         Self {
-            page: 0,
+            page: page_offset,
             margins,
             header,
             footer,
+            cover_footer: None,
+            skip_first_page_header: false,
+            skip_first_page_footer: false,
+            page_background: None,
+            footnotes: BTreeMap::new(),
             tracker,
+            layout,
+            watermarks,
         }
     }
 }
@@ -204,24 +447,52 @@ impl PageDecorator for ConfiguredPageDecorator {
         self.page += 1;
 
         if let Some(tracker) = &self.tracker {
-            tracker.borrow_mut().set_current_page(self.page);
+            tracker.set_current_page(self.page);
         }
 
+        if let Some(background) = self.page_background {
+            fill_page(&area, background);
+        }
+
+        for watermark in &mut self.watermarks {
+            watermark.render(context, area.clone(), style)?;
+        }
+
+        let mut body_origin = (Mm::default(), Mm::default());
         if let Some(margins) = self.margins {
             area.add_margins(margins);
+            body_origin = (margins.left, margins.top);
         }
 
-        if let Some(header_cb) = &self.header {
+        let first_page = self.page == 1;
+        let header = self
+            .header
+            .as_ref()
+            .filter(|_| !(first_page && self.skip_first_page_header));
+        if let Some(header_cb) = header {
             let mut element = header_cb(self.page);
             let result = element.render(context, area.clone(), style)?;
             area.add_offset(Position::new(0, result.size.height));
+            body_origin.1 += result.size.height;
         }
 
-        if let Some(footer) = &self.footer {
+        let footer = if first_page {
+            self.cover_footer
+                .as_ref()
+                .filter(|_| !self.skip_first_page_footer)
+        } else {
+            self.footer.as_ref()
+        };
+        if let Some(footer) = footer {
             let available = area.size().height;
             if footer.height > available {
                 return Err(Error::new(
-                    "Footer height exceeds available space",
+                    format!(
+                        "Footer height exceeds available space ({:.1} mm needed, {:.1} mm left \
+                         after margins and header)",
+                        mm_to_f64(footer.height),
+                        mm_to_f64(available)
+                    ),
                     ErrorKind::InvalidData,
                 ));
             }
@@ -240,10 +511,101 @@ impl PageDecorator for ConfiguredPageDecorator {
             area.set_height(available - footer.height);
         }
 
+        if let Some(notes) = self.footnotes.remove(&self.page) {
+            let mut element = FootnoteArea::new(notes);
+            let available = area.size().height;
+            let height = element
+                .height(context, style, area.size().width)
+                .min(available);
+            let mut notes_area = area.clone();
+            notes_area.add_offset(Position::new(0, available - height));
+            element.render(context, notes_area, style)?;
+            area.set_height(available - height);
+        }
+
+        if let Some(layout) = &self.layout {
+            let size = area.size();
+            layout.begin_page(
+                self.page,
+                Rect::new(
+                    mm_to_f64(body_origin.0),
+                    mm_to_f64(body_origin.1),
+                    mm_to_f64(size.width),
+                    mm_to_f64(size.height),
+                ),
+            );
+        }
+
         Ok(area)
     }
 }
 
+/// Container that content elements are pushed into: the document itself or a nested layout such
+/// as the body of a quote.
+trait ElementSink {
+    fn push_element<E: Element + 'static>(&mut self, element: E);
+}
+
+impl ElementSink for genpdf::Document {
+    fn push_element<E: Element + 'static>(&mut self, element: E) {
+        self.push(element);
+    }
+}
+
+impl ElementSink for LinearLayout {
+    fn push_element<E: Element + 'static>(&mut self, element: E) {
+        self.push(element);
+    }
+}
+
+/// Conversions of the whole document applied to the finished bytes, after bookmarks and link
+/// annotations were added.
+#[cfg(any(feature = "pdfa", feature = "encryption"))]
+#[derive(Clone, Debug, Default)]
+struct Finishing {
+    #[cfg(feature = "pdfa")]
+    pdfa: Option<PdfaLevel>,
+    #[cfg(feature = "encryption")]
+    encryption: Option<EncryptionOptions>,
+}
+
+#[cfg(any(feature = "pdfa", feature = "encryption"))]
+impl Finishing {
+    fn is_empty(&self) -> bool {
+        let mut empty = true;
+        #[cfg(feature = "pdfa")]
+        {
+            empty &= self.pdfa.is_none();
+        }
+        #[cfg(feature = "encryption")]
+        {
+            empty &= self.encryption.is_none();
+        }
+        empty
+    }
+
+    /// Converts the document to PDF/A and then encrypts it, as configured.
+    fn apply(&self, bytes: Vec<u8>) -> Result<Vec<u8>, PdfBuildError> {
+        #[cfg(all(feature = "pdfa", feature = "encryption"))]
+        if self.pdfa.is_some() && self.encryption.is_some() {
+            return Err(PdfBuildError::Pdfa(pdfa::PdfaError::Encrypted));
+        }
+        #[cfg(feature = "pdfa")]
+        let bytes = match self.pdfa {
+            Some(level) => pdfa::apply_pdfa(&bytes, level).map_err(PdfBuildError::Pdfa)?,
+            None => bytes,
+        };
+        #[cfg(feature = "encryption")]
+        let bytes = match &self.encryption {
+            Some(options) => {
+                encryption::encrypt_document(&bytes, options).map_err(PdfBuildError::Encryption)?
+            }
+            None => bytes,
+        };
+        Ok(bytes)
+    }
+}
+
 /// Captures the page on which a section starts when inserted at the beginning of the section.
 struct SectionMarker {
     tracker: PageTrackerHandle,
@@ -269,13 +631,164 @@ impl Element for SectionMarker {
         _style: Style,
     ) -> Result<genpdf::RenderResult, Error> {
         if !self.recorded {
-            self.tracker.borrow_mut().mark_section(self.index);
+            self.tracker.mark_section(self.index);
             self.recorded = true;
         }
         Ok(genpdf::RenderResult::default())
     }
 }
 
+/// Outline of the sections streamed so far, shared between [`SectionStream`] and
+/// [`PdfBuilder::render_streaming`].
+#[derive(Default)]
+struct StreamedOutline {
+    headings: Vec<SectionHeading>,
+    numbers: Vec<Option<String>>,
+    numbering: Vec<usize>,
+    error: Option<PdfBuildError>,
+}
+
+/// Lays out sections pulled from an iterator one at a time while the document is rendered, so
+/// only the elements of the section being placed are held in memory.
+struct SectionStream {
+    builder: Rc<PdfBuilder>,
+    state: Rc<RenderState>,
+    sections: Box<dyn Iterator<Item = Section>>,
+    tracker: PageTrackerHandle,
+    outline: Rc<RefCell<StreamedOutline>>,
+    orientation: Orientation,
+    break_first: bool,
+    position: usize,
+    current: Option<LinearLayout>,
+}
+
+impl SectionStream {
+    /// Pushes the next section into a layout of its own, or returns `None` once the iterator is
+    /// exhausted.
+    fn next_section(&mut self) -> Result<Option<LinearLayout>, PdfBuildError> {
+        let Some(section) = self.sections.next() else {
+            return Ok(None);
+        };
+        let (builder, state) = (&self.builder, &self.state);
+        let mut outline = self.outline.borrow_mut();
+        let mut next_index = outline.headings.len();
+        let entries = flatten_outline(std::slice::from_ref(&section));
+        let numbers = if builder.number_sections {
+            continue_numbering(&mut outline.numbering, &entries)
+        } else {
+            vec![None; entries.len()]
+        };
+        for ((level, item), number) in entries.iter().zip(&numbers) {
+            outline.headings.push(SectionHeading {
+                level: *level,
+                title: item.title().into_owned(),
+                number: number.clone(),
+                identifier: item.identifier().map(str::to_string),
+                page: None,
+            });
+        }
+        outline.numbers.extend(numbers);
+
+        let mut layout = LinearLayout::vertical();
+        let new_page = builder.sections_on_new_pages || section.divider().is_some();
+        if (self.position == 0 && self.break_first) || (self.position > 0 && new_page) {
+            layout.push(PageBreak::new());
+        }
+        self.position += 1;
+        if let Some(divider) = section.divider() {
+            builder.push_divider(
+                state,
+                &mut layout,
+                divider,
+                self.orientation,
+                &mut next_index,
+                Some(&self.tracker),
+            );
+            layout.push(PageBreak::new());
+        }
+        builder.push_section(
+            state,
+            &mut layout,
+            &section,
+            1,
+            &mut next_index,
+            &outline.numbers,
+            Some(&self.tracker),
+        )?;
+        Ok(Some(layout))
+    }
+}
+
+impl Element for SectionStream {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        mut area: genpdf::render::Area<'_>,
+        style: Style,
+    ) -> Result<genpdf::RenderResult, Error> {
+        let mut result = genpdf::RenderResult::default();
+        loop {
+            let mut current = match self.current.take() {
+                Some(current) => current,
+                None => match self.next_section() {
+                    Ok(Some(layout)) => layout,
+                    Ok(None) => return Ok(result),
+                    Err(err) => {
+                        self.outline.borrow_mut().error = Some(err);
+                        return Err(Error::new(
+                            "Failed to lay out a streamed section",
+                            ErrorKind::InvalidData,
+                        ));
+                    }
+                },
+            };
+            let section_result = current.render(context, area.clone(), style)?;
+            area.add_offset(Position::new(0, section_result.size.height));
+            result.size = result.size.stack_vertical(section_result.size);
+            if section_result.has_more {
+                self.current = Some(current);
+                result.has_more = true;
+                return Ok(result);
+            }
+        }
+    }
+}
+
+/// Records the page on which a numbered figure or table first prints content.
+///
+/// Wrapping the element instead of inserting a marker before it keeps the page accurate when the
+/// element does not fit and moves to the next page.
+struct CaptionMarker<E> {
+    element: E,
+    kind: ElementKind,
+    number: usize,
+    layout: LayoutTracker,
+}
+
+impl<E: Element> Element for CaptionMarker<E> {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: genpdf::render::Area<'_>,
+        style: Style,
+    ) -> Result<genpdf::RenderResult, Error> {
+        let result = self.element.render(context, area, style)?;
+        if result.size.height > Mm::default() {
+            self.layout.place_caption(self.kind, self.number);
+        }
+        Ok(result)
+    }
+}
+
+/// Figures or tables listed on a page of the front matter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CaptionList {
+    title: &'static str,
+    label: &'static str,
+    kind: ElementKind,
+    captions: Vec<String>,
+}
+
 /// Errors produced while preparing or rendering a PDF document.
 #[derive(Debug)]
 pub enum PdfBuildError {
@@ -283,13 +796,81 @@ pub enum PdfBuildError {
     FontLoad(Error),
     /// Failure while converting model blocks into renderable elements.
     Content { message: String, source: Error },
+    /// An image could not be read or decoded.
+    ImageDecode {
+        /// Title of the section holding the image, or `None` for the cover.
+        section: Option<String>,
+        /// Zero-based position of the top-level block holding the image within its section or
+        /// the cover; images inside quotes, rows, and groups report the enclosing block.
+        block_index: usize,
+        /// Path of the image, or the size of in-memory image bytes.
+        source: String,
+        /// Error reported while reading or decoding the image.
+        cause: Error,
+    },
     /// Failure reported by `genpdf` when rendering the final document.
     Render(Error),
+    /// Failure while writing the rendered document to the output writer.
+    Io(io::Error),
     /// Failure while embedding bookmarks into the rendered PDF document.
     #[cfg(feature = "bookmarks")]
     Bookmarks(bookmarks::BookmarkError),
+    /// Failure while adding link annotations to the rendered PDF document.
+    #[cfg(feature = "links")]
+    Links(lopdf::Error),
+    /// Failure while joining the page runs of sections with different orientations.
+    #[cfg(feature = "mixed-orientation")]
+    Merge(lopdf::Error),
+    /// Failure while encrypting the rendered PDF document.
+    #[cfg(feature = "encryption")]
+    Encryption(lopdf::Error),
+    /// Failure while converting the rendered PDF document to PDF/A.
+    #[cfg(feature = "pdfa")]
+    Pdfa(pdfa::PdfaError),
+    /// Failure while drawing translucent colors into the rendered PDF document.
+    #[cfg(feature = "transparency")]
+    Transparency(lopdf::Error),
+    /// Failure while writing page labels into the rendered PDF document.
+    #[cfg(feature = "page-labels")]
+    PageLabels(lopdf::Error),
+    /// Page labels were requested but the `page-labels` feature is disabled.
+    PageLabelsUnavailable,
+    /// Failure while fixing the dates and identifiers of the rendered PDF document.
+    #[cfg(feature = "fixed-timestamp")]
+    Timestamp(lopdf::Error),
+    /// A fixed timestamp was requested but the `fixed-timestamp` feature is disabled.
+    FixedTimestampUnavailable,
+    /// Failure while writing the viewer preferences into the rendered PDF document.
+    #[cfg(feature = "viewer-preferences")]
+    ViewerPreferences(lopdf::Error),
+    /// Viewer preferences were requested but the `viewer-preferences` feature is disabled.
+    ViewerPreferencesUnavailable,
+    /// Failure while adding the structure tree to the rendered PDF document.
+    #[cfg(feature = "tagged-pdf")]
+    Tagging(lopdf::Error),
+    /// Tagged output was requested but the `tagged-pdf` feature is disabled.
+    TaggedPdfUnavailable,
+    /// Failure while rasterizing the preview of the first page.
+    #[cfg(feature = "preview")]
+    Preview(preview::PreviewError),
+    /// The blocking task started by [`PdfBuilder::render_async`] was cancelled, for example
+    /// because the runtime shut down.
+    #[cfg(feature = "async")]
+    RenderTask(tokio::task::JoinError),
+    /// Sections override the page orientation but the `mixed-orientation` feature is disabled.
+    MixedOrientationUnavailable,
+    /// A section range passed to [`PdfBuilder::render_sections`] is empty or reaches past the
+    /// last section.
+    SectionRange {
+        start: usize,
+        end: usize,
+        available: usize,
+    },
     /// Hyphenation was requested but no dictionary could be loaded.
     HyphenationUnavailable { language: &'static str },
+    /// The format set through [`PdfBuilder::footer_format`] holds an unknown token, such as
+    /// `{author}`, and [`PdfBuilder::strict_footer_format`] is enabled.
+    FooterFormat { token: String },
     /// Hyphenation dictionary failed to load from the embedded resources.
     #[cfg(feature = "hyphenation")]
     HyphenationLoad {
@@ -312,14 +893,83 @@ impl fmt::Display for PdfBuildError {
         match self {
             Self::FontLoad(err) => write!(f, "Failed to load fonts: {}", err),
             Self::Content { message, .. } => write!(f, "{}", message),
+            Self::ImageDecode {
+                section,
+                block_index,
+                source,
+                ..
+            } => {
+                write!(f, "Failed to load image from {} in block {} of ", source, block_index)?;
+                match section {
+                    Some(title) => write!(f, "section `{}`", title),
+                    None => write!(f, "the cover"),
+                }
+            }
             Self::Render(err) => write!(f, "Failed to render PDF: {}", err),
+            Self::Io(err) => write!(f, "Failed to write PDF: {}", err),
             #[cfg(feature = "bookmarks")]
             Self::Bookmarks(err) => write!(f, "Failed to apply bookmarks: {}", err),
+            #[cfg(feature = "links")]
+            Self::Links(err) => write!(f, "Failed to add link annotations: {}", err),
+            #[cfg(feature = "mixed-orientation")]
+            Self::Merge(err) => write!(f, "Failed to join page runs: {}", err),
+            #[cfg(feature = "encryption")]
+            Self::Encryption(err) => write!(f, "Failed to encrypt PDF: {}", err),
+            #[cfg(feature = "pdfa")]
+            Self::Pdfa(err) => write!(f, "Failed to convert PDF to PDF/A: {}", err),
+            #[cfg(feature = "transparency")]
+            Self::Transparency(err) => write!(f, "Failed to apply color transparency: {}", err),
+            #[cfg(feature = "page-labels")]
+            Self::PageLabels(err) => write!(f, "Failed to add page labels: {}", err),
+            Self::PageLabelsUnavailable => write!(
+                f,
+                "Page labels were requested but the page-labels feature is not available"
+            ),
+            #[cfg(feature = "fixed-timestamp")]
+            Self::Timestamp(err) => write!(f, "Failed to fix the document timestamp: {}", err),
+            Self::FixedTimestampUnavailable => write!(
+                f,
+                "A fixed timestamp was requested but the fixed-timestamp feature is not available"
+            ),
+            #[cfg(feature = "viewer-preferences")]
+            Self::ViewerPreferences(err) => {
+                write!(f, "Failed to set the viewer preferences: {}", err)
+            }
+            Self::ViewerPreferencesUnavailable => write!(
+                f,
+                "Viewer preferences were requested but the viewer-preferences feature is not available"
+            ),
+            #[cfg(feature = "tagged-pdf")]
+            Self::Tagging(err) => write!(f, "Failed to add the structure tree: {}", err),
+            Self::TaggedPdfUnavailable => write!(
+                f,
+                "Tagged output was requested but the tagged-pdf feature is not available"
+            ),
+            #[cfg(feature = "preview")]
+            Self::Preview(err) => write!(f, "Failed to render the page preview: {}", err),
+            #[cfg(feature = "async")]
+            Self::RenderTask(err) => write!(f, "The render task did not complete: {}", err),
+            Self::MixedOrientationUnavailable => write!(
+                f,
+                "Sections change the page orientation but the mixed-orientation feature is not available"
+            ),
+            Self::SectionRange {
+                start,
+                end,
+                available,
+            } => write!(
+                f,
+                "Section range {}..{} does not select any of the {} sections",
+                start, end, available
+            ),
             Self::HyphenationUnavailable { language } => write!(
                 f,
                 "Hyphenation requested for language {} but the feature is not available",
                 language
             ),
+            Self::FooterFormat { token } => {
+                write!(f, "Unknown token {} in the footer format", token)
+            }
             #[cfg(feature = "hyphenation")]
             Self::HyphenationLoad { language, .. } => {
                 write!(f, "Failed to load hyphenation dictionary for {}", language)
@@ -333,15 +983,63 @@ impl std::error::Error for PdfBuildError {
         match self {
             Self::FontLoad(err) | Self::Render(err) => Some(err),
             Self::Content { source, .. } => Some(source),
+            Self::ImageDecode { cause, .. } => Some(cause),
+            Self::Io(err) => Some(err),
             #[cfg(feature = "bookmarks")]
             Self::Bookmarks(err) => Some(err),
-            Self::HyphenationUnavailable { .. } => None,
+            #[cfg(feature = "links")]
+            Self::Links(err) => Some(err),
+            #[cfg(feature = "mixed-orientation")]
+            Self::Merge(err) => Some(err),
+            #[cfg(feature = "encryption")]
+            Self::Encryption(err) => Some(err),
+            #[cfg(feature = "pdfa")]
+            Self::Pdfa(err) => Some(err),
+            #[cfg(feature = "transparency")]
+            Self::Transparency(err) => Some(err),
+            #[cfg(feature = "page-labels")]
+            Self::PageLabels(err) => Some(err),
+            #[cfg(feature = "fixed-timestamp")]
+            Self::Timestamp(err) => Some(err),
+            #[cfg(feature = "viewer-preferences")]
+            Self::ViewerPreferences(err) => Some(err),
+            #[cfg(feature = "tagged-pdf")]
+            Self::Tagging(err) => Some(err),
+            #[cfg(feature = "preview")]
+            Self::Preview(err) => Some(err),
+            #[cfg(feature = "async")]
+            Self::RenderTask(err) => Some(err),
+            Self::MixedOrientationUnavailable
+            | Self::PageLabelsUnavailable
+            | Self::FixedTimestampUnavailable
+            | Self::ViewerPreferencesUnavailable
+            | Self::TaggedPdfUnavailable
+            | Self::SectionRange { .. }
+            | Self::HyphenationUnavailable { .. }
+            | Self::FooterFormat { .. } => None,
             #[cfg(feature = "hyphenation")]
             Self::HyphenationLoad { source, .. } => Some(source),
         }
     }
 }
 
+/// Heading generated for a section or one of its nested subsections.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SectionHeading {
+    /// Nesting level of the heading, starting at 1 for top-level sections.  Section dividers are
+    /// listed at level 0.
+    pub level: usize,
+    /// Title printed for the heading.
+    pub title: String,
+    /// Outline number printed before the title, such as "1.2", when
+    /// [`PdfBuilder::number_sections`] is enabled.
+    pub number: Option<String>,
+    /// Identifier of the originating section, if any.
+    pub identifier: Option<String>,
+    /// Recorded start page (1-indexed), when page collection was enabled.
+    pub page: Option<usize>,
+}
+
 /// Result of a PDF render operation.
 #[derive(Debug, Default)]
 pub struct PdfRenderResult {
@@ -349,498 +1047,4238 @@ pub struct PdfRenderResult {
     pub bytes: Vec<u8>,
     /// Recorded start page (1-indexed) for each section in the order provided to the builder.
     pub section_start_pages: Vec<Option<usize>>,
+    /// Every section and subsection heading in reading order together with its level and page,
+    /// including section dividers at level 0.
+    pub headings: Vec<SectionHeading>,
+    /// Non-fatal issues, such as clipped content, recorded while producing the final output.
+    pub warnings: Vec<RenderWarning>,
+    /// Text of the provisional stamp drawn on every page when
+    /// [`PdfBuilder::stamp_if_warnings`] found warnings, or `None` for a clean render.
+    pub provisional_stamp: Option<String>,
+    /// Location the document's font family was loaded from, including any fallback.
+    pub font_source: ResolvedFontSource,
 }
 
-/// Builder responsible for turning [`Cover`] and [`Section`] definitions into rendered PDFs.
-pub struct PdfBuilder {
-    paper_size: Option<Size>,
-    margins: Option<Margins>,
-    show_header: bool,
-    show_footer: bool,
-    custom_header: Option<SharedHeaderFactory>,
-    custom_footer: Option<FooterConfig>,
-    enable_hyphenation: bool,
-    cover: Option<Cover>,
-    sections: Vec<Section>,
-    include_toc: bool,
-    toc_title: Option<String>,
-    default_alignment: HorizontalAlignment,
-    render_section_headings: bool,
-    collect_section_pages: bool,
+impl PdfRenderResult {
+    fn from_parts(bytes: Vec<u8>, metadata: RenderMetadata) -> Self {
+        Self {
+            bytes,
+            section_start_pages: metadata.section_start_pages,
+            headings: metadata.headings,
+            warnings: metadata.warnings,
+            provisional_stamp: metadata.provisional_stamp,
+            font_source: metadata.font_source,
+        }
+    }
 }
 
-impl Default for PdfBuilder {
-    fn default() -> Self {
-        Self {
-            paper_size: None,
-            margins: None,
-            show_header: false,
-            show_footer: false,
-            custom_header: None,
-            custom_footer: None,
-            enable_hyphenation: false,
-            cover: None,
-            sections: Vec::new(),
-            include_toc: false,
-            toc_title: None,
-            default_alignment: HorizontalAlignment::Left,
-            render_section_headings: true,
-            collect_section_pages: false,
+/// Section metadata returned by [`PdfBuilder::render_to`] after the document was written.
+///
+/// Mirrors [`PdfRenderResult`] without the rendered bytes.
+#[derive(Debug, Default)]
+pub struct RenderMetadata {
+    /// Recorded start page (1-indexed) for each section in the order provided to the builder.
+    pub section_start_pages: Vec<Option<usize>>,
+    /// Every section and subsection heading in reading order together with its level and page,
+    /// including section dividers at level 0.
+    pub headings: Vec<SectionHeading>,
+    /// Non-fatal issues, such as clipped content, recorded while producing the final output.
+    pub warnings: Vec<RenderWarning>,
+    /// Text of the provisional stamp drawn on every page when
+    /// [`PdfBuilder::stamp_if_warnings`] found warnings, or `None` for a clean render.
+    pub provisional_stamp: Option<String>,
+    /// Location the document's font family was loaded from, including any fallback.
+    pub font_source: ResolvedFontSource,
+}
+
+/// Single row of the printed table of contents.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct TocEntry<'a> {
+    level: usize,
+    number: Option<String>,
+    title: Cow<'a, str>,
+    page: Option<usize>,
+}
+
+/// Strategy used to discover the page numbers printed in the table of contents.
+///
+/// The table of contents is rendered before the sections, so their page numbers are only known
+/// after the document was laid out once.  Both modes therefore run a discovery pass before the
+/// final render; they differ in how much work that pass does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    /// The discovery pass renders the document exactly like the final pass, decoding every
+    /// image, so any image error is reported before the final pass starts.
+    #[default]
+    Exact,
+    /// The discovery pass only reads image dimensions and lays out same-sized placeholders,
+    /// roughly halving render time for image-heavy reports.  Page numbers match
+    /// [`RenderMode::Exact`] as long as each image's header describes its pixel data; corrupt
+    /// images are only reported by the final pass.
+    Fast,
+}
+
+/// Text printed by the built-in header enabled through [`PdfBuilder::show_header`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HeaderStyle {
+    /// Prints the cover title on every page; no header is printed without a cover.
+    #[default]
+    DocumentTitle,
+    /// Prints the title of the top-level section active on the page, i.e. the last one starting
+    /// on or before it, like the running heads of a book.
+    ///
+    /// Pages before the first section show the cover title, if any.  The section pages are only
+    /// known once the document was laid out, so this style always runs the page-discovery pass.
+    /// Titles are expected to fit on one line; the header always reserves a single line so the
+    /// discovery pass and the final pass break pages alike.
+    RunningHeading,
+}
+
+/// Text printed by the built-in footer enabled through [`PdfBuilder::show_footer`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FooterStyle {
+    /// Prints the current page, e.g. "Page 3".
+    #[default]
+    PageNumber,
+    /// Prints the current page and the total page count, e.g. "Page 3 of 12".
+    ///
+    /// The total is only known once the document was laid out, so this style always runs the
+    /// page-discovery pass, even without a printed table of contents.
+    PageOfTotal,
+}
+
+impl FooterStyle {
+    /// Formats the footer text from the printed page and total, such as page indices or page
+    /// labels; `total` is `None` while the total is still being discovered.
+    fn labelled_text(self, page: &str, total: Option<&str>) -> String {
+        match (self, total) {
+            (Self::PageNumber, _) => format!("Page {}", page),
+            (Self::PageOfTotal, Some(total)) => format!("Page {} of {}", page, total),
+            (Self::PageOfTotal, None) => format!("Page {} of ?", page),
         }
     }
 }
 
-impl PdfBuilder {
-    /// Creates a new builder instance with default settings.
-    pub fn new() -> Self {
-        Self::default()
+/// Piece of a format set through [`PdfBuilder::footer_format`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum FooterToken {
+    /// Text printed as is.
+    Text(String),
+    /// `{page}`: the current page number or label.
+    Page,
+    /// `{total}`: the total page count or the last page label.
+    Total,
+    /// `{section}`: the title of the top-level section the page belongs to.
+    Section,
+    /// `{date}`: the document date as `YYYY-MM-DD`.
+    Date,
+    /// Any other `{name}`, printed literally unless the format is strict.
+    Unknown(String),
+}
+
+/// Splits a footer format into literal text and `{name}` tokens.
+///
+/// Braces that do not enclose a token, such as a lone `{`, are printed as text.
+fn parse_footer_format(format: &str) -> Vec<FooterToken> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut rest = format;
+    while let Some(end) = rest.find('}') {
+        let Some(start) = rest[..end].rfind('{') else {
+            text.push_str(&rest[..=end]);
+            rest = &rest[end + 1..];
+            continue;
+        };
+        text.push_str(&rest[..start]);
+        let token = match &rest[start + 1..end] {
+            "page" => FooterToken::Page,
+            "total" => FooterToken::Total,
+            "section" => FooterToken::Section,
+            "date" => FooterToken::Date,
+            _ => FooterToken::Unknown(rest[start..=end].to_string()),
+        };
+        if !text.is_empty() {
+            tokens.push(FooterToken::Text(std::mem::take(&mut text)));
+        }
+        tokens.push(token);
+        rest = &rest[end + 1..];
     }
+    text.push_str(rest);
+    if !text.is_empty() {
+        tokens.push(FooterToken::Text(text));
+    }
+    tokens
+}
 
-    /// Overrides the paper size used for the generated document.
-    pub fn with_paper_size(mut self, paper_size: impl Into<Size>) -> Self {
-        self.paper_size = Some(paper_size.into());
-        self
+/// Expands parsed footer `tokens` for one page; a total that is still being discovered prints as
+/// `?` and a page before the first section prints an empty section title.
+fn expand_footer_format(
+    tokens: &[FooterToken],
+    page: &str,
+    total: Option<&str>,
+    section: Option<&str>,
+    date: &str,
+) -> String {
+    tokens
+        .iter()
+        .map(|token| match token {
+            FooterToken::Text(text) | FooterToken::Unknown(text) => text.as_str(),
+            FooterToken::Page => page,
+            FooterToken::Total => total.unwrap_or("?"),
+            FooterToken::Section => section.unwrap_or_default(),
+            FooterToken::Date => date,
+        })
+        .collect()
+}
+
+/// Heading whose appearance is configured through [`PdfBuilder::with_heading_style`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HeadingLevel {
+    /// Title printed on the cover.
+    CoverTitle,
+    /// Subtitle printed below the cover title.
+    CoverSubtitle,
+    /// Section heading at the given depth, starting at 1 for top-level sections.
+    Section(usize),
+}
+
+impl From<usize> for HeadingLevel {
+    /// Converts a section depth, treating 0 like a top-level section.
+    fn from(level: usize) -> Self {
+        Self::Section(level.max(1))
     }
+}
 
-    /// Overrides the page margins applied to every page.
-    pub fn with_margins(mut self, margins: impl Into<Margins>) -> Self {
-        self.margins = Some(margins.into());
-        self
+/// Font and spacing of a heading.
+///
+/// Spacing is measured in lines of body text, like the gaps the builder leaves elsewhere.  See
+/// [`HeadingStyle::default_for`] for the appearance of headings that are not configured.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct HeadingStyle {
+    font_size: u8,
+    bold: bool,
+    italic: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_color::option"))]
+    color: Option<Color>,
+    space_before: f64,
+    space_after: f64,
+}
+
+impl Default for HeadingStyle {
+    /// Returns the style of top-level section headings.
+    fn default() -> Self {
+        Self::default_for(HeadingLevel::Section(1))
     }
+}
 
-    /// Controls whether the default header is printed.
-    pub fn show_header(mut self, show: bool) -> Self {
-        self.show_header = show;
-        self
+impl HeadingStyle {
+    /// Creates a bold heading of the given font size in the text color, followed by three
+    /// quarters of a line.
+    pub fn new(font_size: u8) -> Self {
+        Self {
+            font_size,
+            bold: true,
+            italic: false,
+            color: None,
+            space_before: 0.0,
+            space_after: 0.75,
+        }
     }
 
-    /// Controls whether the default footer with page numbers is printed.
-    pub fn show_footer(mut self, show: bool) -> Self {
-        self.show_footer = show;
-        self
+    /// Returns the built-in style of `level`: a bold 28 pt cover title, an italic 18 pt
+    /// subtitle, and bold section headings of 18 pt, 14 pt, and 12 pt for the third level and
+    /// below.
+    pub fn default_for(level: HeadingLevel) -> Self {
+        match level {
+            HeadingLevel::CoverTitle => Self::new(28).with_space_after(1.5),
+            HeadingLevel::CoverSubtitle => Self::new(18)
+                .with_bold(false)
+                .with_italic(true)
+                .with_space_after(1.0),
+            HeadingLevel::Section(0 | 1) => Self::new(18),
+            HeadingLevel::Section(2) => Self::new(14),
+            HeadingLevel::Section(_) => Self::new(12),
+        }
     }
 
-    /// Installs a custom header renderer that is invoked for every page.
-    pub fn with_header<F, E>(mut self, header: F) -> Self
-    where
-        F: Fn(usize) -> E + 'static,
-        E: Element + 'static,
-    {
-        self.custom_header = Some(Arc::new(move |page| {
-            BoxedElement::new(Box::new(header(page)))
-        }));
-        self
+    /// Returns the font size in points.
+    pub fn font_size(&self) -> u8 {
+        self.font_size
     }
 
-    /// Installs a custom footer renderer with an explicit height measured in millimetres.
-    pub fn with_footer<F, E>(mut self, height_mm: f64, footer: F) -> Self
-    where
-        F: Fn(usize) -> E + 'static,
-        E: Element + 'static,
-    {
-        self.custom_footer = Some(FooterConfig::new(height_mm, footer));
-        self
+    /// Returns whether the heading is bold.
+    pub fn is_bold(&self) -> bool {
+        self.bold
     }
 
-    /// Toggles hyphenation support using the embedded dictionary.
-    pub fn enable_hyphenation(mut self, enable: bool) -> Self {
-        self.enable_hyphenation = enable;
-        self
+    /// Returns whether the heading is italic.
+    pub fn is_italic(&self) -> bool {
+        self.italic
     }
 
-    /// Attaches the cover description that should be rendered as the first page.
-    pub fn with_cover(mut self, cover: impl Into<Option<Cover>>) -> Self {
-        self.cover = cover.into();
-        self
+    /// Returns the text color, or `None` to use the text color of the document.
+    pub fn color(&self) -> Option<Color> {
+        self.color
     }
 
-    /// Appends an additional section to the document.
-    pub fn add_section(mut self, section: Section) -> Self {
-        self.sections.push(section);
-        self
+    /// Returns the space left above the heading, in lines.
+    pub fn space_before(&self) -> f64 {
+        self.space_before
     }
 
-    /// Replaces the entire section list.
-    pub fn with_sections<I>(mut self, sections: I) -> Self
-    where
-        I: IntoIterator<Item = Section>,
-    {
-        self.sections = sections.into_iter().collect();
+    /// Returns the space left below the heading, in lines.
+    pub fn space_after(&self) -> f64 {
+        self.space_after
+    }
+
+    /// Sets the font size in points and returns the updated style.
+    pub fn with_font_size(mut self, font_size: u8) -> Self {
+        self.font_size = font_size;
         self
     }
 
-    /// Requests a printed table of contents before the sections are rendered.
-    pub fn include_printed_toc(mut self, include: bool) -> Self {
-        self.include_toc = include;
+    /// Sets whether the heading is bold and returns the updated style.
+    pub fn with_bold(mut self, bold: bool) -> Self {
+        self.bold = bold;
         self
     }
 
-    /// Sets the title shown above the generated table of contents.
-    pub fn with_toc_title(mut self, title: impl Into<Option<String>>) -> Self {
-        self.toc_title = title.into();
+    /// Sets whether the heading is italic and returns the updated style.
+    pub fn with_italic(mut self, italic: bool) -> Self {
+        self.italic = italic;
         self
     }
 
-    /// Sets the default horizontal alignment applied to paragraphs and images.
-    pub fn with_default_alignment(mut self, alignment: HorizontalAlignment) -> Self {
-        self.default_alignment = alignment;
+    /// Sets the text color and returns the updated style.
+    pub fn with_color(mut self, color: impl Into<Option<Color>>) -> Self {
+        self.color = color.into();
         self
     }
 
-    /// Controls whether section headings generated from their titles are rendered automatically.
-    pub fn render_section_headings(mut self, enabled: bool) -> Self {
-        self.render_section_headings = enabled;
+    /// Sets the space above the heading, in lines, and returns the updated style.
+    pub fn with_space_before(mut self, lines: f64) -> Self {
+        self.space_before = lines;
         self
     }
 
-    /// Toggles recording section start pages for the final render output.
-    pub fn collect_section_pages(mut self, collect: bool) -> Self {
-        self.collect_section_pages = collect;
+    /// Sets the space below the heading, in lines, and returns the updated style.
+    pub fn with_space_after(mut self, lines: f64) -> Self {
+        self.space_after = lines;
         self
     }
 
-    /// Renders the configured document and returns both the PDF bytes and section metadata.
-    pub fn render(self) -> Result<PdfRenderResult, PdfBuildError> {
-        let section_count = self.sections.len();
-        let need_toc = self.include_toc && section_count > 0;
-        let need_tracking = self.collect_section_pages || need_toc;
+    /// Text style of the heading.
+    fn text_style(&self) -> Style {
+        let mut style = Style::new();
+        style.set_font_size(self.font_size);
+        if self.bold {
+            style.set_bold();
+        }
+        if self.italic {
+            style.set_italic();
+        }
+        if let Some(color) = self.color {
+            style.set_color(color);
+        }
+        style
+    }
+}
+
+/// Format of numbered figure and table captions, such as "Figure 3. Revenue by region".
+///
+/// The format holds `{label}` ("Figure" or "Table"), `{n}` (the running number), and `{text}`
+/// (the caption).  The text before `{text}` is the prefix, set in the template's style, while
+/// the caption and any text after it keep the caption's own styling; without `{text}` the
+/// caption follows the prefix.  Captions that are not numbered are printed without the prefix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaptionTemplate {
+    format: String,
+    bold: bool,
+    italic: bool,
+    color: Option<Color>,
+}
 
-        let mut recorded_pages = vec![None; section_count];
+impl Default for CaptionTemplate {
+    /// Returns the built-in "Figure N: " format with a bold prefix.
+    fn default() -> Self {
+        Self::new("{label} {n}: {text}")
+    }
+}
 
-        if need_tracking {
-            let tracker = Rc::new(RefCell::new(PageTracker::new(section_count)));
-            let _ = self.render_internal(Some(Rc::clone(&tracker)), None)?;
-            recorded_pages = tracker.borrow().pages().to_vec();
+impl CaptionTemplate {
+    /// Creates a template with the given format and a bold prefix in the text color.
+    pub fn new(format: impl Into<String>) -> Self {
+        Self {
+            format: format.into(),
+            bold: true,
+            italic: false,
+            color: None,
         }
+    }
 
-        let final_tracker = if need_tracking && section_count > 0 {
-            Some(Rc::new(RefCell::new(PageTracker::new(section_count))))
-        } else if self.collect_section_pages && section_count > 0 {
-            Some(Rc::new(RefCell::new(PageTracker::new(section_count))))
-        } else {
-            None
-        };
+    /// Returns the format string.
+    pub fn format(&self) -> &str {
+        &self.format
+    }
 
-        let bytes = self.render_internal(
-            final_tracker.clone(),
-            if need_toc {
-                Some(&recorded_pages)
-            } else {
-                None
-            },
-        )?;
+    /// Returns whether the prefix is bold.
+    pub fn is_bold(&self) -> bool {
+        self.bold
+    }
 
-        let section_start_pages = final_tracker
-            .map(|tracker| tracker.borrow().pages().to_vec())
-            .unwrap_or_else(|| vec![None; section_count]);
+    /// Returns whether the prefix is italic.
+    pub fn is_italic(&self) -> bool {
+        self.italic
+    }
 
-        Ok(PdfRenderResult {
-            bytes,
-            section_start_pages,
-        })
+    /// Returns the color of the prefix, or `None` to use the color of the caption.
+    pub fn color(&self) -> Option<Color> {
+        self.color
     }
 
-    /// Renders the PDF document and augments it with section bookmarks when the
-    /// `bookmarks` feature is enabled.
-    #[cfg(feature = "bookmarks")]
-    pub fn render_with_bookmarks(mut self) -> Result<PdfRenderResult, PdfBuildError> {
-        let sections = self.sections.clone();
-        self.collect_section_pages = true;
-        let mut result = self.render()?;
-        result.bytes = bookmarks::apply_section_bookmarks(
-            &result.bytes,
-            &sections,
-            &result.section_start_pages,
-        )
-        .map_err(PdfBuildError::Bookmarks)?;
-        Ok(result)
+    /// Sets whether the prefix is bold and returns the updated template.
+    pub fn with_bold(mut self, bold: bool) -> Self {
+        self.bold = bold;
+        self
     }
 
-    fn render_internal(
-        &self,
-        tracker: Option<PageTrackerHandle>,
-        toc_pages: Option<&[Option<usize>]>,
-    ) -> Result<Vec<u8>, PdfBuildError> {
-        let mut document = self.prepare_document(tracker.clone())?;
-        self.populate_document(&mut document, tracker.as_ref(), toc_pages)?;
-        let mut buffer = Vec::new();
-        document
-            .render(&mut buffer)
-            .map_err(PdfBuildError::Render)?;
-        Ok(buffer)
+    /// Sets whether the prefix is italic and returns the updated template.
+    pub fn with_italic(mut self, italic: bool) -> Self {
+        self.italic = italic;
+        self
     }
 
-    fn prepare_document(
-        &self,
-        tracker: Option<PageTrackerHandle>,
-    ) -> Result<genpdf::Document, PdfBuildError> {
-        let mut builder = DocumentBuilder::new();
-        if let Some(size) = self.paper_size {
-            builder = builder.with_paper_size(size);
-        }
-        if let Some(margins) = self.margins {
-            builder = builder.with_margins(margins);
-        }
-        if let Some(tracker) = tracker.clone() {
-            builder = builder.with_page_tracker(tracker);
-        }
+    /// Sets the color of the prefix and returns the updated template.
+    pub fn with_color(mut self, color: impl Into<Option<Color>>) -> Self {
+        self.color = color.into();
+        self
+    }
 
-        #[cfg(feature = "hyphenation")]
-        {
-            builder = self.apply_hyphenation(builder)?;
+    /// Text before and after the caption of the captions labelled `label` and numbered `number`.
+    fn expand(&self, label: &str, number: usize) -> (String, String) {
+        let (prefix, suffix) = self
+            .format
+            .split_once("{text}")
+            .unwrap_or((&self.format, ""));
+        let fill = |part: &str| {
+            part.replace("{label}", label)
+                .replace("{n}", &number.to_string())
+        };
+        (fill(prefix), fill(suffix))
+    }
+
+    /// Styles the prefix `text` as configured.
+    fn prefix_span(&self, text: String) -> Span {
+        Span::new(text)
+            .with_bold(self.bold)
+            .with_italic(self.italic)
+            .with_color(self.color)
+    }
+}
+
+/// Numbering style of a range of page labels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PageNumberStyle {
+    /// Arabic numerals: 1, 2, 3.
+    #[default]
+    Decimal,
+    /// Lowercase roman numerals: i, ii, iii.
+    LowerRoman,
+    /// Uppercase roman numerals: I, II, III.
+    UpperRoman,
+    /// Lowercase letters: a to z, then aa to zz, and so on.
+    LowerAlpha,
+    /// Uppercase letters: A to Z, then AA to ZZ, and so on.
+    UpperAlpha,
+}
+
+impl PageNumberStyle {
+    /// Formats the 1-based page `number` in this style.
+    pub fn format(self, number: usize) -> String {
+        match self {
+            Self::Decimal => number.to_string(),
+            Self::LowerRoman => roman_numeral(number).to_lowercase(),
+            Self::UpperRoman => roman_numeral(number),
+            Self::LowerAlpha => alphabetic_label(number).to_lowercase(),
+            Self::UpperAlpha => alphabetic_label(number),
         }
-        #[cfg(not(feature = "hyphenation"))]
-        {
-            if self.enable_hyphenation {
-                return Err(PdfBuildError::HyphenationUnavailable { language: "en-US" });
-            }
+    }
+
+    /// Value of the `/S` entry of a page label dictionary.
+    #[cfg(feature = "page-labels")]
+    fn pdf_name(self) -> &'static str {
+        match self {
+            Self::Decimal => "D",
+            Self::LowerRoman => "r",
+            Self::UpperRoman => "R",
+            Self::LowerAlpha => "a",
+            Self::UpperAlpha => "A",
         }
+    }
+}
 
-        if let Some(header_cb) = &self.custom_header {
-            let header_cb = Arc::clone(header_cb);
-            builder = builder.with_header(move |page| header_cb(page));
-        } else if self.show_header {
-            if let Some(title) = self.cover.as_ref().map(|cover| cover.title().to_string()) {
-                let header_text = title.clone();
-                builder = builder.with_header(move |_| {
-                    let mut paragraph = Paragraph::new(header_text.clone());
-                    paragraph.set_alignment(Alignment::Center);
-                    paragraph
-                });
-            }
+fn roman_numeral(mut number: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut numeral = String::new();
+    for (value, symbol) in NUMERALS {
+        while number >= value {
+            numeral.push_str(symbol);
+            number -= value;
         }
+    }
+    numeral
+}
 
-        if let Some(footer) = &self.custom_footer {
-            let height = footer.height;
-            let footer_cb = Arc::clone(&footer.factory);
-            builder = builder.with_footer(height, move |page| footer_cb(page));
-        } else if self.show_footer {
-            builder = builder.with_footer(mm_from_f64(12.0), |page| {
-                let mut paragraph = Paragraph::new(format!("Page {}", page));
-                paragraph.set_alignment(Alignment::Right);
-                paragraph
-            });
+fn alphabetic_label(number: usize) -> String {
+    let Some(index) = number.checked_sub(1) else {
+        return String::new();
+    };
+    let letter = char::from(b'A' + (index % 26) as u8);
+    std::iter::repeat(letter).take(index / 26 + 1).collect()
+}
+
+/// Page labels that PDF viewers show instead of the raw page index, set with
+/// [`PdfBuilder::with_page_labels`].
+///
+/// The front matter, meaning the cover, the table of contents, and the lists of figures and
+/// tables, is numbered in lowercase roman numerals by default, and numbering restarts at 1 on the
+/// page the first section starts on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageLabelScheme {
+    front_matter: PageNumberStyle,
+    body: PageNumberStyle,
+    footer: bool,
+}
+
+impl Default for PageLabelScheme {
+    fn default() -> Self {
+        Self {
+            front_matter: PageNumberStyle::LowerRoman,
+            body: PageNumberStyle::Decimal,
+            footer: false,
         }
+    }
+}
 
-        builder.build().map_err(PdfBuildError::FontLoad)
+impl PageLabelScheme {
+    /// Creates the default scheme: i, ii, iii for the front matter and 1, 2, 3 for the body.
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    #[cfg(feature = "hyphenation")]
-    fn apply_hyphenation(
+    /// Returns the numbering style of the front matter.
+    pub fn front_matter_style(&self) -> PageNumberStyle {
+        self.front_matter
+    }
+
+    /// Sets the numbering style of the front matter.
+    pub fn with_front_matter_style(mut self, style: PageNumberStyle) -> Self {
+        self.front_matter = style;
+        self
+    }
+
+    /// Returns the numbering style of the pages from the first section on.
+    pub fn body_style(&self) -> PageNumberStyle {
+        self.body
+    }
+
+    /// Sets the numbering style of the pages from the first section on.
+    pub fn with_body_style(mut self, style: PageNumberStyle) -> Self {
+        self.body = style;
+        self
+    }
+
+    /// Returns whether the built-in footer prints the labels.
+    pub fn labels_footer(&self) -> bool {
+        self.footer
+    }
+
+    /// Controls whether the built-in footer prints the page label instead of the raw page index.
+    ///
+    /// With [`FooterStyle::PageOfTotal`] the total is the last label of the same range, e.g.
+    /// "Page ii of iii" in the front matter and "Page 3 of 10" in the body.  Custom footers keep
+    /// receiving the raw page index.
+    pub fn with_footer_labels(mut self, enabled: bool) -> Self {
+        self.footer = enabled;
+        self
+    }
+
+    /// Labels the 1-based `page` and the last page of its range, given the page the body starts
+    /// on and the page count of the document.
+    ///
+    /// Without a body start every page belongs to the front matter.
+    fn labels(
         &self,
-        builder: DocumentBuilder,
-    ) -> Result<DocumentBuilder, PdfBuildError> {
-        if self.enable_hyphenation {
-            let hyphenator =
-                HyphenationStandard::from_embedded(Language::EnglishUS).map_err(|source| {
-                    PdfBuildError::HyphenationLoad {
-                        language: "en-US",
-                        source,
-                    }
-                })?;
-            Ok(builder.with_hyphenator(hyphenator))
-        } else {
-            Ok(builder)
+        page: usize,
+        body_start: Option<usize>,
+        total: Option<usize>,
+    ) -> (String, Option<String>) {
+        match body_start {
+            Some(start) if page >= start => (
+                self.body.format(page - start + 1),
+                total.map(|total| self.body.format(total + 1 - start.min(total))),
+            ),
+            _ => (
+                self.front_matter.format(page),
+                body_start
+                    .map(|start| start - 1)
+                    .or(total)
+                    .map(|last| self.front_matter.format(last)),
+            ),
         }
     }
 
-    fn populate_document(
-        &self,
-        document: &mut genpdf::Document,
-        tracker: Option<&PageTrackerHandle>,
-        toc_pages: Option<&[Option<usize>]>,
-    ) -> Result<(), PdfBuildError> {
-        if let Some(cover) = &self.cover {
-            self.push_cover(document, cover)?;
-            if self.include_toc || !self.sections.is_empty() {
-                document.push(PageBreak::new());
-            }
+    /// Label ranges as zero-based start page and numbering style.
+    #[cfg(feature = "page-labels")]
+    fn ranges(&self, body_start: Option<usize>) -> Vec<(usize, PageNumberStyle)> {
+        match body_start {
+            Some(1) => vec![(0, self.body)],
+            Some(start) => vec![(0, self.front_matter), (start - 1, self.body)],
+            None => vec![(0, self.front_matter)],
+        }
+    }
+}
+
+/// Panel PDF viewers show next to the pages when the document is opened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageMode {
+    /// Shows the bookmark panel.
+    UseOutlines,
+    /// Shows the page thumbnail panel.
+    UseThumbs,
+    /// Opens the document in full-screen mode without menus or panels.
+    FullScreen,
+}
+
+impl PageMode {
+    /// Value of the catalog `/PageMode` entry.
+    #[cfg(feature = "viewer-preferences")]
+    fn pdf_name(self) -> &'static str {
+        match self {
+            Self::UseOutlines => "UseOutlines",
+            Self::UseThumbs => "UseThumbs",
+            Self::FullScreen => "FullScreen",
         }
+    }
+}
 
-        if self.include_toc && !self.sections.is_empty() {
-            self.push_toc(document, toc_pages)?;
+/// Arrangement of the pages PDF viewers start with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewerLayout {
+    /// Shows one page at a time.
+    SinglePage,
+    /// Shows two pages side by side with the odd pages on the left, like a printed spread.
+    TwoColumnLeft,
+}
+
+impl ViewerLayout {
+    /// Value of the catalog `/PageLayout` entry.
+    #[cfg(feature = "viewer-preferences")]
+    fn pdf_name(self) -> &'static str {
+        match self {
+            Self::SinglePage => "SinglePage",
+            Self::TwoColumnLeft => "TwoColumnLeft",
         }
+    }
+}
 
-        for (index, section) in self.sections.iter().enumerate() {
-            if let Some(tracker) = tracker {
-                document.push(SectionMarker::new(Rc::clone(tracker), index));
-            }
+/// How PDF viewers present the document when it is opened, set with
+/// [`PdfBuilder::viewer_preferences`].
+///
+/// Options left unset keep the viewer's own defaults.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ViewerPrefs {
+    page_mode: Option<PageMode>,
+    page_layout: Option<ViewerLayout>,
+    hide_toolbar: bool,
+}
 
-            if self.render_section_headings {
-                self.push_section_heading(document, section);
-            }
+impl ViewerPrefs {
+    /// Creates preferences that leave every option to the viewer.
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-            self.push_section_blocks(document, section.blocks())?;
+    /// Returns the panel shown when the document is opened, if set.
+    pub fn page_mode(&self) -> Option<PageMode> {
+        self.page_mode
+    }
+
+    /// Sets the panel shown when the document is opened.
+    pub fn with_page_mode(mut self, mode: impl Into<Option<PageMode>>) -> Self {
+        self.page_mode = mode.into();
+        self
+    }
+
+    /// Returns the initial page arrangement, if set.
+    pub fn page_layout(&self) -> Option<ViewerLayout> {
+        self.page_layout
+    }
+
+    /// Sets the initial page arrangement.
+    pub fn with_page_layout(mut self, layout: impl Into<Option<ViewerLayout>>) -> Self {
+        self.page_layout = layout.into();
+        self
+    }
+
+    /// Returns whether the viewer's toolbar is hidden.
+    pub fn hides_toolbar(&self) -> bool {
+        self.hide_toolbar
+    }
+
+    /// Asks the viewer to hide its toolbar while the document is active.
+    pub fn with_hide_toolbar(mut self, hide: bool) -> Self {
+        self.hide_toolbar = hide;
+        self
+    }
+}
+
+/// Filler printed between the titles and page numbers of the table of contents.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TocLeader {
+    /// Leaves the space between title and page number empty.
+    #[default]
+    None,
+    /// Fills the space with a row of periods.
+    Dots,
+}
+
+impl TocLeader {
+    fn character(self) -> Option<char> {
+        match self {
+            Self::None => None,
+            Self::Dots => Some('.'),
         }
+    }
+}
 
-        Ok(())
+/// Converts `range` into a non-empty range of positions within `available` sections.
+fn resolve_section_range(
+    range: &impl RangeBounds<usize>,
+    available: usize,
+) -> Result<Range<usize>, PdfBuildError> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.saturating_add(1),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => available,
+    };
+    if start >= end || end > available {
+        return Err(PdfBuildError::SectionRange {
+            start,
+            end,
+            available,
+        });
     }
+    Ok(start..end)
+}
 
-    fn push_cover(
-        &self,
-        document: &mut genpdf::Document,
-        cover: &Cover,
-    ) -> Result<(), PdfBuildError> {
-        let mut title_style = Style::new();
-        title_style.set_font_size(28);
-        title_style.set_bold();
-        let mut title = Paragraph::new(cover.title());
-        title.set_alignment(Alignment::Center);
-        document.push(title.styled(title_style));
-        document.push(LineBreak::new(1.5));
+/// Consecutive top-level sections sharing a page orientation.
+///
+/// `genpdf` uses one paper size for a whole document, so every run is rendered as a document of
+/// its own and the results are joined.  The cover and printed table of contents belong to the
+/// first run.
+#[derive(Clone, Debug, PartialEq)]
+struct PageRun {
+    orientation: Orientation,
+    front_matter: bool,
+    sections: Range<usize>,
+}
+
+/// Builder responsible for turning [`Cover`] and [`Section`] definitions into rendered PDFs.
+pub struct PdfBuilder {
+    font_source: FontSource,
+    fallback_families: Vec<FontFamily<FontData>>,
+    monospace_family: Option<FontFamily<FontData>>,
+    paper_size: Option<Size>,
+    landscape: bool,
+    margins: Option<Margins>,
+    page_background: Option<Color>,
+    show_header: bool,
+    show_footer: bool,
+    header_on_cover: bool,
+    footer_on_cover: bool,
+    header_style: HeaderStyle,
+    footer_style: FooterStyle,
+    footer_format: Option<String>,
+    strict_footer_format: bool,
+    heading_styles: BTreeMap<HeadingLevel, HeadingStyle>,
+    page_labels: Option<PageLabelScheme>,
+    viewer_preferences: Option<ViewerPrefs>,
+    fixed_timestamp: Option<SystemTime>,
+    custom_header: Option<SharedHeaderFactory>,
+    custom_footer: Option<FooterConfig>,
+    custom_cover_footer: Option<FooterConfig>,
+    enable_hyphenation: bool,
+    min_lines_before_break: usize,
+    min_lines_after_break: usize,
+    tagged: bool,
+    cover: Option<Cover>,
+    cover_renderer: Option<CoverRenderer>,
+    sections: Vec<Section>,
+    pending_divider: Option<Divider>,
+    include_toc: bool,
+    toc_title: Option<String>,
+    toc_depth: Option<usize>,
+    toc_leader: TocLeader,
+    default_alignment: HorizontalAlignment,
+    text_direction: TextDirection,
+    render_section_headings: bool,
+    sections_on_new_pages: bool,
+    number_sections: bool,
+    number_figures: bool,
+    caption_template: CaptionTemplate,
+    include_list_of_figures: bool,
+    include_list_of_tables: bool,
+    collect_section_pages: bool,
+    warnings: WarningSink,
+    provisional_stamp: Option<String>,
+    watermark: Option<Watermark>,
+    layout: LayoutTracker,
+    render_mode: RenderMode,
+    #[cfg(feature = "bookmarks")]
+    bookmark_options: bookmarks::BookmarkOptions,
+    #[cfg(any(feature = "pdfa", feature = "encryption"))]
+    finishing: Finishing,
+    /// Font families read from disk by the first document built, so later render passes and
+    /// page runs do not load the font files again.
+    loaded_fonts: RefCell<LoadedFonts>,
+}
+
+/// State of a single render, created by [`PdfBuilder::write_document`] and the other entry
+/// points and shared by the passes and page runs that lay the document out.
+#[derive(Default)]
+struct RenderState {
+    /// Fallback fonts installed into the document of the current render pass.
+    fallback_fonts: RefCell<FallbackFonts>,
+    /// Monospace family installed into the document of the current render pass for code blocks.
+    monospace_font: Cell<Option<FontFamily<Font>>>,
+    /// Set while a [`RenderMode::Fast`] discovery pass lays out image placeholders.
+    placeholder_images: Cell<bool>,
+    /// Page count found by the discovery pass, printed by [`FooterStyle::PageOfTotal`].
+    total_pages: Cell<Option<usize>>,
+    /// Page the first section starts on according to the discovery pass, used by footers that
+    /// print page labels.
+    body_start_page: Cell<Option<usize>>,
+    /// Start page and printed title of every top-level section according to the discovery pass,
+    /// used by [`HeaderStyle::RunningHeading`].
+    running_headings: RefCell<Vec<(usize, String)>>,
+    /// Translucent colors drawn by the render passes, replaced by their real colors afterwards.
+    #[cfg(feature = "transparency")]
+    translucent_colors: TranslucentColors,
+    /// Note texts of the footnote markers numbered so far in the current render pass.
+    footnote_texts: RefCell<Vec<String>>,
+    /// Footnote numbers and texts printed at the bottom of each page, keyed by page number, as
+    /// placed by the previous render pass.
+    footnote_pages: RefCell<BTreeMap<usize, Vec<(usize, String)>>>,
+    /// Number of the last captioned figure numbered in the current render pass.
+    figure_count: Cell<usize>,
+    /// Start pages of the sections with an identifier, found by the discovery pass and used to
+    /// resolve links between sections.
+    anchor_pages: RefCell<BTreeMap<String, usize>>,
+    /// Number of the last captioned table numbered in the current render pass.
+    table_count: Cell<usize>,
+    /// Pages of the numbered figures, keyed by figure number, as placed by the previous render
+    /// pass.
+    figure_pages: RefCell<BTreeMap<usize, usize>>,
+    /// Pages of the numbered tables, keyed by table number, as placed by the previous render pass.
+    table_pages: RefCell<BTreeMap<usize, usize>>,
+    /// Number of keep-together groups pushed in the current render pass.
+    group_count: Cell<usize>,
+    /// Heights of the keep-together groups in push order, as measured by the previous render pass.
+    group_heights: RefCell<Vec<Rc<Cell<Option<Mm>>>>>,
+    /// Images decoded by earlier passes of the current render, so each source is decoded once.
+    decoded_images: RefCell<HashMap<ImageKey, DecodedImage>>,
+    /// Top-level block being pushed, named by image failures.
+    block_location: RefCell<BlockLocation>,
+    /// Depth of the section whose blocks are being pushed, 0 for the cover.
+    section_level: Cell<usize>,
+    /// Location the font family of the latest render pass was loaded from.
+    resolved_font: RefCell<ResolvedFontSource>,
+}
+
+/// Top-level block of the cover or a section.
+#[derive(Clone, Debug, Default)]
+struct BlockLocation {
+    /// Title of the section, or `None` for the cover.
+    section: Option<String>,
+    /// Zero-based position among the blocks of the section or cover.
+    index: usize,
+}
+
+impl BlockLocation {
+    fn new(section: Option<&str>, index: usize) -> Self {
+        Self {
+            section: section.map(str::to_string),
+            index,
+        }
+    }
+}
+
+/// Identifies the source of an image in [`RenderState::decoded_images`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum ImageKey {
+    /// Hash and length of in-memory image bytes.
+    Bytes(u64, usize),
+    Path(String),
+}
+
+impl ImageKey {
+    fn new(source: &ImageSource) -> Self {
+        match source {
+            ImageSource::Bytes(bytes) => {
+                let mut hasher = DefaultHasher::new();
+                bytes.hash(&mut hasher);
+                Self::Bytes(hasher.finish(), bytes.len())
+            }
+            ImageSource::Path(path) => Self::Path(path.clone()),
+        }
+    }
+}
+
+/// Font families cached by [`PdfBuilder::loaded_fonts`].
+#[derive(Default)]
+struct LoadedFonts {
+    /// Document family and the location it was loaded from.
+    document: Option<(FontFamily<FontData>, ResolvedFontSource)>,
+    /// Monospace family found for code blocks when no family was configured.
+    monospace: Option<FontFamily<FontData>>,
+}
+
+/// Entry of [`RenderState::decoded_images`].
+#[derive(Clone, Debug)]
+struct DecodedImage {
+    /// Source the image was decoded from, compared on lookup because byte keys are only hashes.
+    source: ImageSource,
+    image: Arc<image::DynamicImage>,
+}
+
+/// Returns the image decoded from `source`, if `cache` holds it.
+fn cached_image(
+    cache: &HashMap<ImageKey, DecodedImage>,
+    key: &ImageKey,
+    source: &ImageSource,
+) -> Option<Arc<image::DynamicImage>> {
+    cache
+        .get(key)
+        .filter(|entry| entry.source == *source)
+        .map(|entry| Arc::clone(&entry.image))
+}
+
+impl Default for PdfBuilder {
+    fn default() -> Self {
+        Self {
+            font_source: FontSource::Bundled,
+            fallback_families: Vec::new(),
+            monospace_family: None,
+            paper_size: None,
+            landscape: false,
+            margins: None,
+            page_background: None,
+            show_header: false,
+            show_footer: false,
+            header_on_cover: false,
+            footer_on_cover: true,
+            header_style: HeaderStyle::DocumentTitle,
+            heading_styles: BTreeMap::new(),
+            footer_style: FooterStyle::PageNumber,
+            footer_format: None,
+            strict_footer_format: false,
+            page_labels: None,
+            viewer_preferences: None,
+            fixed_timestamp: None,
+            custom_header: None,
+            custom_footer: None,
+            custom_cover_footer: None,
+            enable_hyphenation: false,
+            min_lines_before_break: 1,
+            min_lines_after_break: 1,
+            tagged: false,
+            cover: None,
+            cover_renderer: None,
+            sections: Vec::new(),
+            pending_divider: None,
+            include_toc: false,
+            toc_title: None,
+            toc_depth: None,
+            toc_leader: TocLeader::None,
+            default_alignment: HorizontalAlignment::Left,
+            text_direction: TextDirection::Ltr,
+            render_section_headings: true,
+            sections_on_new_pages: false,
+            number_sections: false,
+            number_figures: false,
+            caption_template: CaptionTemplate::default(),
+            include_list_of_figures: false,
+            include_list_of_tables: false,
+            collect_section_pages: false,
+            warnings: WarningSink::new(),
+            provisional_stamp: None,
+            watermark: None,
+            layout: LayoutTracker::new(),
+            render_mode: RenderMode::Exact,
+            #[cfg(feature = "bookmarks")]
+            bookmark_options: bookmarks::BookmarkOptions::default(),
+            #[cfg(any(feature = "pdfa", feature = "encryption"))]
+            finishing: Finishing::default(),
+            loaded_fonts: RefCell::new(LoadedFonts::default()),
+        }
+    }
+}
+
+impl PdfBuilder {
+    /// Creates a new builder instance with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the paper size used for the generated document.
+    pub fn with_paper_size(mut self, paper_size: impl Into<Size>) -> Self {
+        self.paper_size = Some(paper_size.into());
+        self
+    }
+
+    /// Renders the document with the given font family instead of the bundled Roboto fonts.
+    pub fn with_font_family(self, family: FontFamily<FontData>) -> Self {
+        self.with_font_source(FontSource::Family(family))
+    }
+
+    /// Selects where the font family is loaded from; defaults to [`FontSource::Bundled`].
+    pub fn with_font_source(mut self, source: impl Into<FontSource>) -> Self {
+        self.font_source = source.into();
+        self.loaded_fonts.get_mut().document = None;
+        self
+    }
+
+    /// Adds font families consulted, in order, for characters the main family cannot render.
+    ///
+    /// Paragraphs, list items, legend labels, and captions are split into runs so that, for
+    /// example, Japanese text or emoji inside a Latin paragraph use a family with matching glyphs.
+    pub fn with_fallback_fonts<I>(mut self, families: I) -> Self
+    where
+        I: IntoIterator<Item = FontFamily<FontData>>,
+    {
+        self.fallback_families.extend(families);
+        self
+    }
+
+    /// Prints code blocks in `family` instead of a discovered monospace family.
+    ///
+    /// Without this, code blocks use Roboto Mono from the bundled font directories, or Consolas,
+    /// DejaVu Sans Mono, Liberation Mono, or Courier New from the installed fonts.  When none of
+    /// them is found, code is printed in the document font and a
+    /// [`RenderWarning::FontFallback`] is reported.
+    pub fn with_monospace_font_family(mut self, family: FontFamily<FontData>) -> Self {
+        self.monospace_family = Some(family);
+        self
+    }
+
+    /// Swaps the width and height of the configured paper size, A4 by default.
+    ///
+    /// Sections can still request the other orientation through [`Section::with_orientation`].
+    pub fn landscape(mut self, landscape: bool) -> Self {
+        self.landscape = landscape;
+        self
+    }
+
+    /// Overrides the page margins applied to every page.
+    pub fn with_margins(mut self, margins: impl Into<Margins>) -> Self {
+        self.margins = Some(margins.into());
+        self
+    }
+
+    /// Overrides the page margins with separate top, right, bottom, and left values in millimetres.
+    ///
+    /// The footer is reserved inside the bottom margin's edge, so a 20 mm bottom margin with the
+    /// default 12 mm footer leaves 32 mm below the body.
+    pub fn with_margins_mm(self, top: f64, right: f64, bottom: f64, left: f64) -> Self {
+        self.with_margins(Margins::trbl(
+            mm_from_f64(top),
+            mm_from_f64(right),
+            mm_from_f64(bottom),
+            mm_from_f64(left),
+        ))
+    }
+
+    /// Fills every page, including the cover, with `color` underneath the watermark, header,
+    /// footer, and body content.
+    pub fn with_page_background(mut self, color: Color) -> Self {
+        self.page_background = Some(color);
+        self
+    }
+
+    /// Controls whether the default header is printed.
+    pub fn show_header(mut self, show: bool) -> Self {
+        self.show_header = show;
+        self
+    }
+
+    /// Controls whether the default footer with page numbers is printed.
+    pub fn show_footer(mut self, show: bool) -> Self {
+        self.show_footer = show;
+        self
+    }
+
+    /// Controls whether the header is also printed on the cover page; off by default.
+    ///
+    /// Applies to the default and custom headers and only matters when a cover is set.
+    pub fn header_on_cover(mut self, show: bool) -> Self {
+        self.header_on_cover = show;
+        self
+    }
+
+    /// Controls whether the footer is also printed on the cover page; on by default.
+    ///
+    /// Applies to the default and custom footers and only matters when a cover is set.
+    pub fn footer_on_cover(mut self, show: bool) -> Self {
+        self.footer_on_cover = show;
+        self
+    }
+
+    /// Selects the text of the default header; has no effect on custom headers.
+    pub fn header_style(mut self, style: HeaderStyle) -> Self {
+        self.header_style = style;
+        self
+    }
+
+    /// Selects the text of the default footer; has no effect on custom footers.
+    pub fn footer_style(mut self, style: FooterStyle) -> Self {
+        self.footer_style = style;
+        self
+    }
+
+    /// Replaces the text of the default footer with `format`, such as `"{page} / {total}"`,
+    /// expanded on every page; has no effect on custom footers.
+    ///
+    /// `{page}` and `{total}` print the current page and the total page count, or page labels
+    /// when [`PageLabelScheme::with_footer_labels`] is enabled.  `{section}` prints the numbered
+    /// title of the top-level section the page belongs to, and `{date}` the document date as
+    /// `YYYY-MM-DD` in UTC, taken from [`PdfBuilder::with_fixed_timestamp`] when set.  `{total}`
+    /// and `{section}` run the page-discovery pass like [`FooterStyle::PageOfTotal`].  Other
+    /// tokens are printed literally unless [`PdfBuilder::strict_footer_format`] is enabled.
+    pub fn footer_format(mut self, format: impl Into<String>) -> Self {
+        self.footer_format = Some(format.into());
+        self
+    }
+
+    /// Makes rendering fail with [`PdfBuildError::FooterFormat`] when the footer format holds an
+    /// unknown token instead of printing it literally; off by default.
+    pub fn strict_footer_format(mut self, strict: bool) -> Self {
+        self.strict_footer_format = strict;
+        self
+    }
+
+    /// Overrides the font and spacing of the cover title, the cover subtitle, or the section
+    /// headings at one depth.
+    ///
+    /// `level` is a [`HeadingLevel`] or a section depth, starting at 1 for top-level sections.
+    /// Each depth is configured separately; headings without a style of their own use
+    /// [`HeadingStyle::default_for`].
+    pub fn with_heading_style(
+        mut self,
+        level: impl Into<HeadingLevel>,
+        style: HeadingStyle,
+    ) -> Self {
+        self.heading_styles.insert(level.into(), style);
+        self
+    }
+
+    /// Writes a `/PageLabels` number tree into the rendered document so viewers number the front
+    /// matter and the body separately, e.g. i, ii for the cover and table of contents and 1, 2, 3
+    /// from the first section on.
+    ///
+    /// The labels are added with `lopdf` and require the `page-labels` feature; without it
+    /// rendering fails with [`PdfBuildError::PageLabelsUnavailable`].
+    pub fn with_page_labels(mut self, scheme: PageLabelScheme) -> Self {
+        self.page_labels = Some(scheme);
+        self
+    }
+
+    /// Sets the catalog entries that control how viewers present the document when it is opened,
+    /// such as `/PageMode /UseOutlines` to show the bookmark panel.
+    ///
+    /// The entries are added with `lopdf` and require the `viewer-preferences` feature; without it
+    /// rendering fails with [`PdfBuildError::ViewerPreferencesUnavailable`].  An explicit page mode
+    /// takes precedence over the one implied by the default open level of the bookmarks.
+    pub fn viewer_preferences(mut self, preferences: ViewerPrefs) -> Self {
+        self.viewer_preferences = Some(preferences);
+        self
+    }
+
+    /// Stamps the document with `time` instead of the current time, or with the current time again
+    /// when `None` is passed.
+    ///
+    /// The creation and modification dates of the document information and the XMP metadata are
+    /// set to `time` in UTC, and the file and XMP identifiers are derived from `time` and the page
+    /// content, so rendering the same document twice yields identical bytes.  The dates are
+    /// rewritten with `lopdf` and require the `fixed-timestamp` feature; without it rendering fails
+    /// with [`PdfBuildError::FixedTimestampUnavailable`].
+    pub fn with_fixed_timestamp(mut self, time: impl Into<Option<SystemTime>>) -> Self {
+        self.fixed_timestamp = time.into();
+        self
+    }
+
+    /// Adds a logical structure tree so screen readers can navigate the document.
+    ///
+    /// Headings become `H1` to `H6` elements by outline level, paragraphs and cover text `P`,
+    /// images `Figure` with their alternate text (see [`ImageBlock::with_alt_text`]), and tables,
+    /// lists, code blocks, and the table of contents `Table`, `L`, `Code`, and `TOC`.  Headers,
+    /// footers, and watermarks are marked as artifacts.  The tree is written with `lopdf` and
+    /// requires the `tagged-pdf` feature; without it rendering fails with
+    /// [`PdfBuildError::TaggedPdfUnavailable`].
+    pub fn tagged(mut self, enable: bool) -> Self {
+        self.tagged = enable;
+        self
+    }
+
+    /// Installs a custom header renderer that is invoked for every page.
+    pub fn with_header<F, E>(mut self, header: F) -> Self
+    where
+        F: Fn(usize) -> E + 'static,
+        E: Element + 'static,
+    {
+        self.custom_header = Some(Arc::new(move |page| {
+            BoxedElement::new(Box::new(header(page)))
+        }));
+        self
+    }
+
+    /// Installs a custom footer renderer with an explicit height measured in millimetres.
+    ///
+    /// The renderer is used on the cover and body pages alike; it is shorthand for
+    /// [`with_body_footer`](Self::with_body_footer) and
+    /// [`with_cover_footer`](Self::with_cover_footer) with the same renderer.
+    pub fn with_footer<F, E>(mut self, height_mm: f64, footer: F) -> Self
+    where
+        F: Fn(usize) -> E + 'static,
+        E: Element + 'static,
+    {
+        let config = FooterConfig::new(height_mm, footer);
+        self.custom_cover_footer = Some(config.clone());
+        self.custom_footer = Some(config);
+        self
+    }
+
+    /// Installs a custom footer renderer for the pages after the cover.
+    ///
+    /// Without a cover this footer is printed on every page.  The cover keeps the footer it would
+    /// otherwise get: the one set through [`with_cover_footer`](Self::with_cover_footer), or the
+    /// built-in footer when [`show_footer`](Self::show_footer) is enabled.
+    pub fn with_body_footer<F, E>(mut self, height_mm: f64, footer: F) -> Self
+    where
+        F: Fn(usize) -> E + 'static,
+        E: Element + 'static,
+    {
+        self.custom_footer = Some(FooterConfig::new(height_mm, footer));
+        self
+    }
+
+    /// Installs a custom footer renderer for the cover page only, such as a classification banner.
+    ///
+    /// Has no effect without a cover, and [`footer_on_cover`](Self::footer_on_cover) can still
+    /// leave it off.  Body pages keep their own footer.
+    pub fn with_cover_footer<F, E>(mut self, height_mm: f64, footer: F) -> Self
+    where
+        F: Fn(usize) -> E + 'static,
+        E: Element + 'static,
+    {
+        self.custom_cover_footer = Some(FooterConfig::new(height_mm, footer));
+        self
+    }
+
+    /// Toggles hyphenation support using the embedded dictionary.
+    pub fn enable_hyphenation(mut self, enable: bool) -> Self {
+        self.enable_hyphenation = enable;
+        self
+    }
+
+    /// Keeps at least `lines` lines of a paragraph at the bottom of a page before it breaks;
+    /// paragraphs that would leave fewer start on the next page instead (orphan control).
+    ///
+    /// Defaults to 1, which breaks paragraphs wherever the page ends.  Paragraphs are laid out by
+    /// the crate's own text element while a limit is set, which does not hyphenate words.
+    pub fn min_lines_before_break(mut self, lines: usize) -> Self {
+        self.min_lines_before_break = lines.max(1);
+        self
+    }
+
+    /// Carries at least `lines` lines of a paragraph over to the next page when it breaks, moving
+    /// more lines along as needed (widow control).
+    ///
+    /// Defaults to 1; see [`min_lines_before_break`](Self::min_lines_before_break).
+    pub fn min_lines_after_break(mut self, lines: usize) -> Self {
+        self.min_lines_after_break = lines.max(1);
+        self
+    }
+
+    /// Attaches the cover description that should be rendered as the first page.
+    pub fn with_cover(mut self, cover: impl Into<Option<Cover>>) -> Self {
+        self.cover = cover.into();
+        self
+    }
+
+    /// Replaces the default cover layout with the element returned by `renderer`, for example a
+    /// left-aligned title below a colored banner.
+    ///
+    /// The renderer receives the cover set through [`with_cover`](Self::with_cover) and is called
+    /// once per render pass; without a cover it is never called.  Its element replaces the whole
+    /// default layout, so the logo, identifier, and cover blocks are only printed if the renderer
+    /// adds them itself, and images or tables in the cover blocks are left out of the lists of
+    /// figures and tables.  The cover still ends with a page break and keeps its header and footer
+    /// settings.
+    pub fn with_cover_renderer<F, E>(mut self, renderer: F) -> Self
+    where
+        F: Fn(&Cover) -> E + 'static,
+        E: Element + 'static,
+    {
+        self.cover_renderer = Some(Arc::new(move |cover| {
+            BoxedElement::new(Box::new(renderer(cover)))
+        }));
+        self
+    }
+
+    /// Appends an additional section to the document.
+    ///
+    /// A divider queued with [`PdfBuilder::add_divider`] is attached to the section.
+    pub fn add_section(mut self, mut section: Section) -> Self {
+        if let Some(divider) = self.pending_divider.take() {
+            section = section.with_divider(divider);
+        }
+        self.sections.push(section);
+        self
+    }
+
+    /// Prints a full-page divider, such as "Part II: Operations", before the next section added
+    /// with [`PdfBuilder::add_section`].
+    ///
+    /// This is a shorthand for [`Section::with_divider`]; a divider without a following section is
+    /// not printed.
+    pub fn add_divider(mut self, divider: impl Into<Divider>) -> Self {
+        self.pending_divider = Some(divider.into());
+        self
+    }
+
+    /// Replaces the entire section list.
+    pub fn with_sections<I>(mut self, sections: I) -> Self
+    where
+        I: IntoIterator<Item = Section>,
+    {
+        self.sections = sections.into_iter().collect();
+        self
+    }
+
+    /// Requests a printed table of contents before the sections are rendered.
+    pub fn include_printed_toc(mut self, include: bool) -> Self {
+        self.include_toc = include;
+        self
+    }
+
+    /// Sets the title shown above the generated table of contents.
+    pub fn with_toc_title(mut self, title: impl Into<Option<String>>) -> Self {
+        self.toc_title = title.into();
+        self
+    }
+
+    /// Limits the printed table of contents to headings up to the given nesting level.
+    ///
+    /// A depth of 1 lists top-level sections only, 2 adds their direct subsections, and so on.
+    /// Headings inside a section body count `level` levels below their section, so
+    /// `Block::heading(1, ..)` in a top-level section is listed from depth 2 on, indented like a
+    /// subsection and with the page it was placed on.  `None` (the default) includes every
+    /// subsection and heading.
+    pub fn with_toc_depth(mut self, depth: impl Into<Option<usize>>) -> Self {
+        self.toc_depth = depth.into();
+        self
+    }
+
+    /// Selects the filler printed between table of contents titles and page numbers.
+    pub fn toc_leader(mut self, leader: TocLeader) -> Self {
+        self.toc_leader = leader;
+        self
+    }
+
+    /// Sets the default horizontal alignment applied to paragraphs and images.
+    pub fn with_default_alignment(mut self, alignment: HorizontalAlignment) -> Self {
+        self.default_alignment = alignment;
+        self
+    }
+
+    /// Applies a [`Theme`]: heading styles, default alignment, table of contents title, margins,
+    /// and the built-in header and footer.
+    ///
+    /// Heading styles set earlier are replaced.  Options set after this call override the theme.
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.heading_styles.clear();
+        theme.apply(self)
+    }
+
+    /// Sets the reading direction of paragraphs that do not choose one themselves.
+    ///
+    /// Right-to-left paragraphs are set flush right unless they are centered; justification is
+    /// not supported for them.
+    pub fn with_text_direction(mut self, direction: TextDirection) -> Self {
+        self.text_direction = direction;
+        self
+    }
+
+    /// Controls whether section headings generated from their titles are rendered automatically.
+    pub fn render_section_headings(mut self, enabled: bool) -> Self {
+        self.render_section_headings = enabled;
+        self
+    }
+
+    /// Starts every top-level section on a new page.
+    ///
+    /// The page break is inserted before the section heading, so recorded start pages and table
+    /// of contents entries point at the new page.  A section that is already the first content of
+    /// its page run, such as the first section without a cover or table of contents, does not get
+    /// a leading blank page, and a leading page break from
+    /// [`SectionBuilder::start_on_new_page`](crate::model::SectionBuilder::start_on_new_page) is
+    /// not repeated.
+    pub fn sections_on_new_pages(mut self, enabled: bool) -> Self {
+        self.sections_on_new_pages = enabled;
+        self
+    }
+
+    /// Prefixes section headings and table of contents entries with their outline number.
+    ///
+    /// Top-level sections are numbered "1.", "2.", and so on, and subsections "1.1", "1.2.3".
+    /// Bookmarks and section identifiers are unaffected.
+    pub fn number_sections(mut self, enabled: bool) -> Self {
+        self.number_sections = enabled;
+        self
+    }
+
+    /// Prefixes the captions of images with a running "Figure N: " label, or the format set with
+    /// [`PdfBuilder::with_caption_template`].
+    ///
+    /// Figures are counted across the cover and all sections in document order.  Images without
+    /// a caption and the cover logo are not numbered.
+    pub fn number_figures(mut self, enabled: bool) -> Self {
+        self.number_figures = enabled;
+        self
+    }
+
+    /// Replaces the "Figure N: " and "Table N: " labels of numbered captions with `template`,
+    /// such as a bold "Figure 3." followed by the regular caption text.
+    ///
+    /// The lists of figures and tables label their entries with the same prefix.
+    pub fn with_caption_template(mut self, template: CaptionTemplate) -> Self {
+        self.caption_template = template;
+        self
+    }
+
+    /// Prints a "List of Figures" page after the table of contents.
+    ///
+    /// Every captioned image is listed with its caption and page number, so enabling the list
+    /// also numbers the figures as [`PdfBuilder::number_figures`] does.  Like the table of
+    /// contents, the pages are found by a discovery pass before the final render.
+    pub fn include_list_of_figures(mut self, include: bool) -> Self {
+        self.include_list_of_figures = include;
+        self
+    }
+
+    /// Prints a "List of Tables" page after the table of contents and the list of figures.
+    ///
+    /// Every captioned table is listed with its caption and page number, and the captions are
+    /// prefixed with a running "Table N: " label.
+    pub fn include_list_of_tables(mut self, include: bool) -> Self {
+        self.include_list_of_tables = include;
+        self
+    }
+
+    /// Toggles recording section start pages for the final render output.
+    pub fn collect_section_pages(mut self, collect: bool) -> Self {
+        self.collect_section_pages = collect;
+        self
+    }
+
+    /// Selects how page numbers for the printed table of contents are discovered.
+    pub fn with_render_mode(mut self, mode: RenderMode) -> Self {
+        self.render_mode = mode;
+        self
+    }
+
+    /// Selects the view PDF readers switch to when a bookmark added by
+    /// [`PdfBuilder::render_with_bookmarks`] is activated.
+    ///
+    /// With the `links` feature the rows of the printed table of contents and links to sections
+    /// jump to the same view.
+    #[cfg(feature = "bookmarks")]
+    pub fn with_bookmark_destination(
+        mut self,
+        destination: bookmarks::BookmarkDestination,
+    ) -> Self {
+        self.bookmark_options = self.bookmark_options.with_destination(destination);
+        self
+    }
+
+    /// Shows the bookmarks at outline `level` in a [`BookmarkStyle`](bookmarks::BookmarkStyle),
+    /// such as bold colored entries for the section dividers at level 0; top-level sections are
+    /// level 1.
+    ///
+    /// Levels without a style keep plain entries.
+    #[cfg(feature = "bookmarks")]
+    pub fn with_bookmark_style(mut self, level: usize, style: bookmarks::BookmarkStyle) -> Self {
+        self.bookmark_options = self.bookmark_options.with_style(level, style);
+        self
+    }
+
+    /// Expands the bookmark outline only `level` levels deep when the document is opened.
+    ///
+    /// `1` shows just the top-level entries with their children collapsed, `2` also expands them,
+    /// and so on; the bookmark panel opens alongside the document.  `0` collapses every entry and
+    /// opens the document with the panel closed, which also applies to flat outlines.  By default
+    /// the whole outline is expanded.
+    #[cfg(feature = "bookmarks")]
+    pub fn with_default_open_level(mut self, level: usize) -> Self {
+        self.bookmark_options = self.bookmark_options.with_default_open_level(Some(level));
+        self
+    }
+
+    /// Password-protects the rendered document.
+    ///
+    /// The finished bytes are encrypted with `lopdf` as the last post-processing step, after
+    /// bookmarks and link annotations were added.  Readers need the user password to open the
+    /// document and are limited to the permissions of `options`; the owner password lifts them.
+    #[cfg(feature = "encryption")]
+    pub fn with_encryption(mut self, options: EncryptionOptions) -> Self {
+        self.finishing.encryption = Some(options);
+        self
+    }
+
+    /// Converts the rendered document to the archival PDF/A format at `level`.
+    ///
+    /// The finished bytes are rewritten with `lopdf` after bookmarks and link annotations were
+    /// added, as described for [`pdfa::apply_pdfa`].  PDF/A forbids encryption, so rendering fails
+    /// with [`PdfaError::Encrypted`](pdfa::PdfaError::Encrypted) when
+    /// [`PdfBuilder::with_encryption`] is configured as well.
+    #[cfg(feature = "pdfa")]
+    pub fn pdfa_mode(mut self, level: PdfaLevel) -> Self {
+        self.finishing.pdfa = Some(level);
+        self
+    }
+
+    /// Draws `text` underneath the content of every page, including the cover and table of
+    /// contents.
+    ///
+    /// The watermark takes up no layout space, so it never changes where content lands.
+    pub fn with_watermark(mut self, text: impl Into<String>, options: WatermarkOptions) -> Self {
+        self.watermark = Some(Watermark::new(text).with_options(options));
+        self
+    }
+
+    /// Marks the output as provisional when rendering records any [`RenderWarning`].
+    ///
+    /// If warnings such as clipped content or a font fallback are reported, the document is
+    /// rendered once more with `text` drawn diagonally across every page and the text is reported
+    /// in [`PdfRenderResult::provisional_stamp`].  Clean renders are left untouched.
+    pub fn stamp_if_warnings(mut self, text: impl Into<String>) -> Self {
+        self.provisional_stamp = Some(text.into());
+        self
+    }
+
+    /// Returns a handle to the sink that collects warnings for this builder's renders.
+    ///
+    /// Clone the handle into header or footer closures and attach it to elements such as
+    /// [`FixedRegion`](crate::elements::FixedRegion) so their warnings appear in
+    /// [`PdfRenderResult::warnings`].  Warnings from the page-discovery pass are discarded so
+    /// each issue is only reported once.
+    pub fn warning_sink(&self) -> WarningSink {
+        self.warnings.clone()
+    }
+
+    /// Checks the configured content for problems that would make rendering fail, without
+    /// rendering anything.
+    ///
+    /// Rendering stops at the first failure; this reports every problem found at once: images
+    /// that cannot be decoded or read, footers taller than the page body left by the margins,
+    /// empty cover or section titles, links to unknown sections, and options whose feature is
+    /// disabled.  Problems that only show during layout, such as a header pushing the footer off
+    /// the page, are still reported by the render itself.
+    pub fn validate(&self) -> Result<(), Vec<PdfBuildError>> {
+        let mut errors = Vec::new();
+        #[cfg(not(feature = "page-labels"))]
+        if self.page_labels.is_some() {
+            errors.push(PdfBuildError::PageLabelsUnavailable);
+        }
+        #[cfg(not(feature = "fixed-timestamp"))]
+        if self.fixed_timestamp.is_some() {
+            errors.push(PdfBuildError::FixedTimestampUnavailable);
+        }
+        #[cfg(not(feature = "viewer-preferences"))]
+        if self.viewer_preferences.is_some() {
+            errors.push(PdfBuildError::ViewerPreferencesUnavailable);
+        }
+        #[cfg(not(feature = "tagged-pdf"))]
+        if self.tagged {
+            errors.push(PdfBuildError::TaggedPdfUnavailable);
+        }
+        #[cfg(not(feature = "hyphenation"))]
+        if self.enable_hyphenation {
+            errors.push(PdfBuildError::HyphenationUnavailable { language: "en-US" });
+        }
+        #[cfg(not(feature = "mixed-orientation"))]
+        if self.page_runs().len() > 1 {
+            errors.push(PdfBuildError::MixedOrientationUnavailable);
+        }
+        if let Err(err) = self.check_footer_format() {
+            errors.push(err);
+        }
+
+        if self
+            .cover
+            .as_ref()
+            .is_some_and(|cover| cover.title().trim().is_empty())
+        {
+            errors.push(invalid_content("The cover title is empty"));
+        }
+        let titled = self.render_section_headings || self.include_toc;
+        for (_, section) in flatten_sections(&self.sections) {
+            if titled && section.title().trim().is_empty() {
+                errors.push(invalid_content("A section title is empty"));
+            }
+        }
+        for (location, image) in self.located_images() {
+            // Paths only need to point at a readable image; decoding them is left to the render.
+            let result = match image.source() {
+                ImageSource::Bytes(bytes) => decode_image_from_bytes(bytes).map(drop),
+                ImageSource::Path(path) => image_dimensions_from_path(path).map(drop),
+            };
+            if let Err(cause) = result {
+                errors.push(image_error(image.source(), &location, cause));
+            }
+        }
+
+        let body_height = self
+            .page_runs()
+            .iter()
+            .map(|run| self.body_height(run.orientation))
+            .reduce(|lowest, height| if height < lowest { height } else { lowest })
+            .unwrap_or_default();
+        let footers = [
+            (
+                "footer",
+                self.custom_footer.as_ref().map(|footer| footer.height),
+            ),
+            (
+                "cover footer",
+                self.custom_cover_footer
+                    .as_ref()
+                    .filter(|_| self.cover.is_some())
+                    .map(|footer| footer.height),
+            ),
+        ];
+        for (name, height) in footers {
+            if let Some(height) = height.filter(|height| *height > body_height) {
+                errors.push(invalid_content(format!(
+                    "The {} height exceeds the page body ({:.1} mm needed, {:.1} mm available)",
+                    name,
+                    mm_to_f64(height),
+                    mm_to_f64(body_height)
+                )));
+            }
+        }
+
+        let identifiers: BTreeSet<&str> = flatten_sections(&self.sections)
+            .into_iter()
+            .filter_map(|(_, section)| section.identifier())
+            .collect();
+        let mut missing = BTreeSet::new();
+        self.visit_spans(&mut |span: &Span| {
+            if let Some(identifier) = span.internal_link() {
+                if !identifiers.contains(identifier) {
+                    missing.insert(identifier.to_string());
+                }
+            }
+        });
+        errors.extend(missing.into_iter().map(unknown_link_anchor));
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Renders the configured document and returns both the PDF bytes and section metadata.
+    pub fn render(self) -> Result<PdfRenderResult, PdfBuildError> {
+        let mut bytes = Vec::new();
+        let metadata = self.render_to(&mut bytes)?;
+        Ok(PdfRenderResult::from_parts(bytes, metadata))
+    }
+
+    /// Renders the document into `writer` with the sections of `sections` following the ones added
+    /// to the builder, pulling each section from the iterator only when the layout reaches it.
+    ///
+    /// Sections are only built once the layout reaches them, so a large document, such as a data
+    /// dump streamed from a channel, never has to exist as a list of [`Section`]s.  The rendered
+    /// pages are still kept in memory by the PDF writer until the document is complete, so the
+    /// peak memory grows with the page count.  The document is rendered in a single pass, which
+    /// means everything that needs to know the whole document in advance is unavailable:
+    ///
+    /// * the printed table of contents and the lists of figures and tables are left out,
+    /// * footers print no page total and running headers fall back to the cover title,
+    /// * footnote markers are printed without their notes, and links to sections are plain text,
+    /// * keep-together groups are not moved as a whole, and section orientation overrides and
+    ///   [`PdfBuilder::stamp_if_warnings`] are ignored,
+    /// * translucent colors are blended towards white even with the `transparency` feature,
+    /// * the alternate text of streamed images is only written with [`PdfBuilder::tagged`].
+    ///
+    /// The iterator is owned by the `genpdf` document while it is laid out, so it has to be
+    /// `'static`, but it never leaves the calling thread and need not be `Send`.
+    ///
+    /// The returned metadata lists the pages of every streamed heading.  Post-processing is still
+    /// applied, so the finished output is buffered before it reaches `writer` in the same cases as
+    /// with [`PdfBuilder::render_to`].
+    pub fn render_streaming<I, W>(
+        self,
+        sections: I,
+        writer: W,
+    ) -> Result<RenderMetadata, PdfBuildError>
+    where
+        I: Iterator<Item = Section> + 'static,
+        W: Write,
+    {
+        self.render_with(writer, |builder, output| {
+            builder.write_streamed(sections, output)
+        })
+    }
+
+    /// Renders only the top-level sections in `range`, for example to preview part of a report.
+    ///
+    /// `range` holds zero-based positions in the list of top-level sections, so `1..4` renders the
+    /// second to fourth section including their subsections.  The cover and printed table of
+    /// contents are included as configured.  Page numbers stay 1-based and count the pages of the
+    /// preview, and [`PdfRenderResult::section_start_pages`] lists the selected sections only.
+    ///
+    /// Returns [`PdfBuildError::SectionRange`] when the range is empty or reaches past the last
+    /// section.
+    pub fn render_sections(
+        mut self,
+        range: impl RangeBounds<usize>,
+    ) -> Result<PdfRenderResult, PdfBuildError> {
+        let range = resolve_section_range(&range, self.sections.len())?;
+        self.sections = self.sections.drain(range).collect();
+        self.render()
+    }
+
+    /// Renders the configured document into `writer` and returns the section metadata.
+    ///
+    /// Unlike [`PdfBuilder::render`] the output is not collected into a `Vec` first.  The rendered
+    /// pages themselves are held in memory until the document is complete, so this saves the copy
+    /// of the finished bytes rather than the memory of the layout.  The final output is buffered
+    /// as well whenever it is post-processed: with [`PdfBuilder::stamp_if_warnings`], because
+    /// warnings are only known once the document was rendered; with the `links` feature enabled
+    /// or, under the `transparency` feature, translucent colors, because annotations and alphas
+    /// are applied to the finished bytes; with [`PdfBuilder::tagged`] or, under the `tagged-pdf`
+    /// feature, images with alternate text, whose structure is added to the finished bytes; and
+    /// with page labels, viewer preferences, a fixed timestamp, `PdfBuilder::pdfa_mode`, or
+    /// `PdfBuilder::with_encryption`, which rewrite the document catalog or its objects.  Nothing
+    /// is written if rendering fails before the final pass, but a failure during the final pass may
+    /// leave partial output in `writer`.
+    pub fn render_to<W: Write>(self, writer: W) -> Result<RenderMetadata, PdfBuildError> {
+        self.render_with(writer, |builder, output| builder.write_document(output))
+    }
+
+    /// Checks the configuration, renders the document with `write`, and applies the
+    /// post-processing described for [`PdfBuilder::render_to`] before the output reaches `writer`.
+    fn render_with<W, F>(self, mut writer: W, write: F) -> Result<RenderMetadata, PdfBuildError>
+    where
+        W: Write,
+        F: FnOnce(Self, &mut dyn Write) -> Result<RenderMetadata, PdfBuildError>,
+    {
+        #[cfg(not(feature = "page-labels"))]
+        if self.page_labels.is_some() {
+            return Err(PdfBuildError::PageLabelsUnavailable);
+        }
+        #[cfg(not(feature = "fixed-timestamp"))]
+        if self.fixed_timestamp.is_some() {
+            return Err(PdfBuildError::FixedTimestampUnavailable);
+        }
+        #[cfg(not(feature = "viewer-preferences"))]
+        if self.viewer_preferences.is_some() {
+            return Err(PdfBuildError::ViewerPreferencesUnavailable);
+        }
+        #[cfg(not(feature = "tagged-pdf"))]
+        if self.tagged {
+            return Err(PdfBuildError::TaggedPdfUnavailable);
+        }
+        self.check_footer_format()?;
+        #[allow(unused_mut)]
+        let mut post_processed = self.page_labels.is_some()
+            || self.viewer_preferences.is_some()
+            || self.fixed_timestamp.is_some()
+            || self.tagged;
+        #[cfg(any(feature = "pdfa", feature = "encryption"))]
+        {
+            post_processed |= !self.finishing.is_empty();
+        }
+        // The alternate text of images is written into the page content of the finished bytes.
+        #[cfg(feature = "tagged-pdf")]
+        {
+            post_processed |= self
+                .located_images()
+                .iter()
+                .any(|(_, image)| image.alt_text().is_some());
+        }
+        if !post_processed {
+            return write(self, &mut writer);
+        }
+
+        #[cfg(feature = "page-labels")]
+        let page_labels = self.page_labels;
+        #[cfg(feature = "viewer-preferences")]
+        let viewer_preferences = self.viewer_preferences;
+        #[cfg(feature = "fixed-timestamp")]
+        let fixed_timestamp = self.fixed_timestamp;
+        #[cfg(any(feature = "pdfa", feature = "encryption"))]
+        let finishing = self.finishing.clone();
+        #[allow(unused_mut)]
+        let mut bytes = Vec::new();
+        #[cfg(feature = "tagged-pdf")]
+        let (tagged, layout) = (self.tagged, self.layout.clone());
+        let metadata = write(self, &mut bytes)?;
+        #[cfg(feature = "tagged-pdf")]
+        {
+            let blocks = layout.tagged_blocks();
+            if tagged {
+                bytes =
+                    tagging::apply_structure(&bytes, &blocks).map_err(PdfBuildError::Tagging)?;
+            } else if blocks.iter().any(|block| block.alt_text.is_some()) {
+                bytes = tagging::apply_alt_text(&bytes, &blocks).map_err(PdfBuildError::Tagging)?;
+            }
+        }
+        #[cfg(feature = "page-labels")]
+        if let Some(scheme) = page_labels {
+            let body_start = metadata.section_start_pages.first().copied().flatten();
+            let ranges: Vec<_> = scheme
+                .ranges(body_start)
+                .into_iter()
+                .map(|(start, style)| (start, style.pdf_name()))
+                .collect();
+            bytes =
+                pdf_objects::set_page_labels(&bytes, &ranges).map_err(PdfBuildError::PageLabels)?;
+        }
+        #[cfg(feature = "viewer-preferences")]
+        if let Some(preferences) = viewer_preferences {
+            bytes = pdf_objects::set_viewer_preferences(
+                &bytes,
+                preferences.page_mode.map(PageMode::pdf_name),
+                preferences.page_layout.map(ViewerLayout::pdf_name),
+                preferences.hide_toolbar,
+            )
+            .map_err(PdfBuildError::ViewerPreferences)?;
+        }
+        #[cfg(feature = "fixed-timestamp")]
+        if let Some(time) = fixed_timestamp {
+            bytes = timestamp::fix_timestamp(&bytes, time).map_err(PdfBuildError::Timestamp)?;
+        }
+        #[cfg(any(feature = "pdfa", feature = "encryption"))]
+        {
+            bytes = finishing.apply(bytes)?;
+        }
+        writer.write_all(&bytes).map_err(PdfBuildError::Io)?;
+        writer.flush().map_err(PdfBuildError::Io)?;
+        Ok(metadata)
+    }
+
+    fn write_document<W: Write>(mut self, mut writer: W) -> Result<RenderMetadata, PdfBuildError> {
+        let state = &RenderState::default();
+        #[cfg(feature = "rayon")]
+        self.predecode_images(state)?;
+        let outline_len = flatten_outline(&self.sections).len();
+        let need_toc = self.include_toc && outline_len > 0;
+        let need_lists = !self.caption_lists().is_empty();
+        let need_anchors = self.check_internal_links()?;
+        let need_tracking = self.collect_section_pages || need_toc || self.page_labels.is_some();
+        let default_footer = self.show_footer
+            && (self.custom_footer.is_none()
+                || (self.cover.is_some() && self.custom_cover_footer.is_none()));
+        let footer_tokens = self.footer_tokens();
+        let need_total = default_footer
+            && (footer_tokens.map_or(self.footer_style == FooterStyle::PageOfTotal, |tokens| {
+                tokens.contains(&FooterToken::Total)
+            }) || self
+                .page_labels
+                .is_some_and(|scheme| scheme.labels_footer()));
+        let need_running_headings = (self.show_header
+            && self.custom_header.is_none()
+            && self.header_style == HeaderStyle::RunningHeading)
+            || (default_footer
+                && footer_tokens.is_some_and(|tokens| tokens.contains(&FooterToken::Section)));
+
+        let mut recorded_pages = vec![None; outline_len];
+
+        // The final pass records its own section pages, so a discovery pass is only needed when
+        // page numbers have to be printed in the table of contents or the lists of figures and
+        // tables, links need the pages of their sections, the footer needs the total or page
+        // labels, the header prints running headings, or footnotes have to be assigned to their
+        // pages.
+        if need_toc
+            || need_lists
+            || need_anchors
+            || need_total
+            || need_running_headings
+            || self.uses_footnotes()
+            || self.uses_keep_together()
+        {
+            recorded_pages = self.discover_pages(state)?.pages();
+            state.total_pages.set(Some(self.layout.page()));
+            state
+                .body_start_page
+                .set(recorded_pages.first().copied().flatten());
+        }
+        if need_running_headings {
+            *state.running_headings.borrow_mut() = self
+                .section_headings(&recorded_pages)
+                .into_iter()
+                .filter(|heading| heading.level == 1)
+                .filter_map(|heading| {
+                    let title = numbered_title(heading.number.as_deref(), &heading.title);
+                    Some((heading.page?, title))
+                })
+                .collect();
+        }
+        *state.anchor_pages.borrow_mut() = self
+            .section_headings(&recorded_pages)
+            .into_iter()
+            .filter_map(|heading| Some((heading.identifier?, heading.page?)))
+            .collect();
+
+        let final_tracker = if need_tracking && outline_len > 0 {
+            Some(PageTrackerHandle::new(outline_len))
+        } else {
+            None
+        };
+
+        let toc_pages = if need_toc {
+            Some(recorded_pages.as_slice())
+        } else {
+            None
+        };
+
+        // Translucent colors are applied to the finished bytes, so the output is buffered when the
+        // document draws one.
+        #[cfg(feature = "transparency")]
+        let translucent = self.uses_translucent_colors();
+        #[cfg(feature = "transparency")]
+        let mut final_bytes = Vec::new();
+        #[cfg(feature = "transparency")]
+        let output: &mut dyn Write = if translucent {
+            &mut final_bytes
+        } else {
+            &mut writer
+        };
+        #[cfg(not(feature = "transparency"))]
+        let output: &mut dyn Write = &mut writer;
+
+        let mut provisional_stamp = None;
+        let warnings = if let Some(stamp) = &self.provisional_stamp {
+            let mut buffer = Vec::new();
+            self.render_internal(state, final_tracker.clone(), toc_pages, None, &mut buffer)?;
+            let mut warnings = self.warnings.take();
+            if !warnings.is_empty() {
+                // The watermark is drawn rather than stacked, so the pages recorded by the previous
+                // pass stay valid and only the bytes and warnings need to be replaced.
+                buffer.clear();
+                self.render_internal(state, None, toc_pages, Some(stamp), &mut buffer)?;
+                warnings = self.warnings.take();
+                provisional_stamp = Some(stamp.clone());
+            }
+            output.write_all(&buffer).map_err(PdfBuildError::Io)?;
+            warnings
+        } else {
+            self.render_internal(state, final_tracker.clone(), toc_pages, None, &mut *output)?;
+            self.warnings.take()
+        };
+        #[cfg(feature = "transparency")]
+        if translucent {
+            let colors = state.translucent_colors.colors();
+            if !colors.is_empty() {
+                // When the document may draw a placeholder color itself, the placeholders are told
+                // apart by the operators that change when they are keyed differently.
+                let mut alternate = None;
+                if self.may_draw_placeholder_colors() {
+                    state.translucent_colors.use_alternate_key();
+                    let mut bytes = Vec::new();
+                    let stamp = provisional_stamp.as_deref();
+                    self.render_internal(state, None, toc_pages, stamp, &mut bytes)?;
+                    self.warnings.take();
+                    alternate = Some(bytes);
+                }
+                final_bytes =
+                    transparency::apply_transparency(&final_bytes, alternate.as_deref(), &colors)
+                        .map_err(PdfBuildError::Transparency)?;
+            }
+            writer.write_all(&final_bytes).map_err(PdfBuildError::Io)?;
+        }
+        writer.flush().map_err(PdfBuildError::Io)?;
+
+        let pages = final_tracker
+            .map(|tracker| tracker.pages())
+            .unwrap_or_else(|| vec![None; outline_len]);
+        let headings = self.section_headings(&pages);
+        let section_start_pages = headings
+            .iter()
+            .filter(|heading| heading.level == 1)
+            .map(|heading| heading.page)
+            .collect();
+
+        Ok(RenderMetadata {
+            section_start_pages,
+            headings,
+            warnings,
+            provisional_stamp,
+            font_source: state.resolved_font.take(),
+        })
+    }
+
+    /// Renders the cover and the sections added to the builder, followed by the sections of
+    /// `stream`, in a single pass.
+    fn write_streamed<I, W>(
+        mut self,
+        stream: I,
+        mut writer: W,
+    ) -> Result<RenderMetadata, PdfBuildError>
+    where
+        I: Iterator<Item = Section> + 'static,
+        W: Write,
+    {
+        let state = Rc::new(RenderState::default());
+        #[cfg(feature = "rayon")]
+        self.predecode_images(&state)?;
+        let added = std::mem::take(&mut self.sections);
+        let stream = Box::new(added.into_iter().chain(stream));
+        // The table of contents needs every section up front.
+        self.include_toc = false;
+        // A single pass cannot be repeated to find the placeholders of translucent colors.
+        #[cfg(feature = "transparency")]
+        state.translucent_colors.disable();
+        let builder = Rc::new(self);
+        builder.layout.reset();
+
+        let tracker = PageTrackerHandle::new(0);
+        let orientation = builder.base_orientation();
+        let mut document =
+            builder.prepare_document(&state, Some(tracker.clone()), None, orientation, 0)?;
+        // Without the added sections the front matter is reduced to the cover.
+        let front_matter = PageRun {
+            orientation,
+            front_matter: builder.cover.is_some(),
+            sections: 0..0,
+        };
+        builder.populate_document(&state, &mut document, Some(&tracker), None, &front_matter)?;
+        let outline = Rc::new(RefCell::new(StreamedOutline::default()));
+        document.push(SectionStream {
+            builder: Rc::clone(&builder),
+            state: Rc::clone(&state),
+            sections: stream,
+            tracker: tracker.clone(),
+            outline: Rc::clone(&outline),
+            orientation,
+            break_first: builder.cover.is_some(),
+            position: 0,
+            current: None,
+        });
+
+        #[cfg(feature = "links")]
+        let mut buffer = Vec::new();
+        #[cfg(feature = "links")]
+        let rendered = document.render(&mut buffer);
+        #[cfg(not(feature = "links"))]
+        let rendered = document.render(&mut writer);
+        let mut outline = outline.take();
+        // A section that failed to lay out surfaces as a generic render error from `genpdf`.
+        if let Err(err) = rendered {
+            return Err(outline.error.take().unwrap_or(PdfBuildError::Render(err)));
+        }
+        #[cfg(feature = "links")]
+        {
+            let buffer = links::apply_link_annotations(
+                &buffer,
+                &builder.layout.links(),
+                builder.page_link_destination(),
+            )
+            .map_err(PdfBuildError::Links)?;
+            writer.write_all(&buffer).map_err(PdfBuildError::Io)?;
+        }
+        writer.flush().map_err(PdfBuildError::Io)?;
+
+        let pages = tracker.pages();
+        let mut headings = outline.headings;
+        for (index, heading) in headings.iter_mut().enumerate() {
+            heading.page = pages.get(index).copied().flatten();
+        }
+        let section_start_pages = headings
+            .iter()
+            .filter(|heading| heading.level == 1)
+            .map(|heading| heading.page)
+            .collect();
+
+        Ok(RenderMetadata {
+            section_start_pages,
+            headings,
+            warnings: builder.warnings.take(),
+            provisional_stamp: None,
+            font_source: state.resolved_font.take(),
+        })
+    }
+
+    /// Computes the start page of every top-level section without producing the final PDF.
+    ///
+    /// Only the page-discovery pass of [`PdfBuilder::render`] runs and its output is discarded,
+    /// which makes this suitable for building an index before rendering.  The pages match
+    /// [`PdfRenderResult::section_start_pages`] of a later render with the same configuration.
+    /// Warnings recorded by the pass are discarded.
+    pub fn compute_layout(&self) -> Result<Vec<Option<usize>>, PdfBuildError> {
+        let pages = self.discover_pages(&RenderState::default())?.pages();
+        Ok(self
+            .section_headings(&pages)
+            .into_iter()
+            .filter(|heading| heading.level == 1)
+            .map(|heading| heading.page)
+            .collect())
+    }
+
+    /// Estimates how many pages [`PdfBuilder::render`] will produce without producing the PDF.
+    ///
+    /// Runs the same page-discovery pass as [`compute_layout`](Self::compute_layout) and returns
+    /// the last page it decorated, cover and table of contents included.  Page numbers printed in
+    /// the table of contents are only filled in by the final render, so in rare cases a longer
+    /// number can wrap an entry and add a page.  Warnings recorded by the pass are discarded.
+    pub fn estimate_page_count(&self) -> Result<usize, PdfBuildError> {
+        Ok(self.discover_pages(&RenderState::default())?.current_page())
+    }
+
+    /// Runs tracking passes into a sink and returns the tracker of the last one, which holds the
+    /// page of every section in reading order and the number of pages.
+    ///
+    /// Footnotes take space away from the page their marker lands on, which can push the marker
+    /// onto the next page.  Passes are therefore repeated with the footnotes placed by the previous
+    /// pass until every marker stays on its page, up to [`MAX_FOOTNOTE_PASSES`] times.
+    fn discover_pages(&self, state: &RenderState) -> Result<PageTrackerHandle, PdfBuildError> {
+        state.footnote_pages.borrow_mut().clear();
+        state.figure_pages.borrow_mut().clear();
+        state.table_pages.borrow_mut().clear();
+        let mut placed = Vec::new();
+        let mut tracker = PageTrackerHandle::new(0);
+        for pass in 0..MAX_FOOTNOTE_PASSES {
+            tracker = PageTrackerHandle::new(flatten_outline(&self.sections).len());
+            state
+                .placeholder_images
+                .set(self.render_mode == RenderMode::Fast);
+            let discovery =
+                self.render_internal(state, Some(tracker.clone()), None, None, io::sink());
+            state.placeholder_images.set(false);
+            self.warnings.clear();
+            discovery?;
+            *state.figure_pages.borrow_mut() = self
+                .layout
+                .caption_pages(ElementKind::Image)
+                .into_iter()
+                .collect();
+            *state.table_pages.borrow_mut() = self
+                .layout
+                .caption_pages(ElementKind::Table)
+                .into_iter()
+                .collect();
+
+            let footnotes = self.layout.footnote_pages();
+            // Keep-together groups only know their height after the first pass, so the pages
+            // recorded by it may still move.
+            if footnotes == placed && !(pass == 0 && self.uses_keep_together()) {
+                break;
+            }
+            let texts = state.footnote_texts.borrow();
+            let mut by_page: BTreeMap<usize, Vec<(usize, String)>> = BTreeMap::new();
+            for &(number, page) in &footnotes {
+                if let Some(text) = texts.get(number - 1) {
+                    by_page
+                        .entry(page)
+                        .or_default()
+                        .push((number, text.clone()));
+                }
+            }
+            *state.footnote_pages.borrow_mut() = by_page;
+            placed = footnotes;
+        }
+        Ok(tracker)
+    }
+
+    /// Renders the PDF document and augments it with section bookmarks when the
+    /// `bookmarks` feature is enabled.
+    #[cfg(feature = "bookmarks")]
+    pub fn render_with_bookmarks(mut self) -> Result<PdfRenderResult, PdfBuildError> {
+        let sections = self.sections.clone();
+        let options = self.bookmark_options.clone();
+        #[cfg(any(feature = "pdfa", feature = "encryption"))]
+        let finishing = std::mem::take(&mut self.finishing);
+        self.collect_section_pages = true;
+        let mut result = self.render()?;
+        let pages: Vec<_> = result.headings.iter().map(|heading| heading.page).collect();
+        result.bytes =
+            bookmarks::apply_section_bookmarks(&result.bytes, &sections, &pages, &options)
+                .map_err(PdfBuildError::Bookmarks)?;
+        #[cfg(any(feature = "pdfa", feature = "encryption"))]
+        {
+            result.bytes = finishing.apply(result.bytes)?;
+        }
+        Ok(result)
+    }
+
+    /// Renders the document like [`PdfBuilder::render`] and additionally reports where every
+    /// block was placed.
+    ///
+    /// The returned [`LayoutDump`] lists each page of the final output with the body rectangle and
+    /// the cover text, table of contents, headings, and content blocks rendered on it, which helps
+    /// when diagnosing overflow or alignment problems.
+    pub fn render_with_layout_debug(self) -> Result<(PdfRenderResult, LayoutDump), PdfBuildError> {
+        let layout = self.layout.clone();
+        layout.set_recording(true);
+        let result = self.render()?;
+        Ok((result, layout.dump()))
+    }
+
+    /// Renders the document like [`PdfBuilder::render`] and rasterizes its first page to a PNG
+    /// image at `first_page_dpi`, for example as a cover thumbnail.
+    ///
+    /// Requires the `preview` feature and a Pdfium library at runtime (see [`preview`]).
+    /// Encrypted output is opened with its user password.
+    #[cfg(feature = "preview")]
+    pub fn render_with_preview(
+        self,
+        first_page_dpi: f32,
+    ) -> Result<(PdfRenderResult, Vec<u8>), PdfBuildError> {
+        #[cfg(feature = "encryption")]
+        let password = self
+            .finishing
+            .encryption
+            .as_ref()
+            .map(|options| options.user_password().to_string());
+        #[cfg(not(feature = "encryption"))]
+        let password: Option<String> = None;
+        let result = self.render()?;
+        let png = preview::first_page_png(&result.bytes, first_page_dpi, password.as_deref())
+            .map_err(PdfBuildError::Preview)?;
+        Ok((result, png))
+    }
+
+    /// Builds and renders a document on Tokio's blocking thread pool, for use from async code such
+    /// as a web handler.
+    ///
+    /// Rendering is CPU-bound, so `factory` and [`render`](Self::render) run through
+    /// [`tokio::task::spawn_blocking`] instead of stalling the runtime's worker threads.  Only the
+    /// factory crosses threads: the builder is created on the blocking thread, so its header,
+    /// footer, and block closures may still capture `Rc` or `RefCell`.  Must be awaited within a
+    /// Tokio runtime.  A panic during rendering is resumed in the awaiting task.  Requires the
+    /// `async` feature.
+    #[cfg(feature = "async")]
+    pub fn render_async<F>(
+        factory: F,
+    ) -> impl std::future::Future<Output = Result<PdfRenderResult, PdfBuildError>> + Send
+    where
+        F: FnOnce() -> PdfBuilder + Send + 'static,
+    {
+        let task = tokio::task::spawn_blocking(move || factory().render());
+        async move {
+            match task.await {
+                Ok(result) => result,
+                Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+                Err(err) => Err(PdfBuildError::RenderTask(err)),
+            }
+        }
+    }
+
+    fn render_internal<W: Write>(
+        &self,
+        state: &RenderState,
+        tracker: Option<PageTrackerHandle>,
+        toc_pages: Option<&[Option<usize>]>,
+        stamp: Option<&str>,
+        mut writer: W,
+    ) -> Result<(), PdfBuildError> {
+        self.layout.reset();
+        state.footnote_texts.borrow_mut().clear();
+        state.figure_count.set(0);
+        state.table_count.set(0);
+        state.group_count.set(0);
+        let runs = self.page_runs();
+        let [run] = runs.as_slice() else {
+            #[cfg(feature = "mixed-orientation")]
+            {
+                let bytes = self.render_runs(state, &runs, tracker, toc_pages, stamp)?;
+                #[cfg(feature = "links")]
+                let bytes = links::apply_link_annotations(
+                    &bytes,
+                    &self.layout.links(),
+                    self.page_link_destination(),
+                )
+                .map_err(PdfBuildError::Links)?;
+                return writer.write_all(&bytes).map_err(PdfBuildError::Io);
+            }
+            #[cfg(not(feature = "mixed-orientation"))]
+            return Err(PdfBuildError::MixedOrientationUnavailable);
+        };
+
+        let mut document =
+            self.prepare_document(state, tracker.clone(), stamp, run.orientation, 0)?;
+        self.populate_document(state, &mut document, tracker.as_ref(), toc_pages, run)?;
+
+        #[cfg(feature = "links")]
+        {
+            let mut buffer = Vec::new();
+            document
+                .render(&mut buffer)
+                .map_err(PdfBuildError::Render)?;
+            let buffer = links::apply_link_annotations(
+                &buffer,
+                &self.layout.links(),
+                self.page_link_destination(),
+            )
+            .map_err(PdfBuildError::Links)?;
+            writer.write_all(&buffer).map_err(PdfBuildError::Io)?;
+        }
+        #[cfg(not(feature = "links"))]
+        {
+            document
+                .render(&mut writer)
+                .map_err(PdfBuildError::Render)?;
+        }
+
+        Ok(())
+    }
+
+    /// Renders every page run as a separate document and joins them in reading order.
+    #[cfg(feature = "mixed-orientation")]
+    fn render_runs(
+        &self,
+        state: &RenderState,
+        runs: &[PageRun],
+        tracker: Option<PageTrackerHandle>,
+        toc_pages: Option<&[Option<usize>]>,
+        stamp: Option<&str>,
+    ) -> Result<Vec<u8>, PdfBuildError> {
+        let mut merged: Option<lopdf::Document> = None;
+        for run in runs {
+            // The layout tracker keeps the number of the last decorated page across runs.
+            let offset = self.layout.page();
+            let mut document =
+                self.prepare_document(state, tracker.clone(), stamp, run.orientation, offset)?;
+            self.populate_document(state, &mut document, tracker.as_ref(), toc_pages, run)?;
+            let mut bytes = Vec::new();
+            document.render(&mut bytes).map_err(PdfBuildError::Render)?;
+            let part = lopdf::Document::load_mem(&bytes).map_err(PdfBuildError::Merge)?;
+            match merged.as_mut() {
+                Some(base) => {
+                    pdf_objects::append_document(base, part).map_err(PdfBuildError::Merge)?;
+                }
+                None => merged = Some(part),
+            }
+        }
+
+        let mut bytes = Vec::new();
+        if let Some(mut document) = merged {
+            document.save_to(&mut bytes).map_err(PdfBuildError::Io)?;
+        }
+        Ok(bytes)
+    }
+
+    /// Returns the paper size for pages in `orientation`.
+    fn page_size(&self, orientation: Orientation) -> Size {
+        let size = self.paper_size.unwrap_or_else(|| PaperSize::A4.into());
+        if orientation_of(size) == orientation {
+            size
+        } else {
+            Size::new(size.height, size.width)
+        }
+    }
+
+    /// Height of the page area inside the margins for pages in `orientation`.
+    fn body_height(&self, orientation: Orientation) -> Mm {
+        let height = self.page_size(orientation).height;
+        match self.margins {
+            Some(margins) => height - margins.top - margins.bottom,
+            None => height,
+        }
+    }
+
+    /// Orientation of the configured paper size after applying [`PdfBuilder::landscape`].
+    fn base_orientation(&self) -> Orientation {
+        let size = self.paper_size.unwrap_or_else(|| PaperSize::A4.into());
+        match (orientation_of(size), self.landscape) {
+            (orientation, false) => orientation,
+            (Orientation::Portrait, true) => Orientation::Landscape,
+            (Orientation::Landscape, true) => Orientation::Portrait,
+        }
+    }
+
+    /// Splits the top-level sections into runs of pages sharing an orientation.
+    fn page_runs(&self) -> Vec<PageRun> {
+        let front_matter = self.cover.is_some()
+            || (self.include_toc && !self.sections.is_empty())
+            || !self.caption_lists().is_empty();
+        let mut runs = Vec::new();
+        let mut current = PageRun {
+            orientation: self.base_orientation(),
+            front_matter,
+            sections: 0..0,
+        };
+
+        for (index, section) in self.sections.iter().enumerate() {
+            let orientation = section.orientation().unwrap_or(current.orientation);
+            if orientation != current.orientation {
+                if current.front_matter || !current.sections.is_empty() {
+                    runs.push(current);
+                }
+                current = PageRun {
+                    orientation,
+                    front_matter: false,
+                    sections: index..index,
+                };
+            }
+            current.sections.end = index + 1;
+        }
+
+        runs.push(current);
+        runs
+    }
+
+    fn prepare_document(
+        &self,
+        state: &RenderState,
+        tracker: Option<PageTrackerHandle>,
+        stamp: Option<&str>,
+        orientation: Orientation,
+        page_offset: usize,
+    ) -> Result<genpdf::Document, PdfBuildError> {
+        let (font_family, font_source) = self.document_font_family()?;
+        let mut builder = DocumentBuilder::new()
+            .with_loaded_font(font_family, font_source)
+            .with_paper_size(self.page_size(orientation))
+            .with_page_offset(page_offset)
+            .with_warnings(self.warnings.clone())
+            .with_layout_tracker(self.layout.clone());
+        if let Some(margins) = self.margins {
+            builder = builder.with_margins(margins);
+        }
+        if let Some(color) = self.page_background {
+            builder = builder.with_page_background(color);
+        }
+        if self.cover.is_some() && page_offset == 0 {
+            builder = builder
+                .skip_first_page_header(!self.header_on_cover)
+                .skip_first_page_footer(!self.footer_on_cover);
+        }
+        if let Some(tracker) = tracker.clone() {
+            builder = builder.with_page_tracker(tracker);
+        }
+        if let Some(watermark) = &self.watermark {
+            builder = builder.with_watermark(self.translucent_watermark(state, watermark));
+        }
+        if let Some(stamp) = stamp {
+            builder = builder.with_watermark(Watermark::new(stamp));
+        }
+        let footnotes: BTreeMap<_, _> = state
+            .footnote_pages
+            .borrow()
+            .iter()
+            .map(|(&page, notes)| {
+                let notes = notes
+                    .iter()
+                    .map(|(number, text)| footnote_spans(*number, text))
+                    .collect();
+                (page, notes)
+            })
+            .collect();
+        if !footnotes.is_empty() {
+            builder = builder.with_footnotes(footnotes);
+        }
+
+        #[cfg(feature = "hyphenation")]
+        {
+            builder = self.apply_hyphenation(builder)?;
+        }
+        #[cfg(not(feature = "hyphenation"))]
+        {
+            if self.enable_hyphenation {
+                return Err(PdfBuildError::HyphenationUnavailable { language: "en-US" });
+            }
+        }
+
+        if let Some(header_cb) = &self.custom_header {
+            let header_cb = Arc::clone(header_cb);
+            builder = builder.with_header(move |page| header_cb(page));
+        } else if self.show_header && self.header_style == HeaderStyle::RunningHeading {
+            let fallback = self.cover.as_ref().map(|cover| cover.title().to_string());
+            let headings = state.running_headings.borrow().clone();
+            builder = builder.with_header(move |page| {
+                let title = active_heading(&headings, page).or(fallback.as_deref());
+                RunningHeader::new(title.map(str::to_string))
+            });
+        } else if self.show_header {
+            if let Some(title) = self.cover.as_ref().map(|cover| cover.title().to_string()) {
+                let header_text = title.clone();
+                builder = builder.with_header(move |_| {
+                    let mut paragraph = Paragraph::new(header_text.clone());
+                    paragraph.set_alignment(Alignment::Center);
+                    paragraph
+                });
+            }
+        }
+
+        if let Some(footer) = &self.custom_footer {
+            let footer_cb = Arc::clone(&footer.factory);
+            builder = builder.with_footer(footer.height, move |page| footer_cb(page));
+        } else if self.show_footer {
+            builder = builder.with_footer(mm_from_f64(12.0), self.page_number_footer(state));
+        }
+        if self.cover.is_some() && page_offset == 0 {
+            if let Some(footer) = &self.custom_cover_footer {
+                let footer_cb = Arc::clone(&footer.factory);
+                builder = builder.with_cover_footer(footer.height, move |page| footer_cb(page));
+            } else if self.show_footer {
+                builder =
+                    builder.with_cover_footer(mm_from_f64(12.0), self.page_number_footer(state));
+            } else {
+                builder = builder.skip_first_page_footer(true);
+            }
+        }
+
+        let (mut document, font_source) = builder
+            .build_with_font_source()
+            .map_err(PdfBuildError::FontLoad)?;
+        *state.resolved_font.borrow_mut() = font_source;
+        *state.fallback_fonts.borrow_mut() = if self.fallback_families.is_empty() {
+            FallbackFonts::default()
+        } else {
+            // `genpdf` takes ownership of the families it embeds, so every document gets its own
+            // copy of the loaded font data.
+            fonts::install_fallback_fonts(&mut document, self.fallback_families.iter().cloned())
+        };
+        let monospace = if self.uses_code_blocks() {
+            self.install_monospace_font(&mut document)
+        } else {
+            None
+        };
+        state.monospace_font.set(monospace);
+        Ok(document)
+    }
+
+    /// Returns the renderer of the built-in footer enabled through [`PdfBuilder::show_footer`].
+    fn page_number_footer(&self, state: &RenderState) -> impl Fn(usize) -> Paragraph + 'static {
+        let style = self.footer_style;
+        let tokens = self.footer_tokens();
+        let total = state.total_pages.get();
+        let labels = self
+            .page_labels
+            .filter(PageLabelScheme::labels_footer)
+            .map(|scheme| (scheme, state.body_start_page.get()));
+        let headings = state.running_headings.borrow().clone();
+        let (year, month, day, ..) =
+            utc_fields(self.fixed_timestamp.unwrap_or_else(SystemTime::now));
+        let date = format!("{year:04}-{month:02}-{day:02}");
+        move |page| {
+            let (label, last) = match labels {
+                Some((scheme, body_start)) => scheme.labels(page, body_start, total),
+                None => (page.to_string(), total.map(|total| total.to_string())),
+            };
+            let text = match &tokens {
+                Some(tokens) => expand_footer_format(
+                    tokens,
+                    &label,
+                    last.as_deref(),
+                    active_heading(&headings, page),
+                    &date,
+                ),
+                None => style.labelled_text(&label, last.as_deref()),
+            };
+            let mut paragraph = Paragraph::new(text);
+            paragraph.set_alignment(Alignment::Right);
+            paragraph
+        }
+    }
+
+    /// Parsed tokens of the format set through [`PdfBuilder::footer_format`], if any.
+    fn footer_tokens(&self) -> Option<Vec<FooterToken>> {
+        self.footer_format.as_deref().map(parse_footer_format)
+    }
+
+    /// Fails with [`PdfBuildError::FooterFormat`] for the first unknown token of a strict footer
+    /// format.
+    fn check_footer_format(&self) -> Result<(), PdfBuildError> {
+        if !self.strict_footer_format {
+            return Ok(());
+        }
+        let unknown = self
+            .footer_tokens()
+            .into_iter()
+            .flatten()
+            .find_map(|token| match token {
+                FooterToken::Unknown(token) => Some(token),
+                _ => None,
+            });
+        match unknown {
+            Some(token) => Err(PdfBuildError::FooterFormat { token }),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns `true` when the cover or any section holds a code block, including inside quotes
+    /// and rows.
+    fn uses_code_blocks(&self) -> bool {
+        fn contains_code(blocks: &[Block]) -> bool {
+            blocks.iter().any(|block| match block {
+                Block::Code(_) => true,
+                Block::Quote(quote) => contains_code(quote.blocks()),
+                Block::KeepTogether(blocks) => contains_code(blocks),
+                Block::Row(columns) => columns.iter().any(|column| contains_code(column.blocks())),
+                _ => false,
+            })
+        }
+
+        contains_code(self.cover_blocks())
+            || flatten_sections(&self.sections)
+                .iter()
+                .any(|(_, section)| contains_code(section.blocks()))
+    }
+
+    /// Returns `true` when the cover or any section holds a keep-together group, including inside
+    /// quotes and rows.
+    fn uses_keep_together(&self) -> bool {
+        fn contains_group(blocks: &[Block]) -> bool {
+            blocks.iter().any(|block| match block {
+                Block::KeepTogether(_) => true,
+                Block::Quote(quote) => contains_group(quote.blocks()),
+                Block::Row(columns) => columns.iter().any(|column| contains_group(column.blocks())),
+                _ => false,
+            })
+        }
+
+        contains_group(self.cover_blocks())
+            || flatten_sections(&self.sections)
+                .iter()
+                .any(|(_, section)| contains_group(section.blocks()))
+    }
+
+    /// Calls `visit` for every span of the paragraphs, headings, lists, quotes, tables, and rows of
+    /// the cover and all sections in reading order.
+    fn visit_spans(&self, visit: &mut dyn FnMut(&Span)) {
+        fn visit_list(list: &ListBlock, visit: &mut dyn FnMut(&Span)) {
+            for item in list.items() {
+                item.content().spans().iter().for_each(&mut *visit);
+                if let Some(sublist) = item.sublist() {
+                    visit_list(sublist, visit);
+                }
+            }
+        }
+        fn visit_blocks(blocks: &[Block], visit: &mut dyn FnMut(&Span)) {
+            for block in blocks {
+                match block {
+                    Block::Paragraph(paragraph) | Block::Heading { paragraph, .. } => {
+                        paragraph.spans().iter().for_each(&mut *visit)
+                    }
+                    Block::List(list) => visit_list(list, visit),
+                    Block::Table(table) => {
+                        let cells = table.rows().iter().flatten().map(TableCell::content);
+                        for paragraph in table.headers().iter().chain(cells) {
+                            paragraph.spans().iter().for_each(&mut *visit);
+                        }
+                    }
+                    Block::Quote(quote) => visit_blocks(quote.blocks(), visit),
+                    Block::KeepTogether(blocks) => visit_blocks(blocks, visit),
+                    Block::Row(columns) => {
+                        for column in columns {
+                            visit_blocks(column.blocks(), visit);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        visit_blocks(self.cover_blocks(), visit);
+        for (_, section) in flatten_sections(&self.sections) {
+            visit_blocks(section.blocks(), visit);
+        }
+    }
+
+    /// Returns `true` when the cover or any section holds a footnote marker in one of the blocks
+    /// visited by [`PdfBuilder::visit_spans`].
+    fn uses_footnotes(&self) -> bool {
+        let mut found = false;
+        self.visit_spans(&mut |span: &Span| found |= span.footnote_text().is_some());
+        found
+    }
+
+    /// Calls `visit` with every color the cover, sections, configured headings and captions, page
+    /// background, and watermark draw, together with its alpha.
+    #[cfg(feature = "transparency")]
+    fn visit_colors(&self, visit: &mut dyn FnMut(Color, u8)) {
+        fn visit_paragraph(paragraph: &RichParagraph, visit: &mut dyn FnMut(Color, u8)) {
+            if let Some(color) = paragraph.background() {
+                visit(color, paragraph.background_alpha());
+            }
+            visit_spans(paragraph.spans(), visit);
+        }
+        fn visit_spans(spans: &[Span], visit: &mut dyn FnMut(Color, u8)) {
+            for span in spans {
+                if let Some(color) = span.color() {
+                    visit(color, span.alpha());
+                }
+            }
+        }
+        fn visit_image(image: &ImageBlock, visit: &mut dyn FnMut(Color, u8)) {
+            if let Some(border) = image.border() {
+                visit(border.color(), u8::MAX);
+            }
+            if let Some(caption) = image.caption() {
+                visit_paragraph(caption, visit);
+            }
+        }
+        fn visit_list(list: &ListBlock, visit: &mut dyn FnMut(Color, u8)) {
+            for item in list.items() {
+                visit_paragraph(item.content(), visit);
+                if let Some(sublist) = item.sublist() {
+                    visit_list(sublist, visit);
+                }
+            }
+        }
+        fn visit_table(table: &TableBlock, visit: &mut dyn FnMut(Color, u8)) {
+            let borders = table.borders();
+            let lines = [
+                borders.top(),
+                borders.bottom(),
+                borders.sides(),
+                borders.header(),
+                borders.rows(),
+                borders.columns(),
+            ];
+            for line in lines.into_iter().flatten() {
+                visit(line.color(), u8::MAX);
+            }
+            if let Some(zebra) = table.zebra() {
+                visit(zebra, u8::MAX);
+            }
+            for cell in table.rows().iter().flatten() {
+                if let Some(fill) = cell.fill() {
+                    visit(fill, u8::MAX);
+                }
+                visit_paragraph(cell.content(), visit);
+            }
+            for paragraph in table.headers().iter().chain(table.caption()) {
+                visit_paragraph(paragraph, visit);
+            }
+        }
+        fn visit_blocks(blocks: &[Block], visit: &mut dyn FnMut(Color, u8)) {
+            for block in blocks {
+                match block {
+                    Block::Paragraph(paragraph) | Block::Heading { paragraph, .. } => {
+                        visit_paragraph(paragraph, visit)
+                    }
+                    Block::Image(image) => visit_image(image, visit),
+                    Block::List(list) => visit_list(list, visit),
+                    Block::Legend(legend) => {
+                        for entry in legend.entries() {
+                            visit(entry.color(), u8::MAX);
+                            visit_spans(entry.label(), visit);
+                        }
+                    }
+                    Block::Table(table) => visit_table(table, visit),
+                    Block::Quote(quote) => {
+                        visit(quote.bar_color(), u8::MAX);
+                        if let Some(background) = quote.background_color() {
+                            visit(background, u8::MAX);
+                        }
+                        visit_blocks(quote.blocks(), visit);
+                    }
+                    Block::HorizontalRule(rule) => {
+                        if let Some(color) = rule.color() {
+                            visit(color, u8::MAX);
+                        }
+                    }
+                    Block::Code(code) => visit(code.background_color(), u8::MAX),
+                    Block::Row(columns) => {
+                        for column in columns {
+                            visit_blocks(column.blocks(), visit);
+                        }
+                    }
+                    Block::KeepTogether(blocks) => visit_blocks(blocks, visit),
+                    Block::Spacer(_) | Block::Custom(_) | Block::PageBreak => {}
+                }
+            }
+        }
+
+        if let Some(logo) = self.cover.as_ref().and_then(Cover::logo) {
+            if self.cover_renderer.is_none() {
+                visit_image(logo, visit);
+            }
+        }
+        visit_blocks(self.cover_blocks(), visit);
+        for (_, section) in flatten_sections(&self.sections) {
+            visit_blocks(section.blocks(), visit);
+        }
+        let styles = self.heading_styles.values().filter_map(HeadingStyle::color);
+        for color in styles.chain(self.caption_template.color()) {
+            visit(color, u8::MAX);
+        }
+        if let Some(color) = self.page_background {
+            visit(color, u8::MAX);
+        }
+        if let Some(watermark) = &self.watermark {
+            let options = watermark.options();
+            visit(options.color(), (options.opacity() * 255.0).round() as u8);
+        }
+    }
+
+    /// Returns `true` when the document draws a translucent color, which the `transparency`
+    /// feature applies to the finished bytes.
+    #[cfg(feature = "transparency")]
+    fn uses_translucent_colors(&self) -> bool {
+        let mut found = false;
+        self.visit_colors(&mut |_, alpha| found |= alpha < u8::MAX);
+        found
+    }
+
+    /// Returns `true` when the document may draw a color that looks like a translucent color
+    /// placeholder, so the placeholders have to be told apart by an alternate render.
+    ///
+    /// Custom elements and renderers are opaque, so they are assumed to draw one.
+    #[cfg(feature = "transparency")]
+    fn may_draw_placeholder_colors(&self) -> bool {
+        fn contains_custom(blocks: &[Block]) -> bool {
+            blocks.iter().any(|block| match block {
+                Block::Custom(_) => true,
+                Block::Quote(quote) => contains_custom(quote.blocks()),
+                Block::KeepTogether(blocks) => contains_custom(blocks),
+                Block::Row(columns) => columns
+                    .iter()
+                    .any(|column| contains_custom(column.blocks())),
+                _ => false,
+            })
+        }
+
+        let mut found = self.custom_header.is_some()
+            || self.custom_footer.is_some()
+            || self.custom_cover_footer.is_some()
+            || self.cover_renderer.is_some()
+            || contains_custom(self.cover_blocks())
+            || flatten_sections(&self.sections)
+                .iter()
+                .any(|(_, section)| contains_custom(section.blocks()));
+        self.visit_colors(&mut |color, _| found |= transparency::matches_placeholder_key(color));
+        found
+    }
+
+    /// Returns the view links to pages open, the one configured for bookmarks when the
+    /// `bookmarks` feature is enabled.
+    #[cfg(feature = "links")]
+    fn page_link_destination(&self) -> links::BookmarkDestination {
+        #[cfg(feature = "bookmarks")]
+        {
+            self.bookmark_options.destination()
+        }
+        #[cfg(not(feature = "bookmarks"))]
+        {
+            links::BookmarkDestination::Fit
+        }
+    }
+
+    /// Checks that every section identifier targeted by a span exists and returns whether any
+    /// span links to a section.
+    fn check_internal_links(&self) -> Result<bool, PdfBuildError> {
+        let identifiers: BTreeSet<&str> = flatten_sections(&self.sections)
+            .into_iter()
+            .filter_map(|(_, section)| section.identifier())
+            .collect();
+        let mut linked = false;
+        let mut missing = None;
+        self.visit_spans(&mut |span: &Span| {
+            if let Some(identifier) = span.internal_link() {
+                linked = true;
+                if missing.is_none() && !identifiers.contains(identifier) {
+                    missing = Some(identifier.to_string());
+                }
+            }
+        });
+        match missing {
+            Some(identifier) => Err(unknown_link_anchor(identifier)),
+            None => Ok(linked),
+        }
+    }
+
+    /// Returns the document font family and where it was found, loading it on first use.
+    fn document_font_family(
+        &self,
+    ) -> Result<(FontFamily<FontData>, ResolvedFontSource), PdfBuildError> {
+        let mut loaded = self.loaded_fonts.borrow_mut();
+        if let Some(document) = &loaded.document {
+            return Ok(document.clone());
+        }
+        let document = self.font_source.load().map_err(PdfBuildError::FontLoad)?;
+        loaded.document = Some(document.clone());
+        Ok(document)
+    }
+
+    /// Adds the monospace family for code blocks to `document`, or reports a warning and returns
+    /// `None` when no monospace family is available.
+    fn install_monospace_font(&self, document: &mut genpdf::Document) -> Option<FontFamily<Font>> {
+        let family = match &self.monospace_family {
+            Some(family) => family.clone(),
+            None => match self.system_monospace_family() {
+                Ok(family) => family,
+                Err(_) => {
+                    self.warnings.push(RenderWarning::FontFallback {
+                        requested: fonts::MONOSPACE_FONT_FAMILY_NAME.to_string(),
+                        substitute: "document font".to_string(),
+                    });
+                    return None;
+                }
+            },
+        };
+        Some(document.add_font_family(family))
+    }
+
+    /// Looks up the monospace family for code blocks, reusing the family found by an earlier
+    /// document of the same builder.
+    fn system_monospace_family(&self) -> Result<FontFamily<FontData>, Error> {
+        let mut loaded = self.loaded_fonts.borrow_mut();
+        if let Some(family) = &loaded.monospace {
+            return Ok(family.clone());
+        }
+        let (family, _) = fonts::monospace_font_family()?;
+        loaded.monospace = Some(family.clone());
+        Ok(family)
+    }
+
+    #[cfg(feature = "hyphenation")]
+    fn apply_hyphenation(
+        &self,
+        builder: DocumentBuilder,
+    ) -> Result<DocumentBuilder, PdfBuildError> {
+        if self.enable_hyphenation {
+            let hyphenator =
+                HyphenationStandard::from_embedded(Language::EnglishUS).map_err(|source| {
+                    PdfBuildError::HyphenationLoad {
+                        language: "en-US",
+                        source,
+                    }
+                })?;
+            Ok(builder.with_hyphenator(hyphenator))
+        } else {
+            Ok(builder)
+        }
+    }
+
+    fn populate_document(
+        &self,
+        state: &RenderState,
+        document: &mut genpdf::Document,
+        tracker: Option<&PageTrackerHandle>,
+        toc_pages: Option<&[Option<usize>]>,
+        run: &PageRun,
+    ) -> Result<(), PdfBuildError> {
+        if run.front_matter {
+            let lists = self.caption_lists();
+            if let Some(cover) = &self.cover {
+                self.push_cover(state, document, cover)?;
+                if self.include_toc || !lists.is_empty() || !run.sections.is_empty() {
+                    document.push(PageBreak::new());
+                }
+            }
+
+            if self.include_toc && !self.sections.is_empty() {
+                self.push_toc(document, toc_pages)?;
+                if !lists.is_empty() || !run.sections.is_empty() {
+                    document.push(PageBreak::new());
+                }
+            }
+
+            for (index, list) in lists.iter().enumerate() {
+                self.push_caption_list(state, document, list);
+                if index + 1 < lists.len() || !run.sections.is_empty() {
+                    document.push(PageBreak::new());
+                }
+            }
+        }
+
+        let numbers = self.section_numbers();
+        let mut next_index = flatten_outline(&self.sections[..run.sections.start]).len();
+        for (position, section) in self.sections[run.sections.clone()].iter().enumerate() {
+            // The first section of a run already starts on a fresh page, either after the front
+            // matter or at the top of the run's document.
+            if (self.sections_on_new_pages || section.divider().is_some()) && position > 0 {
+                document.push(PageBreak::new());
+            }
+            if let Some(divider) = section.divider() {
+                self.push_divider(
+                    state,
+                    document,
+                    divider,
+                    run.orientation,
+                    &mut next_index,
+                    tracker,
+                );
+                document.push(PageBreak::new());
+            }
+            self.push_section(
+                state,
+                document,
+                section,
+                1,
+                &mut next_index,
+                &numbers,
+                tracker,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints the title and subtitle of `divider` centered on the current page.
+    fn push_divider<S: ElementSink>(
+        &self,
+        state: &RenderState,
+        document: &mut S,
+        divider: &Divider,
+        orientation: Orientation,
+        next_index: &mut usize,
+        tracker: Option<&PageTrackerHandle>,
+    ) {
+        outline_entry(document, next_index, &[], tracker);
+        state.section_level.set(0);
+        // Starting a third of the way down leaves the title block close to the optical center.
+        let offset = mm_to_f64(self.body_height(orientation)) / 3.0;
+        document.push_element(Spacer::new(mm_from_f64(offset)));
+
+        let title_style = self.heading_style(HeadingLevel::CoverTitle);
+        let mut title = Paragraph::new(divider.title());
+        title.set_alignment(Alignment::Center);
+        self.push_described(
+            document,
+            ElementKind::Heading,
+            title.styled(title_style.text_style()),
+            1,
+            None,
+        );
+        push_space(document, title_style.space_after());
+
+        if let Some(subtitle) = divider.subtitle() {
+            let subtitle_style = self.heading_style(HeadingLevel::CoverSubtitle);
+            push_space(document, subtitle_style.space_before());
+            let mut paragraph = Paragraph::new(subtitle);
+            paragraph.set_alignment(Alignment::Center);
+            self.push_probed(
+                document,
+                ElementKind::Paragraph,
+                paragraph.styled(subtitle_style.text_style()),
+            );
+        }
+    }
+
+    fn push_section<S: ElementSink>(
+        &self,
+        state: &RenderState,
+        document: &mut S,
+        section: &Section,
+        level: usize,
+        next_index: &mut usize,
+        numbers: &[Option<String>],
+        tracker: Option<&PageTrackerHandle>,
+    ) -> Result<(), PdfBuildError> {
+        let number = outline_entry(document, next_index, numbers, tracker);
+        state.section_level.set(level);
+        if self.render_section_headings {
+            self.push_section_heading(document, section, level, number.as_deref());
+        }
+
+        let mut blocks = section.blocks();
+        let mut first_index = 0;
+        if level == 1 && (self.sections_on_new_pages || section.divider().is_some()) {
+            // The section already starts on a new page, so a leading break would leave one blank.
+            if let Some((Block::PageBreak, rest)) = blocks.split_first() {
+                blocks = rest;
+                first_index = 1;
+            }
+        }
+        for (offset, block) in blocks.iter().enumerate() {
+            let index = first_index + offset;
+            if let Block::Heading {
+                level: depth,
+                paragraph,
+            } = block
+            {
+                // Headings at the top of a section body are outline entries like subsections.
+                *state.block_location.borrow_mut() =
+                    BlockLocation::new(Some(section.title()), index);
+                let number = outline_entry(document, next_index, numbers, tracker);
+                self.push_heading_block(state, document, *depth, paragraph, number.as_deref());
+            } else {
+                self.push_top_level_blocks(
+                    state,
+                    document,
+                    std::slice::from_ref(block),
+                    Some(section.title()),
+                    index,
+                )?;
+            }
+        }
+
+        for subsection in section.subsections() {
+            self.push_section(
+                state,
+                document,
+                subsection,
+                level + 1,
+                next_index,
+                numbers,
+                tracker,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn push_cover(
+        &self,
+        state: &RenderState,
+        document: &mut genpdf::Document,
+        cover: &Cover,
+    ) -> Result<(), PdfBuildError> {
+        state.section_level.set(0);
+        if let Some(renderer) = &self.cover_renderer {
+            self.push_probed(document, ElementKind::CoverText, renderer(cover));
+            return Ok(());
+        }
+
+        if let Some(logo) = cover.logo() {
+            match self.build_image(state, logo, None) {
+                Ok(element) => {
+                    self.push_probed(document, ElementKind::Image, element);
+                    document.push(LineBreak::new(1.5));
+                }
+                Err(PdfBuildError::Content { message, source }) => {
+                    self.warnings.push(RenderWarning::ImageSkipped {
+                        location: "cover logo".to_string(),
+                        reason: format!("{}: {}", message, source),
+                    });
+                }
+                Err(PdfBuildError::ImageDecode { source, cause, .. }) => {
+                    self.warnings.push(RenderWarning::ImageSkipped {
+                        location: "cover logo".to_string(),
+                        reason: format!("Failed to load image from {}: {}", source, cause),
+                    });
+                }
+                Err(other) => return Err(other),
+            }
+        }
+
+        let title_style = self.heading_style(HeadingLevel::CoverTitle);
+        push_space(document, title_style.space_before());
+        let mut title = Paragraph::new(cover.title());
+        title.set_alignment(Alignment::Center);
+        self.push_probed(
+            document,
+            ElementKind::CoverText,
+            title.styled(title_style.text_style()),
+        );
+        push_space(document, title_style.space_after());
+
+        if let Some(subtitle) = cover.subtitle() {
+            let subtitle_style = self.heading_style(HeadingLevel::CoverSubtitle);
+            push_space(document, subtitle_style.space_before());
+            let mut paragraph = Paragraph::new(subtitle);
+            paragraph.set_alignment(Alignment::Center);
+            self.push_probed(
+                document,
+                ElementKind::CoverText,
+                paragraph.styled(subtitle_style.text_style()),
+            );
+            push_space(document, subtitle_style.space_after());
+        }
+
+        if let Some(identifier) = cover.identifier() {
+            let mut paragraph = Paragraph::new(identifier);
+            paragraph.set_alignment(Alignment::Center);
+            self.push_probed(document, ElementKind::CoverText, paragraph);
+            document.push(LineBreak::new(1.0));
+        }
+
+        self.push_top_level_blocks(state, document, cover.blocks(), None, 0)
+    }
+
+    fn push_toc(
+        &self,
+        document: &mut genpdf::Document,
+        toc_pages: Option<&[Option<usize>]>,
+    ) -> Result<(), PdfBuildError> {
+        let toc_title = self.toc_title.as_deref().unwrap_or("Table of Contents");
+        self.push_contents(document, toc_title, self.toc_entries(toc_pages));
+        Ok(())
+    }
+
+    /// Prints a list of figures or tables with the page each of them starts on.
+    fn push_caption_list(
+        &self,
+        state: &RenderState,
+        document: &mut genpdf::Document,
+        list: &CaptionList,
+    ) {
+        let pages = match list.kind {
+            ElementKind::Table => state.table_pages.borrow(),
+            _ => state.figure_pages.borrow(),
+        };
+        let entries = list
+            .captions
+            .iter()
+            .enumerate()
+            .map(|(index, caption)| TocEntry {
+                level: 1,
+                number: Some(
+                    self.caption_template
+                        .expand(list.label, index + 1)
+                        .0
+                        .trim_end()
+                        .to_string(),
+                )
+                .filter(|number| !number.is_empty()),
+                title: Cow::Borrowed(caption.as_str()),
+                page: pages.get(&(index + 1)).copied(),
+            })
+            .collect();
+        self.push_contents(document, list.title, entries);
+    }
+
+    /// Prints a centered `title` followed by one row per entry, ending in the entry's page number.
+    fn push_contents(
+        &self,
+        document: &mut genpdf::Document,
+        title: &str,
+        entries: Vec<TocEntry<'_>>,
+    ) {
+        let mut title_style = Style::new();
+        title_style.set_font_size(20);
+        title_style.set_bold();
+        let mut heading = Paragraph::new(title);
+        heading.set_alignment(Alignment::Center);
+        self.push_probed(
+            document,
+            ElementKind::TableOfContents,
+            heading.styled(title_style),
+        );
+        document.push(LineBreak::new(1.0));
+
+        let mut rows = LinearLayout::vertical();
+        for entry in entries {
+            let indent = mm_from_f64(TOC_INDENT_MM * entry.level.saturating_sub(1) as f64);
+            let page = entry
+                .page
+                .map(|value| value.to_string())
+                .unwrap_or_else(|| "--".into());
+            let title = numbered_title(entry.number.as_deref(), &entry.title);
+            let mut style = Style::new();
+            if entry.level == 0 {
+                // Section dividers stand out above the sections listed after them.
+                style.set_bold();
+            }
+            let row = TocRow::new(title, page)
+                .with_indent(indent)
+                .with_leader(self.toc_leader.character());
+            // The row links its printed title, leader, and page number, leaving the indent out.
+            #[cfg(feature = "links")]
+            let row = match entry.page {
+                Some(target) => row.with_link(target, self.layout.clone()),
+                None => row,
+            };
+            rows.push(row.styled(style));
+        }
+
+        self.push_probed(document, ElementKind::TableOfContents, rows);
+    }
+
+    /// Lists of figures and tables requested for the front matter, skipping empty lists.
+    ///
+    /// The captions are collected in the order the figures and tables are numbered while
+    /// rendering: the cover blocks first, then every section in reading order, including blocks
+    /// nested in quotes and the columns of rows.
+    fn caption_lists(&self) -> Vec<CaptionList> {
+        fn collect(blocks: &[Block], kind: ElementKind, captions: &mut Vec<String>) {
+            for block in blocks {
+                match block {
+                    Block::Image(image) if kind == ElementKind::Image => {
+                        captions.extend(image.caption().map(plain_text));
+                    }
+                    Block::Table(table)
+                        if kind == ElementKind::Table && table.column_count() > 0 =>
+                    {
+                        captions.extend(table.caption().map(plain_text));
+                    }
+                    Block::Quote(quote) => collect(quote.blocks(), kind, captions),
+                    Block::KeepTogether(blocks) => collect(blocks, kind, captions),
+                    Block::Row(columns) => {
+                        for column in columns {
+                            collect(column.blocks(), kind, captions);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let requested = [
+            (
+                self.include_list_of_figures,
+                "List of Figures",
+                "Figure",
+                ElementKind::Image,
+            ),
+            (
+                self.include_list_of_tables,
+                "List of Tables",
+                "Table",
+                ElementKind::Table,
+            ),
+        ];
+        requested
+            .into_iter()
+            .filter(|(include, ..)| *include)
+            .filter_map(|(_, title, label, kind)| {
+                let mut captions = Vec::new();
+                collect(self.cover_blocks(), kind, &mut captions);
+                for (_, section) in flatten_sections(&self.sections) {
+                    collect(section.blocks(), kind, &mut captions);
+                }
+                (!captions.is_empty()).then(|| CaptionList {
+                    title,
+                    label,
+                    kind,
+                    captions,
+                })
+            })
+            .collect()
+    }
+
+    /// Lists the table of contents rows for every outline entry in reading order.
+    fn toc_entries(&self, toc_pages: Option<&[Option<usize>]>) -> Vec<TocEntry<'_>> {
+        flatten_outline(&self.sections)
+            .into_iter()
+            .zip(self.section_numbers())
+            .enumerate()
+            .filter(|(_, ((level, _), _))| self.toc_depth.map_or(true, |depth| *level <= depth))
+            .map(|(index, ((level, item), number))| TocEntry {
+                level,
+                number,
+                title: item.title(),
+                page: toc_pages
+                    .and_then(|pages| pages.get(index).copied())
+                    .flatten(),
+            })
+            .collect()
+    }
+
+    /// Pairs every section heading and body heading with the page recorded for it during the
+    /// render pass.
+    fn section_headings(&self, pages: &[Option<usize>]) -> Vec<SectionHeading> {
+        flatten_outline(&self.sections)
+            .into_iter()
+            .zip(self.section_numbers())
+            .enumerate()
+            .map(|(index, ((level, item), number))| SectionHeading {
+                level,
+                title: item.title().into_owned(),
+                number,
+                identifier: item.identifier().map(str::to_string),
+                page: pages.get(index).copied().flatten(),
+            })
+            .collect()
+    }
+
+    /// Outline numbers of all outline entries in reading order, or `None` for each entry when
+    /// numbering is disabled.  Section dividers are never numbered.
+    fn section_numbers(&self) -> Vec<Option<String>> {
+        let outline = flatten_outline(&self.sections);
+        if self.number_sections {
+            number_outline(&outline)
+        } else {
+            vec![None; outline.len()]
+        }
+    }
+
+    fn push_section_heading<S: ElementSink>(
+        &self,
+        document: &mut S,
+        section: &Section,
+        level: usize,
+        number: Option<&str>,
+    ) {
+        let style = self.heading_style(HeadingLevel::from(level));
+        push_space(document, style.space_before());
+        let mut heading = Paragraph::new(numbered_title(number, section.title()));
+        heading.set_alignment(self.resolve_alignment(self.default_alignment));
+        self.push_described(
+            document,
+            ElementKind::Heading,
+            heading.styled(style.text_style()),
+            level,
+            None,
+        );
+        push_space(document, style.space_after());
+    }
+
+    /// Pushes a [`Block::Heading`] styled like the section headings `depth` levels below the
+    /// section being pushed.
+    fn push_heading_block<S: ElementSink>(
+        &self,
+        state: &RenderState,
+        document: &mut S,
+        depth: u8,
+        paragraph: &RichParagraph,
+        number: Option<&str>,
+    ) {
+        let level = state.section_level.get() + usize::from(depth).max(1);
+        let style = self.heading_style(HeadingLevel::Section(level));
+        let alignment = self.resolve_alignment(paragraph.alignment());
+        let prefix = number.map(|number| StyledString::new(format!("{} ", number), Style::new()));
+        push_space(document, style.space_before());
+        if is_spaced(paragraph) || is_decorated(paragraph) {
+            // Tracked headings, such as letter-spaced small caps, and headings carrying footnote
+            // markers or links need the custom text element.
+            let mut spans = self.paragraph_spans(state, paragraph);
+            if let Some(prefix) = prefix {
+                spans.insert(0, StyledSpan::new(prefix, false));
+            }
+            let heading = WrappedText::new(spans)
+                .with_alignment(alignment)
+                .with_layout(self.layout.clone());
+            self.push_described(
+                document,
+                ElementKind::Heading,
+                heading.styled(line_spaced(style.text_style(), paragraph)),
+                level,
+                None,
+            );
+        } else {
+            let mut strings = self.paragraph_strings(state, paragraph.spans());
+            if let Some(prefix) = prefix {
+                strings.insert(0, prefix);
+            }
+            let mut heading = paragraph_from_strings(strings);
+            heading.set_alignment(alignment);
+            self.push_described(
+                document,
+                ElementKind::Heading,
+                heading.styled(style.text_style()),
+                level,
+                None,
+            );
+        }
+        push_space(document, style.space_after());
+    }
+
+    /// Style of the heading at `level`, as configured or by default.
+    fn heading_style(&self, level: HeadingLevel) -> HeadingStyle {
+        self.heading_styles
+            .get(&level)
+            .copied()
+            .unwrap_or_else(|| HeadingStyle::default_for(level))
+    }
+
+    /// Pushes the blocks of the cover or a section, starting at position `first_index`, and
+    /// records which one is being pushed for [`PdfBuildError::ImageDecode`].
+    fn push_top_level_blocks<S: ElementSink>(
+        &self,
+        state: &RenderState,
+        document: &mut S,
+        blocks: &[Block],
+        section: Option<&str>,
+        first_index: usize,
+    ) -> Result<(), PdfBuildError> {
+        for (index, block) in blocks.iter().enumerate() {
+            *state.block_location.borrow_mut() = BlockLocation::new(section, first_index + index);
+            self.push_block(state, document, block)?;
+        }
+        Ok(())
+    }
+
+    fn push_section_blocks<S: ElementSink>(
+        &self,
+        state: &RenderState,
+        document: &mut S,
+        blocks: &[Block],
+    ) -> Result<(), PdfBuildError> {
+        for block in blocks {
+            self.push_block(state, document, block)?;
+        }
+        Ok(())
+    }
+
+    fn push_block<S: ElementSink>(
+        &self,
+        state: &RenderState,
+        document: &mut S,
+        block: &Block,
+    ) -> Result<(), PdfBuildError> {
+        match block {
+            Block::Paragraph(paragraph) => {
+                let alignment = self.effective_alignment(paragraph.alignment());
+                let decorated = is_decorated(paragraph) || is_spaced(paragraph);
+                let direction = paragraph.direction().unwrap_or(self.text_direction);
+                let (before, after) = (self.min_lines_before_break, self.min_lines_after_break);
+                if direction == TextDirection::Rtl {
+                    let alignment = match alignment {
+                        HorizontalAlignment::Center => Alignment::Center,
+                        _ => Alignment::Right,
+                    };
+                    self.push_paragraph(
+                        state,
+                        document,
+                        WrappedText::new(self.paragraph_spans(state, paragraph))
+                            .with_alignment(alignment)
+                            .with_direction(direction)
+                            .with_min_lines(before, after)
+                            .with_layout(self.layout.clone()),
+                        paragraph,
+                    );
+                } else if alignment == HorizontalAlignment::Justified {
+                    self.push_paragraph(
+                        state,
+                        document,
+                        JustifiedText::new(self.paragraph_spans(state, paragraph))
+                            .with_min_lines(before, after)
+                            .with_layout(self.layout.clone()),
+                        paragraph,
+                    );
+                } else if decorated || before > 1 || after > 1 {
+                    // `genpdf`'s paragraph splits wherever the page ends, so paragraphs with
+                    // widow or orphan control are laid out by the wrapped text element as well.
+                    self.push_paragraph(
+                        state,
+                        document,
+                        WrappedText::new(self.paragraph_spans(state, paragraph))
+                            .with_alignment(self.map_alignment(alignment))
+                            .with_min_lines(before, after)
+                            .with_layout(self.layout.clone()),
+                        paragraph,
+                    );
+                } else {
+                    self.push_paragraph(
+                        state,
+                        document,
+                        self.build_paragraph(state, paragraph),
+                        paragraph,
+                    );
+                }
+            }
+            Block::Image(image) => {
+                let figure = self.next_figure_number(state, image);
+                let element = self.build_image(state, image, figure)?;
+                let alt_text = match image.alt_text() {
+                    Some(alt_text) => Some(alt_text.to_string()),
+                    None if self.tagged => image.caption().map(plain_text),
+                    None => None,
+                };
+                self.push_listed(document, ElementKind::Image, figure, element, alt_text);
+            }
+            Block::List(list) => {
+                self.push_probed(document, ElementKind::List, self.build_list(state, list));
+            }
+            Block::Legend(legend) => {
+                self.push_probed(
+                    document,
+                    ElementKind::Legend,
+                    self.build_legend(state, legend),
+                );
+            }
+            Block::Table(table) => {
+                if let Some(element) = self.build_table(state, table) {
+                    let number = self.next_table_number(state, table);
+                    match table.caption() {
+                        Some(caption) => {
+                            let mut content = LinearLayout::vertical();
+                            content.push(paragraph_from_strings(
+                                self.caption_strings(state, caption, "Table", number),
+                            ));
+                            content.push(LineBreak::new(0.5));
+                            content.push(element);
+                            self.push_listed(document, ElementKind::Table, number, content, None);
+                        }
+                        None => {
+                            self.push_listed(document, ElementKind::Table, number, element, None)
+                        }
+                    }
+                }
+            }
+            Block::Quote(quote) => {
+                let element = self.build_quote(state, quote)?;
+                self.push_probed(document, ElementKind::Quote, element);
+            }
+            Block::HorizontalRule(rule) => {
+                let element = HorizontalRule::new(mm_from_f64(rule.thickness_mm()))
+                    .with_color(rule.color())
+                    .with_margin(mm_from_f64(rule.margin_mm()));
+                self.push_probed(document, ElementKind::Rule, element);
+            }
+            Block::Code(code) => {
+                let element = CodeText::new(code.code())
+                    .with_font_family(state.monospace_font.get())
+                    .with_background(code.background_color());
+                self.push_probed(document, ElementKind::Code, element);
+            }
+            Block::Spacer(height) => {
+                let element = Spacer::new(mm_from_f64(*height));
+                self.push_probed(document, ElementKind::Spacer, element);
+            }
+            Block::Row(columns) => {
+                if let Some(element) = self.build_row(state, columns)? {
+                    self.push_probed(document, ElementKind::Row, element);
+                }
+            }
+            Block::KeepTogether(blocks) => {
+                let mut content = LinearLayout::vertical();
+                for block in blocks {
+                    if !matches!(block, Block::PageBreak) {
+                        self.push_block(state, &mut content, block)?;
+                    }
+                }
+                let element = KeepTogether::new(content)
+                    .with_height(self.next_group_height(state))
+                    .with_layout(self.layout.clone());
+                self.push_probed(document, ElementKind::KeepTogether, element);
+            }
+            Block::Custom(custom) => {
+                let element = BoxedElement::new(custom.build());
+                self.push_probed(document, ElementKind::Custom, element);
+            }
+            Block::Heading { level, paragraph } => {
+                self.push_heading_block(state, document, *level, paragraph, None);
+            }
+            Block::PageBreak => {
+                document.push_element(PageBreak::new());
+            }
+        }
+        Ok(())
+    }
+
+    /// Pushes the element laying out `paragraph` with the paragraph's line spacing, on a filled
+    /// background spanning the content width if requested.
+    fn push_paragraph<S: ElementSink, E: Element + 'static>(
+        &self,
+        state: &RenderState,
+        document: &mut S,
+        element: E,
+        paragraph: &RichParagraph,
+    ) {
+        let element = element.styled(line_spaced(Style::new(), paragraph));
+        let background = paragraph
+            .background()
+            .map(|color| self.translucent_color(state, color, paragraph.background_alpha()));
+        match background {
+            Some(color) => self.push_probed(
+                document,
+                ElementKind::Paragraph,
+                Callout::shaded(element, color),
+            ),
+            None => self.push_probed(document, ElementKind::Paragraph, element),
+        }
+    }
+
+    /// Pushes a figure or table, recording the page it lands on when it is numbered.
+    fn push_listed<S: ElementSink, E: Element + 'static>(
+        &self,
+        document: &mut S,
+        kind: ElementKind,
+        number: Option<usize>,
+        element: E,
+        alt_text: Option<String>,
+    ) {
+        match number {
+            Some(number) => {
+                let marked = CaptionMarker {
+                    element,
+                    kind,
+                    number,
+                    layout: self.layout.clone(),
+                };
+                self.push_described(document, kind, marked, 0, alt_text);
+            }
+            None => self.push_described(document, kind, element, 0, alt_text),
+        }
+    }
+
+    /// Pushes `element` wrapped in a probe that reports its placement to the layout tracker.
+    fn push_probed<S: ElementSink, E: Element + 'static>(
+        &self,
+        document: &mut S,
+        kind: ElementKind,
+        element: E,
+    ) {
+        self.push_described(document, kind, element, 0, None);
+    }
+
+    /// Pushes a probed element that tagged output describes with the heading `level` and
+    /// alternate text.
+    fn push_described<S: ElementSink, E: Element + 'static>(
+        &self,
+        document: &mut S,
+        kind: ElementKind,
+        element: E,
+        level: usize,
+        alt_text: Option<String>,
+    ) {
+        let probe = LayoutProbe::new(element, kind, self.layout.clone());
+        #[cfg(feature = "tagged-pdf")]
+        let probe = if (self.tagged || alt_text.is_some())
+            && tagging::structure_type(kind, level).is_some()
+        {
+            probe.with_tag(level, alt_text)
+        } else {
+            probe
+        };
+        #[cfg(not(feature = "tagged-pdf"))]
+        let _ = (level, alt_text);
+        document.push_element(probe);
+    }
+
+    fn build_quote(
+        &self,
+        state: &RenderState,
+        quote: &QuoteBlock,
+    ) -> Result<Callout, PdfBuildError> {
+        let mut content = LinearLayout::vertical();
+        self.push_section_blocks(state, &mut content, quote.blocks())?;
+        if let Some(attribution) = quote.attribution() {
+            let mut style = Style::new();
+            style.set_italic();
+            let mut paragraph = Paragraph::new(format!("\u{2014} {}", attribution));
+            paragraph.set_alignment(Alignment::Right);
+            content.push(paragraph.styled(style));
+        }
+
+        Ok(Callout::new(content)
+            .with_bar_color(quote.bar_color())
+            .with_background(quote.background_color()))
+    }
+
+    /// Lays out the columns of a row as the cells of a single table row without borders.
+    ///
+    /// Page breaks cannot be honoured within a single column and are skipped.
+    fn build_row(
+        &self,
+        state: &RenderState,
+        columns: &[ColumnBlock],
+    ) -> Result<Option<TableLayout>, PdfBuildError> {
+        let Some(last) = columns.len().checked_sub(1) else {
+            return Ok(None);
+        };
+
+        let gap = mm_from_f64(ROW_COLUMN_GAP_MM / 2.0);
+        let mut cells: Vec<Box<dyn Element>> = Vec::with_capacity(columns.len());
+        for (index, column) in columns.iter().enumerate() {
+            let mut content = LinearLayout::vertical();
+            for block in column.blocks() {
+                if !matches!(block, Block::PageBreak) {
+                    self.push_block(state, &mut content, block)?;
+                }
+            }
+            let left = if index > 0 { gap } else { Mm::default() };
+            let right = if index < last { gap } else { Mm::default() };
+            let margins = Margins::trbl(Mm::default(), right, Mm::default(), left);
+            cells.push(Box::new(content.padded(margins)));
+        }
+
+        let mut layout = TableLayout::new(
+            columns
+                .iter()
+                .map(|column| column.weight().max(1))
+                .collect(),
+        );
+        layout
+            .push_row(cells)
+            .map_err(|err| PdfBuildError::content("Failed to lay out row columns", err))?;
+        Ok(Some(layout))
+    }
+
+    /// Converts spans for the custom text elements, merging spans with identical styling and
+    /// splitting them by fallback font coverage.
+    ///
+    /// Footnote markers are numbered in the order they are converted, which follows the reading
+    /// order of the document.
+    fn styled_spans(&self, state: &RenderState, spans: &[Span]) -> Vec<StyledSpan> {
+        let fallback = state.fallback_fonts.borrow();
+        coalesce_spans(spans.iter().cloned())
+            .into_iter()
+            .map(|span| self.styled_span(state, &self.resolve_alpha(state, span)))
+            .flat_map(|span| fallback.split_span(span))
+            .collect()
+    }
+
+    /// Converts the spans of `paragraph` like [`PdfBuilder::styled_spans`] and applies the word
+    /// spacing of the paragraph to them.
+    fn paragraph_spans(&self, state: &RenderState, paragraph: &RichParagraph) -> Vec<StyledSpan> {
+        let word_spacing = points_to_mm(paragraph.word_spacing());
+        self.styled_spans(state, paragraph.spans())
+            .into_iter()
+            .map(|span| span.with_word_spacing(word_spacing))
+            .collect()
+    }
+
+    /// Replaces the color of a translucent span with the color drawn for it.
+    fn resolve_alpha(&self, state: &RenderState, span: Span) -> Span {
+        match span.color() {
+            Some(color) if span.alpha() < u8::MAX => {
+                let color = self.translucent_color(state, color, span.alpha());
+                span.with_color(Some(color)).with_alpha(None)
+            }
+            _ => span,
+        }
+    }
+
+    /// Returns the color drawn for `color` at the given opacity.
+    ///
+    /// With the `transparency` feature this is a placeholder that is replaced by `color` and a
+    /// matching graphics state once the document was rendered; otherwise, and in streamed
+    /// renders, `color` is blended towards white.
+    fn translucent_color(&self, state: &RenderState, color: Color, alpha: u8) -> Color {
+        if alpha == u8::MAX {
+            return color;
+        }
+        #[cfg(feature = "transparency")]
+        {
+            state
+                .translucent_colors
+                .placeholder(color, alpha)
+                .unwrap_or_else(|| faded_color(color, f64::from(alpha) / 255.0))
+        }
+        #[cfg(not(feature = "transparency"))]
+        {
+            let _ = state;
+            faded_color(color, f64::from(alpha) / 255.0)
+        }
+    }
+
+    /// Returns the watermark drawn for `watermark`, at its opacity when the `transparency`
+    /// feature is enabled.
+    fn translucent_watermark(&self, state: &RenderState, watermark: &Watermark) -> Watermark {
+        let options = *watermark.options();
+        if !cfg!(feature = "transparency") || options.opacity() >= 1.0 {
+            return watermark.clone();
+        }
+        let alpha = (options.opacity() * 255.0).round() as u8;
+        let color = self.translucent_color(state, options.color(), alpha);
+        watermark
+            .clone()
+            .with_options(options.with_opacity(1.0).with_color(color))
+    }
+
+    /// Converts a single span, replacing footnote markers with their superscript number.
+    fn styled_span(&self, state: &RenderState, span: &Span) -> StyledSpan {
+        let mut styled = StyledSpan::from(span);
+        if let Some(identifier) = span.internal_link() {
+            styled = styled.with_page_link(state.anchor_pages.borrow().get(identifier).copied());
+        }
+        if let Some(text) = span.footnote_text() {
+            let mut texts = state.footnote_texts.borrow_mut();
+            texts.push(text.to_string());
+            styled.string.s = texts.len().to_string();
+            styled = styled
+                .with_vertical_offset(VerticalOffset::Super)
+                .with_footnote(Some(texts.len()));
+        }
+        styled
+    }
+
+    /// Converts spans for `genpdf` paragraphs, merging spans with identical styling and splitting
+    /// them by fallback font coverage.
+    fn paragraph_strings(&self, state: &RenderState, spans: &[Span]) -> Vec<StyledString> {
+        let fallback = state.fallback_fonts.borrow();
+        coalesce_spans(spans.iter().cloned())
+            .into_iter()
+            .flat_map(|span| {
+                fallback.split_string(StyledString::from(&self.resolve_alpha(state, span)))
+            })
+            .collect()
+    }
+
+    /// Caption strings laid out by the caption template when the figure or table is numbered.
+    fn caption_strings(
+        &self,
+        state: &RenderState,
+        caption: &RichParagraph,
+        label: &str,
+        number: Option<usize>,
+    ) -> Vec<StyledString> {
+        let Some(number) = number else {
+            return self.paragraph_strings(state, caption.spans());
+        };
+        let (prefix, suffix) = self.caption_template.expand(label, number);
+        let mut spans = Vec::with_capacity(caption.spans().len() + 2);
+        if !prefix.is_empty() {
+            spans.push(self.caption_template.prefix_span(prefix));
+        }
+        spans.extend(caption.spans().iter().cloned());
+        if !suffix.is_empty() {
+            spans.push(Span::new(suffix));
+        }
+        self.paragraph_strings(state, &spans)
+    }
+
+    fn build_paragraph(&self, state: &RenderState, paragraph: &RichParagraph) -> Paragraph {
+        let mut element = paragraph_from_strings(self.paragraph_strings(state, paragraph.spans()));
+        element.set_alignment(self.resolve_alignment(paragraph.alignment()));
+        element
+    }
+
+    /// Translates a table block into a [`DataTable`], or `None` when the table has no columns.
+    fn build_table(&self, state: &RenderState, table: &TableBlock) -> Option<DataTable> {
+        let columns = table.column_count();
+        if columns == 0 {
+            return None;
+        }
+
+        let padding = mm_from_f64(TABLE_CELL_PADDING_MM);
+        let cell_margins = Margins::trbl(padding, padding, padding, padding);
+        let mut layout = DataTable::new(table.column_weights())
+            .with_repeated_header(table.repeat_header())
+            .with_borders(table.borders());
+
+        if !table.headers().is_empty() {
+            let mut header_style = Style::new();
+            header_style.set_bold();
+            let header: Vec<Paragraph> = (0..columns)
+                .map(|column| {
+                    self.build_table_cell(
+                        state,
+                        table.headers().get(column),
+                        table.column_alignment(column),
+                    )
+                })
+                .collect();
+            layout = layout.with_header(move || {
+                header
+                    .iter()
+                    .map(|cell| {
+                        Box::new(cell.clone().styled(header_style).padded(cell_margins))
+                            as Box<dyn Element>
+                    })
+                    .collect()
+            });
+        }
+
+        let decimal_columns: Vec<Option<Vec<StyledString>>> = (0..columns)
+            .map(|column| {
+                (table.column_alignment(column) == Some(ColumnAlignment::Decimal)).then(|| {
+                    table
+                        .rows()
+                        .iter()
+                        .filter_map(|row| row.get(column))
+                        .map(|cell| table_cell_text(cell.content()))
+                        .collect()
+                })
+            })
+            .collect();
+
+        for (index, row) in table.rows().iter().enumerate() {
+            let cells: Vec<Box<dyn Element>> = (0..columns)
+                .map(|column| match (&decimal_columns[column], row.get(column)) {
+                    (Some(values), Some(cell)) => Box::new(
+                        DecimalAlignedText::new(table_cell_text(cell.content()), values.clone())
+                            .padded(cell_margins),
+                    ) as Box<dyn Element>,
+                    (None, Some(cell)) if cell.height_mm().is_some() => Box::new(
+                        self.build_limited_table_cell(state, cell, table.column_alignment(column))
+                            .with_location(format!("table row {} column {}", index + 1, column + 1))
+                            .padded(cell_margins),
+                    ),
+                    (_, cell) => Box::new(
+                        self.build_table_cell(
+                            state,
+                            cell.map(TableCell::content),
+                            table.column_alignment(column),
+                        )
+                        .padded(cell_margins),
+                    ),
+                })
+                .collect();
+            let fills = (0..columns)
+                .map(|column| table.cell_fill(index, column))
+                .collect();
+            layout.push_filled_row(cells, fills);
+        }
+
+        Some(layout)
+    }
+
+    /// Builds a text cell, letting an explicit column alignment override the paragraph alignment.
+    ///
+    /// Missing cells of short rows render as empty paragraphs.  Header cells of decimal columns are
+    /// right aligned so they line up with the right-aligned block of numbers below them.
+    fn build_table_cell(
+        &self,
+        state: &RenderState,
+        cell: Option<&RichParagraph>,
+        alignment: Option<ColumnAlignment>,
+    ) -> Paragraph {
+        let mut element = match cell {
+            Some(cell) => self.build_paragraph(state, cell),
+            None => self.build_paragraph(state, &RichParagraph::default()),
+        };
+        match alignment {
+            Some(ColumnAlignment::Left) => element.set_alignment(Alignment::Left),
+            Some(ColumnAlignment::Center) => element.set_alignment(Alignment::Center),
+            Some(ColumnAlignment::Right | ColumnAlignment::Decimal) => {
+                element.set_alignment(Alignment::Right)
+            }
+            None => {}
+        }
+        element
+    }
+
+    /// Builds a cell whose text is clipped to its fixed height, reporting dropped lines to the
+    /// builder's warning sink.
+    fn build_limited_table_cell(
+        &self,
+        state: &RenderState,
+        cell: &TableCell,
+        alignment: Option<ColumnAlignment>,
+    ) -> FixedRegion {
+        let height = mm_from_f64(cell.height_mm().unwrap_or_default());
+        let alignment = match alignment {
+            Some(ColumnAlignment::Left) => Alignment::Left,
+            Some(ColumnAlignment::Center) => Alignment::Center,
+            Some(ColumnAlignment::Right | ColumnAlignment::Decimal) => Alignment::Right,
+            None => self.resolve_alignment(cell.content().alignment()),
+        };
+        FixedRegion::new(height, self.paragraph_spans(state, cell.content()))
+            .with_alignment(alignment)
+            .with_warnings(self.warnings.clone())
+    }
+
+    fn build_list(&self, state: &RenderState, list: &ListBlock) -> MarkedList {
+        let mut items = Vec::new();
+        self.collect_list_items(state, list, 0, &mut items);
+        MarkedList::new(items)
+            .with_min_items_together(list.min_items_together())
+            .with_layout(self.layout.clone())
+    }
+
+    fn collect_list_items(
+        &self,
+        state: &RenderState,
+        list: &ListBlock,
+        depth: usize,
+        items: &mut Vec<MarkedItem>,
+    ) {
+        for (offset, item) in list.items().iter().enumerate() {
+            let marker = list.style().marker(list.start() + offset);
+            let content = item.content();
+            items.push(
+                MarkedItem::new(marker, depth, self.styled_spans(state, content.spans()))
+                    .with_alignment(self.resolve_alignment(content.alignment())),
+            );
+            if let Some(sublist) = item.sublist() {
+                self.collect_list_items(state, sublist, depth + 1, items);
+            }
+        }
+    }
+
+    fn build_legend(&self, state: &RenderState, legend: &LegendBlock) -> Legend {
+        let entries = legend
+            .entries()
+            .iter()
+            .map(|entry| (entry.color(), self.styled_spans(state, entry.label())))
+            .collect();
+        Legend::new(entries).with_orientation(legend.orientation())
+    }
 
-        if let Some(subtitle) = cover.subtitle() {
-            let mut subtitle_style = Style::new();
-            subtitle_style.set_font_size(18);
-            subtitle_style.set_italic();
-            let mut paragraph = Paragraph::new(subtitle);
-            paragraph.set_alignment(Alignment::Center);
-            document.push(paragraph.styled(subtitle_style));
-            document.push(LineBreak::new(1.0));
+    /// Counts `block` as the next figure when figure numbering is enabled and it has a caption.
+    fn next_figure_number(&self, state: &RenderState, block: &ImageBlock) -> Option<usize> {
+        if !(self.number_figures || self.include_list_of_figures) || block.caption().is_none() {
+            return None;
         }
+        state.figure_count.set(state.figure_count.get() + 1);
+        Some(state.figure_count.get())
+    }
 
-        if let Some(identifier) = cover.identifier() {
-            let mut paragraph = Paragraph::new(identifier);
-            paragraph.set_alignment(Alignment::Center);
-            document.push(paragraph);
-            document.push(LineBreak::new(1.0));
+    /// Returns the height cell of the next keep-together group, shared with the other passes.
+    fn next_group_height(&self, state: &RenderState) -> Rc<Cell<Option<Mm>>> {
+        let index = state.group_count.get();
+        state.group_count.set(index + 1);
+        let mut heights = state.group_heights.borrow_mut();
+        if heights.len() <= index {
+            heights.push(Rc::new(Cell::new(None)));
         }
+        Rc::clone(&heights[index])
+    }
 
-        self.push_section_blocks(document, cover.blocks())
+    /// Counts `table` as the next table when the list of tables is enabled and it has a caption.
+    fn next_table_number(&self, state: &RenderState, table: &TableBlock) -> Option<usize> {
+        if !self.include_list_of_tables || table.caption().is_none() {
+            return None;
+        }
+        state.table_count.set(state.table_count.get() + 1);
+        Some(state.table_count.get())
     }
 
-    fn push_toc(
+    fn build_image(
         &self,
-        document: &mut genpdf::Document,
-        toc_pages: Option<&[Option<usize>]>,
-    ) -> Result<(), PdfBuildError> {
-        let mut title_style = Style::new();
-        title_style.set_font_size(20);
-        title_style.set_bold();
-        let toc_title = self.toc_title.as_deref().unwrap_or("Table of Contents");
-        let mut heading = Paragraph::new(toc_title);
-        heading.set_alignment(Alignment::Center);
-        document.push(heading.styled(title_style));
-        document.push(LineBreak::new(1.0));
+        state: &RenderState,
+        block: &ImageBlock,
+        figure: Option<usize>,
+    ) -> Result<CaptionedImage, PdfBuildError> {
+        let alignment = self.resolve_alignment(block.alignment());
+        let caption_paragraph = Paragraph::new(StyledString::new(String::new(), Style::new()));
 
-        let placeholder;
-        let pages = if let Some(pages) = toc_pages {
-            pages
+        let mut element = if state.placeholder_images.get() {
+            let dimensions = match block.source() {
+                ImageSource::Bytes(bytes) => image_dimensions_from_bytes(bytes),
+                ImageSource::Path(path) => image_dimensions_from_path(path),
+            }
+            .map_err(|err| image_error(block.source(), &state.block_location.borrow(), err))?;
+            CaptionedImage::placeholder(dimensions, caption_paragraph)
+                .map_err(|err| PdfBuildError::content("Failed to create image placeholder", err))?
         } else {
-            placeholder = vec![None; self.sections.len()];
-            &placeholder
+            // genpdf takes ownership of the pixels, so every pass embeds its own copy.
+            let image = self.decoded_image(state, block.source())?;
+            CaptionedImage::from_dynamic_image(image.as_ref().clone(), caption_paragraph)
+                .map_err(|err| PdfBuildError::content("Failed to embed image", err))?
         };
 
-        let mut table = TableLayout::new(vec![6, 1]);
-        for (section, page) in self.sections.iter().zip(pages.iter()) {
-            let mut title = Paragraph::new(section.title());
-            title.set_alignment(Alignment::Left);
-            let mut page_number = Paragraph::new(
-                page.map(|value| value.to_string())
-                    .unwrap_or_else(|| "--".into()),
-            );
-            page_number.set_alignment(Alignment::Right);
-            table
-                .push_row(vec![Box::new(title), Box::new(page_number)])
-                .map_err(|err| PdfBuildError::content("Failed to append TOC row", err))?;
+        // Captions are passed as strings so their height counts towards the maximum height.
+        if let Some(caption) = block.caption() {
+            element.set_caption_strings(self.caption_strings(state, caption, "Figure", figure));
         }
-
-        document.push(table);
-        document.push(PageBreak::new());
-        Ok(())
-    }
-
-    fn push_section_heading(&self, document: &mut genpdf::Document, section: &Section) {
-        let mut style = Style::new();
-        style.set_bold();
-        style.set_font_size(18);
-        let mut heading = Paragraph::new(section.title());
-        heading.set_alignment(self.resolve_alignment(self.default_alignment));
-        document.push(heading.styled(style));
-        document.push(LineBreak::new(0.75));
+        element.set_alignment(alignment);
+        if let Some(dpi) = block.dpi() {
+            element.set_dpi(dpi);
+        }
+        if let Some(width) = block.width_mm() {
+            element.set_width(Some(mm_from_f64(width)));
+        }
+        element.set_width_percent(block.width_percent());
+        element.set_max_height(block.max_height_mm().map(mm_from_f64));
+        element.set_border(block.border());
+        element.set_caption_position(block.caption_position());
+        Ok(element)
     }
 
-    fn push_section_blocks(
+    /// Decodes the image of `source`, reusing the result of an earlier pass of the same render.
+    fn decoded_image(
         &self,
-        document: &mut genpdf::Document,
-        blocks: &[Block],
-    ) -> Result<(), PdfBuildError> {
-        for block in blocks {
-            self.push_block(document, block)?;
+        state: &RenderState,
+        source: &ImageSource,
+    ) -> Result<Arc<image::DynamicImage>, PdfBuildError> {
+        let key = ImageKey::new(source);
+        if let Some(image) = cached_image(&state.decoded_images.borrow(), &key, source) {
+            return Ok(image);
         }
-        Ok(())
+        let image = decode_image_source(source)
+            .map_err(|err| image_error(source, &state.block_location.borrow(), err))?;
+        let image = Arc::new(image);
+        let entry = DecodedImage {
+            source: source.clone(),
+            image: Arc::clone(&image),
+        };
+        state.decoded_images.borrow_mut().insert(key, entry);
+        Ok(image)
     }
 
-    fn push_block(
-        &self,
-        document: &mut genpdf::Document,
-        block: &Block,
-    ) -> Result<(), PdfBuildError> {
-        match block {
-            Block::Paragraph(paragraph) => {
-                document.push(self.build_paragraph(paragraph));
-            }
-            Block::Image(image) => {
-                let element = self.build_image(image)?;
-                document.push(element);
-            }
-            Block::PageBreak => {
-                document.push(PageBreak::new());
-            }
+    /// Returns the blocks printed on the cover, which a cover renderer replaces.
+    fn cover_blocks(&self) -> &[Block] {
+        match &self.cover {
+            Some(cover) if self.cover_renderer.is_none() => cover.blocks(),
+            _ => &[],
         }
-        Ok(())
     }
 
-    fn build_paragraph(&self, paragraph: &RichParagraph) -> Paragraph {
-        let mut iter = paragraph.spans().iter();
-        let mut element = if let Some(first) = iter.next() {
-            Paragraph::new(StyledString::from(first))
-        } else {
-            Paragraph::new(StyledString::new(String::new(), Style::new()))
+    /// Lists the images of the cover and sections in document order together with the top-level
+    /// block holding them.
+    fn located_images(&self) -> Vec<(BlockLocation, &ImageBlock)> {
+        let mut images = Vec::new();
+        let mut collect = |blocks: &'_ [Block], section: Option<&str>| {
+            for (index, block) in blocks.iter().enumerate() {
+                visit_images(std::slice::from_ref(block), &mut |image| {
+                    images.push((BlockLocation::new(section, index), image));
+                });
+            }
         };
-        for span in iter {
-            element.push(StyledString::from(span));
+        collect(self.cover_blocks(), None);
+        for (_, section) in flatten_sections(&self.sections) {
+            collect(section.blocks(), Some(section.title()));
         }
-        element.set_alignment(self.resolve_alignment(paragraph.alignment()));
-        element
+        images
     }
 
-    fn build_image(&self, block: &ImageBlock) -> Result<CaptionedImage, PdfBuildError> {
-        let alignment = self.resolve_alignment(block.alignment());
-        let caption_paragraph = block
-            .caption()
-            .map(|caption| {
-                let mut paragraph = self.build_paragraph(caption);
-                paragraph.set_alignment(alignment);
-                paragraph
-            })
-            .unwrap_or_else(|| {
-                let mut paragraph = Paragraph::new(StyledString::new(String::new(), Style::new()));
-                paragraph.set_alignment(alignment);
-                paragraph
-            });
+    /// Decodes every image of the cover and sections on the `rayon` thread pool and stores them
+    /// for [`PdfBuilder::decoded_image`].
+    ///
+    /// The first failure in document order is returned as [`PdfBuildError::ImageDecode`].
+    #[cfg(feature = "rayon")]
+    fn predecode_images(&self, state: &RenderState) -> Result<(), PdfBuildError> {
+        use rayon::prelude::*;
 
-        let mut element = match block.source() {
-            ImageSource::Bytes(bytes) => CaptionedImage::from_bytes(bytes, caption_paragraph)
-                .map_err(|err| PdfBuildError::content("Failed to decode image bytes", err))?,
-            ImageSource::Path(path) => CaptionedImage::from_path(path, caption_paragraph)
-                .map_err(|err| PdfBuildError::content("Failed to load image from path", err))?,
-        };
+        let mut cache = state.decoded_images.borrow_mut();
+        let mut seen = std::collections::HashSet::new();
+        let images: Vec<_> = self
+            .located_images()
+            .into_iter()
+            .map(|(location, image)| (location, image.source(), ImageKey::new(image.source())))
+            .filter(|(_, source, key)| {
+                cached_image(&cache, key, source).is_none() && seen.insert(key.clone())
+            })
+            .collect();
 
-        element.set_alignment(alignment);
-        if let Some(width) = block.width_mm() {
-            element.set_width(Some(mm_from_f64(width)));
+        let decoded: Vec<_> = images
+            .par_iter()
+            .map(|(_, source, _)| decode_image_source(source))
+            .collect();
+        for ((location, source, key), result) in images.into_iter().zip(decoded) {
+            let image = result.map_err(|err| image_error(source, &location, err))?;
+            let entry = DecodedImage {
+                source: source.clone(),
+                image: Arc::new(image),
+            };
+            cache.insert(key, entry);
         }
-        Ok(element)
+        Ok(())
     }
 
-    fn resolve_alignment(&self, requested: HorizontalAlignment) -> Alignment {
-        self.map_alignment(match requested {
+    /// Applies the builder's default alignment to content that did not request another one.
+    fn effective_alignment(&self, requested: HorizontalAlignment) -> HorizontalAlignment {
+        match requested {
             HorizontalAlignment::Left => self.default_alignment,
             other => other,
-        })
+        }
+    }
+
+    fn resolve_alignment(&self, requested: HorizontalAlignment) -> Alignment {
+        self.map_alignment(self.effective_alignment(requested))
     }
 
+    /// Maps to the `genpdf` alignment of contexts that cannot justify text, such as list items,
+    /// table cells, and captions; body paragraphs are justified by [`JustifiedText`] instead.
     fn map_alignment(&self, alignment: HorizontalAlignment) -> Alignment {
         match alignment {
             HorizontalAlignment::Left | HorizontalAlignment::Justified => Alignment::Left,
@@ -850,34 +5288,282 @@ impl PdfBuilder {
     }
 }
 
+/// Spans of a footnote printed at the bottom of a page: the raised number followed by the text.
+fn footnote_spans(number: usize, text: &str) -> Vec<StyledSpan> {
+    spans_to_styled_strings(&[
+        Span::new(number.to_string()).superscript(),
+        Span::new(format!(" {text}")),
+    ])
+}
+
+/// Joins the spans of a table cell into a single string styled like its first span.
+fn table_cell_text(cell: &RichParagraph) -> StyledString {
+    let text: String = cell.spans().iter().map(Span::text).collect();
+    let style = cell
+        .spans()
+        .first()
+        .map(|span| StyledString::from(span).style)
+        .unwrap_or_default();
+    StyledString::new(text, style)
+}
+
 impl fmt::Debug for PdfBuilder {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("PdfBuilder")
+            .field("font_source", &self.font_source)
+            .field("fallback_families", &self.fallback_families.len())
+            .field("monospace_family", &self.monospace_family.is_some())
             .field("paper_size", &self.paper_size)
+            .field("landscape", &self.landscape)
             .field("margins", &self.margins)
+            .field("page_background", &self.page_background)
             .field("show_header", &self.show_header)
             .field("show_footer", &self.show_footer)
+            .field("header_on_cover", &self.header_on_cover)
+            .field("footer_on_cover", &self.footer_on_cover)
+            .field("header_style", &self.header_style)
+            .field("footer_style", &self.footer_style)
+            .field("footer_format", &self.footer_format)
+            .field("strict_footer_format", &self.strict_footer_format)
+            .field("heading_styles", &self.heading_styles)
+            .field("page_labels", &self.page_labels)
+            .field("viewer_preferences", &self.viewer_preferences)
+            .field("fixed_timestamp", &self.fixed_timestamp)
             .field("custom_header", &self.custom_header.is_some())
             .field(
                 "custom_footer_height",
                 &self.custom_footer.as_ref().map(|config| config.height),
             )
+            .field(
+                "custom_cover_footer_height",
+                &self
+                    .custom_cover_footer
+                    .as_ref()
+                    .map(|config| config.height),
+            )
             .field("enable_hyphenation", &self.enable_hyphenation)
+            .field("min_lines_before_break", &self.min_lines_before_break)
+            .field("min_lines_after_break", &self.min_lines_after_break)
+            .field("tagged", &self.tagged)
             .field("cover", &self.cover)
+            .field("cover_renderer", &self.cover_renderer.is_some())
             .field("sections", &self.sections)
+            .field("pending_divider", &self.pending_divider)
             .field("include_toc", &self.include_toc)
             .field("toc_title", &self.toc_title)
+            .field("toc_depth", &self.toc_depth)
+            .field("toc_leader", &self.toc_leader)
             .field("default_alignment", &self.default_alignment)
+            .field("text_direction", &self.text_direction)
             .field("render_section_headings", &self.render_section_headings)
+            .field("sections_on_new_pages", &self.sections_on_new_pages)
+            .field("number_sections", &self.number_sections)
+            .field("number_figures", &self.number_figures)
+            .field("caption_template", &self.caption_template)
+            .field("include_list_of_figures", &self.include_list_of_figures)
+            .field("include_list_of_tables", &self.include_list_of_tables)
             .field("collect_section_pages", &self.collect_section_pages)
+            .field("warnings", &self.warnings.warnings().len())
+            .field("provisional_stamp", &self.provisional_stamp)
+            .field("watermark", &self.watermark)
             .finish()
     }
 }
 
+fn orientation_of(size: Size) -> Orientation {
+    if size.width > size.height {
+        Orientation::Landscape
+    } else {
+        Orientation::Portrait
+    }
+}
+
+fn unknown_link_anchor(identifier: String) -> PdfBuildError {
+    PdfBuildError::content(
+        format!("Unknown link anchor `{}`", identifier),
+        Error::new(
+            format!("no section has the identifier `{}`", identifier),
+            ErrorKind::InvalidData,
+        ),
+    )
+}
+
+fn invalid_content(message: impl Into<String>) -> PdfBuildError {
+    let message = message.into();
+    PdfBuildError::content(message.clone(), Error::new(message, ErrorKind::InvalidData))
+}
+
+/// Calls `visit` for the images in `blocks`, including those inside quotes, rows, and
+/// keep-together groups.
+fn visit_images<'a>(blocks: &'a [Block], visit: &mut dyn FnMut(&'a ImageBlock)) {
+    for block in blocks {
+        match block {
+            Block::Image(image) => visit(image),
+            Block::Quote(quote) => visit_images(quote.blocks(), visit),
+            Block::KeepTogether(blocks) => visit_images(blocks, visit),
+            Block::Row(columns) => {
+                for column in columns {
+                    visit_images(column.blocks(), visit);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn decode_image_source(source: &ImageSource) -> Result<image::DynamicImage, Error> {
+    match source {
+        ImageSource::Bytes(bytes) => decode_image_from_bytes(bytes),
+        ImageSource::Path(path) => decode_image_from_path(path),
+    }
+}
+
+fn image_error(source: &ImageSource, location: &BlockLocation, cause: Error) -> PdfBuildError {
+    PdfBuildError::ImageDecode {
+        section: location.section.clone(),
+        block_index: location.index,
+        source: match source {
+            ImageSource::Bytes(bytes) => format!("{} bytes of image data", bytes.len()),
+            ImageSource::Path(path) => path.clone(),
+        },
+        cause,
+    }
+}
+
 fn mm_from_f64(value: f64) -> Mm {
     Mm::from(printpdf::Mm(value))
 }
 
+fn mm_to_f64(value: Mm) -> f64 {
+    let mm: printpdf::Mm = value.into();
+    mm.0
+}
+
+/// Builds a paragraph from `strings`, or an empty paragraph when there are none.
+fn paragraph_from_strings(strings: Vec<StyledString>) -> Paragraph {
+    let mut strings = strings.into_iter();
+    let mut element = match strings.next() {
+        Some(first) => Paragraph::new(first),
+        None => Paragraph::new(StyledString::new(String::new(), Style::new())),
+    };
+    for string in strings {
+        element.push(string);
+    }
+    element
+}
+
+/// Joins the text of the spans of a caption without their styling.
+fn plain_text(caption: &RichParagraph) -> String {
+    caption.spans().iter().map(Span::text).collect()
+}
+
+fn numbered_title(number: Option<&str>, title: &str) -> String {
+    match number {
+        Some(number) => format!("{} {}", number, title),
+        None => title.to_string(),
+    }
+}
+
+/// Leaves `lines` of vertical space unless it is not positive.
+fn push_space<S: ElementSink>(document: &mut S, lines: f64) {
+    if lines > 0.0 {
+        document.push_element(LineBreak::new(lines));
+    }
+}
+
+/// Records the next outline entry on `tracker` and returns its outline number, if numbered.
+fn outline_entry<S: ElementSink>(
+    document: &mut S,
+    next_index: &mut usize,
+    numbers: &[Option<String>],
+    tracker: Option<&PageTrackerHandle>,
+) -> Option<String> {
+    if let Some(tracker) = tracker {
+        document.push_element(SectionMarker::new(tracker.clone(), *next_index));
+    }
+    let number = numbers.get(*next_index).cloned().flatten();
+    *next_index += 1;
+    number
+}
+
+/// Returns `true` when a span of `paragraph` is linked, underlined, struck through, raised or
+/// lowered, sized, or a footnote marker, which only the custom text elements can print.
+fn is_decorated(paragraph: &RichParagraph) -> bool {
+    paragraph.spans().iter().any(|span| {
+        span.link().is_some()
+            || span.internal_link().is_some()
+            || span.is_underlined()
+            || span.is_strikethrough()
+            || span.vertical_offset() != VerticalOffset::Normal
+            || span.footnote_text().is_some()
+            || span.font_size().is_some()
+    })
+}
+
+/// Returns `true` when `paragraph` sets letter, word, or line spacing, which only the custom text
+/// elements can print.
+fn is_spaced(paragraph: &RichParagraph) -> bool {
+    paragraph.word_spacing() != 0.0
+        || paragraph.line_spacing() != 1.0
+        || paragraph
+            .spans()
+            .iter()
+            .any(|span| span.letter_spacing() != 0.0)
+}
+
+/// Returns `style` with the line spacing of `paragraph`, or unchanged when the paragraph keeps the
+/// natural line height.
+fn line_spaced(mut style: Style, paragraph: &RichParagraph) -> Style {
+    if paragraph.line_spacing() != 1.0 {
+        style.set_line_spacing(paragraph.line_spacing());
+    }
+    style
+}
+
+/// Title of the last heading in `headings`, ordered by start page, that starts on or before `page`.
+fn active_heading(headings: &[(usize, String)], page: usize) -> Option<&str> {
+    headings
+        .iter()
+        .take_while(|(start, _)| *start <= page)
+        .last()
+        .map(|(_, title)| title.as_str())
+}
+
+/// Header line of [`HeaderStyle::RunningHeading`] that keeps its height when there is no title.
+struct RunningHeader {
+    title: Option<String>,
+}
+
+impl RunningHeader {
+    fn new(title: Option<String>) -> Self {
+        Self { title }
+    }
+}
+
+impl Element for RunningHeader {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: genpdf::render::Area<'_>,
+        style: Style,
+    ) -> Result<genpdf::RenderResult, Error> {
+        let mut result = match self.title.take() {
+            Some(title) => {
+                let mut paragraph = Paragraph::new(title);
+                paragraph.set_alignment(Alignment::Center);
+                paragraph.render(context, area.clone(), style)?
+            }
+            None => genpdf::RenderResult::default(),
+        };
+        result.size.height = result
+            .size
+            .height
+            .max(style.line_height(&context.font_cache));
+        result.has_more = false;
+        Ok(result)
+    }
+}
+
 struct BoxedElement {
     inner: Box<dyn Element>,
 }
@@ -899,6 +5585,7 @@ impl Element for BoxedElement {
     }
 }
 
+#[derive(Clone)]
 struct FooterConfig {
     height: Mm,
     factory: SharedFooterFactory,
@@ -916,3 +5603,685 @@ impl FooterConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nested_builder() -> PdfBuilder {
+        PdfBuilder::new().add_section(
+            Section::new("Chapter")
+                .with_subsection(Section::new("Background"))
+                .with_subsection(Section::new("Findings")),
+        )
+    }
+
+    #[test]
+    fn subsections_produce_headings_at_two_levels() {
+        let builder = nested_builder();
+        let headings = builder.section_headings(&[Some(2), Some(2), Some(3)]);
+
+        let levels: Vec<_> = headings
+            .iter()
+            .map(|heading| (heading.level, heading.title.as_str(), heading.page))
+            .collect();
+        assert_eq!(
+            levels,
+            vec![
+                (1, "Chapter", Some(2)),
+                (2, "Background", Some(2)),
+                (2, "Findings", Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn toc_lists_subsections_beneath_their_parent() {
+        let builder = nested_builder();
+        let entries = builder.toc_entries(Some(&[Some(2), Some(2), Some(3)]));
+
+        assert_eq!(
+            entries,
+            vec![
+                TocEntry {
+                    level: 1,
+                    number: None,
+                    title: "Chapter".into(),
+                    page: Some(2),
+                },
+                TocEntry {
+                    level: 2,
+                    number: None,
+                    title: "Background".into(),
+                    page: Some(2),
+                },
+                TocEntry {
+                    level: 2,
+                    number: None,
+                    title: "Findings".into(),
+                    page: Some(3),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn toc_depth_hides_deeper_headings() {
+        let builder = PdfBuilder::new()
+            .add_section(Section::new("Chapter").with_subsection(
+                Section::new("Background").with_subsection(Section::new("Details")),
+            ))
+            .with_toc_depth(2);
+
+        let levels: Vec<_> = builder
+            .toc_entries(Some(&[Some(2), Some(2), Some(3)]))
+            .into_iter()
+            .map(|entry| (entry.level, entry.title.into_owned()))
+            .collect();
+        assert_eq!(
+            levels,
+            vec![(1, "Chapter".to_string()), (2, "Background".to_string())]
+        );
+    }
+
+    #[test]
+    fn toc_depth_counts_body_headings_below_their_section() {
+        let builder = PdfBuilder::new().add_section(
+            Section::new("Results")
+                .with_block(Block::heading(1, vec![Span::new("Findings")]))
+                .with_block(Block::heading(2, vec![Span::new("Outliers")])),
+        );
+        let pages = [Some(2), Some(2), Some(3)];
+        let rows = |builder: &PdfBuilder| -> Vec<_> {
+            builder
+                .toc_entries(Some(&pages))
+                .into_iter()
+                .map(|entry| (entry.level, entry.title.into_owned(), entry.page))
+                .collect()
+        };
+
+        assert_eq!(
+            rows(&builder),
+            vec![
+                (1, "Results".to_string(), Some(2)),
+                (2, "Findings".to_string(), Some(2)),
+                (3, "Outliers".to_string(), Some(3)),
+            ]
+        );
+        let builder = builder.with_toc_depth(1);
+        assert_eq!(rows(&builder), vec![(1, "Results".to_string(), Some(2))]);
+        let builder = builder.with_toc_depth(2);
+        assert_eq!(rows(&builder).len(), 2);
+    }
+
+    #[test]
+    fn justified_default_applies_to_unaligned_paragraphs() {
+        let builder = PdfBuilder::new().with_default_alignment(HorizontalAlignment::Justified);
+
+        assert_eq!(
+            builder.effective_alignment(HorizontalAlignment::Left),
+            HorizontalAlignment::Justified
+        );
+        assert_eq!(
+            builder.effective_alignment(HorizontalAlignment::Center),
+            HorizontalAlignment::Center
+        );
+        assert_eq!(
+            builder.resolve_alignment(HorizontalAlignment::Justified),
+            Alignment::Left
+        );
+    }
+
+    #[test]
+    fn orientation_changes_split_sections_into_page_runs() {
+        let builder = PdfBuilder::new()
+            .with_cover(Cover::new("Report"))
+            .add_section(Section::new("Body"))
+            .add_section(Section::new("Wide").with_orientation(Orientation::Landscape))
+            .add_section(Section::new("Wider").with_orientation(Orientation::Landscape))
+            .add_section(Section::new("Closing"));
+
+        let runs = builder.page_runs();
+        assert_eq!(
+            runs,
+            vec![
+                PageRun {
+                    orientation: Orientation::Portrait,
+                    front_matter: true,
+                    sections: 0..1,
+                },
+                PageRun {
+                    orientation: Orientation::Landscape,
+                    front_matter: false,
+                    sections: 1..3,
+                },
+                PageRun {
+                    orientation: Orientation::Portrait,
+                    front_matter: false,
+                    sections: 3..4,
+                },
+            ]
+        );
+
+        let size = builder.landscape(true).page_size(Orientation::Landscape);
+        assert!(size.width > size.height);
+    }
+
+    #[test]
+    fn page_of_total_footer_prints_the_discovered_total() {
+        assert_eq!(
+            FooterStyle::PageNumber.labelled_text("3", Some("12")),
+            "Page 3"
+        );
+        assert_eq!(
+            FooterStyle::PageOfTotal.labelled_text("3", Some("12")),
+            "Page 3 of 12"
+        );
+        assert_eq!(
+            FooterStyle::PageOfTotal.labelled_text("3", None),
+            "Page 3 of ?"
+        );
+    }
+
+    #[test]
+    fn footer_formats_expand_known_tokens() {
+        let tokens = parse_footer_format("{section} | {page} / {total} {x} {date}{");
+        assert_eq!(
+            expand_footer_format(&tokens, "3", Some("12"), Some("2. Usage"), "2024-05-01"),
+            "2. Usage | 3 / 12 {x} 2024-05-01{"
+        );
+        assert_eq!(
+            expand_footer_format(&tokens, "1", None, None, "2024-05-01"),
+            " | 1 / ? {x} 2024-05-01{"
+        );
+        assert_eq!(
+            parse_footer_format("}{{page}"),
+            vec![FooterToken::Text("}{".to_string()), FooterToken::Page]
+        );
+
+        let strict = PdfBuilder::new()
+            .footer_format("Page {page} by {author}")
+            .strict_footer_format(true);
+        assert!(matches!(
+            strict.check_footer_format(),
+            Err(PdfBuildError::FooterFormat { token }) if token == "{author}"
+        ));
+        assert!(strict
+            .strict_footer_format(false)
+            .check_footer_format()
+            .is_ok());
+    }
+
+    #[test]
+    fn section_ranges_must_select_existing_sections() {
+        assert_eq!(resolve_section_range(&(1..4), 5).unwrap(), 1..4);
+        assert_eq!(resolve_section_range(&(2..=4), 5).unwrap(), 2..5);
+        assert_eq!(resolve_section_range(&(3..), 5).unwrap(), 3..5);
+        assert_eq!(resolve_section_range(&(..), 2).unwrap(), 0..2);
+
+        for (range, expected) in [
+            (resolve_section_range(&(4..7), 5), (4, 7)),
+            (resolve_section_range(&(2..2), 5), (2, 2)),
+            (resolve_section_range(&(..), 0), (0, 0)),
+        ] {
+            match range {
+                Err(PdfBuildError::SectionRange { start, end, .. }) => {
+                    assert_eq!((start, end), expected)
+                }
+                other => panic!("expected a section range error, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn numbering_is_shared_by_toc_entries_and_headings() {
+        let builder = nested_builder().number_sections(true);
+
+        let toc: Vec<_> = builder
+            .toc_entries(None)
+            .into_iter()
+            .map(|entry| numbered_title(entry.number.as_deref(), &entry.title))
+            .collect();
+        assert_eq!(toc, vec!["1. Chapter", "1.1 Background", "1.2 Findings"]);
+
+        let headings = builder.section_headings(&[None, None, None]);
+        let numbers: Vec<_> = headings
+            .iter()
+            .map(|heading| heading.number.as_deref())
+            .collect();
+        assert_eq!(numbers, vec![Some("1."), Some("1.1"), Some("1.2")]);
+        assert_eq!(headings[1].title, "Background");
+    }
+
+    #[test]
+    fn only_captioned_images_count_as_figures() {
+        let source = ImageSource::from_bytes(Vec::new());
+        let captioned = ImageBlock::new(source.clone())
+            .with_caption(RichParagraph::new(vec![Span::new("Chart")]));
+        let bare = ImageBlock::new(source);
+
+        let builder = PdfBuilder::new().number_figures(true);
+        let state = RenderState::default();
+        let numbers: Vec<_> = [&captioned, &bare, &captioned]
+            .into_iter()
+            .map(|block| builder.next_figure_number(&state, block))
+            .collect();
+        assert_eq!(numbers, vec![Some(1), None, Some(2)]);
+
+        let unnumbered = PdfBuilder::new();
+        assert_eq!(unnumbered.next_figure_number(&state, &captioned), None);
+    }
+
+    #[test]
+    fn caption_lists_follow_the_numbering_order() {
+        let figure = |caption: &str| {
+            Block::Image(
+                ImageBlock::new(ImageSource::from_bytes(Vec::new()))
+                    .with_caption(RichParagraph::new(vec![Span::new(caption).bold()])),
+            )
+        };
+        let table = TableBlock::new(vec![RichParagraph::new(vec![Span::new("Metric")])])
+            .with_caption(RichParagraph::new(vec![Span::new("Totals")]));
+        let builder = PdfBuilder::new()
+            .with_cover(Cover::new("Report").with_block(figure("Overview")))
+            .add_section(
+                Section::new("Results")
+                    .with_block(Block::image(ImageSource::from_bytes(Vec::new())))
+                    .with_block(Block::Quote(QuoteBlock::new(vec![figure("Quoted")])))
+                    .with_block(Block::Table(table))
+                    .with_subsection(Section::new("Detail").with_block(figure("Detail"))),
+            );
+
+        assert!(builder.caption_lists().is_empty());
+
+        let lists = builder
+            .include_list_of_figures(true)
+            .include_list_of_tables(true)
+            .caption_lists();
+        let captions: Vec<_> = lists
+            .iter()
+            .map(|list| (list.title, list.captions.clone()))
+            .collect();
+        assert_eq!(
+            captions,
+            vec![
+                (
+                    "List of Figures",
+                    vec!["Overview".to_string(), "Quoted".into(), "Detail".into()]
+                ),
+                ("List of Tables", vec!["Totals".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn page_number_styles_format_roman_and_alphabetic_labels() {
+        assert_eq!(PageNumberStyle::Decimal.format(14), "14");
+        assert_eq!(PageNumberStyle::LowerRoman.format(4), "iv");
+        assert_eq!(PageNumberStyle::UpperRoman.format(1994), "MCMXCIV");
+        assert_eq!(PageNumberStyle::LowerAlpha.format(3), "c");
+        assert_eq!(PageNumberStyle::UpperAlpha.format(28), "BB");
+    }
+
+    #[test]
+    fn page_label_footers_count_within_their_range() {
+        let scheme = PageLabelScheme::new().with_footer_labels(true);
+        assert_eq!(
+            scheme.labels(2, Some(4), Some(10)),
+            ("ii".to_string(), Some("iii".to_string()))
+        );
+        assert_eq!(
+            scheme.labels(5, Some(4), Some(10)),
+            ("2".to_string(), Some("7".to_string()))
+        );
+        assert_eq!(scheme.labels(3, None, None), ("iii".to_string(), None));
+        assert_eq!(
+            FooterStyle::PageOfTotal.labelled_text("ii", Some("iii")),
+            "Page ii of iii"
+        );
+    }
+
+    #[test]
+    fn running_heading_is_the_last_section_started_on_or_before_the_page() {
+        let headings = vec![
+            (2, "1. Intro".to_string()),
+            (4, "2. Results".to_string()),
+            (4, "3. Outlook".to_string()),
+        ];
+        assert_eq!(active_heading(&headings, 1), None);
+        assert_eq!(active_heading(&headings, 3), Some("1. Intro"));
+        assert_eq!(active_heading(&headings, 4), Some("3. Outlook"));
+        assert_eq!(active_heading(&headings, 9), Some("3. Outlook"));
+    }
+
+    #[test]
+    fn validation_reports_every_problem_at_once() {
+        let builder = PdfBuilder::new()
+            .with_margins_mm(20.0, 20.0, 20.0, 20.0)
+            .with_footer(400.0, |_| Paragraph::new("Footer"))
+            .add_section(
+                Section::new(" ").with_block(Block::image(ImageSource::from_bytes(
+                    b"not an image".to_vec(),
+                ))),
+            )
+            .add_section(Section::new("Links").with_block(Block::paragraph(vec![
+                Span::new("see").linked_to_section("missing"),
+            ])));
+
+        let messages: Vec<String> = builder
+            .validate()
+            .unwrap_err()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(messages.len(), 4, "{:?}", messages);
+        assert!(messages.contains(&"A section title is empty".to_string()));
+        assert!(messages.contains(
+            &"Failed to load image from 12 bytes of image data in block 0 of section ` `"
+                .to_string()
+        ));
+        assert!(messages
+            .iter()
+            .any(|message| message.starts_with("The footer height exceeds")));
+        assert!(messages.contains(&"Unknown link anchor `missing`".to_string()));
+
+        assert!(nested_builder().validate().is_ok());
+    }
+
+    #[test]
+    fn images_are_decoded_once_per_source() {
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(4, 2)
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .expect("encode png");
+        let block = ImageBlock::new(ImageSource::from_bytes(png.into_inner()));
+        let (builder, state) = (PdfBuilder::new(), RenderState::default());
+
+        builder
+            .build_image(&state, &block, None)
+            .expect("first pass");
+        builder
+            .build_image(&state, &block, None)
+            .expect("second pass");
+        assert_eq!(state.decoded_images.borrow().len(), 1);
+
+        let broken = ImageBlock::new(ImageSource::from_bytes(b"not an image".to_vec()));
+        assert!(builder.build_image(&state, &broken, None).is_err());
+        assert_eq!(state.decoded_images.borrow().len(), 1);
+    }
+
+    #[test]
+    fn cached_images_are_only_reused_for_the_same_bytes() {
+        use image::GenericImageView;
+
+        let png = |width| {
+            let mut png = std::io::Cursor::new(Vec::new());
+            image::DynamicImage::new_rgb8(width, 2)
+                .write_to(&mut png, image::ImageOutputFormat::Png)
+                .expect("encode png");
+            ImageSource::from_bytes(png.into_inner())
+        };
+        let (wide, narrow) = (png(8), png(4));
+        let (builder, state) = (PdfBuilder::new(), RenderState::default());
+        // Files the narrow image under the key of the wide one, as a hash collision would.
+        state.decoded_images.borrow_mut().insert(
+            ImageKey::new(&wide),
+            DecodedImage {
+                source: narrow.clone(),
+                image: Arc::new(image::DynamicImage::new_rgb8(4, 2)),
+            },
+        );
+
+        let image = builder.decoded_image(&state, &wide).expect("decodes");
+        assert_eq!(image.dimensions(), (8, 2));
+        let again = builder.decoded_image(&state, &wide).expect("cached");
+        assert!(Arc::ptr_eq(&image, &again));
+        assert!(builder.decoded_image(&state, &narrow).is_ok());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn predecoding_attributes_failures_to_their_block() {
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(4, 2)
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .expect("encode png");
+        let image = Block::image(ImageSource::from_bytes(png.into_inner()));
+        let builder = PdfBuilder::new().add_section(
+            Section::new("Figures")
+                .with_block(image.clone())
+                .with_block(image),
+        );
+        let state = RenderState::default();
+        builder.predecode_images(&state).expect("decodes");
+        assert_eq!(state.decoded_images.borrow().len(), 1);
+
+        let builder = builder.add_section(
+            Section::new("Broken")
+                .with_block(Block::paragraph(vec![Span::new("Intro")]))
+                .with_block(Block::image(ImageSource::from_bytes(
+                    b"not an image".to_vec(),
+                ))),
+        );
+        let err = builder
+            .predecode_images(&RenderState::default())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            PdfBuildError::ImageDecode { section: Some(ref title), block_index: 1, .. }
+                if title == "Broken"
+        ));
+    }
+
+    #[test]
+    fn heading_styles_override_only_their_own_level() {
+        let brand = HeadingStyle::new(22)
+            .with_color(Color::Rgb(0, 70, 140))
+            .with_space_before(1.0);
+        let builder = PdfBuilder::new()
+            .with_heading_style(1, brand)
+            .with_heading_style(HeadingLevel::CoverSubtitle, HeadingStyle::new(16));
+
+        assert_eq!(builder.heading_style(HeadingLevel::Section(1)), brand);
+        assert_eq!(
+            builder.heading_style(HeadingLevel::Section(2)).font_size(),
+            14
+        );
+        assert_eq!(
+            builder.heading_style(HeadingLevel::Section(5)).font_size(),
+            12
+        );
+        assert_eq!(
+            builder
+                .heading_style(HeadingLevel::CoverSubtitle)
+                .font_size(),
+            16
+        );
+        let title = builder.heading_style(HeadingLevel::CoverTitle);
+        assert_eq!(
+            (title.font_size(), title.is_bold(), title.space_after()),
+            (28, true, 1.5)
+        );
+        assert_eq!(HeadingLevel::from(0), HeadingLevel::Section(1));
+    }
+
+    #[test]
+    fn later_options_override_the_theme() {
+        let builder = PdfBuilder::new()
+            .with_heading_style(4, HeadingStyle::new(10))
+            .with_theme(Theme::academic())
+            .with_default_alignment(HorizontalAlignment::Left);
+
+        assert_eq!(builder.default_alignment, HorizontalAlignment::Left);
+        assert_eq!(builder.header_style, HeaderStyle::RunningHeading);
+        assert_eq!(builder.toc_title.as_deref(), Some("Table of Contents"));
+        assert_eq!(
+            builder.heading_style(HeadingLevel::Section(1)).font_size(),
+            16
+        );
+        assert_eq!(
+            builder.heading_style(HeadingLevel::Section(4)).font_size(),
+            12
+        );
+    }
+
+    #[test]
+    fn body_headings_are_numbered_between_their_section_and_its_subsections() {
+        let builder = PdfBuilder::new().number_sections(true).add_section(
+            Section::new("Chapter")
+                .with_block(Block::heading(1, vec![Span::new("Method")]))
+                .with_subsection(Section::new("Findings")),
+        );
+
+        let headings: Vec<_> = builder
+            .section_headings(&[Some(1), Some(2), Some(3)])
+            .into_iter()
+            .map(|heading| (heading.level, heading.number, heading.title, heading.page))
+            .collect();
+        assert_eq!(
+            headings,
+            vec![
+                (1, Some("1.".to_string()), "Chapter".to_string(), Some(1)),
+                (2, Some("1.1".to_string()), "Method".to_string(), Some(2)),
+                (2, Some("1.2".to_string()), "Findings".to_string(), Some(3)),
+            ]
+        );
+        assert_eq!(builder.toc_entries(None)[1].title, "Method");
+    }
+
+    #[test]
+    fn render_results_can_move_between_threads() {
+        fn assert_send<T: Send>() {}
+        assert_send::<PdfRenderResult>();
+        assert_send::<PdfBuildError>();
+    }
+
+    #[test]
+    fn renderers_may_capture_thread_local_state() {
+        let pages = Rc::new(Cell::new(0));
+        let (header_pages, footer_pages) = (Rc::clone(&pages), Rc::clone(&pages));
+        let builder = PdfBuilder::new()
+            .with_header(move |page| {
+                header_pages.set(page);
+                Paragraph::new("Header")
+            })
+            .with_footer(10.0, move |page| {
+                footer_pages.set(page);
+                Paragraph::new("Footer")
+            });
+        assert!(builder.custom_header.is_some());
+        assert!(builder.custom_footer.is_some());
+    }
+
+    #[test]
+    fn page_tracker_can_move_between_threads() {
+        fn assert_send<T: Send>() {}
+        assert_send::<PageTrackerHandle>();
+
+        let tracker = PageTrackerHandle::new(2);
+        let handle = tracker.clone();
+        std::thread::spawn(move || {
+            handle.set_current_page(3);
+            handle.mark_section(1);
+        })
+        .join()
+        .expect("tracker thread panicked");
+        assert_eq!(tracker.pages(), vec![None, Some(3)]);
+    }
+
+    #[test]
+    fn caption_templates_style_the_numbered_prefix() {
+        let caption = RichParagraph::new(vec![Span::new("Revenue by region")]);
+        let text = |strings: &[StyledString]| {
+            strings
+                .iter()
+                .map(|string| string.s.as_str())
+                .collect::<String>()
+        };
+
+        let (default, state) = (PdfBuilder::new(), RenderState::default());
+        let strings = default.caption_strings(&state, &caption, "Figure", Some(3));
+        assert_eq!(text(&strings), "Figure 3: Revenue by region");
+        assert!(strings[0].style.is_bold());
+
+        let builder = PdfBuilder::new().with_caption_template(
+            CaptionTemplate::new("{label} {n}. {text} (draft)")
+                .with_bold(false)
+                .with_italic(true),
+        );
+        let strings = builder.caption_strings(&state, &caption, "Table", Some(2));
+        assert_eq!(text(&strings), "Table 2. Revenue by region (draft)");
+        assert_eq!(strings[0].s, "Table 2. ");
+        assert!(strings[0].style.is_italic());
+        assert!(!strings[0].style.is_bold());
+        assert!(!strings[1].style.is_italic());
+
+        // Unnumbered captions are printed as they are.
+        let strings = builder.caption_strings(&state, &caption, "Figure", None);
+        assert_eq!(text(&strings), "Revenue by region");
+    }
+
+    #[test]
+    fn decimal_cells_take_the_style_of_their_first_span() {
+        let cell = RichParagraph::new(vec![Span::new("1").bold(), Span::new(".5").italic()]);
+        let text = table_cell_text(&cell);
+        assert_eq!(text.s, "1.5");
+        assert!(text.style.is_bold());
+        assert!(!text.style.is_italic());
+    }
+
+    #[test]
+    fn document_fonts_are_loaded_once_per_font_source() {
+        let bundled = fonts::bundled_fonts_source_dir();
+        let directory =
+            std::env::temp_dir().join(format!("pdf_helper_loaded_fonts_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).expect("create font directory");
+        for variant in ["Regular", "Bold", "Italic", "BoldItalic"] {
+            let file = format!("Roboto-{variant}.ttf");
+            if std::fs::copy(bundled.join(&file), directory.join(&file)).is_err() {
+                let _ = std::fs::remove_dir_all(&directory);
+                eprintln!("Skipping font cache assertions: bundled fonts are not available");
+                return;
+            }
+        }
+
+        let source = FontSource::directory(&directory, "Roboto");
+        let builder = PdfBuilder::new().with_font_source(source.clone());
+        let loaded = builder.document_font_family().map(|(_, source)| source);
+        // Later documents reuse the loaded family even though the files are gone.
+        std::fs::remove_dir_all(&directory).expect("remove font directory");
+        let reused = builder.document_font_family().map(|(_, source)| source);
+        let reloaded = builder.with_font_source(source).document_font_family();
+
+        let expected = ResolvedFontSource::Directory(directory);
+        assert_eq!(loaded.expect("fonts load"), expected);
+        assert_eq!(reused.expect("fonts are reused"), expected);
+        assert!(matches!(reloaded, Err(PdfBuildError::FontLoad(_))));
+    }
+
+    #[cfg(feature = "transparency")]
+    #[test]
+    fn translucent_and_placeholder_colors_are_found_in_the_model() {
+        let opaque = PdfBuilder::new().add_section(Section::new("Chapter").with_block(
+            Block::paragraph(vec![Span::new("Plain").colored(Color::Rgb(200, 0, 0))]),
+        ));
+        assert!(!opaque.uses_translucent_colors());
+        assert!(!opaque.may_draw_placeholder_colors());
+
+        let faint = Span::new("Faint").with_color_alpha(Color::Rgb(200, 0, 0), 96);
+        let translucent = PdfBuilder::new().add_section(Section::new("Chapter").with_block(
+            Block::Quote(QuoteBlock::new(vec![Block::paragraph(vec![faint])])),
+        ));
+        assert!(translucent.uses_translucent_colors());
+        assert!(!translucent.may_draw_placeholder_colors());
+
+        let watermarked =
+            PdfBuilder::new().with_watermark("DRAFT", WatermarkOptions::new().with_opacity(0.5));
+        assert!(watermarked.uses_translucent_colors());
+
+        let keyed = PdfBuilder::new().with_page_background(Color::Cmyk(0, 0, 1, 1));
+        assert!(keyed.may_draw_placeholder_colors());
+        let custom = PdfBuilder::new().with_header(|_| Paragraph::new("Header"));
+        assert!(custom.may_draw_placeholder_colors());
+    }
+}
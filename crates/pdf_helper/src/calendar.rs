@@ -0,0 +1,62 @@
+//! Conversion of system times into UTC calendar dates.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Calendar fields of a time in UTC: year, month, day, hour, minute, and second.
+pub(crate) type DateFields = (i64, i64, i64, i64, i64, i64);
+
+/// Splits `time` into UTC calendar fields, using the proleptic Gregorian calendar.
+pub(crate) fn utc_fields(time: SystemTime) -> DateFields {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => elapsed.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs_f64().ceil() as i64),
+    };
+    let days = seconds.div_euclid(86_400);
+    let time_of_day = seconds.rem_euclid(86_400);
+
+    // Shift the epoch to 0000-03-01 so leap days fall at the end of each 400-year era.
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (
+        year,
+        month,
+        day,
+        time_of_day / 3_600,
+        time_of_day % 3_600 / 60,
+        time_of_day % 60,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn times_convert_to_utc_calendar_dates() {
+        assert_eq!(utc_fields(at(0)), (1970, 1, 1, 0, 0, 0));
+        assert_eq!(utc_fields(at(951_782_400)), (2000, 2, 29, 0, 0, 0));
+        assert_eq!(utc_fields(at(1_700_000_000)), (2023, 11, 14, 22, 13, 20));
+        assert_eq!(
+            utc_fields(UNIX_EPOCH - Duration::from_secs(1)),
+            (1969, 12, 31, 23, 59, 59)
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Non-fatal diagnostics collected while rendering a document.
+//!
+//! Rendering favours producing a document over failing outright, so layout problems such as
+//! content that does not fit into a fixed-size region are reported as [`RenderWarning`] values
+//! instead of errors, as are falling back to a substitute font family and leaving out an optional
+//! image that failed to load.  Elements record warnings
+//! through a shared [`WarningSink`], and
+//! [`PdfBuilder`](crate::builder::PdfBuilder) surfaces the collected list on the render result.
+
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Maximum number of characters kept in the preview of clipped content.
+const CLIPPED_PREVIEW_CHARS: usize = 40;
+
+/// Non-fatal issue detected while rendering a document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenderWarning {
+    /// Content did not fit into a fixed-size region and the remainder was dropped.
+    ContentClipped {
+        /// Description of the region that clipped the content.
+        location: String,
+        /// Beginning of the text that was dropped from the output.
+        dropped_preview: String,
+    },
+    /// The requested font family was unavailable and a substitute family was used instead.
+    FontFallback {
+        /// Name of the font family that could not be loaded.
+        requested: String,
+        /// Name of the family used in its place.
+        substitute: String,
+    },
+    /// An optional image, such as the cover logo, could not be loaded and was left out.
+    ImageSkipped {
+        /// Description of where the image would have been placed.
+        location: String,
+        /// Reason the image could not be loaded.
+        reason: String,
+    },
+}
+
+impl RenderWarning {
+    /// Creates a [`RenderWarning::ContentClipped`] warning, shortening the dropped text to a preview.
+    pub fn content_clipped(location: impl Into<String>, dropped: &str) -> Self {
+        let dropped = dropped.trim();
+        let mut dropped_preview: String = dropped.chars().take(CLIPPED_PREVIEW_CHARS).collect();
+        if dropped.chars().count() > CLIPPED_PREVIEW_CHARS {
+            dropped_preview.push('…');
+        }
+
+        Self::ContentClipped {
+            location: location.into(),
+            dropped_preview,
+        }
+    }
+}
+
+impl fmt::Display for RenderWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ContentClipped {
+                location,
+                dropped_preview,
+            } => write!(
+                f,
+                "Content clipped in {}; dropped text begins with \"{}\"",
+                location, dropped_preview
+            ),
+            Self::FontFallback {
+                requested,
+                substitute,
+            } => write!(
+                f,
+                "Font family '{}' unavailable; rendered with '{}' instead",
+                requested, substitute
+            ),
+            Self::ImageSkipped { location, reason } => {
+                write!(f, "Image for {} skipped: {}", location, reason)
+            }
+        }
+    }
+}
+
+/// Shared collector that elements use to report warnings during rendering.
+///
+/// Cloning the sink yields another handle to the same underlying list, so a clone can be moved
+/// into header or footer closures while the builder keeps the original.
+#[derive(Clone, Debug, Default)]
+pub struct WarningSink {
+    warnings: Arc<Mutex<Vec<RenderWarning>>>,
+}
+
+impl WarningSink {
+    /// Creates an empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a warning.
+    pub fn push(&self, warning: RenderWarning) {
+        self.lock().push(warning);
+    }
+
+    /// Returns a copy of the warnings recorded so far.
+    pub fn warnings(&self) -> Vec<RenderWarning> {
+        self.lock().clone()
+    }
+
+    /// Removes and returns every recorded warning.
+    pub fn take(&self) -> Vec<RenderWarning> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    /// Discards every recorded warning.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Locks the shared list; a panic in another handle leaves it usable.
+    fn lock(&self) -> MutexGuard<'_, Vec<RenderWarning>> {
+        self.warnings.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clipped_preview_is_shortened() {
+        let dropped = "word ".repeat(20);
+        let warning = RenderWarning::content_clipped("footer", &dropped);
+
+        let RenderWarning::ContentClipped {
+            location,
+            dropped_preview,
+        } = &warning
+        else {
+            panic!("expected a clipped-content warning, got {warning:?}");
+        };
+        assert_eq!(location, "footer");
+        assert!(dropped_preview.starts_with("word word"));
+        assert!(dropped_preview.ends_with('…'));
+        assert_eq!(dropped_preview.chars().count(), CLIPPED_PREVIEW_CHARS + 1);
+    }
+
+    #[test]
+    fn sink_clones_share_warnings() {
+        let sink = WarningSink::new();
+        let handle = sink.clone();
+        handle.push(RenderWarning::content_clipped("cell", "overflow"));
+
+        assert_eq!(sink.warnings().len(), 1);
+        assert_eq!(sink.take().len(), 1);
+        assert!(handle.warnings().is_empty());
+    }
+}
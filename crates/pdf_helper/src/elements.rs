@@ -1,23 +1,59 @@
 //! Extended element implementations built on top of `genpdf` primitives.
 //!
 //! This module adds convenience wrappers for rendering images with captions, helpers for decoding
-//! image data, and custom text elements that the upstream crate does not ship with.
+//! image data, and custom text elements that the upstream crate does not ship with.  The text
+//! elements share a small word-wrapping routine so they can lay out styled spans across multiple
+//! lines while keeping per-span decorations such as underlines.
 
+use std::cell::Cell;
+use std::io::Cursor;
 use std::path::Path;
+use std::rc::Rc;
 
 use image::GenericImageView;
 
 use genpdf::elements::{Image, Paragraph};
-use genpdf::error::{Context as _, Error};
-use genpdf::style::{Style, StyledString};
-use genpdf::{render, Alignment, Element, Mm, Position, RenderResult, Scale, Size};
+use genpdf::error::{Context as _, Error, ErrorKind};
+use genpdf::fonts::{Font, FontCache, FontFamily};
+use genpdf::style::{Color, LineStyle, Style, StyledString};
+use genpdf::{render, Alignment, Element, Margins, Mm, Position, RenderResult, Scale, Size};
 
-use crate::richtext::StyledSpan;
+use crate::diagnostics::{RenderWarning, WarningSink};
+use crate::layout::{LayoutTracker, LinkArea, LinkTarget, Rect};
+use crate::model::{
+    BorderStyle, CaptionPosition, LegendOrientation, RichParagraph, TableBorders, TextDirection,
+};
+use crate::richtext::{points_to_mm, Span, StyledSpan, UnderlineStyle, VerticalOffset};
 
 const DEFAULT_IMAGE_DPI: f64 = 300.0;
 const MM_PER_INCH: f64 = 25.4;
 const DEFAULT_CAPTION_SPACING_MM: f64 = 2.0;
 const DEFAULT_UNDERLINE_OFFSET_MM: f64 = 0.4;
+/// Thickness of `genpdf`'s default line style, used by underlines without a thickness.
+const DEFAULT_STROKE_THICKNESS_MM: f64 = 0.1;
+/// Smallest distance between the strokes of a double underline.
+const DOUBLE_UNDERLINE_GAP_MM: f64 = 0.5;
+/// Smallest length of the dashes of a dotted underline, which are as far apart as they are long.
+const UNDERLINE_DOT_MM: f64 = 0.4;
+/// Smallest height of a wavy underline, which is also the distance between its peaks and troughs.
+const UNDERLINE_WAVE_MM: f64 = 0.6;
+/// Height of the strikethrough stroke above the baseline as a fraction of the glyph height.
+const STRIKETHROUGH_RISE_RATIO: f64 = 0.3;
+/// Font size of superscript and subscript text as a fraction of the surrounding text.
+const SCRIPT_FONT_SCALE: f64 = 0.65;
+/// Distance superscript baselines are raised as a fraction of the surrounding glyph height.
+const SUPERSCRIPT_RISE_RATIO: f64 = 0.35;
+/// Distance subscript baselines are lowered as a fraction of the surrounding glyph height.
+const SUBSCRIPT_DROP_RATIO: f64 = 0.15;
+const DEFAULT_DECIMAL_SEPARATOR: char = '.';
+const DEFAULT_LIST_INDENT_MM: f64 = 8.0;
+const LEGEND_SWATCH_MM: f64 = 3.5;
+const LEGEND_LABEL_GAP_MM: f64 = 2.0;
+const LEGEND_ENTRY_SPACING_MM: f64 = 6.0;
+const DEFAULT_WATERMARK_FONT_SIZE: u8 = 48;
+const DEFAULT_CALLOUT_BAR_MM: f64 = 1.2;
+const DEFAULT_CALLOUT_INDENT_MM: f64 = 6.0;
+const DEFAULT_CALLOUT_PADDING_MM: f64 = 1.5;
 
 fn mm_from_f64(value: f64) -> Mm {
     Mm::from(printpdf::Mm(value))
@@ -28,8 +64,7 @@ fn mm_to_f64(value: Mm) -> f64 {
     mm.0
 }
 
-fn estimated_image_size(image: &image::DynamicImage, dpi: f64) -> Size {
-    let (px_width, px_height) = image.dimensions();
+fn estimated_image_size((px_width, px_height): (u32, u32), dpi: f64) -> Size {
     let width_mm = MM_PER_INCH * (px_width as f64) / dpi;
     let height_mm = MM_PER_INCH * (px_height as f64) / dpi;
     Size::new(mm_from_f64(width_mm), mm_from_f64(height_mm))
@@ -52,22 +87,55 @@ pub fn decode_image_from_path(path: impl AsRef<Path>) -> Result<image::DynamicIm
         .with_context(|| format!("Failed to decode image file {}", path.display()))
 }
 
-fn image_from_dynamic(image: image::DynamicImage) -> Result<(Image, Size), Error> {
-    let size = estimated_image_size(&image, DEFAULT_IMAGE_DPI);
-    let image = Image::from_dynamic_image(image)?;
+/// Reads the pixel dimensions of encoded image bytes without decoding the pixel data.
+pub fn image_dimensions_from_bytes(bytes: impl AsRef<[u8]>) -> Result<(u32, u32), Error> {
+    image::io::Reader::new(Cursor::new(bytes.as_ref()))
+        .with_guessed_format()
+        .context("Unable to determine image format")?
+        .into_dimensions()
+        .context("Failed to read image dimensions from provided bytes")
+}
+
+/// Reads the pixel dimensions of the image at `path` without decoding the pixel data.
+pub fn image_dimensions_from_path(path: impl AsRef<Path>) -> Result<(u32, u32), Error> {
+    let path = path.as_ref();
+    let reader = image::io::Reader::open(path)
+        .with_context(|| format!("Failed to open image file {}", path.display()))?;
+    reader
+        .with_guessed_format()
+        .context("Unable to determine image format")?
+        .into_dimensions()
+        .with_context(|| format!("Failed to read image dimensions from {}", path.display()))
+}
+
+fn image_from_dynamic(image: image::DynamicImage, dpi: f64) -> Result<(Image, Size), Error> {
+    let size = estimated_image_size(image.dimensions(), dpi);
+    let image = Image::from_dynamic_image(image)?.with_dpi(dpi);
     Ok((image, size))
 }
 
-/// Converts the provided image bytes into a `genpdf` image together with its estimated size.
+/// Converts the provided image bytes into a `genpdf` image together with its estimated size at
+/// 300 DPI.
 pub fn image_from_bytes(bytes: impl AsRef<[u8]>) -> Result<(Image, Size), Error> {
+    image_from_bytes_at_dpi(bytes, DEFAULT_IMAGE_DPI)
+}
+
+/// Converts the provided image bytes into a `genpdf` image sized for a resolution of `dpi`.
+pub fn image_from_bytes_at_dpi(bytes: impl AsRef<[u8]>, dpi: f64) -> Result<(Image, Size), Error> {
     let dynamic = decode_image_from_bytes(bytes)?;
-    image_from_dynamic(dynamic)
+    image_from_dynamic(dynamic, dpi)
 }
 
-/// Converts the image at `path` into a `genpdf` image together with its estimated size.
+/// Converts the image at `path` into a `genpdf` image together with its estimated size at
+/// 300 DPI.
 pub fn image_from_path(path: impl AsRef<Path>) -> Result<(Image, Size), Error> {
+    image_from_path_at_dpi(path, DEFAULT_IMAGE_DPI)
+}
+
+/// Converts the image at `path` into a `genpdf` image sized for a resolution of `dpi`.
+pub fn image_from_path_at_dpi(path: impl AsRef<Path>, dpi: f64) -> Result<(Image, Size), Error> {
     let dynamic = decode_image_from_path(path)?;
-    image_from_dynamic(dynamic)
+    image_from_dynamic(dynamic, dpi)
 }
 
 fn default_caption_spacing() -> Mm {
@@ -78,18 +146,159 @@ fn default_underline_offset() -> Mm {
     mm_from_f64(DEFAULT_UNDERLINE_OFFSET_MM)
 }
 
-/// A convenience element that renders an image with an optional caption stacked underneath.
+/// Line style of the underline and strikethrough strokes drawn for text styled with
+/// `fragment_style` inside an element rendered with `style`.
+fn decoration_style(fragment_style: Style, style: Style) -> LineStyle {
+    let mut line_style = LineStyle::new();
+    if let Some(color) = fragment_style.color().or(style.color()) {
+        line_style = line_style.with_color(color);
+    }
+    line_style
+}
+
+/// Draws a horizontal stroke from `start` to `end` at `y` below the top of `area`.
+fn draw_stroke(area: &render::Area<'_>, start: Mm, end: Mm, y: Mm, line_style: LineStyle) {
+    area.draw_line(
+        vec![Position::new(start, y), Position::new(end, y)],
+        line_style,
+    );
+}
+
+/// Polylines, as `(x, y)` points in millimetres, that draw an underline with the stroke pattern
+/// `style` from `start` to `end` at `y`, for strokes `weight` millimetres thick.
+///
+/// Thicker strokes space the patterns out so doubled lines, dots, and waves stay distinct.
+fn underline_strokes(
+    style: UnderlineStyle,
+    start: f64,
+    end: f64,
+    y: f64,
+    weight: f64,
+) -> Vec<Vec<(f64, f64)>> {
+    let scale = |minimum: f64| (weight * 3.0).max(minimum);
+    match style {
+        UnderlineStyle::Single => vec![vec![(start, y), (end, y)]],
+        UnderlineStyle::Double => {
+            let gap = scale(DOUBLE_UNDERLINE_GAP_MM);
+            vec![
+                vec![(start, y), (end, y)],
+                vec![(start, y + gap), (end, y + gap)],
+            ]
+        }
+        UnderlineStyle::Dotted => {
+            let dot = scale(UNDERLINE_DOT_MM);
+            let mut strokes = Vec::new();
+            let mut x = start;
+            while x < end {
+                strokes.push(vec![(x, y), ((x + dot).min(end), y)]);
+                x += dot * 2.0;
+            }
+            strokes
+        }
+        UnderlineStyle::Wavy => {
+            if end <= start {
+                return Vec::new();
+            }
+            let height = scale(UNDERLINE_WAVE_MM);
+            let crest = |index: usize| if index % 2 == 0 { y } else { y + height };
+            let mut points = Vec::new();
+            let mut x = start;
+            while x < end {
+                points.push((x, crest(points.len())));
+                x += height;
+            }
+            points.push((end, crest(points.len())));
+            vec![points]
+        }
+    }
+}
+
+/// Draws the underline of `fragment` from `start` to `end` at `y` below the top of `area`.
+fn draw_underline(
+    area: &render::Area<'_>,
+    fragment: &LineFragment,
+    start: Mm,
+    end: Mm,
+    y: Mm,
+    mut line_style: LineStyle,
+) {
+    let weight = match fragment.underline_thickness {
+        Some(thickness) => {
+            line_style = line_style.with_thickness(thickness);
+            mm_to_f64(thickness)
+        }
+        None => DEFAULT_STROKE_THICKNESS_MM,
+    };
+    let strokes = underline_strokes(
+        fragment.underline_style,
+        mm_to_f64(start),
+        mm_to_f64(end),
+        mm_to_f64(y),
+        weight,
+    );
+    for points in strokes {
+        let points = points
+            .into_iter()
+            .map(|(x, y)| Position::new(mm_from_f64(x), mm_from_f64(y)))
+            .collect();
+        area.draw_line(points, line_style);
+    }
+}
+
+/// Distance between the baseline and the strikethrough stroke, roughly the middle of lowercase
+/// glyphs.
+fn strikethrough_rise(context: &genpdf::Context, style: Style) -> Mm {
+    let glyph_height = style
+        .font(&context.font_cache)
+        .glyph_height(style.font_size());
+    mm_from_f64(mm_to_f64(glyph_height) * STRIKETHROUGH_RISE_RATIO)
+}
+
+/// Style used to print text with the given vertical offset, shrinking raised and lowered text.
+fn script_style(mut style: Style, offset: VerticalOffset) -> Style {
+    if offset != VerticalOffset::Normal {
+        let font_size = (f64::from(style.font_size()) * SCRIPT_FONT_SCALE).round() as u8;
+        style.set_font_size(font_size.max(1));
+    }
+    style
+}
+
+/// Distance the baseline of text in `style` moves up for `offset`; negative values move it down.
+fn baseline_shift(context: &genpdf::Context, style: Style, offset: VerticalOffset) -> Mm {
+    let glyph_height = mm_to_f64(
+        style
+            .font(&context.font_cache)
+            .glyph_height(style.font_size()),
+    );
+    match offset {
+        VerticalOffset::Normal => Mm::default(),
+        VerticalOffset::Super => mm_from_f64(glyph_height * SUPERSCRIPT_RISE_RATIO),
+        VerticalOffset::Sub => mm_from_f64(-glyph_height * SUBSCRIPT_DROP_RATIO),
+    }
+}
+
+/// A convenience element that renders an image with an optional caption stacked underneath or,
+/// with [`CaptionPosition::Above`], on top of it.
 ///
 /// The image and the caption share the same alignment and the image can be rescaled to a specific
-/// width while keeping the aspect ratio.  The element supports creating the image from raw bytes or
-/// file paths, delegating the decoding to the [`image`] crate to provide friendly error messages.
+/// width while keeping the aspect ratio.  An optional [`BorderStyle`] frames the scaled image.
+/// The element supports creating the image from raw bytes or file paths, delegating the decoding
+/// to the [`image`] crate to provide friendly error messages.
 pub struct CaptionedImage {
     image: Image,
     caption: Paragraph,
     alignment: Alignment,
     natural_size: Size,
+    base_scale: (f64, f64),
     requested_width: Option<Mm>,
+    requested_percent: Option<f64>,
+    max_height: Option<Mm>,
+    caption_strings: Vec<StyledString>,
+    border: Option<BorderStyle>,
+    scale: f64,
     spacing: Mm,
+    caption_position: CaptionPosition,
+    dpi: f64,
 }
 
 impl CaptionedImage {
@@ -99,8 +308,16 @@ impl CaptionedImage {
             caption,
             alignment: Alignment::Left,
             natural_size,
+            base_scale: (1.0, 1.0),
             requested_width: None,
+            requested_percent: None,
+            max_height: None,
+            caption_strings: Vec::new(),
+            border: None,
+            scale: 1.0,
             spacing: default_caption_spacing(),
+            caption_position: CaptionPosition::Below,
+            dpi: DEFAULT_IMAGE_DPI,
         };
         element.apply_alignment();
         element
@@ -111,7 +328,7 @@ impl CaptionedImage {
         image: image::DynamicImage,
         caption: Paragraph,
     ) -> Result<Self, Error> {
-        let (image, size) = image_from_dynamic(image)?;
+        let (image, size) = image_from_dynamic(image, DEFAULT_IMAGE_DPI)?;
         Ok(Self::new(image, caption, size))
     }
 
@@ -127,6 +344,44 @@ impl CaptionedImage {
         Ok(Self::new(image, caption, size))
     }
 
+    /// Creates a stand-in that occupies the same space as an image of `dimensions` pixels.
+    ///
+    /// A single pixel is stretched to the image's size, so the element lays out exactly like the
+    /// decoded image without paying for decoding and embedding it.  This suits layout passes whose
+    /// output is discarded.
+    pub fn placeholder(dimensions: (u32, u32), caption: Paragraph) -> Result<Self, Error> {
+        let image = Image::from_dynamic_image(image::DynamicImage::new_rgb8(1, 1))?;
+        let size = estimated_image_size(dimensions, DEFAULT_IMAGE_DPI);
+        let mut element = Self::new(image, caption, size);
+        element.base_scale = (f64::from(dimensions.0), f64::from(dimensions.1));
+        element.apply_scale(None, Mm::default());
+        Ok(element)
+    }
+
+    /// Replaces the caption with a paragraph made of `strings`.
+    ///
+    /// Unlike a caption paragraph passed to the constructors, the height of these strings can be
+    /// measured before rendering, so [`CaptionedImage::set_max_height`] accounts for them.
+    pub fn set_caption_strings(&mut self, strings: Vec<StyledString>) {
+        let mut rest = strings.iter().cloned();
+        let first = rest
+            .next()
+            .unwrap_or_else(|| StyledString::new(String::new(), Style::new()));
+        let mut caption = Paragraph::new(first);
+        for string in rest {
+            caption.push(string);
+        }
+        self.caption = caption;
+        self.caption_strings = strings;
+        self.apply_alignment();
+    }
+
+    /// Replaces the caption with `strings` and returns the updated element.
+    pub fn with_caption_strings(mut self, strings: Vec<StyledString>) -> Self {
+        self.set_caption_strings(strings);
+        self
+    }
+
     /// Returns a mutable reference to the caption paragraph for additional customization.
     pub fn caption_mut(&mut self) -> &mut Paragraph {
         &mut self.caption
@@ -149,6 +404,29 @@ impl CaptionedImage {
         self
     }
 
+    /// Sets the resolution of the image in dots per inch, which determines its natural size.
+    ///
+    /// Images start out at 300 DPI; values that are not positive are ignored.
+    pub fn set_dpi(&mut self, dpi: f64) {
+        if dpi.is_nan() || dpi <= 0.0 {
+            return;
+        }
+        let ratio = self.dpi / dpi;
+        self.natural_size = Size::new(
+            self.natural_size.width * ratio,
+            self.natural_size.height * ratio,
+        );
+        self.dpi = dpi;
+        self.image.set_dpi(dpi);
+        self.apply_scale(None, Mm::default());
+    }
+
+    /// Sets the resolution in dots per inch and returns the updated element.
+    pub fn with_dpi(mut self, dpi: f64) -> Self {
+        self.set_dpi(dpi);
+        self
+    }
+
     /// Sets the spacing between the image and the caption.
     pub fn set_spacing(&mut self, spacing: Mm) {
         self.spacing = spacing;
@@ -160,10 +438,26 @@ impl CaptionedImage {
         self
     }
 
+    /// Places the caption above or below the image.
+    ///
+    /// The spacing separates the caption from the image in either position.
+    pub fn set_caption_position(&mut self, position: CaptionPosition) {
+        self.caption_position = position;
+    }
+
+    /// Sets the caption position and returns the updated element.
+    pub fn with_caption_position(mut self, position: CaptionPosition) -> Self {
+        self.set_caption_position(position);
+        self
+    }
+
     /// Constrains the rendered width of the image while preserving the aspect ratio.
+    ///
+    /// An absolute width takes precedence over a width set with
+    /// [`CaptionedImage::set_width_percent`].
     pub fn set_width(&mut self, width: Option<Mm>) {
         self.requested_width = width;
-        self.apply_width();
+        self.apply_scale(None, Mm::default());
     }
 
     /// Constrains the rendered width and returns the updated element.
@@ -172,22 +466,143 @@ impl CaptionedImage {
         self
     }
 
+    /// Shrinks the image so that it, the caption spacing, and a caption set with
+    /// [`CaptionedImage::set_caption_strings`] fit into `height`, preserving the aspect ratio.
+    ///
+    /// When a requested width would make the image taller, the smaller scale wins.  Images are
+    /// never enlarged to reach the height.
+    pub fn set_max_height(&mut self, height: Option<Mm>) {
+        self.max_height = height;
+        self.apply_scale(None, Mm::default());
+    }
+
+    /// Sets the maximum height and returns the updated element.
+    pub fn with_max_height(mut self, height: impl Into<Option<Mm>>) -> Self {
+        self.set_max_height(height.into());
+        self
+    }
+
+    /// Draws a frame around the image, or removes it with `None`.
+    ///
+    /// The frame surrounds the image at its scaled size and the caption sits outside it.  Relative
+    /// widths and the maximum height leave room for the frame, while an absolute width applies to
+    /// the image itself.
+    pub fn set_border(&mut self, border: Option<BorderStyle>) {
+        self.border = border;
+        self.apply_scale(None, Mm::default());
+    }
+
+    /// Sets the frame drawn around the image and returns the updated element.
+    pub fn with_border(mut self, border: impl Into<Option<BorderStyle>>) -> Self {
+        self.set_border(border.into());
+        self
+    }
+
+    /// Scales the image to `percent` of the width of the area it is rendered into, preserving the
+    /// aspect ratio.
+    ///
+    /// The width is resolved on every render, so the image follows the content width of the page
+    /// it lands on.
+    pub fn set_width_percent(&mut self, percent: Option<f64>) {
+        self.requested_percent = percent;
+    }
+
+    /// Sets the relative width and returns the updated element.
+    pub fn with_width_percent(mut self, percent: impl Into<Option<f64>>) -> Self {
+        self.set_width_percent(percent.into());
+        self
+    }
+
     fn apply_alignment(&mut self) {
         self.image.set_alignment(self.alignment);
         self.caption.set_alignment(self.alignment);
     }
 
-    fn apply_width(&mut self) {
-        if let Some(width) = self.requested_width {
-            let natural = mm_to_f64(self.natural_size.width);
-            if natural > f64::EPSILON {
-                let desired = mm_to_f64(width);
-                let scale = desired / natural;
-                self.image.set_scale(Scale::new(scale, scale));
-            }
-        } else {
-            self.image.set_scale(Scale::new(1.0, 1.0));
+    /// Scales the image to the requested width and maximum height, resolving a relative width
+    /// against `available_width` when it is known.
+    fn apply_scale(&mut self, available_width: Option<Mm>, caption_height: Mm) {
+        let (base_x, base_y) = self.base_scale;
+        let frame = self.border_inset() * 2.0;
+        let scale = image_scale(
+            self.natural_size,
+            self.requested_width.or_else(|| {
+                self.requested_percent
+                    .zip(available_width)
+                    .map(|(percent, available)| {
+                        mm_from_f64(mm_to_f64(available) * percent / 100.0) - frame
+                    })
+            }),
+            self.max_height
+                .map(|height| height - self.spacing - caption_height - frame),
+        );
+        self.scale = scale;
+        self.image
+            .set_scale(Scale::new(base_x * scale, base_y * scale));
+    }
+
+    /// Distance between the outer edge of the frame and the image, or zero without a frame.
+    fn border_inset(&self) -> Mm {
+        self.border
+            .map(|border| mm_from_f64(border.inset_mm()))
+            .unwrap_or_default()
+    }
+
+    /// Strokes the frame around an image of `image_size` that was aligned within `area` after
+    /// insetting it by the frame on the left and right.
+    fn draw_border(&self, area: &render::Area<'_>, image_size: Size) {
+        let border = match self.border {
+            Some(border) if border.thickness_mm() > 0.0 => border,
+            _ => return,
+        };
+        let inset = self.border_inset();
+        let inner_width = area.size().width - inset * 2.0;
+        let image_x = inset
+            + match self.alignment {
+                Alignment::Left => Mm::default(),
+                Alignment::Center => (inner_width - image_size.width) / 2.0,
+                Alignment::Right => inner_width - image_size.width,
+            };
+        // Lines are stroked along their center, so the path runs half a thickness inside the frame.
+        let half = mm_from_f64(border.thickness_mm() / 2.0);
+        let left = image_x - inset + half;
+        let right = image_x + image_size.width + inset - half;
+        let top = half;
+        let bottom = image_size.height + inset * 2.0 - half;
+        area.draw_line(
+            vec![
+                Position::new(left, top),
+                Position::new(right, top),
+                Position::new(right, bottom),
+                Position::new(left, bottom),
+                Position::new(left, top),
+            ],
+            LineStyle::new()
+                .with_color(border.color())
+                .with_thickness(mm_from_f64(border.thickness_mm())),
+        );
+    }
+
+    /// Moves `area` past the spacing between the image and the caption and counts it in `result`.
+    fn skip_spacing(&self, area: &mut render::Area<'_>, result: &mut RenderResult) {
+        area.add_offset(Position::new(0, self.spacing));
+        if mm_to_f64(self.spacing) > 0.0 {
+            result.size = result.size.stack_vertical(Size::new(0, self.spacing));
+        }
+    }
+
+    /// Height of the caption strings when wrapped to `width`, or zero for other captions.
+    fn caption_height(&self, context: &genpdf::Context, style: Style, width: Mm) -> Mm {
+        if self.caption_strings.is_empty() {
+            return Mm::default();
         }
+        let spans: Vec<StyledSpan> = self
+            .caption_strings
+            .iter()
+            .map(|string| StyledSpan::new(string.clone(), false))
+            .collect();
+        wrap_spans(context, &spans, style, width)
+            .iter()
+            .fold(Mm::default(), |height, line| height + line.line_height)
     }
 }
 
@@ -199,32 +614,84 @@ impl Element for CaptionedImage {
         style: Style,
     ) -> Result<RenderResult, Error> {
         self.apply_alignment();
-        self.apply_width();
+        let width = area.size().width;
+        let caption_height = self.caption_height(context, style, width);
+        self.apply_scale(Some(width), caption_height);
 
         let mut result = RenderResult::default();
-        let image_result = self.image.render(context, area.clone(), style)?;
-        result.size = result.size.stack_vertical(image_result.size);
-        result.has_more |= image_result.has_more;
+        if self.caption_position == CaptionPosition::Above {
+            let caption_result = self.caption.render(context, area.clone(), style)?;
+            result.size = result.size.stack_vertical(caption_result.size);
+            if caption_result.has_more {
+                // The rest of the caption continues on the next page, followed by the image.
+                result.has_more = true;
+                return Ok(result);
+            }
+            area.add_offset(Position::new(0, caption_result.size.height));
+            self.skip_spacing(&mut area, &mut result);
+        }
 
-        let spacing = self.spacing;
-        area.add_offset(Position::new(0, image_result.size.height + spacing));
-        if mm_to_f64(spacing) > 0.0 {
-            result.size = result.size.stack_vertical(Size::new(0, spacing));
+        let inset = self.border_inset();
+        let mut image_area = area.clone();
+        image_area.add_margins(Margins::trbl(inset, inset, 0, inset));
+        let image_result = self.image.render(context, image_area, style)?;
+        let mut image_height = image_result.size.height;
+        if self.border.is_some() && !image_result.has_more {
+            let image_size = Size::new(
+                mm_from_f64(mm_to_f64(self.natural_size.width) * self.scale),
+                mm_from_f64(mm_to_f64(self.natural_size.height) * self.scale),
+            );
+            self.draw_border(&area, image_size);
+            image_height += inset * 2.0;
         }
+        result.size = result
+            .size
+            .stack_vertical(Size::new(image_result.size.width, image_height));
+        result.has_more |= image_result.has_more;
 
-        let caption_result = self.caption.render(context, area, style)?;
-        result.size = result.size.stack_vertical(caption_result.size);
-        result.has_more |= caption_result.has_more;
+        if self.caption_position == CaptionPosition::Below {
+            area.add_offset(Position::new(0, image_height));
+            self.skip_spacing(&mut area, &mut result);
+            let caption_result = self.caption.render(context, area, style)?;
+            result.size = result.size.stack_vertical(caption_result.size);
+            result.has_more |= caption_result.has_more;
+        }
 
         Ok(result)
     }
 }
 
-/// A single line of styled text that supports underlines by drawing thin strokes underneath.
+/// Scale factor that fits an image of `natural` size to `width` and keeps it within
+/// `max_height`, never enlarging it to reach the height.
+fn image_scale(natural: Size, width: Option<Mm>, max_height: Option<Mm>) -> f64 {
+    let (natural_width, natural_height) = (mm_to_f64(natural.width), mm_to_f64(natural.height));
+    let mut scale = match width {
+        Some(width) if natural_width > f64::EPSILON => mm_to_f64(width) / natural_width,
+        _ => 1.0,
+    };
+    if let Some(height) = max_height {
+        if natural_height > f64::EPSILON {
+            scale = scale.min((mm_to_f64(height) / natural_height).max(0.0));
+        }
+    }
+    scale
+}
+
+/// Styled text that supports underlines and strikethrough by drawing thin strokes.
+///
+/// The text wraps at word boundaries and each line segment receives its own strokes.  Every line
+/// is aligned on its own, and a line wider than the available width, such as a single unbreakable
+/// word, starts at the left edge.  Justified text stretches all lines but the last to the full
+/// width, like [`JustifiedText`].  Lines that do not fit on the current page continue on the next
+/// one.  With a [`LayoutTracker`] attached, the rectangles of linked spans are recorded so they can
+/// be turned into clickable link annotations.
 pub struct UnderlinedText {
     spans: Vec<StyledSpan>,
     alignment: Alignment,
+    justified: bool,
     underline_offset: Mm,
+    next_line: usize,
+    layout: Option<LayoutTracker>,
 }
 
 impl UnderlinedText {
@@ -233,7 +700,10 @@ impl UnderlinedText {
         Self {
             spans,
             alignment: Alignment::Left,
+            justified: false,
             underline_offset: default_underline_offset(),
+            next_line: 0,
+            layout: None,
         }
     }
 
@@ -245,7 +715,7 @@ impl UnderlinedText {
         Self::new(spans.into_iter().collect())
     }
 
-    /// Sets the alignment for the rendered line.
+    /// Sets the alignment applied to every line.
     pub fn set_alignment(&mut self, alignment: Alignment) {
         self.alignment = alignment;
     }
@@ -256,6 +726,19 @@ impl UnderlinedText {
         self
     }
 
+    /// Stretches every line except the last, and lines ending in a line feed, to the full width.
+    ///
+    /// The alignment still applies to the lines that are not stretched.
+    pub fn set_justified(&mut self, justified: bool) {
+        self.justified = justified;
+    }
+
+    /// Enables or disables justification and returns the updated element.
+    pub fn with_justified(mut self, justified: bool) -> Self {
+        self.set_justified(justified);
+        self
+    }
+
     /// Sets the distance between the baseline and the underline stroke.
     pub fn set_underline_offset(&mut self, offset: Mm) {
         self.underline_offset = offset;
@@ -266,91 +749,3092 @@ impl UnderlinedText {
         self.set_underline_offset(offset);
         self
     }
+
+    /// Attaches the tracker that records the rectangles of linked spans.
+    pub fn with_layout(mut self, layout: LayoutTracker) -> Self {
+        self.layout = Some(layout);
+        self
+    }
 }
 
 impl Element for UnderlinedText {
     fn render(
         &mut self,
         context: &genpdf::Context,
-        mut area: render::Area<'_>,
+        area: render::Area<'_>,
         style: Style,
     ) -> Result<RenderResult, Error> {
-        let mut prepared: Vec<(StyledString, bool, Mm)> = Vec::with_capacity(self.spans.len());
-        let mut total_width = Mm::default();
-        let mut max_line_height = style.line_height(&context.font_cache);
-        let mut max_glyph_height = Mm::default();
-
-        for span in &self.spans {
-            let mut string = span.string.clone();
-            string.style = style.and(string.style);
-            let width = string.width(&context.font_cache);
-            total_width += width;
-            max_line_height = max_line_height.max(string.style.line_height(&context.font_cache));
-            let glyph_height = string
-                .style
-                .font(&context.font_cache)
-                .glyph_height(string.style.font_size());
-            max_glyph_height = max_glyph_height.max(glyph_height);
-            prepared.push((string, span.underline, width));
+        let available_width = area.size().width;
+        let lines = wrap_spans(context, &self.spans, style, available_width);
+        print_lines(
+            &area,
+            &lines,
+            &mut self.next_line,
+            lines.len(),
+            |line_area, line| {
+                if self.justified && !line.ends_paragraph {
+                    let printed = print_justified_line(
+                        context,
+                        line_area,
+                        line,
+                        available_width,
+                        self.underline_offset,
+                        style,
+                        self.layout.as_ref(),
+                    )?;
+                    Ok(printed.then_some(available_width))
+                } else {
+                    let printed = print_wrapped_line(
+                        context,
+                        line_area,
+                        line,
+                        aligned_offset(self.alignment, available_width, line.width),
+                        self.underline_offset,
+                        style,
+                        self.layout.as_ref(),
+                    )?;
+                    Ok(printed.then_some(line.width))
+                }
+            },
+        )
+    }
+}
+
+/// Prints `lines` from `next_line` up to `stop` below each other with `print_line`, advancing
+/// `next_line` past every printed line.
+///
+/// `print_line` receives the area moved down to the line and returns the printed width, or `None`
+/// when the area cannot host the line.  The result is as wide as the widest printed line and has
+/// more content when a line before `stop` does not fit.
+fn print_lines<F>(
+    area: &render::Area<'_>,
+    lines: &[WrappedLine],
+    next_line: &mut usize,
+    stop: usize,
+    mut print_line: F,
+) -> Result<RenderResult, Error>
+where
+    F: FnMut(&render::Area<'_>, &WrappedLine) -> Result<Option<Mm>, Error>,
+{
+    let mut result = RenderResult::default();
+    let mut width = Mm::default();
+    let mut offset = Mm::default();
+    while *next_line < stop {
+        let line = &lines[*next_line];
+        if offset + line.line_height > area.size().height {
+            result.has_more = true;
+            break;
         }
 
-        let available_width = area.size().width;
-        let x_offset = match self.alignment {
-            Alignment::Left => Mm::default(),
-            Alignment::Center => (available_width - total_width) / 2.0,
-            Alignment::Right => available_width - total_width,
+        let mut line_area = area.clone();
+        line_area.add_offset(Position::new(0, offset));
+        let Some(line_width) = print_line(&line_area, line)? else {
+            result.has_more = true;
+            break;
         };
 
-        if max_line_height > area.size().height {
-            let mut result = RenderResult::default();
-            result.has_more = true;
-            return Ok(result);
+        width = width.max(line_width);
+        offset += line.line_height;
+        *next_line += 1;
+    }
+
+    result.size = Size::new(width, offset);
+    Ok(result)
+}
+
+/// Word-sized piece of styled text placed on a wrapped line.
+#[derive(Clone, Debug)]
+struct LineFragment {
+    string: StyledString,
+    underline: bool,
+    underline_style: UnderlineStyle,
+    underline_thickness: Option<Mm>,
+    strikethrough: bool,
+    link: Option<LinkTarget>,
+    width: Mm,
+    baseline_shift: Mm,
+    footnote: Option<usize>,
+    span_index: usize,
+    letter_spacing: Mm,
+    word_spacing: Mm,
+}
+
+impl LineFragment {
+    /// Returns `true` when the characters of the fragment have to be placed one by one.
+    fn is_spaced(&self) -> bool {
+        self.letter_spacing != Mm::default() || self.word_spacing != Mm::default()
+    }
+
+    /// Width of `text` printed in the style and with the spacing of the fragment.
+    fn measure(&self, context: &genpdf::Context, text: &str) -> Mm {
+        spaced_width(
+            &context.font_cache,
+            &StyledString::new(text.to_string(), self.string.style),
+            self.letter_spacing,
+            self.word_spacing,
+        )
+    }
+}
+
+/// Width of `string` with `letter_spacing` added after every character and `word_spacing` added
+/// to every space, matching how [`print_spaced`] places the characters.
+fn spaced_width(
+    font_cache: &FontCache,
+    string: &StyledString,
+    letter_spacing: Mm,
+    word_spacing: Mm,
+) -> Mm {
+    let characters = string.s.chars().count() as f64;
+    let spaces = string.s.matches(' ').count() as f64;
+    string.width(font_cache) + letter_spacing * characters + word_spacing * spaces
+}
+
+/// Width of `span` set in the fonts of `font_cache` at `font_size` points, measured the way the
+/// custom text elements lay it out.
+///
+/// A font size set on the span takes precedence over `font_size`, raised and lowered text is
+/// measured at its reduced size, and letter spacing is included.  Custom elements can use this
+/// to place text without reimplementing the measurement of [`UnderlinedText`].
+pub fn measure_span(span: &Span, font_size: u8, font_cache: &FontCache) -> Mm {
+    measure_styled_span(StyledSpan::from(span), font_size, font_cache)
+}
+
+/// Width of the spans of `paragraph` set on a single line without wrapping, as measured by
+/// [`measure_span`], including the word spacing of the paragraph.
+pub fn measure_paragraph(paragraph: &RichParagraph, font_size: u8, font_cache: &FontCache) -> Mm {
+    let word_spacing = points_to_mm(paragraph.word_spacing());
+    paragraph.spans().iter().fold(Mm::default(), |width, span| {
+        let span = StyledSpan::from(span).with_word_spacing(word_spacing);
+        width + measure_styled_span(span, font_size, font_cache)
+    })
+}
+
+fn measure_styled_span(span: StyledSpan, font_size: u8, font_cache: &FontCache) -> Mm {
+    let mut style = Style::new();
+    style.set_font_size(font_size);
+    let string = StyledString::new(
+        span.string.s,
+        script_style(style.and(span.string.style), span.vertical_offset),
+    );
+    spaced_width(font_cache, &string, span.letter_spacing, span.word_spacing)
+}
+
+/// Prints `text` of `fragment` one character at a time from `origin`, advancing by each
+/// character's width plus the letter and word spacing of the fragment.
+///
+/// `genpdf` offers no access to the character and word spacing operators of the text state, so
+/// the spacing is applied by positioning every character.  Returns `false` when the area cannot
+/// host a text section.
+fn print_spaced(
+    context: &genpdf::Context,
+    area: &render::Area<'_>,
+    fragment: &LineFragment,
+    text: &str,
+    origin: Position,
+    metrics: Style,
+) -> Result<bool, Error> {
+    let mut x = origin.x;
+    let mut buffer = [0; 4];
+    for ch in text.chars() {
+        let glyph: &str = ch.encode_utf8(&mut buffer);
+        match area.text_section(&context.font_cache, Position::new(x, origin.y), metrics) {
+            Some(mut section) => section.print_str(glyph, fragment.string.style)?,
+            None => return Ok(false),
         }
+        x += fragment.measure(context, glyph);
+    }
+    Ok(true)
+}
 
-        let mut result = RenderResult::default();
+/// A single wrapped line together with the metrics needed to print and decorate it.
+///
+/// `baseline_style` is the style of the tallest fragment.  Text sections opened with it place
+/// their baseline low enough for every fragment, so spans of different sizes share a baseline
+/// without the taller glyphs reaching into the previous line.  Superscript and subscript
+/// fragments are measured at the size of their span, so they never change the line height.
+#[derive(Clone, Debug, Default)]
+struct WrappedLine {
+    fragments: Vec<LineFragment>,
+    width: Mm,
+    line_height: Mm,
+    glyph_height: Mm,
+    baseline_style: Style,
+    ends_paragraph: bool,
+}
 
-        if let Some(mut section) =
-            area.text_section(&context.font_cache, Position::new(x_offset, 0), style)
-        {
-            for (string, _, _) in &prepared {
-                section.print_str(&string.s, string.style)?;
+impl WrappedLine {
+    fn new(style: Style, line_height: Mm) -> Self {
+        Self {
+            line_height,
+            baseline_style: style,
+            ..Self::default()
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.fragments.is_empty()
+    }
+
+    fn text(&self) -> String {
+        self.fragments
+            .iter()
+            .map(|fragment| fragment.string.s.as_str())
+            .collect()
+    }
+
+    fn push(
+        &mut self,
+        context: &genpdf::Context,
+        token: &str,
+        style: Style,
+        span: &StyledSpan,
+        index: usize,
+    ) {
+        let string =
+            StyledString::new(token.to_string(), script_style(style, span.vertical_offset));
+        let width = spaced_width(
+            &context.font_cache,
+            &string,
+            span.letter_spacing,
+            span.word_spacing,
+        );
+        self.width += width;
+        self.line_height = self.line_height.max(style.line_height(&context.font_cache));
+        let glyph_height = style
+            .font(&context.font_cache)
+            .glyph_height(style.font_size());
+        if glyph_height > self.glyph_height {
+            self.glyph_height = glyph_height;
+            self.baseline_style = style;
+        }
+
+        match self.fragments.last_mut() {
+            Some(last) if last.span_index == index => {
+                last.string.s.push_str(token);
+                last.width += width;
             }
-        } else {
-            result.has_more = true;
-            return Ok(result);
+            _ => self.fragments.push(LineFragment {
+                string,
+                underline: span.underline,
+                underline_style: span.underline_style,
+                underline_thickness: span.underline_thickness,
+                strikethrough: span.strikethrough,
+                link: span
+                    .link
+                    .clone()
+                    .map(LinkTarget::Uri)
+                    .or(span.page_link.map(LinkTarget::Page)),
+                width,
+                baseline_shift: baseline_shift(context, style, span.vertical_offset),
+                footnote: span.footnote,
+                span_index: index,
+                letter_spacing: span.letter_spacing,
+                word_spacing: span.word_spacing,
+            }),
         }
+    }
 
-        let baseline = max_glyph_height + self.underline_offset;
-        let mut cursor = x_offset;
-        for (string, underline, width) in &prepared {
-            if *underline {
-                let mut line_style = Style::new();
-                if let Some(color) = string.style.color().or(style.color()) {
-                    line_style = line_style.with_color(color);
-                }
-                area.draw_line(
-                    vec![
-                        Position::new(cursor, baseline),
-                        Position::new(cursor + *width, baseline),
-                    ],
-                    line_style,
+    /// Reorders the fragments of a right-to-left line from reading order into display order.
+    ///
+    /// Words are the unit of reordering: the word order is reversed and the characters of words
+    /// holding right-to-left script are reversed, while other words, such as numbers or Latin
+    /// names, keep their own order.  This covers right-to-left text with embedded single words
+    /// but is not a full implementation of the Unicode bidirectional algorithm, and Arabic letters
+    /// are not shaped into their joined forms.
+    fn reorder_right_to_left(&mut self, context: &genpdf::Context) {
+        let mut pieces = Vec::new();
+        for fragment in std::mem::take(&mut self.fragments) {
+            for token in split_words(&fragment.string.s) {
+                let mut piece = fragment.clone();
+                piece.string.s = right_to_left_display(token);
+                piece.width = piece.measure(context, &piece.string.s);
+                pieces.push(piece);
+            }
+        }
+        pieces.reverse();
+        self.fragments = pieces;
+    }
+
+    /// Drops trailing whitespace so alignment and underlines ignore the gap at the line end.
+    fn trim_end(&mut self, context: &genpdf::Context) {
+        if let Some(last) = self.fragments.last_mut() {
+            let trimmed_len = last.string.s.trim_end().len();
+            if trimmed_len < last.string.s.len() {
+                last.string.s.truncate(trimmed_len);
+                let width = last.measure(context, &last.string.s);
+                self.width = self.width - last.width + width;
+                last.width = width;
+            }
+        }
+    }
+}
+
+/// Returns `true` for letters of scripts written from right to left, such as Hebrew and Arabic.
+fn is_right_to_left(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{0590}'..='\u{08FF}' | '\u{FB1D}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFC}'
+    )
+}
+
+/// Display order of a word token of right-to-left text.
+///
+/// The whitespace following the word in reading order moves to its left, and the characters are
+/// reversed when the word holds right-to-left script.
+fn right_to_left_display(token: &str) -> String {
+    let word = token.trim_end();
+    let space = &token[word.len()..];
+    if word.chars().any(is_right_to_left) {
+        space.chars().chain(word.chars().rev()).collect()
+    } else {
+        format!("{}{}", space, word)
+    }
+}
+
+/// Splits `text` into words that keep their trailing whitespace, emitting line feeds separately.
+fn split_words(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut in_space = false;
+
+    for (index, ch) in text.char_indices() {
+        if ch == '\n' {
+            if start < index {
+                tokens.push(&text[start..index]);
+            }
+            tokens.push(&text[index..index + 1]);
+            start = index + 1;
+            in_space = false;
+        } else if ch.is_whitespace() {
+            in_space = true;
+        } else if in_space {
+            tokens.push(&text[start..index]);
+            start = index;
+            in_space = false;
+        }
+    }
+
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// Greedily wraps styled spans into lines no wider than `max_width`.
+///
+/// Words are never split: a single word wider than `max_width` occupies a line on its own.  Line
+/// feeds inside span text force a break.  Consecutive words from the same span are merged into a
+/// single fragment so decorations can be drawn per span.
+fn wrap_spans(
+    context: &genpdf::Context,
+    spans: &[StyledSpan],
+    style: Style,
+    max_width: Mm,
+) -> Vec<WrappedLine> {
+    let base_line_height = style.line_height(&context.font_cache);
+    let mut lines = Vec::new();
+    let mut current = WrappedLine::new(style, base_line_height);
+
+    for (index, span) in spans.iter().enumerate() {
+        let span_style = style.and(span.string.style);
+        for token in split_words(&span.string.s) {
+            if token == "\n" {
+                current.trim_end(context);
+                current.ends_paragraph = true;
+                lines.push(std::mem::replace(
+                    &mut current,
+                    WrappedLine::new(style, base_line_height),
+                ));
+                continue;
+            }
+
+            let word = token.trim_end();
+            if current.is_empty() && word.is_empty() {
+                continue;
+            }
+
+            let word_width = spaced_width(
+                &context.font_cache,
+                &StyledString::new(
+                    word.to_string(),
+                    script_style(span_style, span.vertical_offset),
+                ),
+                span.letter_spacing,
+                span.word_spacing,
+            );
+            if !current.is_empty() && current.width + word_width > max_width {
+                current.trim_end(context);
+                lines.push(std::mem::replace(
+                    &mut current,
+                    WrappedLine::new(style, base_line_height),
+                ));
+            }
+
+            current.push(context, token, span_style, span, index);
+        }
+    }
+
+    if !current.is_empty() || lines.is_empty() {
+        current.trim_end(context);
+        current.ends_paragraph = true;
+        lines.push(current);
+    }
+
+    lines
+}
+
+/// Prints a wrapped line at `x_offset` and draws the underline and strikethrough strokes of its
+/// fragments.
+///
+/// Returns `false` when the area cannot host a text section.  When a [`LayoutTracker`] is given,
+/// the rectangles of linked fragments and the pages of footnote markers are recorded in it.
+fn print_wrapped_line(
+    context: &genpdf::Context,
+    area: &render::Area<'_>,
+    line: &WrappedLine,
+    x_offset: Mm,
+    underline_offset: Mm,
+    style: Style,
+    layout: Option<&LayoutTracker>,
+) -> Result<bool, Error> {
+    // Fragments on the baseline share one text section; raised and lowered fragments interrupt
+    // it with a section of their own.
+    let mut section = None;
+    let mut cursor = x_offset;
+    for fragment in &line.fragments {
+        if fragment.is_spaced() {
+            section = None;
+            let (top, metrics) = fragment_section_metrics(context, line, fragment);
+            let origin = Position::new(cursor, top);
+            if !print_spaced(context, area, fragment, &fragment.string.s, origin, metrics)? {
+                return Ok(false);
+            }
+        } else if fragment.baseline_shift == Mm::default() {
+            if section.is_none() {
+                section = area.text_section(
+                    &context.font_cache,
+                    Position::new(cursor, 0),
+                    line.baseline_style,
                 );
             }
-            cursor += *width;
+            let Some(section) = section.as_mut() else {
+                return Ok(false);
+            };
+            section.print_str(&fragment.string.s, fragment.string.style)?;
+        } else {
+            section = None;
+            let (top, metrics) = fragment_section_metrics(context, line, fragment);
+            match area.text_section(&context.font_cache, Position::new(cursor, top), metrics) {
+                Some(mut script) => script.print_str(&fragment.string.s, fragment.string.style)?,
+                None => return Ok(false),
+            }
+        }
+        cursor += fragment.width;
+    }
+    drop(section);
+
+    let mut cursor = x_offset;
+    for fragment in &line.fragments {
+        draw_fragment_decorations(
+            context,
+            area,
+            fragment,
+            cursor,
+            cursor + fragment.width,
+            line.glyph_height - fragment.baseline_shift,
+            underline_offset,
+            style,
+        );
+        if let (Some(layout), Some(target)) = (layout, &fragment.link) {
+            register_link(
+                layout,
+                area,
+                cursor,
+                fragment.width,
+                line.line_height,
+                target,
+            );
         }
+        if let (Some(layout), Some(number)) = (layout, fragment.footnote) {
+            layout.place_footnote(number);
+        }
+        cursor += fragment.width;
+    }
+
+    Ok(true)
+}
+
+/// Returns the distance from the top of the line and the style of a text section that prints
+/// `fragment` on its shifted baseline.
+fn fragment_section_metrics(
+    context: &genpdf::Context,
+    line: &WrappedLine,
+    fragment: &LineFragment,
+) -> (Mm, Style) {
+    if fragment.baseline_shift == Mm::default() {
+        return (Mm::default(), line.baseline_style);
+    }
+    let style = fragment.string.style;
+    let glyph_height = style
+        .font(&context.font_cache)
+        .glyph_height(style.font_size());
+    let top = line.glyph_height - fragment.baseline_shift - glyph_height;
+    (top.max(Mm::default()), style)
+}
+
+/// Draws the underline and strikethrough strokes of a fragment printed from `start` to `end` on a
+/// line whose baseline lies `baseline` below the top of `area`.
+#[allow(clippy::too_many_arguments)]
+fn draw_fragment_decorations(
+    context: &genpdf::Context,
+    area: &render::Area<'_>,
+    fragment: &LineFragment,
+    start: Mm,
+    end: Mm,
+    baseline: Mm,
+    underline_offset: Mm,
+    style: Style,
+) {
+    let line_style = decoration_style(fragment.string.style, style);
+    if fragment.underline {
+        draw_underline(
+            area,
+            fragment,
+            start,
+            end,
+            baseline + underline_offset,
+            line_style,
+        );
+    }
+    if fragment.strikethrough {
+        let rise = strikethrough_rise(context, fragment.string.style);
+        draw_stroke(area, start, end, baseline - rise, line_style);
+    }
+}
+
+/// Records the rectangle of a linked fragment that starts `x_offset` into `area`.
+fn register_link(
+    layout: &LayoutTracker,
+    area: &render::Area<'_>,
+    x_offset: Mm,
+    width: Mm,
+    height: Mm,
+    target: &LinkTarget,
+) {
+    let (page, origin) = layout.locate(area);
+    layout.push_link(LinkArea {
+        page,
+        rect: Rect::new(
+            origin.x + mm_to_f64(x_offset),
+            origin.y,
+            mm_to_f64(width),
+            mm_to_f64(height),
+        ),
+        target: target.clone(),
+    });
+}
+
+/// Wraps an element so that everything it renders links to another page of the document.
+///
+/// The link covers the size the element reported, once per page the element spans, so elements
+/// that report the full width of their area are clickable across all of it.
+pub struct PageLink<E: Element> {
+    element: E,
+    target_page: usize,
+    layout: LayoutTracker,
+}
 
-        result.size = Size::new(total_width, max_line_height);
-        area.add_offset(Position::new(0, max_line_height));
+impl<E: Element> PageLink<E> {
+    /// Wraps `element` so that it links to the one-based `target_page`, recording link areas in
+    /// `layout`.
+    pub fn new(element: E, target_page: usize, layout: LayoutTracker) -> Self {
+        Self {
+            element,
+            target_page,
+            layout,
+        }
+    }
+}
 
+impl<E: Element> Element for PageLink<E> {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let result = self.element.render(context, area.clone(), style)?;
+        if result.size.height > Mm::default() {
+            let (page, origin) = self.layout.locate(&area);
+            self.layout.push_link(LinkArea {
+                page,
+                rect: Rect::new(
+                    origin.x,
+                    origin.y,
+                    mm_to_f64(result.size.width),
+                    mm_to_f64(result.size.height),
+                ),
+                target: LinkTarget::Page(self.target_page),
+            });
+        }
         Ok(result)
     }
 }
 
-impl<I> From<I> for UnderlinedText
-where
-    I: IntoIterator<Item = StyledSpan>,
-{
-    fn from(iter: I) -> Self {
-        Self::from_spans(iter)
+/// Paragraph whose lines are stretched to fill the available width.
+///
+/// Each line is measured word by word through the font cache and the remaining space is spread
+/// evenly across its inter-word gaps.  The last line of the paragraph, and any line ending in an
+/// explicit line feed, stays left aligned, as does a line holding a single word.  Lines that do
+/// not fit on the current page continue on the next one.  Words are never hyphenated.
+pub struct JustifiedText {
+    spans: Vec<StyledSpan>,
+    next_line: usize,
+    breaks: BreakControl,
+    layout: Option<LayoutTracker>,
+}
+
+impl JustifiedText {
+    /// Creates a justified paragraph from the provided spans.
+    pub fn new(spans: Vec<StyledSpan>) -> Self {
+        Self {
+            spans,
+            next_line: 0,
+            breaks: BreakControl::default(),
+            layout: None,
+        }
+    }
+
+    /// Keeps at least `before` lines above and `after` lines below a page break inside the
+    /// paragraph; see [`WrappedText::set_min_lines`].
+    pub fn set_min_lines(&mut self, before: usize, after: usize) {
+        self.breaks = BreakControl::new(before, after);
+    }
+
+    /// Sets the minimum number of lines around a page break and returns the updated element.
+    pub fn with_min_lines(mut self, before: usize, after: usize) -> Self {
+        self.set_min_lines(before, after);
+        self
+    }
+
+    /// Attaches the tracker that records the rectangles of linked spans.
+    pub fn with_layout(mut self, layout: LayoutTracker) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+}
+
+impl Element for JustifiedText {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let available_width = area.size().width;
+        let lines = wrap_spans(context, &self.spans, style, available_width);
+
+        let mut result = RenderResult::default();
+        let Some(stop) = self.breaks.stop(&lines, self.next_line, area.size().height) else {
+            result.size = Size::new(1, 0);
+            result.has_more = true;
+            return Ok(result);
+        };
+        let mut offset = Mm::default();
+        while self.next_line < stop {
+            let line = &lines[self.next_line];
+            if offset + line.line_height > area.size().height {
+                result.has_more = true;
+                break;
+            }
+
+            let mut line_area = area.clone();
+            line_area.add_offset(Position::new(0, offset));
+            let printed = if line.ends_paragraph {
+                print_wrapped_line(
+                    context,
+                    &line_area,
+                    line,
+                    Mm::default(),
+                    default_underline_offset(),
+                    style,
+                    self.layout.as_ref(),
+                )?
+            } else {
+                print_justified_line(
+                    context,
+                    &line_area,
+                    line,
+                    available_width,
+                    default_underline_offset(),
+                    style,
+                    self.layout.as_ref(),
+                )?
+            };
+            if !printed {
+                result.has_more = true;
+                break;
+            }
+
+            offset += line.line_height;
+            self.next_line += 1;
+        }
+
+        result.has_more |= self.next_line < lines.len();
+        result.size = Size::new(available_width, offset);
+        Ok(result)
+    }
+}
+
+/// Multi-line styled text that keeps span underlines, strikethrough, and hyperlinks.
+///
+/// `genpdf`'s paragraph drops everything a [`StyledString`] cannot express, so text carrying
+/// decorations or links is laid out by this element instead: spans wrap to the available width,
+/// every line is aligned on its own, and lines that do not fit continue on the next page.
+/// Right-to-left text is wrapped in reading order and every line is then reordered for display.
+pub struct WrappedText {
+    spans: Vec<StyledSpan>,
+    alignment: Alignment,
+    direction: TextDirection,
+    next_line: usize,
+    breaks: BreakControl,
+    layout: Option<LayoutTracker>,
+}
+
+impl WrappedText {
+    /// Creates a left-aligned text element from the provided spans.
+    pub fn new(spans: Vec<StyledSpan>) -> Self {
+        Self {
+            spans,
+            alignment: Alignment::Left,
+            direction: TextDirection::Ltr,
+            next_line: 0,
+            breaks: BreakControl::default(),
+            layout: None,
+        }
+    }
+
+    /// Keeps at least `before` lines above and `after` lines below a page break inside the
+    /// paragraph.
+    ///
+    /// A paragraph whose first `before` lines do not fit starts on the next page, and lines move
+    /// to the next page with the rest when fewer than `after` would follow the break.  Values of
+    /// 0 and 1 leave the break where the page ends.
+    pub fn set_min_lines(&mut self, before: usize, after: usize) {
+        self.breaks = BreakControl::new(before, after);
+    }
+
+    /// Sets the minimum number of lines around a page break and returns the updated element.
+    pub fn with_min_lines(mut self, before: usize, after: usize) -> Self {
+        self.set_min_lines(before, after);
+        self
+    }
+
+    /// Sets the alignment applied to every line.
+    pub fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+
+    /// Sets the alignment and returns the updated element.
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.set_alignment(alignment);
+        self
+    }
+
+    /// Sets the reading direction of the spans, which are always given in reading order.
+    pub fn set_direction(&mut self, direction: TextDirection) {
+        self.direction = direction;
+    }
+
+    /// Sets the reading direction and returns the updated element.
+    pub fn with_direction(mut self, direction: TextDirection) -> Self {
+        self.set_direction(direction);
+        self
+    }
+
+    /// Attaches the tracker that records the rectangles of linked spans.
+    pub fn with_layout(mut self, layout: LayoutTracker) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+}
+
+impl Element for WrappedText {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let available_width = area.size().width;
+        let mut lines = wrap_spans(context, &self.spans, style, available_width);
+        if self.direction == TextDirection::Rtl {
+            for line in &mut lines {
+                line.reorder_right_to_left(context);
+            }
+        }
+
+        let Some(stop) = self.breaks.stop(&lines, self.next_line, area.size().height) else {
+            let mut result = RenderResult::default();
+            result.size = Size::new(1, 0);
+            result.has_more = true;
+            return Ok(result);
+        };
+        let mut result = print_lines(
+            &area,
+            &lines,
+            &mut self.next_line,
+            stop,
+            |line_area, line| {
+                let printed = print_wrapped_line(
+                    context,
+                    line_area,
+                    line,
+                    aligned_offset(self.alignment, available_width, line.width),
+                    default_underline_offset(),
+                    style,
+                    self.layout.as_ref(),
+                )?;
+                Ok(printed.then_some(line.width))
+            },
+        )?;
+
+        result.has_more |= self.next_line < lines.len();
+        result.size.width = available_width;
+        Ok(result)
+    }
+}
+
+/// Widow and orphan control shared by the paragraph elements.
+#[derive(Clone, Copy, Debug, Default)]
+struct BreakControl {
+    before: usize,
+    after: usize,
+    deferred: bool,
+}
+
+impl BreakControl {
+    fn new(before: usize, after: usize) -> Self {
+        Self {
+            before,
+            after,
+            deferred: false,
+        }
+    }
+
+    /// Returns the index of the line the current page stops before, or `None` when the paragraph
+    /// should start on the next page instead.
+    ///
+    /// A paragraph is deferred at most once, so it cannot be pushed ahead indefinitely.
+    fn stop(&mut self, lines: &[WrappedLine], next: usize, available: Mm) -> Option<usize> {
+        let heights: Vec<Mm> = lines.iter().map(|line| line.line_height).collect();
+        match lines_before_break(&heights, next, available, self.before, self.after) {
+            Some(count) => Some(next + count),
+            None if !self.deferred => {
+                self.deferred = true;
+                None
+            }
+            None => Some(lines.len()),
+        }
+    }
+}
+
+/// Counts the lines from `next` onwards to print into `available` before a page break.
+///
+/// `heights` holds the height of every line of the paragraph.  At least `before` lines stay above
+/// a break in the first page of the paragraph, and at least `after` lines follow it on the next
+/// page; `None` asks for the paragraph to start on the next page.  A continued paragraph always
+/// prints the lines that fit when the limits cannot be met.
+fn lines_before_break(
+    heights: &[Mm],
+    next: usize,
+    available: Mm,
+    before: usize,
+    after: usize,
+) -> Option<usize> {
+    let remaining = heights.len().saturating_sub(next);
+    let mut used = Mm::default();
+    let fitting = heights[next.min(heights.len())..]
+        .iter()
+        .take_while(|height| {
+            used += **height;
+            used <= available
+        })
+        .count();
+    if fitting >= remaining {
+        return Some(remaining);
+    }
+
+    let kept = fitting.min(remaining.saturating_sub(after));
+    if next == 0 {
+        (kept >= before.max(1)).then_some(kept)
+    } else if kept == 0 {
+        Some(fitting)
+    } else {
+        Some(kept)
+    }
+}
+
+/// Prints a wrapped line word by word, widening every inter-word gap so the line fills
+/// `available_width`.
+///
+/// Returns `false` when the area cannot host a text section.
+fn print_justified_line(
+    context: &genpdf::Context,
+    area: &render::Area<'_>,
+    line: &WrappedLine,
+    available_width: Mm,
+    underline_offset: Mm,
+    style: Style,
+    layout: Option<&LayoutTracker>,
+) -> Result<bool, Error> {
+    let gaps = line
+        .fragments
+        .iter()
+        .flat_map(|fragment| split_words(&fragment.string.s))
+        .filter(|word| word.ends_with(char::is_whitespace))
+        .count();
+    let extra = justified_gap(available_width, line.width, gaps);
+
+    let mut cursor = Mm::default();
+    for fragment in &line.fragments {
+        let start = cursor;
+        let (top, metrics) = fragment_section_metrics(context, line, fragment);
+        for word in split_words(&fragment.string.s) {
+            let origin = Position::new(cursor, top);
+            if fragment.is_spaced() {
+                if !print_spaced(context, area, fragment, word, origin, metrics)? {
+                    return Ok(false);
+                }
+            } else {
+                match area.text_section(&context.font_cache, origin, metrics) {
+                    Some(mut section) => section.print_str(word, fragment.string.style)?,
+                    None => return Ok(false),
+                }
+            }
+            cursor += fragment.measure(context, word);
+            if word.ends_with(char::is_whitespace) {
+                cursor += extra;
+            }
+        }
+
+        draw_fragment_decorations(
+            context,
+            area,
+            fragment,
+            start,
+            cursor,
+            line.glyph_height - fragment.baseline_shift,
+            underline_offset,
+            style,
+        );
+        if let (Some(layout), Some(target)) = (layout, &fragment.link) {
+            register_link(
+                layout,
+                area,
+                start,
+                cursor - start,
+                line.line_height,
+                target,
+            );
+        }
+        if let (Some(layout), Some(number)) = (layout, fragment.footnote) {
+            layout.place_footnote(number);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Extra space added to each of `gaps` inter-word gaps so a line of `line_width` fills
+/// `available_width`.
+fn justified_gap(available_width: Mm, line_width: Mm, gaps: usize) -> Mm {
+    if gaps == 0 || line_width >= available_width {
+        Mm::default()
+    } else {
+        (available_width - line_width) / gaps as f64
+    }
+}
+
+/// Styled text confined to a region of fixed height.
+///
+/// The text wraps to the available width and every line that does not fit into the region is
+/// dropped.  When a [`WarningSink`] is attached, clipping records a
+/// [`RenderWarning::ContentClipped`] with a preview of the dropped text so authors can spot
+/// overflowing templates, cells, or footers.
+pub struct FixedRegion {
+    spans: Vec<StyledSpan>,
+    height: Mm,
+    alignment: Alignment,
+    location: String,
+    warnings: Option<WarningSink>,
+}
+
+impl FixedRegion {
+    /// Creates a region of the given height that hosts the provided spans.
+    pub fn new(height: impl Into<Mm>, spans: Vec<StyledSpan>) -> Self {
+        Self {
+            spans,
+            height: height.into(),
+            alignment: Alignment::Left,
+            location: "fixed region".to_string(),
+            warnings: None,
+        }
+    }
+
+    /// Sets the alignment applied to every line.
+    pub fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+
+    /// Sets the alignment and returns the updated element.
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.set_alignment(alignment);
+        self
+    }
+
+    /// Sets the description of the region reported in clipping warnings.
+    pub fn with_location(mut self, location: impl Into<String>) -> Self {
+        self.location = location.into();
+        self
+    }
+
+    /// Attaches the sink that receives clipping warnings and returns the updated element.
+    pub fn with_warnings(mut self, warnings: WarningSink) -> Self {
+        self.warnings = Some(warnings);
+        self
+    }
+}
+
+impl Element for FixedRegion {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let available_width = area.size().width;
+        let height = if self.height > area.size().height {
+            area.size().height
+        } else {
+            self.height
+        };
+        let lines = wrap_spans(context, &self.spans, style, available_width);
+
+        let mut offset = Mm::default();
+        let mut rendered = 0;
+        for line in &lines {
+            if offset + line.line_height > height {
+                break;
+            }
+            let x_offset = aligned_offset(self.alignment, available_width, line.width);
+            let mut line_area = area.clone();
+            line_area.add_offset(Position::new(0, offset));
+            if !print_wrapped_line(
+                context,
+                &line_area,
+                line,
+                x_offset,
+                default_underline_offset(),
+                style,
+                None,
+            )? {
+                break;
+            }
+            offset += line.line_height;
+            rendered += 1;
+        }
+
+        if rendered < lines.len() {
+            if let Some(warnings) = &self.warnings {
+                let dropped = lines[rendered..]
+                    .iter()
+                    .map(WrappedLine::text)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                warnings.push(RenderWarning::content_clipped(&self.location, &dropped));
+            }
+        }
+
+        let mut result = RenderResult::default();
+        result.size = Size::new(available_width, height);
+        Ok(result)
+    }
+}
+
+/// Single table cell value aligned on its decimal separator relative to the rest of its column.
+///
+/// The element measures every value of the column to find the widest fractional part (the
+/// separator and everything after it) and anchors all separators at the same horizontal position,
+/// leaving the column right-aligned as a whole.  Values without a separator end at the anchor, and
+/// prefixes such as a minus sign or currency symbol are simply part of the integer portion.
+pub struct DecimalAlignedText {
+    value: StyledString,
+    column: Vec<StyledString>,
+    separator: char,
+}
+
+impl DecimalAlignedText {
+    /// Creates a cell for `value`, aligned against all values of its `column`.
+    ///
+    /// `column` should contain `value` itself as well as every other value of the column.
+    pub fn new(value: impl Into<StyledString>, column: Vec<StyledString>) -> Self {
+        Self {
+            value: value.into(),
+            column,
+            separator: DEFAULT_DECIMAL_SEPARATOR,
+        }
+    }
+
+    /// Sets the decimal separator used to split values, such as `,` for European formatting.
+    pub fn set_separator(&mut self, separator: char) {
+        self.separator = separator;
+    }
+
+    /// Sets the decimal separator and returns the updated element.
+    pub fn with_separator(mut self, separator: char) -> Self {
+        self.set_separator(separator);
+        self
+    }
+
+    fn measure(&self, context: &genpdf::Context, value: &StyledString, style: Style) -> (Mm, Mm) {
+        let style = style.and(value.style);
+        let (integer, fraction) = split_decimal(&value.s, self.separator);
+        let integer_width =
+            StyledString::new(integer.to_string(), style).width(&context.font_cache);
+        let fraction_width =
+            StyledString::new(fraction.to_string(), style).width(&context.font_cache);
+        (integer_width, fraction_width)
+    }
+}
+
+impl Element for DecimalAlignedText {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let mut result = RenderResult::default();
+        let value_style = style.and(self.value.style);
+        let line_height = value_style.line_height(&context.font_cache);
+        if line_height > area.size().height {
+            result.has_more = true;
+            return Ok(result);
+        }
+
+        let mut parts: Vec<(Mm, Mm)> = self
+            .column
+            .iter()
+            .map(|value| self.measure(context, value, style))
+            .collect();
+        parts.push(self.measure(context, &self.value, style));
+
+        let available_width = area.size().width;
+        let offsets = decimal_offsets(available_width, &parts);
+        let x_offset = offsets.last().copied().unwrap_or_default();
+
+        if let Some(mut section) =
+            area.text_section(&context.font_cache, Position::new(x_offset, 0), style)
+        {
+            section.print_str(&self.value.s, value_style)?;
+        } else {
+            result.has_more = true;
+            return Ok(result);
+        }
+
+        result.size = Size::new(available_width, line_height);
+        Ok(result)
+    }
+}
+
+/// Default gap between a table of contents title, its leader, and the page number.
+const DEFAULT_TOC_LEADER_GAP_MM: f64 = 1.5;
+
+/// Table of contents row with the title on the left and the page number on the right.
+///
+/// Titles too long for the space left of the page number wrap onto further lines, and the page
+/// number is printed on the last one.  An optional leader character, such as `.`, is repeated
+/// between the end of the title and the page number.
+///
+/// A row given a link with [`TocRow::with_link`] records one link area per printed line, from the
+/// indent to the end of the title, and on the last line on to the end of the page number.
+pub struct TocRow {
+    title: StyledString,
+    page: StyledString,
+    indent: Mm,
+    leader: Option<char>,
+    link: Option<(usize, LayoutTracker)>,
+}
+
+impl TocRow {
+    /// Creates a row for `title` that ends in `page`, without indentation or leader.
+    pub fn new(title: impl Into<StyledString>, page: impl Into<StyledString>) -> Self {
+        Self {
+            title: title.into(),
+            page: page.into(),
+            indent: Mm::default(),
+            leader: None,
+            link: None,
+        }
+    }
+
+    /// Indents the title from the left edge and returns the updated element.
+    pub fn with_indent(mut self, indent: impl Into<Mm>) -> Self {
+        self.indent = indent.into();
+        self
+    }
+
+    /// Sets the character repeated between title and page number and returns the updated element.
+    pub fn with_leader(mut self, leader: impl Into<Option<char>>) -> Self {
+        self.leader = leader.into();
+        self
+    }
+
+    /// Links the printed row to the one-based `target_page`, recording its link areas in
+    /// `layout`, and returns the updated element.
+    pub fn with_link(mut self, target_page: usize, layout: LayoutTracker) -> Self {
+        self.link = Some((target_page, layout));
+        self
+    }
+}
+
+impl Element for TocRow {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        mut area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let mut result = RenderResult::default();
+        let width = area.size().width;
+        let gap = mm_from_f64(DEFAULT_TOC_LEADER_GAP_MM);
+        let page_style = style.and(self.page.style);
+        let page_width =
+            StyledString::new(self.page.s.clone(), page_style).width(&context.font_cache);
+        let page_x = width - page_width;
+
+        let title = StyledSpan::new(self.title.clone(), false);
+        let title_width = (page_x - self.indent - gap * 2.0).max(Mm::default());
+        let lines = wrap_spans(context, std::slice::from_ref(&title), style, title_width);
+        let height = lines
+            .iter()
+            .fold(Mm::default(), |height, line| height + line.line_height);
+        if height > area.size().height {
+            result.has_more = true;
+            return Ok(result);
+        }
+
+        for (index, line) in lines.iter().enumerate() {
+            if !print_wrapped_line(
+                context,
+                &area,
+                line,
+                self.indent,
+                Mm::default(),
+                style,
+                None,
+            )? {
+                result.has_more = true;
+                return Ok(result);
+            }
+
+            let last = index + 1 == lines.len();
+            if let Some((target_page, layout)) = &self.link {
+                let end = if last {
+                    width
+                } else {
+                    self.indent + line.width
+                };
+                register_link(
+                    layout,
+                    &area,
+                    self.indent,
+                    end - self.indent,
+                    line.line_height,
+                    &LinkTarget::Page(*target_page),
+                );
+            }
+
+            if last {
+                let leader_start = self.indent + line.width + gap;
+                let leader_end = page_x - gap;
+                if let Some(leader) = self.leader {
+                    let leader_width =
+                        StyledString::new(leader.to_string(), style).width(&context.font_cache);
+                    let count = leader_count(leader_end - leader_start, leader_width);
+                    if count > 0 {
+                        let leaders = leader.to_string().repeat(count);
+                        let x = leader_end - leader_width * count as f64;
+                        if let Some(mut section) = area.text_section(
+                            &context.font_cache,
+                            Position::new(x, 0),
+                            line.baseline_style,
+                        ) {
+                            section.print_str(&leaders, style)?;
+                        }
+                    }
+                }
+                if let Some(mut section) = area.text_section(
+                    &context.font_cache,
+                    Position::new(page_x, 0),
+                    line.baseline_style,
+                ) {
+                    section.print_str(&self.page.s, page_style)?;
+                }
+            }
+
+            area.add_offset(Position::new(0, line.line_height));
+        }
+
+        result.size = Size::new(width, height);
+        Ok(result)
+    }
+}
+
+/// Number of leader characters of `leader_width` that fit into `space`.
+fn leader_count(space: Mm, leader_width: Mm) -> usize {
+    if leader_width <= Mm::default() || space <= Mm::default() {
+        return 0;
+    }
+    (mm_to_f64(space) / mm_to_f64(leader_width)).floor() as usize
+}
+
+/// Splits a numeric value into its integer part and the fractional part starting at `separator`.
+fn split_decimal(value: &str, separator: char) -> (&str, &str) {
+    match value.find(separator) {
+        Some(index) => value.split_at(index),
+        None => (value, ""),
+    }
+}
+
+/// Computes the x-offset of every `(integer width, fraction width)` pair so that all fractional
+/// parts start at the same position while the widest value ends at the right edge.
+fn decimal_offsets(available_width: Mm, parts: &[(Mm, Mm)]) -> Vec<Mm> {
+    let max_fraction = parts
+        .iter()
+        .fold(Mm::default(), |max, (_, fraction)| max.max(*fraction));
+    let anchor = available_width - max_fraction;
+    parts
+        .iter()
+        .map(|(integer, _)| {
+            if *integer > anchor {
+                Mm::default()
+            } else {
+                anchor - *integer
+            }
+        })
+        .collect()
+}
+
+/// Item of a [`MarkedList`]: its marker, nesting depth, and styled text.
+#[derive(Clone, Debug)]
+pub struct MarkedItem {
+    marker: String,
+    depth: usize,
+    spans: Vec<StyledSpan>,
+    alignment: Alignment,
+}
+
+impl MarkedItem {
+    /// Creates an item printed at the given nesting `depth` (0 for top-level items).
+    pub fn new(marker: impl Into<String>, depth: usize, spans: Vec<StyledSpan>) -> Self {
+        Self {
+            marker: marker.into(),
+            depth,
+            spans,
+            alignment: Alignment::Left,
+        }
+    }
+
+    /// Sets the alignment of the item text and returns the updated item.
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+}
+
+/// List whose items are indented with their marker printed in the gutter.
+///
+/// Item text wraps within the indented column so continuation lines align with the text rather
+/// than the marker.  Items may break across pages line by line, but a marker is always printed
+/// together with the first line of its item.  When a minimum number of items must stay together,
+/// the list is moved to the next page if those leading items do not fit in the remaining space;
+/// if they do not fit on the following page either, the list is split there as a fallback.
+pub struct MarkedList {
+    items: Vec<MarkedItem>,
+    indent: Mm,
+    min_items_together: usize,
+    next_item: usize,
+    next_line: usize,
+    deferred: bool,
+    layout: Option<LayoutTracker>,
+}
+
+impl MarkedList {
+    /// Creates a list from the provided items.
+    pub fn new(items: Vec<MarkedItem>) -> Self {
+        Self {
+            items,
+            indent: mm_from_f64(DEFAULT_LIST_INDENT_MM),
+            min_items_together: 1,
+            next_item: 0,
+            next_line: 0,
+            deferred: false,
+            layout: None,
+        }
+    }
+
+    /// Sets the indentation applied per nesting level, which also sizes the marker gutter.
+    pub fn set_indent(&mut self, indent: Mm) {
+        self.indent = indent;
+    }
+
+    /// Sets the indentation and returns the updated element.
+    pub fn with_indent(mut self, indent: Mm) -> Self {
+        self.set_indent(indent);
+        self
+    }
+
+    /// Requires at least `count` leading items to fit before the list starts on a page.
+    pub fn set_min_items_together(&mut self, count: usize) {
+        self.min_items_together = count.max(1);
+    }
+
+    /// Sets the minimum number of leading items kept together and returns the updated element.
+    pub fn with_min_items_together(mut self, count: usize) -> Self {
+        self.set_min_items_together(count);
+        self
+    }
+
+    /// Attaches the tracker that records the rectangles of linked spans.
+    pub fn with_layout(mut self, layout: LayoutTracker) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+}
+
+impl Element for MarkedList {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let available_width = area.size().width;
+        let indent = self.indent;
+        let text_width = |depth: usize| {
+            let used = indent * (depth + 1) as f64;
+            if used > available_width {
+                Mm::default()
+            } else {
+                available_width - used
+            }
+        };
+        let layouts: Vec<Vec<WrappedLine>> = self
+            .items
+            .iter()
+            .map(|item| wrap_spans(context, &item.spans, style, text_width(item.depth)))
+            .collect();
+
+        let mut result = RenderResult::default();
+        if self.next_item == 0 && self.next_line == 0 && !self.deferred {
+            let heights: Vec<Mm> = layouts
+                .iter()
+                .map(|lines| {
+                    lines
+                        .iter()
+                        .fold(Mm::default(), |total, line| total + line.line_height)
+                })
+                .collect();
+            if defer_list_start(&heights, self.min_items_together, area.size().height) {
+                // A non-zero size keeps genpdf from treating the deferral as an element that can
+                // never fit, mirroring how `genpdf::elements::PageBreak` requests a new page.
+                self.deferred = true;
+                result.size = Size::new(1, 0);
+                result.has_more = true;
+                return Ok(result);
+            }
+        }
+
+        let mut offset = Mm::default();
+        while self.next_item < self.items.len() {
+            let item = &self.items[self.next_item];
+            let lines = &layouts[self.next_item];
+            let marker_x = indent * item.depth as f64;
+            let text_x = indent * (item.depth + 1) as f64;
+
+            while self.next_line < lines.len() {
+                let line = &lines[self.next_line];
+                if offset + line.line_height > area.size().height {
+                    result.size = Size::new(available_width, offset);
+                    result.has_more = true;
+                    return Ok(result);
+                }
+
+                let mut line_area = area.clone();
+                line_area.add_offset(Position::new(0, offset));
+                if self.next_line == 0 {
+                    if let Some(mut section) = line_area.text_section(
+                        &context.font_cache,
+                        Position::new(marker_x, 0),
+                        style,
+                    ) {
+                        section.print_str(&item.marker, style)?;
+                    }
+                }
+
+                line_area.add_offset(Position::new(text_x, 0));
+                let x_offset = aligned_offset(item.alignment, text_width(item.depth), line.width);
+                print_wrapped_line(
+                    context,
+                    &line_area,
+                    line,
+                    x_offset,
+                    default_underline_offset(),
+                    style,
+                    self.layout.as_ref(),
+                )?;
+
+                offset += line.line_height;
+                self.next_line += 1;
+            }
+
+            self.next_item += 1;
+            self.next_line = 0;
+        }
+
+        result.size = Size::new(available_width, offset);
+        Ok(result)
+    }
+}
+
+/// Decides whether a list should move to the next page before printing anything.
+///
+/// `heights` holds the full height of every item; the list is deferred when the first
+/// `min_items` of them do not fit into `available_height`.
+fn defer_list_start(heights: &[Mm], min_items: usize, available_height: Mm) -> bool {
+    if min_items <= 1 || heights.is_empty() {
+        return false;
+    }
+    let needed = heights
+        .iter()
+        .take(min_items)
+        .fold(Mm::default(), |total, height| total + *height);
+    needed > available_height
+}
+
+/// Legend that pairs small colored swatches with their labels.
+///
+/// Horizontal legends flow entries left to right and wrap onto further rows when the width runs
+/// out; vertical legends stack one entry per row.  Rows that do not fit onto the current page
+/// continue on the next one.
+pub struct Legend {
+    entries: Vec<(Color, Vec<StyledSpan>)>,
+    orientation: LegendOrientation,
+    next_entry: usize,
+}
+
+impl Legend {
+    /// Creates a legend from color/label pairs.
+    pub fn new(entries: Vec<(Color, Vec<StyledSpan>)>) -> Self {
+        Self {
+            entries,
+            orientation: LegendOrientation::Horizontal,
+            next_entry: 0,
+        }
+    }
+
+    /// Sets the orientation of the entries.
+    pub fn set_orientation(&mut self, orientation: LegendOrientation) {
+        self.orientation = orientation;
+    }
+
+    /// Sets the orientation and returns the updated element.
+    pub fn with_orientation(mut self, orientation: LegendOrientation) -> Self {
+        self.set_orientation(orientation);
+        self
+    }
+}
+
+impl Element for Legend {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let swatch = mm_from_f64(LEGEND_SWATCH_MM);
+        let gap = mm_from_f64(LEGEND_LABEL_GAP_MM);
+        let available_width = area.size().width;
+
+        let measured: Vec<(Mm, Mm)> = self
+            .entries
+            .iter()
+            .map(|(_, label)| measure_spans(context, label, style))
+            .collect();
+        let widths: Vec<Mm> = measured
+            .iter()
+            .map(|(width, _)| swatch + gap + *width)
+            .collect();
+        let placements = match self.orientation {
+            LegendOrientation::Horizontal => flow_entries(
+                &widths,
+                available_width,
+                mm_from_f64(LEGEND_ENTRY_SPACING_MM),
+            ),
+            LegendOrientation::Vertical => (0..widths.len())
+                .map(|index| (index, Mm::default()))
+                .collect(),
+        };
+
+        let mut result = RenderResult::default();
+        let mut offset = Mm::default();
+        let mut index = self.next_entry;
+        while index < self.entries.len() {
+            let row = placements[index].0;
+            let row_end = (index..self.entries.len())
+                .find(|candidate| placements[*candidate].0 != row)
+                .unwrap_or(self.entries.len());
+            let row_height = measured[index..row_end]
+                .iter()
+                .fold(swatch, |max, (_, height)| max.max(*height));
+
+            if offset + row_height > area.size().height {
+                result.has_more = true;
+                break;
+            }
+
+            for entry in index..row_end {
+                let (color, label) = &self.entries[entry];
+                let x = placements[entry].1;
+                fill_rect(
+                    &area,
+                    Position::new(x, offset + (row_height - swatch) / 2.0),
+                    Size::new(swatch, swatch),
+                    *color,
+                );
+
+                let mut label_area = area.clone();
+                label_area.add_offset(Position::new(0, offset));
+                if let Some(mut section) = label_area.text_section(
+                    &context.font_cache,
+                    Position::new(x + swatch + gap, 0),
+                    style,
+                ) {
+                    for span in label {
+                        section.print_str(&span.string.s, style.and(span.string.style))?;
+                    }
+                }
+            }
+
+            offset += row_height;
+            index = row_end;
+        }
+
+        self.next_entry = index;
+        result.size = Size::new(available_width, offset);
+        Ok(result)
+    }
+}
+
+/// Measures the total width and the tallest line height of `spans` laid out on a single line.
+fn measure_spans(context: &genpdf::Context, spans: &[StyledSpan], style: Style) -> (Mm, Mm) {
+    spans.iter().fold(
+        (Mm::default(), style.line_height(&context.font_cache)),
+        |(width, height), span| {
+            let span_style = style.and(span.string.style);
+            let string = StyledString::new(span.string.s.clone(), span_style);
+            (
+                width
+                    + spaced_width(
+                        &context.font_cache,
+                        &string,
+                        span.letter_spacing,
+                        span.word_spacing,
+                    ),
+                height.max(span_style.line_height(&context.font_cache)),
+            )
+        },
+    )
+}
+
+/// Flows items of the given widths into rows no wider than `available_width`.
+///
+/// Returns the row index and x-offset of every item.  Items wider than the available width still
+/// occupy a row of their own.
+fn flow_entries(widths: &[Mm], available_width: Mm, spacing: Mm) -> Vec<(usize, Mm)> {
+    let mut placements = Vec::with_capacity(widths.len());
+    let mut row = 0;
+    let mut cursor = Mm::default();
+
+    for (index, width) in widths.iter().enumerate() {
+        if index > 0 {
+            if cursor + spacing + *width > available_width {
+                row += 1;
+                cursor = Mm::default();
+            } else {
+                cursor += spacing;
+            }
+        }
+        placements.push((row, cursor));
+        cursor += *width;
+    }
+
+    placements
+}
+
+/// Fills the rectangle at `position` with the given `size` and `color`.
+///
+/// `genpdf` only exposes stroked lines, so the fill is drawn as a single horizontal stroke whose
+/// thickness equals the rectangle height.
+fn fill_rect(area: &render::Area<'_>, position: Position, size: Size, color: Color) {
+    let center = position.y + size.height / 2.0;
+    area.draw_line(
+        vec![
+            Position::new(position.x, center),
+            Position::new(position.x + size.width, center),
+        ],
+        LineStyle::new()
+            .with_color(color)
+            .with_thickness(size.height),
+    );
+}
+
+/// Fills the whole of `area` with `color`.
+pub(crate) fn fill_page(area: &render::Area<'_>, color: Color) {
+    fill_rect(area, Position::new(0, 0), area.size(), color);
+}
+
+/// Font size of footnotes in points.
+const FOOTNOTE_FONT_SIZE: u8 = 8;
+
+/// Space above and below the rule separating footnotes from the body text.
+const FOOTNOTE_RULE_SPACING_MM: f64 = 1.5;
+
+/// Fraction of the body width covered by the rule above the footnotes.
+const FOOTNOTE_RULE_WIDTH_RATIO: f64 = 0.3;
+
+/// Notes printed at the bottom of a page below a short separator rule.
+///
+/// The page decorator measures the notes with [`FootnoteArea::height`] to reserve their space
+/// before the body of the page is laid out, so the notes never overlap the content.
+pub(crate) struct FootnoteArea {
+    notes: Vec<Vec<StyledSpan>>,
+}
+
+impl FootnoteArea {
+    /// Creates the area for `notes`, each given as the spans of a single note.
+    pub(crate) fn new(notes: Vec<Vec<StyledSpan>>) -> Self {
+        Self { notes }
+    }
+
+    /// Returns the height the notes and their rule take up at `width`.
+    pub(crate) fn height(&self, context: &genpdf::Context, style: Style, width: Mm) -> Mm {
+        self.lines(context, style, width).iter().fold(
+            mm_from_f64(FOOTNOTE_RULE_SPACING_MM * 2.0),
+            |height, line| height + line.line_height,
+        )
+    }
+
+    fn lines(&self, context: &genpdf::Context, style: Style, width: Mm) -> Vec<WrappedLine> {
+        let style = footnote_style(style);
+        self.notes
+            .iter()
+            .flat_map(|note| wrap_spans(context, note, style, width))
+            .collect()
+    }
+}
+
+impl Element for FootnoteArea {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let width = area.size().width;
+        let spacing = mm_from_f64(FOOTNOTE_RULE_SPACING_MM);
+        draw_stroke(
+            &area,
+            Mm::default(),
+            width * FOOTNOTE_RULE_WIDTH_RATIO,
+            spacing,
+            LineStyle::new().with_thickness(mm_from_f64(0.2)),
+        );
+
+        let style = footnote_style(style);
+        let mut result = RenderResult::default();
+        let mut offset = spacing * 2.0;
+        for line in self.lines(context, style, width) {
+            if offset + line.line_height > area.size().height {
+                result.has_more = true;
+                break;
+            }
+            let mut line_area = area.clone();
+            line_area.add_offset(Position::new(0, offset));
+            if !print_wrapped_line(
+                context,
+                &line_area,
+                &line,
+                Mm::default(),
+                default_underline_offset(),
+                style,
+                None,
+            )? {
+                result.has_more = true;
+                break;
+            }
+            offset += line.line_height;
+        }
+
+        result.size = Size::new(width, offset);
+        Ok(result)
+    }
+}
+
+fn footnote_style(mut style: Style) -> Style {
+    style.set_font_size(FOOTNOTE_FONT_SIZE);
+    style
+}
+
+/// Default font size of code blocks in points.
+const DEFAULT_CODE_FONT_SIZE: u8 = 9;
+
+/// Default space between the background edge of a code block and its text.
+const DEFAULT_CODE_PADDING_MM: f64 = 2.0;
+
+/// Number of spaces a tab expands to in code blocks.
+const CODE_TAB_WIDTH: usize = 4;
+
+/// Preformatted text printed line by line on a filled background.
+///
+/// Whitespace is kept as written and lines are never hyphenated.  Lines wider than the area wrap
+/// at the last character that fits.  The background is filled once per page, covering the lines
+/// printed there.
+pub struct CodeText {
+    lines: Vec<String>,
+    font_family: Option<FontFamily<Font>>,
+    font_size: u8,
+    background: Option<Color>,
+    padding: Mm,
+    wrapped: Option<Vec<String>>,
+    next_line: usize,
+}
+
+impl CodeText {
+    /// Creates a code element for `code` in the document's default font without background.
+    pub fn new(code: &str) -> Self {
+        let tab = " ".repeat(CODE_TAB_WIDTH);
+        Self {
+            lines: code.lines().map(|line| line.replace('\t', &tab)).collect(),
+            font_family: None,
+            font_size: DEFAULT_CODE_FONT_SIZE,
+            background: None,
+            padding: mm_from_f64(DEFAULT_CODE_PADDING_MM),
+            wrapped: None,
+            next_line: 0,
+        }
+    }
+
+    /// Prints the code in `family`, typically a monospace family, and returns the updated element.
+    pub fn with_font_family(mut self, family: impl Into<Option<FontFamily<Font>>>) -> Self {
+        self.font_family = family.into();
+        self
+    }
+
+    /// Sets the font size in points and returns the updated element.
+    pub fn with_font_size(mut self, font_size: u8) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// Sets the color filled behind the code and returns the updated element.
+    pub fn with_background(mut self, color: impl Into<Option<Color>>) -> Self {
+        self.background = color.into();
+        self
+    }
+}
+
+impl Element for CodeText {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let mut result = RenderResult::default();
+        let mut style = style;
+        style.set_font_size(self.font_size);
+        if let Some(family) = self.font_family {
+            style.set_font_family(family);
+        }
+
+        let width = area.size().width;
+        let text_width = width - self.padding * 2.0;
+        let lines = self.wrapped.get_or_insert_with(|| {
+            let mut wrapped = Vec::new();
+            for line in &self.lines {
+                wrapped.extend(wrap_code_line(context, line, style, text_width));
+            }
+            if wrapped.is_empty() {
+                wrapped.push(String::new());
+            }
+            wrapped
+        });
+
+        let line_height = style.line_height(&context.font_cache);
+        let available = area.size().height - self.padding * 2.0;
+        let fitting = if available > Mm::default() {
+            (mm_to_f64(available) / mm_to_f64(line_height)).floor() as usize
+        } else {
+            0
+        };
+        let count = fitting.min(lines.len() - self.next_line);
+        if count == 0 {
+            result.has_more = true;
+            return Ok(result);
+        }
+
+        let height = line_height * count as f64 + self.padding * 2.0;
+        if let Some(background) = self.background {
+            fill_rect(
+                &area,
+                Position::new(0, 0),
+                Size::new(width, height),
+                background,
+            );
+        }
+
+        for (index, line) in lines[self.next_line..self.next_line + count]
+            .iter()
+            .enumerate()
+        {
+            let position = Position::new(self.padding, self.padding + line_height * index as f64);
+            match area.text_section(&context.font_cache, position, style) {
+                Some(mut section) => section.print_str(line, style)?,
+                None => {
+                    result.has_more = true;
+                    return Ok(result);
+                }
+            }
+        }
+
+        self.next_line += count;
+        result.has_more = self.next_line < lines.len();
+        result.size = Size::new(width, height);
+        Ok(result)
+    }
+}
+
+/// Breaks a line of code into pieces no wider than `max_width`, splitting between characters.
+///
+/// Every piece holds at least one character, and an empty line yields a single empty piece.
+fn wrap_code_line(
+    context: &genpdf::Context,
+    line: &str,
+    style: Style,
+    max_width: Mm,
+) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut width = Mm::default();
+    for character in line.chars() {
+        let character_width = style.char_width(&context.font_cache, character);
+        if !current.is_empty() && width + character_width > max_width {
+            pieces.push(std::mem::take(&mut current));
+            width = Mm::default();
+        }
+        current.push(character);
+        width += character_width;
+    }
+    pieces.push(current);
+    pieces
+}
+
+/// Horizontal line spanning the available width with some space above and below it.
+///
+/// A rule that does not fit at the bottom of a page moves to the next page.  If it does not fit
+/// there either, its margins shrink to the space available, and a stroke thicker than the page
+/// body fails to render.
+pub struct HorizontalRule {
+    thickness: Mm,
+    color: Option<Color>,
+    margin: Mm,
+    deferred: bool,
+}
+
+impl HorizontalRule {
+    /// Creates a rule of the given stroke thickness in the text color without surrounding space.
+    pub fn new(thickness: impl Into<Mm>) -> Self {
+        Self {
+            thickness: thickness.into(),
+            color: None,
+            margin: Mm::default(),
+            deferred: false,
+        }
+    }
+
+    /// Sets the stroke color and returns the updated element.
+    pub fn with_color(mut self, color: impl Into<Option<Color>>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    /// Sets the space kept above and below the stroke and returns the updated element.
+    pub fn with_margin(mut self, margin: impl Into<Mm>) -> Self {
+        self.margin = margin.into();
+        self
+    }
+}
+
+impl Element for HorizontalRule {
+    fn render(
+        &mut self,
+        _context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let mut result = RenderResult::default();
+        let available = area.size().height;
+        let mut margin = self.margin;
+        if margin * 2.0 + self.thickness > available {
+            if !self.deferred {
+                // Mirrors the deferral of `MarkedList`: a non-zero size requests a new page.
+                self.deferred = true;
+                result.size = Size::new(1, 0);
+                result.has_more = true;
+                return Ok(result);
+            }
+            if self.thickness > available {
+                return Err(Error::new(
+                    "Horizontal rule is thicker than the page body",
+                    ErrorKind::PageSizeExceeded,
+                ));
+            }
+            margin = fitted_rule_margin(margin, self.thickness, available);
+        }
+        let height = margin * 2.0 + self.thickness;
+
+        let mut line_style = LineStyle::new().with_thickness(self.thickness);
+        if let Some(color) = self.color.or(style.color()) {
+            line_style = line_style.with_color(color);
+        }
+        let y = margin + self.thickness / 2.0;
+        draw_stroke(&area, Mm::default(), area.size().width, y, line_style);
+
+        result.size = Size::new(area.size().width, height);
+        Ok(result)
+    }
+}
+
+/// Shrinks the space kept above and below a rule of `thickness` so the rule fits into `available`.
+fn fitted_rule_margin(margin: Mm, thickness: Mm, available: Mm) -> Mm {
+    margin.min((available - thickness) / 2.0).max(Mm::default())
+}
+
+/// Blank vertical space of a fixed height.
+///
+/// Space that does not fit on the current page is clamped to the remaining height and the rest
+/// continues at the top of the next page.
+pub struct Spacer {
+    remaining: Mm,
+}
+
+impl Spacer {
+    /// Creates a spacer of the given height; negative heights are treated as zero.
+    pub fn new(height: impl Into<Mm>) -> Self {
+        Self {
+            remaining: height.into().max(Mm::default()),
+        }
+    }
+}
+
+impl Element for Spacer {
+    fn render(
+        &mut self,
+        _context: &genpdf::Context,
+        area: render::Area<'_>,
+        _style: Style,
+    ) -> Result<RenderResult, Error> {
+        let (consumed, leftover) = spacer_advance(self.remaining, area.size().height);
+        self.remaining = leftover;
+
+        let mut result = RenderResult::default();
+        result.size = Size::new(area.size().width, consumed);
+        result.has_more = leftover > Mm::default();
+        Ok(result)
+    }
+}
+
+/// Splits `remaining` spacer height into the part that fits in `available` and the leftover.
+fn spacer_advance(remaining: Mm, available: Mm) -> (Mm, Mm) {
+    if remaining <= available {
+        (remaining, Mm::default())
+    } else {
+        let available = available.max(Mm::default());
+        (available, remaining - available)
+    }
+}
+
+/// Content that moves to the next page as a whole instead of being split at a page boundary.
+///
+/// The height of the content is only known once it was rendered, so it is kept in a cell shared
+/// across render passes: when an earlier pass measured the content and it does not fit into the
+/// remaining space, it starts on the next page.  Content taller than the page body, as reported by
+/// the attached [`LayoutTracker`], is split like any other element.  Without a tracker the page
+/// body is assumed to be tall enough.
+pub struct KeepTogether {
+    content: Box<dyn Element>,
+    height: Rc<Cell<Option<Mm>>>,
+    layout: Option<LayoutTracker>,
+    rendered: Mm,
+    started: bool,
+}
+
+impl KeepTogether {
+    /// Wraps `content` without a known height; it is measured by the first render.
+    pub fn new(content: impl Element + 'static) -> Self {
+        Self {
+            content: Box::new(content),
+            height: Rc::new(Cell::new(None)),
+            layout: None,
+            rendered: Mm::default(),
+            started: false,
+        }
+    }
+
+    /// Shares the cell that holds the measured height with other render passes of the same content
+    /// and returns the updated element.
+    pub fn with_height(mut self, height: Rc<Cell<Option<Mm>>>) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Attaches the tracker that reports the height of the page body.
+    pub fn with_layout(mut self, layout: LayoutTracker) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+}
+
+impl Element for KeepTogether {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        if !self.started {
+            self.started = true;
+            let body = self
+                .layout
+                .as_ref()
+                .map(|layout| mm_from_f64(layout.body().height));
+            if defer_group(self.height.get(), area.size().height, body) {
+                // Mirrors the deferral of `MarkedList`: a non-zero size requests a new page.
+                let mut result = RenderResult::default();
+                result.size = Size::new(1, 0);
+                result.has_more = true;
+                return Ok(result);
+            }
+        }
+
+        let result = self.content.render(context, area, style)?;
+        self.rendered += result.size.height;
+        if !result.has_more {
+            self.height.set(Some(self.rendered));
+        }
+        Ok(result)
+    }
+}
+
+/// Decides whether a group of `height` should move to the next page before printing anything.
+///
+/// Groups of unknown height, groups that fit into `available`, and groups taller than the page
+/// `body` are rendered in place.
+fn defer_group(height: Option<Mm>, available: Mm, body: Option<Mm>) -> bool {
+    match height {
+        Some(height) => height > available && !body.is_some_and(|body| height > body),
+        None => false,
+    }
+}
+
+/// Builds the cells of a table header row.
+type HeaderCells = dyn Fn() -> Vec<Box<dyn Element>>;
+
+/// Cells of a [`DataTable`] row together with the colors filled behind them.
+struct TableRow {
+    cells: Vec<Box<dyn Element>>,
+    fills: Vec<Option<Color>>,
+}
+
+/// Table whose columns share the width by weight, like [`genpdf::elements::TableLayout`], with an
+/// optional header row that can be printed again at the top of every page the table continues on.
+///
+/// Rows that do not fit into the remaining space are split across pages cell by cell.  When a
+/// row cannot print anything below a repeated header, for example because it holds an image
+/// taller than the rest of the page, the header is left off the next page so the row gets the
+/// whole page body.  A header that is itself split across pages is never repeated.
+///
+/// Cell fills are drawn on the layer below the cell content and span the full height of their row,
+/// so the cells of a row share one band of color however many lines each of them has.  Border
+/// lines are drawn once the rows of a page are laid out, at the column and row boundaries found
+/// while rendering them.
+pub struct DataTable {
+    column_weights: Vec<usize>,
+    header: Option<Box<HeaderCells>>,
+    repeat_header: bool,
+    borders: TableBorders,
+    rows: Vec<TableRow>,
+    /// Header row being printed, kept while it continues on the next page.
+    header_cells: Option<Vec<Box<dyn Element>>>,
+    next_row: usize,
+    started: bool,
+    header_split: bool,
+    /// Set when a page printed the header but no body content below it.
+    header_stalled: bool,
+}
+
+impl DataTable {
+    /// Creates a table without rows whose columns share the width by `column_weights`.
+    pub fn new(column_weights: Vec<usize>) -> Self {
+        Self {
+            column_weights,
+            header: None,
+            repeat_header: false,
+            borders: TableBorders::default(),
+            rows: Vec::new(),
+            header_cells: None,
+            next_row: 0,
+            started: false,
+            header_split: false,
+            header_stalled: false,
+        }
+    }
+
+    /// Sets the function building the cells of the header row and returns the updated table.
+    ///
+    /// The function runs for the first page and for every page the header is repeated on.
+    pub fn with_header<F>(mut self, cells: F) -> Self
+    where
+        F: Fn() -> Vec<Box<dyn Element>> + 'static,
+    {
+        self.header = Some(Box::new(cells));
+        self
+    }
+
+    /// Controls whether the header row is printed again at the top of every continuation page and
+    /// returns the updated table.
+    pub fn with_repeated_header(mut self, repeat: bool) -> Self {
+        self.repeat_header = repeat;
+        self
+    }
+
+    /// Sets the lines drawn around and between the cells and returns the updated table.
+    pub fn with_borders(mut self, borders: TableBorders) -> Self {
+        self.borders = borders;
+        self
+    }
+
+    /// Appends a body row holding one cell per column.
+    pub fn push_row(&mut self, cells: Vec<Box<dyn Element>>) {
+        self.push_filled_row(cells, Vec::new());
+    }
+
+    /// Appends a body row holding one cell per column, filling the cell at each position of
+    /// `fills` with its color.
+    pub fn push_filled_row(&mut self, cells: Vec<Box<dyn Element>>, fills: Vec<Option<Color>>) {
+        self.rows.push(TableRow { cells, fills });
+    }
+
+    /// Draws the borders of the part of the table rendered into `area` with the given `height`.
+    ///
+    /// `header_bottom` is the offset of the bottom of the header row if it was printed on the page,
+    /// and `row_bottoms` the offsets of the bottoms of the body rows that were printed completely.
+    fn draw_borders(
+        &self,
+        area: &render::Area<'_>,
+        height: Mm,
+        header_bottom: Option<Mm>,
+        row_bottoms: &[Mm],
+    ) {
+        if height <= Mm::default() {
+            return;
+        }
+        let width = area.size().width;
+        let borders = &self.borders;
+        let horizontal = |y: Mm, line: BorderStyle| {
+            stroke_border(area, Position::new(0, y), Position::new(width, y), line)
+        };
+        let vertical = |x: Mm, line: BorderStyle| {
+            stroke_border(area, Position::new(x, 0), Position::new(x, height), line)
+        };
+
+        if let Some(line) = borders.header().or(borders.rows()) {
+            if let Some(y) = header_bottom.filter(|y| *y < height) {
+                horizontal(y, line);
+            }
+        }
+        if let Some(line) = borders.rows() {
+            for y in row_bottoms.iter().copied().filter(|y| *y < height) {
+                horizontal(y, line);
+            }
+        }
+        if let (Some(line), Some((_, leading))) =
+            (borders.columns(), self.column_weights.split_last())
+        {
+            let total = self.column_weights.iter().sum::<usize>().max(1) as f64;
+            let mut weight = 0;
+            for column_weight in leading {
+                weight += column_weight;
+                vertical(width * (weight as f64 / total), line);
+            }
+        }
+        // Lines are stroked along their center, so the edges are moved inside by half a thickness.
+        let inset = |line: BorderStyle| mm_from_f64(line.thickness_mm() / 2.0);
+        if let Some(line) = borders.top() {
+            horizontal(inset(line), line);
+        }
+        if let Some(line) = borders.bottom() {
+            horizontal(height - inset(line), line);
+        }
+        if let Some(line) = borders.sides() {
+            vertical(inset(line), line);
+            vertical(width - inset(line), line);
+        }
+    }
+
+    fn repeats_header(&self) -> bool {
+        self.repeat_header
+            && !self.header_split
+            && !self.header_stalled
+            && self.next_row < self.rows.len()
+    }
+}
+
+impl Element for DataTable {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        mut area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let mut result = RenderResult::default();
+        result.size.width = area.size().width;
+        let table_area = area.clone();
+        let first_page = !self.started;
+        self.started = true;
+
+        if self.header_cells.is_none() && (first_page || self.repeats_header()) {
+            self.header_cells = self.header.as_ref().map(|header| header());
+        }
+        let printed_header = self.header_cells.is_some();
+        let mut header_bottom = None;
+        if let Some(cells) = &mut self.header_cells {
+            let row = render_table_row(cells, &[], &self.column_weights, context, &area, style)?;
+            result.size.height += row.size.height;
+            area.add_offset(Position::new(0, row.size.height));
+            if row.has_more {
+                self.header_split = true;
+                result.has_more = true;
+                self.draw_borders(&table_area, result.size.height, None, &[]);
+                return Ok(result);
+            }
+            self.header_cells = None;
+            header_bottom = Some(result.size.height);
+        }
+
+        let body_top = result.size.height;
+        let mut row_bottoms = Vec::new();
+        while let Some(table_row) = self.rows.get_mut(self.next_row) {
+            let row = render_table_row(
+                &mut table_row.cells,
+                &table_row.fills,
+                &self.column_weights,
+                context,
+                &area,
+                style,
+            )?;
+            result.size.height += row.size.height;
+            area.add_offset(Position::new(0, row.size.height));
+            if row.has_more {
+                result.has_more = true;
+                break;
+            }
+            self.next_row += 1;
+            row_bottoms.push(result.size.height);
+        }
+        self.header_stalled = printed_header && result.has_more && result.size.height == body_top;
+        self.draw_borders(&table_area, result.size.height, header_bottom, &row_bottoms);
+        Ok(result)
+    }
+}
+
+/// Strokes a table border from `start` to `end`; lines without thickness are skipped.
+fn stroke_border(area: &render::Area<'_>, start: Position, end: Position, line: BorderStyle) {
+    if line.thickness_mm() <= 0.0 {
+        return;
+    }
+    area.draw_line(
+        vec![start, end],
+        LineStyle::new()
+            .with_color(line.color())
+            .with_thickness(mm_from_f64(line.thickness_mm())),
+    );
+}
+
+/// Renders `cells` side by side in columns sized by `weights` and returns the height of the
+/// tallest cell; the row has more content if any cell does.
+///
+/// The cells at the positions of `fills` are then filled with their color down to the bottom of
+/// the row, on the layer below the cell content.
+fn render_table_row(
+    cells: &mut [Box<dyn Element>],
+    fills: &[Option<Color>],
+    weights: &[usize],
+    context: &genpdf::Context,
+    area: &render::Area<'_>,
+    style: Style,
+) -> Result<RenderResult, Error> {
+    let filled = fills.iter().any(Option::is_some);
+    let content_area = if filled {
+        area.next_layer()
+    } else {
+        area.clone()
+    };
+
+    let mut result = RenderResult::default();
+    result.size.width = area.size().width;
+    for (cell, cell_area) in cells
+        .iter_mut()
+        .zip(content_area.split_horizontally(weights))
+    {
+        let cell_result = cell.render(context, cell_area, style)?;
+        result.size.height = result.size.height.max(cell_result.size.height);
+        result.has_more |= cell_result.has_more;
+    }
+
+    if filled && result.size.height > Mm::default() {
+        for (fill, cell_area) in fills.iter().zip(area.split_horizontally(weights)) {
+            if let Some(color) = fill {
+                let size = Size::new(cell_area.size().width, result.size.height);
+                fill_rect(&cell_area, Position::new(0, 0), size, *color);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Content indented behind a colored bar along its left edge, with an optional background.
+///
+/// The content keeps its indent on every page it spans, and the bar and background are drawn for
+/// the part rendered on each page.  The background is only known to fit once the content was
+/// rendered, so the content is placed on the next layer of the page to stay above it.
+pub struct Callout {
+    content: Box<dyn Element>,
+    bar_color: Color,
+    background: Option<Color>,
+    bar_width: Mm,
+    indent: Mm,
+    padding: Mm,
+}
+
+impl Callout {
+    /// Wraps `content` with a gray bar, a 6 mm indent, and no background.
+    pub fn new(content: impl Element + 'static) -> Self {
+        Self {
+            content: Box::new(content),
+            bar_color: Color::Greyscale(160),
+            background: None,
+            bar_width: mm_from_f64(DEFAULT_CALLOUT_BAR_MM),
+            indent: mm_from_f64(DEFAULT_CALLOUT_INDENT_MM),
+            padding: mm_from_f64(DEFAULT_CALLOUT_PADDING_MM),
+        }
+    }
+
+    /// Wraps `content` in a filled background spanning the full width, without bar or indent
+    /// beyond the padding.
+    pub fn shaded(content: impl Element + 'static, background: Color) -> Self {
+        let callout = Self::new(content).with_background(background);
+        let padding = callout.padding;
+        callout.with_bar_width(0).with_indent(padding)
+    }
+
+    /// Sets the bar color and returns the updated element.
+    pub fn with_bar_color(mut self, color: Color) -> Self {
+        self.bar_color = color;
+        self
+    }
+
+    /// Sets the background color and returns the updated element.
+    pub fn with_background(mut self, color: impl Into<Option<Color>>) -> Self {
+        self.background = color.into();
+        self
+    }
+
+    /// Sets the width of the bar and returns the updated element.
+    pub fn with_bar_width(mut self, width: impl Into<Mm>) -> Self {
+        self.bar_width = width.into();
+        self
+    }
+
+    /// Sets the distance between the left edge and the content and returns the updated element.
+    pub fn with_indent(mut self, indent: impl Into<Mm>) -> Self {
+        self.indent = indent.into();
+        self
+    }
+}
+
+impl Element for Callout {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let mut content_area = if self.background.is_some() {
+            area.next_layer()
+        } else {
+            area.clone()
+        };
+        content_area.add_margins(Margins::trbl(
+            self.padding,
+            self.padding,
+            self.padding,
+            self.indent,
+        ));
+
+        let content = self.content.render(context, content_area, style)?;
+        let mut result = RenderResult::default();
+        result.has_more = content.has_more;
+        if content.size.height == Mm::default() {
+            return Ok(result);
+        }
+
+        let size = Size::new(area.size().width, content.size.height + self.padding * 2.0);
+        if let Some(background) = self.background {
+            fill_rect(&area, Position::new(0, 0), size, background);
+        }
+        if self.bar_width > Mm::default() {
+            fill_rect(
+                &area,
+                Position::new(0, 0),
+                Size::new(self.bar_width, size.height),
+                self.bar_color,
+            );
+        }
+
+        result.size = size;
+        Ok(result)
+    }
+}
+
+/// Appearance of a [`Watermark`].
+///
+/// The defaults draw bold, light gray text along the diagonal of the page.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WatermarkOptions {
+    angle: Option<f64>,
+    opacity: f64,
+    font_size: u8,
+    color: Color,
+}
+
+impl Default for WatermarkOptions {
+    fn default() -> Self {
+        Self {
+            angle: None,
+            opacity: 1.0,
+            font_size: DEFAULT_WATERMARK_FONT_SIZE,
+            color: Color::Greyscale(200),
+        }
+    }
+}
+
+impl WatermarkOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the angle of the text line in degrees, or `None` to follow the page diagonal.
+    pub fn angle(&self) -> Option<f64> {
+        self.angle
+    }
+
+    /// Sets the counter-clockwise angle of the text line in degrees; `0.0` runs horizontally.
+    pub fn with_angle(mut self, angle: impl Into<Option<f64>>) -> Self {
+        self.angle = angle.into();
+        self
+    }
+
+    /// Returns the opacity between `0.0` (invisible) and `1.0` (solid).
+    pub fn opacity(&self) -> f64 {
+        self.opacity
+    }
+
+    /// Sets the opacity, clamped to `0.0..=1.0`.
+    ///
+    /// `genpdf` cannot draw translucent text, so the color is blended towards white instead.  The
+    /// result matches true transparency on white pages, which is where watermarks are drawn.  With
+    /// the `transparency` feature [`PdfBuilder`](crate::builder::PdfBuilder) draws the watermark
+    /// at the given opacity instead, so it also stays translucent over page backgrounds.
+    pub fn with_opacity(mut self, opacity: f64) -> Self {
+        self.opacity = opacity.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Returns the font size of the watermark characters.
+    pub fn font_size(&self) -> u8 {
+        self.font_size
+    }
+
+    /// Sets the font size of the watermark characters.
+    pub fn with_font_size(mut self, font_size: u8) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// Returns the text color before the opacity is applied.
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Sets the text color.
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+/// Text drawn across its area without taking up any layout space.
+///
+/// `genpdf` cannot rotate text, so the upright characters are placed one by one along a line
+/// through the centre of the area, by default the diagonal from the bottom-left to the top-right
+/// corner.  The element always reports an empty size, which lets a page decorator draw it
+/// underneath the page content.
+#[derive(Clone, Debug)]
+pub struct Watermark {
+    text: String,
+    options: WatermarkOptions,
+}
+
+impl Watermark {
+    /// Creates a light gray watermark with the provided text.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            options: WatermarkOptions::default(),
+        }
+    }
+
+    /// Returns the watermark text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the appearance of the watermark.
+    pub fn options(&self) -> &WatermarkOptions {
+        &self.options
+    }
+
+    /// Replaces the appearance of the watermark and returns the updated element.
+    pub fn with_options(mut self, options: WatermarkOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the font size of the watermark characters.
+    pub fn set_font_size(&mut self, font_size: u8) {
+        self.options.font_size = font_size;
+    }
+
+    /// Sets the font size and returns the updated element.
+    pub fn with_font_size(mut self, font_size: u8) -> Self {
+        self.set_font_size(font_size);
+        self
+    }
+
+    /// Sets the text color.
+    pub fn set_color(&mut self, color: Color) {
+        self.options.color = color;
+    }
+
+    /// Sets the text color and returns the updated element.
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.set_color(color);
+        self
+    }
+}
+
+impl Element for Watermark {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let mut text_style = style;
+        text_style.set_font_size(self.options.font_size);
+        text_style.set_bold();
+        text_style.set_color(faded_color(self.options.color, self.options.opacity));
+
+        let characters: Vec<String> = self.text.chars().map(String::from).collect();
+        let advances: Vec<f64> = characters
+            .iter()
+            .map(|character| {
+                mm_to_f64(
+                    StyledString::new(character.clone(), text_style).width(&context.font_cache),
+                )
+            })
+            .collect();
+        let line_height = mm_to_f64(text_style.line_height(&context.font_cache));
+        let size = area.size();
+        let width = mm_to_f64(size.width);
+        let height = mm_to_f64(size.height) - line_height;
+        let positions = match self.options.angle {
+            Some(angle) => angled_positions(width, height, angle.to_radians(), &advances),
+            None => diagonal_positions(width, height, &advances),
+        };
+
+        for (character, (x, y)) in characters.iter().zip(positions) {
+            let position = Position::new(mm_from_f64(x), mm_from_f64(y));
+            if let Some(mut section) = area.text_section(&context.font_cache, position, text_style)
+            {
+                section.print_str(character, text_style)?;
+            }
+        }
+
+        Ok(RenderResult::default())
+    }
+}
+
+/// Places characters with the given advances along the diagonal of a `width` x `height` box.
+///
+/// The diagonal runs from the bottom-left to the top-right corner and the text is centred on it.
+/// Returned positions are the top-left corners of the characters in millimetres.
+fn diagonal_positions(width: f64, height: f64, advances: &[f64]) -> Vec<(f64, f64)> {
+    if width.hypot(height) <= 0.0 {
+        return vec![(0.0, 0.0); advances.len()];
+    }
+    angled_positions(width, height, height.atan2(width), advances)
+}
+
+/// Places characters along a line at `angle` radians (counter-clockwise) through the centre of a
+/// `width` x `height` box.
+///
+/// Returned positions are the top-left corners of the characters in millimetres, clamped to the
+/// top-left corner of the box.
+fn angled_positions(width: f64, height: f64, angle: f64, advances: &[f64]) -> Vec<(f64, f64)> {
+    // Page coordinates grow downwards, so a counter-clockwise angle moves up the page.
+    let (dx, dy) = (angle.cos(), -angle.sin());
+    let half_length = advances.iter().sum::<f64>() / 2.0;
+    let mut x = width / 2.0 - dx * half_length;
+    let mut y = height / 2.0 - dy * half_length;
+
+    advances
+        .iter()
+        .map(|advance| {
+            let position = (x.max(0.0), y.max(0.0));
+            x += dx * advance;
+            y += dy * advance;
+            position
+        })
+        .collect()
+}
+
+/// Blends `color` towards white so it looks translucent on a white page.
+pub(crate) fn faded_color(color: Color, opacity: f64) -> Color {
+    let fade = |value: u8| (255.0 - (255.0 - f64::from(value)) * opacity).round() as u8;
+    let ink = |value: u8| (f64::from(value) * opacity).round() as u8;
+    match color {
+        Color::Rgb(r, g, b) => Color::Rgb(fade(r), fade(g), fade(b)),
+        Color::Greyscale(value) => Color::Greyscale(fade(value)),
+        Color::Cmyk(c, m, y, k) => Color::Cmyk(ink(c), ink(m), ink(y), ink(k)),
+    }
+}
+
+/// Horizontal offset that places a line of `line_width` within `available_width`.
+///
+/// Lines wider than the available space start at the left edge instead of being pushed off it.
+fn aligned_offset(alignment: Alignment, available_width: Mm, line_width: Mm) -> Mm {
+    if line_width > available_width {
+        return Mm::default();
+    }
+    match alignment {
+        Alignment::Left => Mm::default(),
+        Alignment::Center => (available_width - line_width) / 2.0,
+        Alignment::Right => available_width - line_width,
+    }
+}
+
+impl<I> From<I> for UnderlinedText
+where
+    I: IntoIterator<Item = StyledSpan>,
+{
+    fn from(iter: I) -> Self {
+        Self::from_spans(iter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_decimal_keeps_prefixes_in_integer_part() {
+        assert_eq!(split_decimal("123.25", '.'), ("123", ".25"));
+        assert_eq!(split_decimal("-$1.5", '.'), ("-$1", ".5"));
+        assert_eq!(split_decimal("42", '.'), ("42", ""));
+        assert_eq!(split_decimal("1,5", ','), ("1", ",5"));
+    }
+
+    #[test]
+    fn decimal_offsets_align_fractional_parts() {
+        // Widths for "1.5" and "123.25" measured as integer part and fractional part.
+        let parts = [
+            (mm_from_f64(2.0), mm_from_f64(4.0)),
+            (mm_from_f64(6.0), mm_from_f64(6.0)),
+        ];
+        let offsets = decimal_offsets(mm_from_f64(30.0), &parts);
+
+        let fraction_starts: Vec<f64> = offsets
+            .iter()
+            .zip(parts.iter())
+            .map(|(offset, (integer, _))| mm_to_f64(*offset + *integer))
+            .collect();
+        assert_eq!(fraction_starts, vec![24.0, 24.0]);
+        assert_eq!(mm_to_f64(offsets[1]), 18.0);
+    }
+
+    #[test]
+    fn legend_entries_flow_across_rows() {
+        let widths = [mm_from_f64(20.0), mm_from_f64(20.0), mm_from_f64(20.0)];
+        let placements = flow_entries(&widths, mm_from_f64(50.0), mm_from_f64(5.0));
+
+        let rows: Vec<(usize, f64)> = placements
+            .iter()
+            .map(|(row, x)| (*row, mm_to_f64(*x)))
+            .collect();
+        assert_eq!(rows, vec![(0, 0.0), (0, 25.0), (1, 0.0)]);
+    }
+
+    #[test]
+    fn list_start_deferred_when_minimum_items_do_not_fit() {
+        let heights = [mm_from_f64(5.0), mm_from_f64(5.0), mm_from_f64(5.0)];
+
+        assert!(defer_list_start(&heights, 3, mm_from_f64(12.0)));
+        assert!(!defer_list_start(&heights, 2, mm_from_f64(12.0)));
+        assert!(!defer_list_start(&heights, 1, mm_from_f64(1.0)));
+    }
+
+    #[test]
+    fn watermark_characters_climb_along_the_diagonal() {
+        let positions = diagonal_positions(30.0, 40.0, &[10.0, 10.0, 10.0]);
+
+        assert_eq!(positions.len(), 3);
+        assert!((positions[0].0 - 6.0).abs() < 1e-9);
+        assert!((positions[0].1 - 32.0).abs() < 1e-9);
+        assert!((positions[2].0 - 18.0).abs() < 1e-9);
+        assert!((positions[2].1 - 16.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn angled_watermark_is_centred_and_faded() {
+        let positions = angled_positions(100.0, 40.0, 0.0, &[10.0, 10.0]);
+        assert_eq!(positions, vec![(40.0, 20.0), (50.0, 20.0)]);
+
+        assert_eq!(
+            faded_color(Color::Rgb(255, 0, 55), 0.5),
+            Color::Rgb(255, 128, 155)
+        );
+        assert_eq!(
+            faded_color(Color::Greyscale(200), 1.0),
+            Color::Greyscale(200)
+        );
+        assert_eq!(
+            faded_color(Color::Cmyk(0, 0, 0, 100), 0.25),
+            Color::Cmyk(0, 0, 0, 25)
+        );
+    }
+
+    #[test]
+    fn justified_gap_spreads_remaining_width() {
+        let gap = justified_gap(mm_from_f64(100.0), mm_from_f64(88.0), 4);
+        assert!((mm_to_f64(gap) - 3.0).abs() < 1e-9);
+
+        assert_eq!(
+            justified_gap(mm_from_f64(100.0), mm_from_f64(88.0), 0),
+            Mm::default()
+        );
+        assert_eq!(
+            justified_gap(mm_from_f64(100.0), mm_from_f64(120.0), 3),
+            Mm::default()
+        );
+    }
+
+    #[test]
+    fn rule_margins_shrink_to_the_available_height() {
+        let fitted = |margin: f64, thickness: f64, available: f64| {
+            mm_to_f64(fitted_rule_margin(
+                mm_from_f64(margin),
+                mm_from_f64(thickness),
+                mm_from_f64(available),
+            ))
+        };
+        assert_eq!(fitted(4.0, 1.0, 100.0), 4.0);
+        assert_eq!(fitted(400.0, 1.0, 101.0), 50.0);
+        assert_eq!(fitted(400.0, 1.0, 0.5), 0.0);
+    }
+
+    #[test]
+    fn spacer_clamps_to_the_page_and_carries_the_rest() {
+        let (consumed, leftover) = spacer_advance(mm_from_f64(30.0), mm_from_f64(12.0));
+        assert_eq!(mm_to_f64(consumed), 12.0);
+        assert_eq!(mm_to_f64(leftover), 18.0);
+
+        let (consumed, leftover) = spacer_advance(leftover, mm_from_f64(200.0));
+        assert_eq!(mm_to_f64(consumed), 18.0);
+        assert_eq!(mm_to_f64(leftover), 0.0);
+    }
+
+    #[test]
+    fn leader_count_fills_the_gap_without_overrunning_it() {
+        assert_eq!(leader_count(mm_from_f64(10.0), mm_from_f64(1.5)), 6);
+        assert_eq!(leader_count(mm_from_f64(1.0), mm_from_f64(1.5)), 0);
+        assert_eq!(leader_count(mm_from_f64(-3.0), mm_from_f64(1.5)), 0);
+        assert_eq!(leader_count(mm_from_f64(10.0), Mm::default()), 0);
+    }
+
+    #[test]
+    fn script_style_shrinks_only_offset_text() {
+        let style = Style::new().with_font_size(12);
+        assert_eq!(script_style(style, VerticalOffset::Normal).font_size(), 12);
+        assert_eq!(script_style(style, VerticalOffset::Super).font_size(), 8);
+        assert_eq!(script_style(style, VerticalOffset::Sub).font_size(), 8);
+        assert_eq!(
+            script_style(Style::new().with_font_size(1), VerticalOffset::Super).font_size(),
+            1
+        );
+    }
+
+    #[test]
+    fn image_scale_picks_the_tighter_constraint() {
+        let natural = Size::new(100, 200);
+        assert_eq!(image_scale(natural, None, None), 1.0);
+        assert_eq!(image_scale(natural, Some(mm_from_f64(50.0)), None), 0.5);
+        // A 50 mm wide image would be 100 mm tall, so the 80 mm limit wins.
+        assert_eq!(
+            image_scale(natural, Some(mm_from_f64(50.0)), Some(mm_from_f64(80.0))),
+            0.4
+        );
+        // The height limit shrinks but never enlarges the image.
+        assert_eq!(image_scale(natural, None, Some(mm_from_f64(400.0))), 1.0);
+        assert_eq!(image_scale(natural, None, Some(mm_from_f64(-5.0))), 0.0);
+    }
+
+    #[test]
+    fn right_to_left_words_are_mirrored() {
+        assert_eq!(
+            right_to_left_display("\u{05E9}\u{05DC}\u{05D5}\u{05DD} "),
+            " \u{05DD}\u{05D5}\u{05DC}\u{05E9}"
+        );
+        // Numbers and Latin words keep their order inside right-to-left lines.
+        assert_eq!(right_to_left_display("2024 "), " 2024");
+        assert_eq!(right_to_left_display("PDF"), "PDF");
+    }
+
+    #[test]
+    fn groups_defer_only_when_they_fit_on_a_fresh_page() {
+        let mm = mm_from_f64;
+        assert!(!defer_group(None, mm(10.0), Some(mm(250.0))));
+        assert!(!defer_group(Some(mm(40.0)), mm(60.0), Some(mm(250.0))));
+        assert!(defer_group(Some(mm(40.0)), mm(30.0), Some(mm(250.0))));
+        assert!(defer_group(Some(mm(40.0)), mm(30.0), None));
+        assert!(!defer_group(Some(mm(300.0)), mm(30.0), Some(mm(250.0))));
+    }
+
+    #[test]
+    fn line_breaks_keep_the_minimum_lines_on_both_pages() {
+        let heights = vec![mm_from_f64(5.0); 6];
+        let available = mm_from_f64(16.0);
+        assert_eq!(lines_before_break(&heights, 0, available, 1, 1), Some(3));
+        assert_eq!(lines_before_break(&heights, 0, available, 4, 1), None);
+        assert_eq!(
+            lines_before_break(&heights[..4], 0, available, 1, 2),
+            Some(2)
+        );
+        assert_eq!(lines_before_break(&heights[..4], 0, available, 3, 2), None);
+        assert_eq!(lines_before_break(&heights, 2, available, 4, 1), Some(3));
+        assert_eq!(lines_before_break(&heights, 4, available, 4, 4), Some(2));
+        assert_eq!(
+            lines_before_break(&heights, 0, mm_from_f64(2.0), 2, 2),
+            None
+        );
+    }
+
+    #[test]
+    fn image_resolution_sets_the_natural_size() {
+        let caption = Paragraph::new("");
+        let mut image = CaptionedImage::placeholder((300, 150), caption).expect("placeholder");
+        assert!((mm_to_f64(image.natural_size.width) - 25.4).abs() < 1e-6);
+
+        image.set_dpi(96.0);
+        assert!((mm_to_f64(image.natural_size.width) - 79.375).abs() < 1e-6);
+        assert!((mm_to_f64(image.natural_size.height) - 39.6875).abs() < 1e-6);
+
+        image.set_dpi(0.0);
+        assert!((mm_to_f64(image.natural_size.width) - 79.375).abs() < 1e-6);
+    }
+
+    #[test]
+    fn overlong_lines_start_at_the_left_edge() {
+        let available = mm_from_f64(100.0);
+        assert_eq!(
+            aligned_offset(Alignment::Right, available, mm_from_f64(60.0)),
+            mm_from_f64(40.0)
+        );
+        assert_eq!(
+            aligned_offset(Alignment::Center, available, mm_from_f64(60.0)),
+            mm_from_f64(20.0)
+        );
+        for alignment in [Alignment::Left, Alignment::Center, Alignment::Right] {
+            assert_eq!(
+                aligned_offset(alignment, available, mm_from_f64(130.0)),
+                Mm::default()
+            );
+        }
+    }
+
+    #[test]
+    fn underline_patterns_vary_the_strokes() {
+        let single = underline_strokes(UnderlineStyle::Single, 0.0, 10.0, 5.0, 0.1);
+        assert_eq!(single, vec![vec![(0.0, 5.0), (10.0, 5.0)]]);
+
+        let double = underline_strokes(UnderlineStyle::Double, 0.0, 10.0, 5.0, 0.1);
+        assert_eq!(double.len(), 2);
+        assert_eq!(double[1][0].1, 5.0 + DOUBLE_UNDERLINE_GAP_MM);
+
+        let dotted = underline_strokes(UnderlineStyle::Dotted, 0.0, 2.0, 5.0, 0.1);
+        assert_eq!(
+            dotted,
+            vec![
+                vec![(0.0, 5.0), (0.4, 5.0)],
+                vec![(0.8, 5.0), (1.2000000000000002, 5.0)],
+                vec![(1.6, 5.0), (2.0, 5.0)],
+            ]
+        );
+        // A thick stroke spreads the dots out.
+        assert_eq!(
+            underline_strokes(UnderlineStyle::Dotted, 0.0, 2.0, 5.0, 0.5).len(),
+            1
+        );
+
+        let wavy = underline_strokes(UnderlineStyle::Wavy, 0.0, 3.0, 5.0, 0.1);
+        assert_eq!(wavy.len(), 1);
+        assert_eq!(wavy[0].first(), Some(&(0.0, 5.0)));
+        assert_eq!(wavy[0].last().map(|point| point.0), Some(3.0));
+        assert!(wavy[0].iter().any(|point| point.1 > 5.0));
     }
 }
@@ -0,0 +1,598 @@
+//! Password protection built on top of `lopdf`.
+//!
+//! Rendered documents are encrypted with the standard security handler of the PDF specification:
+//! a user password opens the document with the permissions chosen in [`EncryptionOptions`], and
+//! an owner password opens it without restrictions.
+
+use std::io;
+
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockEncryptMut, KeyIvInit};
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+use md5::{Digest, Md5};
+
+type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+
+/// Bytes appended to passwords shorter than 32 bytes, as defined by the standard security handler.
+const PASSWORD_PADDING: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08,
+    0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+
+/// Cipher used to encrypt the strings and streams of a document.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EncryptionAlgorithm {
+    /// 128-bit RC4, understood by viewers supporting PDF 1.4.
+    Rc4,
+    /// 128-bit AES in CBC mode, understood by viewers supporting PDF 1.6.
+    #[default]
+    Aes128,
+}
+
+/// Passwords and permissions applied by [`PdfBuilder::with_encryption`].
+///
+/// All permissions are granted by default.  Permissions only restrict readers opening the
+/// document with the user password; viewers are trusted to enforce them.
+///
+/// [`PdfBuilder::with_encryption`]: crate::builder::PdfBuilder::with_encryption
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptionOptions {
+    user_password: String,
+    owner_password: String,
+    algorithm: EncryptionAlgorithm,
+    allow_print: bool,
+    allow_copy: bool,
+    allow_modify: bool,
+}
+
+impl EncryptionOptions {
+    /// Creates options with the password needed to open the document and the password that lifts
+    /// its restrictions.
+    ///
+    /// An empty user password lets anyone open the document with the restricted permissions, and
+    /// an empty owner password falls back to the user password.  Only the first 32 bytes of each
+    /// password are used; stick to ASCII, as viewers encode passwords in PDFDocEncoding.
+    pub fn new(user_password: impl Into<String>, owner_password: impl Into<String>) -> Self {
+        Self {
+            user_password: user_password.into(),
+            owner_password: owner_password.into(),
+            algorithm: EncryptionAlgorithm::default(),
+            allow_print: true,
+            allow_copy: true,
+            allow_modify: true,
+        }
+    }
+
+    /// Returns the password needed to open the document.
+    pub fn user_password(&self) -> &str {
+        &self.user_password
+    }
+
+    /// Returns the password that opens the document without restrictions.
+    pub fn owner_password(&self) -> &str {
+        &self.owner_password
+    }
+
+    /// Returns the cipher used for the document content.
+    pub fn algorithm(&self) -> EncryptionAlgorithm {
+        self.algorithm
+    }
+
+    /// Selects the cipher used for the document content.
+    pub fn with_algorithm(mut self, algorithm: EncryptionAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Returns whether the document may be printed.
+    pub fn print_allowed(&self) -> bool {
+        self.allow_print
+    }
+
+    /// Controls whether the document may be printed, including at full quality.
+    pub fn allow_print(mut self, allowed: bool) -> Self {
+        self.allow_print = allowed;
+        self
+    }
+
+    /// Returns whether text and images may be copied.
+    pub fn copy_allowed(&self) -> bool {
+        self.allow_copy
+    }
+
+    /// Controls whether text and images may be copied.  Accessibility tools may always extract
+    /// text.
+    pub fn allow_copy(mut self, allowed: bool) -> Self {
+        self.allow_copy = allowed;
+        self
+    }
+
+    /// Returns whether the document may be modified.
+    pub fn modify_allowed(&self) -> bool {
+        self.allow_modify
+    }
+
+    /// Controls whether pages may be changed, annotated, filled in, or reassembled.
+    pub fn allow_modify(mut self, allowed: bool) -> Self {
+        self.allow_modify = allowed;
+        self
+    }
+
+    /// Computes the `/P` entry of the encryption dictionary.
+    fn permission_flags(&self) -> i32 {
+        // Bits 7, 8, and 13 to 32 are reserved and must be set; bit 10 keeps text extraction for
+        // accessibility tools available.
+        let mut flags: u32 = 0xFFFF_F0C0 | 1 << 9;
+        if self.allow_print {
+            flags |= 1 << 2 | 1 << 11;
+        }
+        if self.allow_modify {
+            flags |= 1 << 3 | 1 << 5 | 1 << 8 | 1 << 10;
+        }
+        if self.allow_copy {
+            flags |= 1 << 4;
+        }
+        flags as i32
+    }
+
+    fn effective_owner_password(&self) -> &str {
+        if self.owner_password.is_empty() {
+            &self.user_password
+        } else {
+            &self.owner_password
+        }
+    }
+}
+
+/// Encrypts every string and stream of the document with the given passwords and permissions.
+///
+/// The key is derived from the first file identifier in the trailer; documents without one get
+/// an identifier hashed from their bytes.  AES encryption draws a fresh random initialisation vector
+/// for every string and stream, so encrypting the same input twice produces different output.
+pub fn encrypt_document(
+    pdf_bytes: &[u8],
+    options: &EncryptionOptions,
+) -> Result<Vec<u8>, lopdf::Error> {
+    let mut document = Document::load_mem(pdf_bytes)?;
+    let file_id = match first_file_id(&document) {
+        Some(id) => id,
+        None => {
+            let id = md5(&[pdf_bytes]).to_vec();
+            let entry = Object::String(id.clone(), StringFormat::Hexadecimal);
+            document
+                .trailer
+                .set("ID", Object::Array(vec![entry.clone(), entry]));
+            id
+        }
+    };
+
+    let algorithm = options.algorithm();
+    let permissions = options.permission_flags();
+    let owner = owner_value(options.effective_owner_password(), options.user_password());
+    let key = file_key(
+        &pad_password(options.user_password()),
+        &owner,
+        permissions,
+        &file_id,
+    );
+    let user = user_value(&key, &file_id);
+
+    for (&id, object) in document.objects.iter_mut() {
+        if !is_cross_reference_stream(object) {
+            encrypt_object(object, algorithm, &object_key(&key, id, algorithm))?;
+        }
+    }
+
+    let encrypt_id =
+        document.add_object(encryption_dictionary(algorithm, owner, user, permissions));
+    document
+        .trailer
+        .set("Encrypt", Object::Reference(encrypt_id));
+
+    let mut buffer = Vec::new();
+    document.save_to(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn first_file_id(document: &Document) -> Option<Vec<u8>> {
+    let ids = document
+        .trailer
+        .get(b"ID")
+        .and_then(Object::as_array)
+        .ok()?;
+    ids.first()?.as_str().ok().map(<[u8]>::to_vec)
+}
+
+fn is_cross_reference_stream(object: &Object) -> bool {
+    matches!(object, Object::Stream(stream)
+        if stream.dict.get(b"Type").and_then(Object::as_name).ok() == Some(b"XRef".as_slice()))
+}
+
+fn encryption_dictionary(
+    algorithm: EncryptionAlgorithm,
+    owner: Vec<u8>,
+    user: Vec<u8>,
+    permissions: i32,
+) -> Dictionary {
+    let mut dictionary = Dictionary::new();
+    dictionary.set("Filter", Object::Name("Standard".into()));
+    dictionary.set("Length", Object::Integer(128));
+    match algorithm {
+        EncryptionAlgorithm::Rc4 => {
+            dictionary.set("V", Object::Integer(2));
+            dictionary.set("R", Object::Integer(3));
+        }
+        EncryptionAlgorithm::Aes128 => {
+            let mut filter = Dictionary::new();
+            filter.set("CFM", Object::Name("AESV2".into()));
+            filter.set("AuthEvent", Object::Name("DocOpen".into()));
+            filter.set("Length", Object::Integer(16));
+            let mut filters = Dictionary::new();
+            filters.set("StdCF", Object::Dictionary(filter));
+            dictionary.set("V", Object::Integer(4));
+            dictionary.set("R", Object::Integer(4));
+            dictionary.set("CF", Object::Dictionary(filters));
+            dictionary.set("StmF", Object::Name("StdCF".into()));
+            dictionary.set("StrF", Object::Name("StdCF".into()));
+        }
+    }
+    dictionary.set("O", Object::String(owner, StringFormat::Hexadecimal));
+    dictionary.set("U", Object::String(user, StringFormat::Hexadecimal));
+    dictionary.set("P", Object::Integer(i64::from(permissions)));
+    dictionary
+}
+
+fn encrypt_object(
+    object: &mut Object,
+    algorithm: EncryptionAlgorithm,
+    key: &[u8; 16],
+) -> io::Result<()> {
+    match object {
+        Object::String(bytes, format) => {
+            *bytes = encrypt_bytes(algorithm, key, bytes)?;
+            *format = StringFormat::Hexadecimal;
+        }
+        Object::Array(items) => {
+            for item in items {
+                encrypt_object(item, algorithm, key)?;
+            }
+        }
+        Object::Dictionary(dictionary) => encrypt_dictionary(dictionary, algorithm, key)?,
+        Object::Stream(stream) => {
+            encrypt_dictionary(&mut stream.dict, algorithm, key)?;
+            let content = encrypt_bytes(algorithm, key, &stream.content)?;
+            stream.set_content(content);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn encrypt_dictionary(
+    dictionary: &mut Dictionary,
+    algorithm: EncryptionAlgorithm,
+    key: &[u8; 16],
+) -> io::Result<()> {
+    for (_, value) in dictionary.iter_mut() {
+        encrypt_object(value, algorithm, key)?;
+    }
+    Ok(())
+}
+
+fn encrypt_bytes(
+    algorithm: EncryptionAlgorithm,
+    key: &[u8; 16],
+    data: &[u8],
+) -> io::Result<Vec<u8>> {
+    match algorithm {
+        EncryptionAlgorithm::Rc4 => Ok(rc4(key, data)),
+        EncryptionAlgorithm::Aes128 => {
+            // CBC needs an unpredictable IV; reusing one across streams leaks equal prefixes.
+            let mut iv = [0; 16];
+            getrandom::getrandom(&mut iv)?;
+            let mut output = iv.to_vec();
+            output.extend(
+                Aes128CbcEnc::new(key.into(), &iv.into()).encrypt_padded_vec_mut::<Pkcs7>(data),
+            );
+            Ok(output)
+        }
+    }
+}
+
+/// Pads or truncates a password to the 32 bytes hashed by the security handler.
+fn pad_password(password: &str) -> [u8; 32] {
+    let bytes = password.as_bytes();
+    let length = bytes.len().min(32);
+    let mut padded = PASSWORD_PADDING;
+    padded[..length].copy_from_slice(&bytes[..length]);
+    padded[length..].copy_from_slice(&PASSWORD_PADDING[..32 - length]);
+    padded
+}
+
+/// Computes the `/O` entry, which holds the padded user password encrypted with the owner
+/// password.
+fn owner_value(owner_password: &str, user_password: &str) -> Vec<u8> {
+    let digest = owner_digest(owner_password);
+    let mut value = pad_password(user_password).to_vec();
+    for round in 0..=19 {
+        value = rc4(&xor_key(&digest, round), &value);
+    }
+    value
+}
+
+fn owner_digest(owner_password: &str) -> [u8; 16] {
+    let mut digest = md5(&[&pad_password(owner_password)]);
+    for _ in 0..50 {
+        digest = md5(&[&digest]);
+    }
+    digest
+}
+
+/// Derives the key of the whole document from the padded user password.
+fn file_key(
+    padded_user_password: &[u8],
+    owner: &[u8],
+    permissions: i32,
+    file_id: &[u8],
+) -> [u8; 16] {
+    let mut digest = md5(&[
+        padded_user_password,
+        owner,
+        &permissions.to_le_bytes(),
+        file_id,
+    ]);
+    for _ in 0..50 {
+        digest = md5(&[&digest]);
+    }
+    digest
+}
+
+/// Computes the `/U` entry, which lets viewers check a user password without decrypting content.
+fn user_value(key: &[u8; 16], file_id: &[u8]) -> Vec<u8> {
+    let mut value = md5(&[&PASSWORD_PADDING, file_id]).to_vec();
+    for round in 0..=19 {
+        value = rc4(&xor_key(key, round), &value);
+    }
+    // Only the first 16 bytes are compared; the rest is arbitrary padding.
+    value.extend_from_slice(&PASSWORD_PADDING[..16]);
+    value
+}
+
+/// Derives the key used for the strings and streams of one object.
+fn object_key(key: &[u8; 16], id: ObjectId, algorithm: EncryptionAlgorithm) -> [u8; 16] {
+    let salt: &[u8] = match algorithm {
+        EncryptionAlgorithm::Rc4 => b"",
+        EncryptionAlgorithm::Aes128 => b"sAlT",
+    };
+    md5(&[key, &id.0.to_le_bytes()[..3], &id.1.to_le_bytes(), salt])
+}
+
+fn xor_key(key: &[u8; 16], round: u8) -> [u8; 16] {
+    key.map(|byte| byte ^ round)
+}
+
+fn md5(parts: &[&[u8]]) -> [u8; 16] {
+    let mut hasher = Md5::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut digest = [0; 16];
+    digest.copy_from_slice(&hasher.finalize());
+    digest
+}
+
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: [u8; 256] = std::array::from_fn(|index| index as u8);
+    let mut j = 0u8;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, usize::from(j));
+    }
+
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[usize::from(i)]);
+            state.swap(usize::from(i), usize::from(j));
+            let index = state[usize::from(i)].wrapping_add(state[usize::from(j)]);
+            byte ^ state[usize::from(index)]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::BlockDecryptMut;
+    use lopdf::Stream;
+
+    type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+
+    const CONTENT: &[u8] = b"BT /F1 12 Tf 72 720 Td (Quarterly results) Tj ET";
+
+    fn sample_document() -> Vec<u8> {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let content_id = document.add_object(Stream::new(Dictionary::new(), CONTENT.to_vec()));
+
+        let mut page = Dictionary::new();
+        page.set("Type", Object::Name("Page".into()));
+        page.set("Parent", Object::Reference(pages_id));
+        page.set("Contents", Object::Reference(content_id));
+        let page_id = document.add_object(page);
+
+        let mut pages = Dictionary::new();
+        pages.set("Type", Object::Name("Pages".into()));
+        pages.set("Kids", Object::Array(vec![Object::Reference(page_id)]));
+        pages.set("Count", Object::Integer(1));
+        pages.set(
+            "MediaBox",
+            Object::Array([0, 0, 595, 842].map(Object::Integer).to_vec()),
+        );
+        document.objects.insert(pages_id, Object::Dictionary(pages));
+
+        let mut catalog = Dictionary::new();
+        catalog.set("Type", Object::Name("Catalog".into()));
+        catalog.set("Pages", Object::Reference(pages_id));
+        let catalog_id = document.add_object(catalog);
+        document.trailer.set("Root", Object::Reference(catalog_id));
+
+        let mut info = Dictionary::new();
+        info.set("Title", Object::string_literal("Quarterly report"));
+        let info_id = document.add_object(info);
+        document.trailer.set("Info", Object::Reference(info_id));
+
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).expect("save sample document");
+        bytes
+    }
+
+    /// Checks `password` the way a viewer does and returns the key it unlocks.
+    fn authenticate(document: &Document, password: &str) -> Option<[u8; 16]> {
+        let encrypt_id = document.trailer.get(b"Encrypt").ok()?.as_reference().ok()?;
+        let encrypt = document.get_dictionary(encrypt_id).ok()?;
+        let owner = encrypt.get(b"O").ok()?.as_str().ok()?;
+        let user = encrypt.get(b"U").ok()?.as_str().ok()?;
+        let permissions = encrypt.get(b"P").ok()?.as_i64().ok()? as i32;
+        let file_id = first_file_id(document)?;
+
+        let unlock = |padded_user_password: &[u8]| {
+            let key = file_key(padded_user_password, owner, permissions, &file_id);
+            (user_value(&key, &file_id)[..16] == user[..16]).then_some(key)
+        };
+        unlock(&pad_password(password)).or_else(|| {
+            // The owner password recovers the padded user password stored in `/O`.
+            let digest = owner_digest(password);
+            let mut padded_user_password = owner.to_vec();
+            for round in (0..=19).rev() {
+                padded_user_password = rc4(&xor_key(&digest, round), &padded_user_password);
+            }
+            unlock(&padded_user_password)
+        })
+    }
+
+    fn decrypt(
+        algorithm: EncryptionAlgorithm,
+        key: &[u8; 16],
+        id: ObjectId,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let key = object_key(key, id, algorithm);
+        match algorithm {
+            EncryptionAlgorithm::Rc4 => rc4(&key, data),
+            EncryptionAlgorithm::Aes128 => {
+                let (iv, data) = data.split_at(16);
+                Aes128CbcDec::new_from_slices(&key, iv)
+                    .expect("key and iv lengths")
+                    .decrypt_padded_vec_mut::<Pkcs7>(data)
+                    .expect("valid padding")
+            }
+        }
+    }
+
+    #[test]
+    fn only_the_passwords_unlock_the_document() {
+        for algorithm in [EncryptionAlgorithm::Rc4, EncryptionAlgorithm::Aes128] {
+            let options = EncryptionOptions::new("reader", "author").with_algorithm(algorithm);
+            let bytes = encrypt_document(&sample_document(), &options).expect("encrypt");
+            let document = Document::load_mem(&bytes).expect("encrypted PDF parses");
+
+            let user_key = authenticate(&document, "reader").expect("user password opens");
+            let owner_key = authenticate(&document, "author").expect("owner password opens");
+            assert_eq!(user_key, owner_key);
+            assert_eq!(authenticate(&document, "guess"), None);
+            assert_eq!(authenticate(&document, ""), None);
+        }
+    }
+
+    #[test]
+    fn strings_and_streams_decrypt_with_the_file_key() {
+        for algorithm in [EncryptionAlgorithm::Rc4, EncryptionAlgorithm::Aes128] {
+            let options = EncryptionOptions::new("reader", "").with_algorithm(algorithm);
+            let bytes = encrypt_document(&sample_document(), &options).expect("encrypt");
+            assert!(!bytes.windows(CONTENT.len()).any(|window| window == CONTENT));
+
+            let document = Document::load_mem(&bytes).expect("encrypted PDF parses");
+            let key = authenticate(&document, "reader").expect("user password opens");
+            let page_id = document.get_pages()[&1];
+            let content_id = document
+                .get_dictionary(page_id)
+                .and_then(|page| page.get(b"Contents"))
+                .and_then(Object::as_reference)
+                .expect("content reference");
+            let content = document
+                .get_object(content_id)
+                .and_then(Object::as_stream)
+                .expect("content stream");
+            assert_eq!(
+                decrypt(algorithm, &key, content_id, &content.content),
+                CONTENT
+            );
+
+            let info_id = document
+                .trailer
+                .get(b"Info")
+                .and_then(Object::as_reference)
+                .expect("info reference");
+            let title = document
+                .get_dictionary(info_id)
+                .and_then(|info| info.get(b"Title"))
+                .and_then(Object::as_str)
+                .expect("title");
+            assert_eq!(
+                decrypt(algorithm, &key, info_id, title),
+                b"Quarterly report"
+            );
+        }
+    }
+
+    #[test]
+    fn permission_flags_follow_the_options() {
+        let all = EncryptionOptions::new("", "owner").permission_flags();
+        assert_eq!(all & 0b1_1111_1111_1100, 0b1_1111_1111_1100);
+
+        let print_only = EncryptionOptions::new("", "owner")
+            .allow_copy(false)
+            .allow_modify(false)
+            .permission_flags();
+        assert_eq!(print_only & 0b1_1111_1111_1100, 0b1_1010_1100_0100);
+        assert!(print_only < 0);
+    }
+
+    #[test]
+    fn aes_strings_and_streams_use_a_fresh_initialisation_vector() {
+        let key = [7; 16];
+        let first = encrypt_bytes(EncryptionAlgorithm::Aes128, &key, CONTENT).expect("encrypt");
+        let second = encrypt_bytes(EncryptionAlgorithm::Aes128, &key, CONTENT).expect("encrypt");
+        assert_ne!(first[..16], second[..16]);
+
+        for ciphertext in [first, second] {
+            let (iv, data) = ciphertext.split_at(16);
+            let plain = Aes128CbcDec::new_from_slices(&key, iv)
+                .expect("key and iv lengths")
+                .decrypt_padded_vec_mut::<Pkcs7>(data)
+                .expect("valid padding");
+            assert_eq!(plain, CONTENT);
+        }
+    }
+
+    #[test]
+    fn lopdf_decrypts_rc4_documents_with_the_user_password() {
+        // An independent implementation of the key and `/U` derivation; lopdf 0.31 only handles
+        // RC4 and leaves strings nested in dictionaries encrypted, so only the stream is checked.
+        let options =
+            EncryptionOptions::new("reader", "author").with_algorithm(EncryptionAlgorithm::Rc4);
+        let bytes = encrypt_document(&sample_document(), &options).expect("encrypt");
+
+        let mut document = Document::load_mem(&bytes).expect("encrypted PDF parses");
+        assert!(document.decrypt("guess").is_err());
+        document
+            .decrypt("reader")
+            .expect("lopdf accepts the user password");
+
+        let page_id = document.get_pages()[&1];
+        let content = document.get_page_content(page_id).expect("content stream");
+        assert_eq!(content, CONTENT);
+    }
+}
@@ -0,0 +1,35 @@
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use super::shared;
+
+/// Number of renders timed when no count is passed on the command line.
+const DEFAULT_RENDERS: usize = 20;
+
+/// Renders the sample report repeatedly and prints the fastest and mean render times.
+///
+/// The first command line argument overrides the number of renders. Building the sample report
+/// is not timed, so the figures cover the render passes and image decoding only.
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let renders = match std::env::args().nth(1) {
+        Some(count) => count.parse::<usize>()?.max(1),
+        None => DEFAULT_RENDERS,
+    };
+
+    let mut timings = Vec::with_capacity(renders);
+    for _ in 0..renders {
+        let builder = shared::build_sample_report_builder()?;
+        let start = Instant::now();
+        builder.render()?;
+        timings.push(start.elapsed());
+    }
+
+    let fastest = timings.iter().min().copied().unwrap_or_default();
+    let mean = timings.iter().sum::<Duration>() / renders as u32;
+    println!(
+        "Rendered the sample report {renders} times: fastest {:.1} ms, mean {:.1} ms",
+        fastest.as_secs_f64() * 1000.0,
+        mean.as_secs_f64() * 1000.0
+    );
+    Ok(())
+}
@@ -3,7 +3,7 @@ use std::io::Cursor;
 
 use crate::builder::PdfBuilder;
 use crate::model::{
-    Block, Cover, HorizontalAlignment, ImageBlock, ImageSource, RichParagraph, Section,
+    Block, Cover, HorizontalAlignment, ImageBlock, ImageSource, ListStyle, RichParagraph, Section,
 };
 use crate::richtext::Span;
 use genpdf::elements::{LinearLayout, Paragraph};
@@ -73,7 +73,8 @@ pub fn build_sample_report_builder() -> Result<PdfBuilder, Box<dyn Error>> {
             Span::new(" • "),
             Span::new("https://intranet.example.com/reports")
                 .colored(Color::Rgb(36, 92, 160))
-                .underline(),
+                .underline()
+                .linked("https://intranet.example.com/reports"),
         ]));
 
     let highlights = Section::new("Executive Highlights")
@@ -97,17 +98,19 @@ pub fn build_sample_report_builder() -> Result<PdfBuilder, Box<dyn Error>> {
             Span::new("\"no surprises\"").italic(),
             Span::new(" communication model that paired annotated prototypes with support runbooks."),
         ]))
-        .with_block(Block::paragraph(vec![
-            Span::new("• ").bold(),
-            Span::new("Launch readiness: "),
-            Span::new("green").bold().colored(Color::Rgb(44, 160, 44)),
-            Span::new(" after resilience tests validated "),
-            Span::new("automated failover drills").italic(),
-            Span::new("; "),
-            Span::new("• ").bold(),
-            Span::new("Talent: "),
-            Span::new("hiring freeze lifted for core reliability roles").colored(Color::Rgb(180, 120, 40)),
-            Span::new(" with onboarding cohorts scheduled bi-weekly."),
+        .with_block(Block::list(ListStyle::Bulleted, vec![
+            RichParagraph::new(vec![
+                Span::new("Launch readiness: "),
+                Span::new("green").bold().colored(Color::Rgb(44, 160, 44)),
+                Span::new(" after resilience tests validated "),
+                Span::new("automated failover drills").italic(),
+                Span::new("."),
+            ]),
+            RichParagraph::new(vec![
+                Span::new("Talent: "),
+                Span::new("hiring freeze lifted for core reliability roles").colored(Color::Rgb(180, 120, 40)),
+                Span::new(" with onboarding cohorts scheduled bi-weekly."),
+            ]),
         ]))
         .with_block(Block::Image(hero_image.clone()))
         .with_block(Block::paragraph(vec![
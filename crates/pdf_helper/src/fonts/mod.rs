@@ -1,14 +1,19 @@
 //! Font loading utilities for the `pdf_helper` crate.
 
 use std::env;
+use std::fmt;
 use std::io;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
 use genpdf::error::{Error, ErrorKind};
-use genpdf::fonts::{self, FontData, FontFamily};
+use genpdf::fonts::{self, Font, FontData, FontFamily};
+use genpdf::style::StyledString;
 use genpdf::Document;
 use log::warn;
 
+use crate::richtext::StyledSpan;
+
 /// Name of the bundled font family.
 pub const DEFAULT_FONT_FAMILY_NAME: &str = "Roboto";
 
@@ -21,25 +26,128 @@ const FONT_FILES: &[&str] = &[
 
 const WINDOWS_FALLBACK_FAMILY_NAME: &str = "Arial";
 
+/// Name reported in warnings when no monospace family could be found for code blocks.
+pub const MONOSPACE_FONT_FAMILY_NAME: &str = "monospace";
+
+/// Maximum depth below a system font directory searched for fallback font files.
+///
+/// Linux distributions group fonts by package, e.g. `/usr/share/fonts/truetype/dejavu`.
+const SYSTEM_FONT_SEARCH_DEPTH: usize = 4;
+
 /// Path to the bundled fonts directory inside the crate source tree.
 pub fn bundled_fonts_source_dir() -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets/fonts")
 }
 
-struct WindowsFontFiles {
+struct FallbackFontFiles {
     regular: &'static str,
     bold: &'static str,
     italic: &'static str,
     bold_italic: &'static str,
 }
 
-const WINDOWS_FONT_FILES: WindowsFontFiles = WindowsFontFiles {
+impl FallbackFontFiles {
+    fn all(&self) -> [&'static str; 4] {
+        [self.regular, self.bold, self.italic, self.bold_italic]
+    }
+}
+
+const WINDOWS_FONT_FILES: FallbackFontFiles = FallbackFontFiles {
     regular: "arial.ttf",
     bold: "arialbd.ttf",
     italic: "ariali.ttf",
     bold_italic: "arialbi.ttf",
 };
 
+/// Sans-serif families searched for on Linux and macOS, in order of preference.
+const SYSTEM_FALLBACK_FAMILIES: &[(&str, FallbackFontFiles)] = &[
+    (
+        "DejaVu Sans",
+        FallbackFontFiles {
+            regular: "DejaVuSans.ttf",
+            bold: "DejaVuSans-Bold.ttf",
+            italic: "DejaVuSans-Oblique.ttf",
+            bold_italic: "DejaVuSans-BoldOblique.ttf",
+        },
+    ),
+    (
+        "Liberation Sans",
+        FallbackFontFiles {
+            regular: "LiberationSans-Regular.ttf",
+            bold: "LiberationSans-Bold.ttf",
+            italic: "LiberationSans-Italic.ttf",
+            bold_italic: "LiberationSans-BoldItalic.ttf",
+        },
+    ),
+    (
+        "Noto Sans",
+        FallbackFontFiles {
+            regular: "NotoSans-Regular.ttf",
+            bold: "NotoSans-Bold.ttf",
+            italic: "NotoSans-Italic.ttf",
+            bold_italic: "NotoSans-BoldItalic.ttf",
+        },
+    ),
+    (
+        "Arial",
+        FallbackFontFiles {
+            regular: "Arial.ttf",
+            bold: "Arial Bold.ttf",
+            italic: "Arial Italic.ttf",
+            bold_italic: "Arial Bold Italic.ttf",
+        },
+    ),
+];
+
+/// Monospace families searched for code blocks, in order of preference.
+const MONOSPACE_FAMILIES: &[(&str, FallbackFontFiles)] = &[
+    (
+        "Roboto Mono",
+        FallbackFontFiles {
+            regular: "RobotoMono-Regular.ttf",
+            bold: "RobotoMono-Bold.ttf",
+            italic: "RobotoMono-Italic.ttf",
+            bold_italic: "RobotoMono-BoldItalic.ttf",
+        },
+    ),
+    (
+        "Consolas",
+        FallbackFontFiles {
+            regular: "consola.ttf",
+            bold: "consolab.ttf",
+            italic: "consolai.ttf",
+            bold_italic: "consolaz.ttf",
+        },
+    ),
+    (
+        "DejaVu Sans Mono",
+        FallbackFontFiles {
+            regular: "DejaVuSansMono.ttf",
+            bold: "DejaVuSansMono-Bold.ttf",
+            italic: "DejaVuSansMono-Oblique.ttf",
+            bold_italic: "DejaVuSansMono-BoldOblique.ttf",
+        },
+    ),
+    (
+        "Liberation Mono",
+        FallbackFontFiles {
+            regular: "LiberationMono-Regular.ttf",
+            bold: "LiberationMono-Bold.ttf",
+            italic: "LiberationMono-Italic.ttf",
+            bold_italic: "LiberationMono-BoldItalic.ttf",
+        },
+    ),
+    (
+        "Courier New",
+        FallbackFontFiles {
+            regular: "Courier New.ttf",
+            bold: "Courier New Bold.ttf",
+            italic: "Courier New Italic.ttf",
+            bold_italic: "Courier New Bold Italic.ttf",
+        },
+    ),
+];
+
 fn font_directory_candidates() -> Vec<PathBuf> {
     let mut candidates = Vec::new();
 
@@ -117,14 +225,21 @@ fn resolve_font_directory() -> Result<PathBuf, Error> {
     ))
 }
 
-fn load_bundled_font_family() -> Result<FontFamily<FontData>, Error> {
+fn load_bundled_font_family() -> Result<(FontFamily<FontData>, PathBuf), Error> {
     let directory = resolve_font_directory()?;
+    let family = load_font_family_from_directory(&directory, DEFAULT_FONT_FAMILY_NAME)?;
+    Ok((family, directory))
+}
 
-    fonts::from_files(&directory, DEFAULT_FONT_FAMILY_NAME, None).map_err(|err| {
+fn load_font_family_from_directory(
+    directory: &Path,
+    name: &str,
+) -> Result<FontFamily<FontData>, Error> {
+    fonts::from_files(directory, name, None).map_err(|err| {
         Error::new(
             format!(
-                "Failed to load default font family '{}' from {}: {}",
-                DEFAULT_FONT_FAMILY_NAME,
+                "Failed to load font family '{}' from {}: {}",
+                name,
                 directory.display(),
                 err
             ),
@@ -184,7 +299,7 @@ fn load_windows_font(directory: &Path, file: &str, style: &str) -> Result<FontDa
     })
 }
 
-fn windows_fallback_font_family() -> Result<FontFamily<FontData>, Error> {
+fn windows_fallback_font_family() -> Result<(FontFamily<FontData>, PathBuf), Error> {
     let directory = windows_font_directory().ok_or_else(|| {
         Error::new(
             "Windows font directory not found for fallback",
@@ -192,14 +307,157 @@ fn windows_fallback_font_family() -> Result<FontFamily<FontData>, Error> {
         )
     })?;
 
-    Ok(FontFamily {
+    let family = FontFamily {
         regular: load_windows_font(&directory, WINDOWS_FONT_FILES.regular, "regular")?,
         bold: load_windows_font(&directory, WINDOWS_FONT_FILES.bold, "bold")?,
         italic: load_windows_font(&directory, WINDOWS_FONT_FILES.italic, "italic")?,
         bold_italic: load_windows_font(&directory, WINDOWS_FONT_FILES.bold_italic, "bold italic")?,
+    };
+    Ok((family, directory))
+}
+
+fn system_font_directories() -> Vec<PathBuf> {
+    if let Some(path) = env_path("PDF_HELPER_SYSTEM_FONTS_DIR") {
+        return vec![path];
+    }
+
+    #[allow(unused_mut)]
+    let mut directories = Vec::new();
+
+    #[cfg(target_os = "linux")]
+    {
+        directories.push(PathBuf::from("/usr/share/fonts"));
+        directories.push(PathBuf::from("/usr/local/share/fonts"));
+        if let Some(home) = env_path("HOME") {
+            directories.push(home.join(".fonts"));
+            directories.push(home.join(".local/share/fonts"));
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        directories.push(PathBuf::from("/System/Library/Fonts"));
+        directories.push(PathBuf::from("/System/Library/Fonts/Supplemental"));
+        directories.push(PathBuf::from("/Library/Fonts"));
+        if let Some(home) = env_path("HOME") {
+            directories.push(home.join("Library/Fonts"));
+        }
+    }
+
+    directories
+}
+
+/// Finds the directory below `root` that holds all four files of a family.
+///
+/// Subdirectories are visited in name order so the same installation always yields the same
+/// directory.
+fn find_family_directory(root: &Path, files: &FallbackFontFiles, depth: usize) -> Option<PathBuf> {
+    if files.all().iter().all(|file| root.join(file).is_file()) {
+        return Some(root.to_path_buf());
+    }
+    if depth == 0 {
+        return None;
+    }
+
+    let mut subdirectories: Vec<PathBuf> = std::fs::read_dir(root)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    subdirectories.sort();
+    subdirectories
+        .iter()
+        .find_map(|directory| find_family_directory(directory, files, depth - 1))
+}
+
+fn load_family_font(
+    directory: &Path,
+    file: &str,
+    family: &str,
+    style: &str,
+) -> Result<FontData, Error> {
+    let path = directory.join(file);
+    FontData::load(&path, None).map_err(|err| {
+        Error::new(
+            format!(
+                "Failed to load {} {} font at {}: {}",
+                family,
+                style,
+                path.display(),
+                err
+            ),
+            io::Error::new(io::ErrorKind::Other, err.to_string()),
+        )
+    })
+}
+
+/// Finds the first of `families` whose four files are installed below one of `directories` and
+/// returns it together with the directory holding the files.
+fn locate_installed_family<'a>(
+    families: &'a [(&'static str, FallbackFontFiles)],
+    directories: &[PathBuf],
+) -> Option<(&'a (&'static str, FallbackFontFiles), PathBuf)> {
+    families.iter().find_map(|family| {
+        let directory = directories
+            .iter()
+            .find_map(|root| find_family_directory(root, &family.1, SYSTEM_FONT_SEARCH_DEPTH))?;
+        Some((family, directory))
     })
 }
 
+/// Loads the first of `families` whose four files are installed below one of `directories` and
+/// returns it together with its name and directory.
+fn find_installed_family(
+    families: &[(&'static str, FallbackFontFiles)],
+    directories: &[PathBuf],
+) -> Option<Result<(FontFamily<FontData>, &'static str, PathBuf), Error>> {
+    let ((name, files), directory) = locate_installed_family(families, directories)?;
+    Some(load_family_files(&directory, name, files).map(|family| (family, *name, directory)))
+}
+
+fn load_family_files(
+    directory: &Path,
+    name: &str,
+    files: &FallbackFontFiles,
+) -> Result<FontFamily<FontData>, Error> {
+    Ok(FontFamily {
+        regular: load_family_font(directory, files.regular, name, "regular")?,
+        bold: load_family_font(directory, files.bold, name, "bold")?,
+        italic: load_family_font(directory, files.italic, name, "italic")?,
+        bold_italic: load_family_font(directory, files.bold_italic, name, "bold italic")?,
+    })
+}
+
+fn system_fallback_font_family() -> Result<(FontFamily<FontData>, &'static str, PathBuf), Error> {
+    find_installed_family(SYSTEM_FALLBACK_FAMILIES, &system_font_directories()).unwrap_or_else(
+        || {
+            Err(Error::new(
+                "No sans-serif system font family with all four variants found for fallback",
+                io::Error::new(io::ErrorKind::NotFound, "system fonts not found"),
+            ))
+        },
+    )
+}
+
+/// Locates a monospace family for code blocks and returns it together with its name.
+///
+/// Roboto Mono is looked up in the bundled font directories first, followed by Consolas in the
+/// Windows font directory and DejaVu Sans Mono, Liberation Mono, or Courier New in the Linux and
+/// macOS system font directories.
+pub fn monospace_font_family() -> Result<(FontFamily<FontData>, &'static str), Error> {
+    let mut directories = font_directory_candidates();
+    directories.extend(windows_font_directory());
+    directories.extend(system_font_directories());
+    find_installed_family(MONOSPACE_FAMILIES, &directories)
+        .map(|found| found.map(|(family, name, _)| (family, name)))
+        .unwrap_or_else(|| {
+            Err(Error::new(
+                "No monospace font family with all four variants found",
+                io::Error::new(io::ErrorKind::NotFound, "monospace fonts not found"),
+            ))
+        })
+}
+
 fn fonts_missing(err: &Error) -> bool {
     matches!(
         err.kind(),
@@ -209,37 +467,185 @@ fn fonts_missing(err: &Error) -> bool {
     )
 }
 
-/// Returns the bundled Roboto font family if available and falls back to the Windows Arial family
-/// when the bundled fonts are missing.
+/// Returns the bundled Roboto font family if available and falls back to the Windows Arial family,
+/// then to a sans-serif family installed on Linux or macOS, when the bundled fonts are missing.
 pub fn default_font_family() -> Result<FontFamily<FontData>, Error> {
+    default_font_family_with_fallback().map(|(family, _)| family)
+}
+
+/// Loads the default font family like [`default_font_family`] and additionally reports where it
+/// was found.
+pub(crate) fn default_font_family_with_fallback(
+) -> Result<(FontFamily<FontData>, ResolvedFontSource), Error> {
     match load_bundled_font_family() {
-        Ok(family) => Ok(family),
+        Ok((family, directory)) => Ok((family, ResolvedFontSource::Bundled(directory))),
         Err(err) if fonts_missing(&err) => match windows_fallback_font_family() {
-            Ok(fallback) => {
+            Ok((fallback, directory)) => {
                 warn!(
                     "Bundled fonts unavailable ({}); falling back to Windows '{}' family.",
                     err, WINDOWS_FALLBACK_FAMILY_NAME
                 );
-                Ok(fallback)
-            }
-            Err(fallback_err) => {
-                warn!(
-                    "Bundled fonts unavailable ({}); Windows fallback failed: {}",
-                    err, fallback_err
-                );
-                Err(Error::new(
-                    format!(
-                        "Bundled fonts unavailable and Windows fallback failed: {}",
-                        fallback_err
-                    ),
-                    io::Error::new(io::ErrorKind::NotFound, "default fonts are not available"),
-                ))
+                Ok((fallback, ResolvedFontSource::WindowsFallback(directory)))
             }
+            Err(windows_err) => match system_fallback_font_family() {
+                Ok((fallback, name, directory)) => {
+                    warn!(
+                        "Bundled fonts unavailable ({}); falling back to system '{}' family.",
+                        err, name
+                    );
+                    Ok((
+                        fallback,
+                        ResolvedFontSource::SystemFallback {
+                            directory,
+                            family: name,
+                        },
+                    ))
+                }
+                Err(system_err) => {
+                    warn!(
+                        "Bundled fonts unavailable ({}); Windows fallback failed: {}; system fallback failed: {}",
+                        err, windows_err, system_err
+                    );
+                    Err(Error::new(
+                        format!(
+                            "Bundled fonts unavailable, Windows fallback failed: {}; system fallback failed: {}",
+                            windows_err, system_err
+                        ),
+                        io::Error::new(io::ErrorKind::NotFound, "default fonts are not available"),
+                    ))
+                }
+            },
         },
         Err(err) => Err(err),
     }
 }
 
+/// Origin of the font family used to render a document.
+#[derive(Clone, Default)]
+pub enum FontSource {
+    /// The bundled Roboto family, located through the search order described in the README, with
+    /// the Windows Arial family as fallback.
+    #[default]
+    Bundled,
+    /// A font family that was already loaded by the caller.
+    Family(FontFamily<FontData>),
+    /// A family stored as `{name}-Regular.ttf`, `{name}-Bold.ttf`, `{name}-Italic.ttf`, and
+    /// `{name}-BoldItalic.ttf` inside `directory`.
+    Directory {
+        /// Directory holding the four font files.
+        directory: PathBuf,
+        /// Common prefix of the font file names.
+        name: String,
+    },
+}
+
+impl FontSource {
+    /// Creates a source that loads the `name` family from `directory`.
+    pub fn directory(directory: impl Into<PathBuf>, name: impl Into<String>) -> Self {
+        Self::Directory {
+            directory: directory.into(),
+            name: name.into(),
+        }
+    }
+
+    /// Loads the font family and reports where it was found.
+    ///
+    /// Only [`FontSource::Bundled`] falls back; the other sources fail when their fonts cannot
+    /// be loaded.
+    pub(crate) fn load(&self) -> Result<(FontFamily<FontData>, ResolvedFontSource), Error> {
+        match self {
+            Self::Bundled => default_font_family_with_fallback(),
+            Self::Family(family) => Ok((family.clone(), ResolvedFontSource::Family)),
+            Self::Directory { directory, name } => load_font_family_from_directory(directory, name)
+                .map(|family| (family, ResolvedFontSource::Directory(directory.clone()))),
+        }
+    }
+}
+
+impl fmt::Debug for FontSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bundled => f.write_str("Bundled"),
+            // Printing the font data would dump the raw font bytes.
+            Self::Family(_) => f.write_str("Family(..)"),
+            Self::Directory { directory, name } => f
+                .debug_struct("Directory")
+                .field("directory", directory)
+                .field("name", name)
+                .finish(),
+        }
+    }
+}
+
+/// Location the font family of a document was loaded from.
+///
+/// Reported by [`resolved_font_source`] before rendering and by
+/// [`PdfRenderResult::font_source`](crate::builder::PdfRenderResult::font_source) afterwards.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ResolvedFontSource {
+    /// The bundled Roboto family in the given directory.
+    Bundled(PathBuf),
+    /// The Windows Arial family in the given directory, used because the bundled fonts are
+    /// missing.
+    WindowsFallback(PathBuf),
+    /// A sans-serif family installed on Linux or macOS, used because neither the bundled fonts
+    /// nor the Windows fonts are available.
+    SystemFallback {
+        /// Directory holding the four font files.
+        directory: PathBuf,
+        /// Name of the family, such as "DejaVu Sans".
+        family: &'static str,
+    },
+    /// The family loaded from the directory given by [`FontSource::Directory`].
+    Directory(PathBuf),
+    /// The family passed to the builder as [`FontSource::Family`].
+    Family,
+    /// No usable font family was found.
+    #[default]
+    Unavailable,
+}
+
+impl ResolvedFontSource {
+    /// Returns the name of the family that replaced the bundled Roboto family, if a fallback was
+    /// used.
+    pub fn fallback_family(&self) -> Option<&'static str> {
+        match self {
+            Self::WindowsFallback(_) => Some(WINDOWS_FALLBACK_FAMILY_NAME),
+            Self::SystemFallback { family, .. } => Some(family),
+            _ => None,
+        }
+    }
+}
+
+/// Reports where the default font family would be loaded from, without loading it.
+///
+/// The search follows [`default_font_family`]: the bundled font directories, then the Windows
+/// fonts, then the Linux and macOS system fonts.  Only the presence of the font files is checked
+/// and nothing is logged, so this is cheap enough for health checks.
+pub fn resolved_font_source() -> ResolvedFontSource {
+    if let Ok(directory) = resolve_font_directory() {
+        return ResolvedFontSource::Bundled(directory);
+    }
+    if let Some(directory) = windows_font_directory().filter(|directory| {
+        WINDOWS_FONT_FILES
+            .all()
+            .iter()
+            .all(|file| directory.join(file).is_file())
+    }) {
+        return ResolvedFontSource::WindowsFallback(directory);
+    }
+    match locate_installed_family(SYSTEM_FALLBACK_FAMILIES, &system_font_directories()) {
+        Some(((family, _), directory)) => ResolvedFontSource::SystemFallback { directory, family },
+        None => ResolvedFontSource::Unavailable,
+    }
+}
+
+impl From<FontFamily<FontData>> for FontSource {
+    fn from(family: FontFamily<FontData>) -> Self {
+        Self::Family(family)
+    }
+}
+
 /// Adds the bundled Roboto font family to the given document and returns the cached fonts.
 pub fn install_default_fonts(
     document: &mut Document,
@@ -248,7 +654,251 @@ pub fn install_default_fonts(
     Ok(document.add_font_family(family))
 }
 
+/// Font families that supply the glyphs missing from a document's primary family, such as CJK
+/// characters or emoji.
+///
+/// Created by [`install_fallback_fonts`].  Coverage is decided by the regular face of each family;
+/// characters that no family covers stay with the primary family.
+#[derive(Clone, Default)]
+pub struct FallbackFonts {
+    primary: Option<rusttype::Font<'static>>,
+    families: Vec<(FontFamily<Font>, rusttype::Font<'static>)>,
+}
+
+impl FallbackFonts {
+    /// Returns `true` when no fallback family is installed.
+    pub fn is_empty(&self) -> bool {
+        self.families.is_empty()
+    }
+
+    /// Splits `text` into runs and pairs each with the fallback family that renders it, or `None`
+    /// for runs rendered with the primary family.
+    pub fn split<'a>(&self, text: &'a str) -> Vec<(&'a str, Option<FontFamily<Font>>)> {
+        if self.is_empty() {
+            return vec![(text, None)];
+        }
+        split_runs(text, |character| self.family_index(character))
+            .into_iter()
+            .map(|(range, index)| (&text[range], index.map(|index| self.families[index].0)))
+            .collect()
+    }
+
+    /// Splits a styled string into runs that each use a font covering their characters.
+    pub fn split_string(&self, string: StyledString) -> Vec<StyledString> {
+        if self.is_empty() {
+            return vec![string];
+        }
+        self.split(&string.s)
+            .into_iter()
+            .map(|(text, family)| {
+                let mut style = string.style;
+                if let Some(family) = family {
+                    style.set_font_family(family);
+                }
+                StyledString::new(text, style)
+            })
+            .collect()
+    }
+
+    /// Splits a styled span like [`FallbackFonts::split_string`], keeping its decorations, link,
+    /// vertical offset, and footnote number.
+    pub fn split_span(&self, span: StyledSpan) -> Vec<StyledSpan> {
+        if self.is_empty() {
+            return vec![span];
+        }
+        let StyledSpan {
+            string,
+            underline,
+            underline_style,
+            underline_thickness,
+            strikethrough,
+            link,
+            page_link,
+            vertical_offset,
+            footnote,
+            letter_spacing,
+            word_spacing,
+        } = span;
+        self.split_string(string)
+            .into_iter()
+            .map(|string| StyledSpan {
+                string,
+                underline,
+                underline_style,
+                underline_thickness,
+                strikethrough,
+                link: link.clone(),
+                page_link,
+                vertical_offset,
+                footnote,
+                letter_spacing,
+                word_spacing,
+            })
+            .collect()
+    }
+
+    fn family_index(&self, character: char) -> Option<usize> {
+        if self
+            .primary
+            .as_ref()
+            .map_or(true, |primary| covers(primary, character))
+        {
+            return None;
+        }
+        self.families
+            .iter()
+            .position(|(_, glyphs)| covers(glyphs, character))
+    }
+}
+
+impl fmt::Debug for FallbackFonts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FallbackFonts")
+            .field("families", &self.families.len())
+            .finish()
+    }
+}
+
+/// Adds `families` to the document and returns the chain used to split text by glyph coverage.
+///
+/// Families are consulted in order for every character the document's default family lacks.
+pub fn install_fallback_fonts<I>(document: &mut Document, families: I) -> FallbackFonts
+where
+    I: IntoIterator<Item = FontFamily<FontData>>,
+{
+    let primary = document.font_cache().default_font_family().regular;
+    let primary = document.font_cache().get_rt_font(primary).clone();
+    let families = families
+        .into_iter()
+        .map(|family| {
+            let family = document.add_font_family(family);
+            let glyphs = document.font_cache().get_rt_font(family.regular).clone();
+            (family, glyphs)
+        })
+        .collect();
+
+    FallbackFonts {
+        primary: Some(primary),
+        families,
+    }
+}
+
+fn covers(font: &rusttype::Font<'static>, character: char) -> bool {
+    character.is_control() || font.glyph(character).id().0 != 0
+}
+
+/// Groups the characters of `text` into runs that share the font index chosen by `select`.
+///
+/// Whitespace joins the preceding run so spaces between words never form runs of their own.
+fn split_runs(
+    text: &str,
+    select: impl Fn(char) -> Option<usize>,
+) -> Vec<(Range<usize>, Option<usize>)> {
+    let mut runs: Vec<(Range<usize>, Option<usize>)> = Vec::new();
+    for (start, character) in text.char_indices() {
+        let end = start + character.len_utf8();
+        match runs.last_mut() {
+            Some((range, _)) if character.is_whitespace() => range.end = end,
+            Some((range, font)) if *font == select(character) => range.end = end,
+            _ => runs.push((start..end, select(character))),
+        }
+    }
+    runs
+}
+
 /// Indicates whether all bundled fonts required for the default font family are present on disk.
+///
+/// See [`resolved_font_source`] to find out which fallback would be used otherwise.
 pub fn default_fonts_available() -> bool {
     resolve_font_directory().is_ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_family_found_in_nested_directory() {
+        let root = env::temp_dir().join(format!("pdf_helper_system_fonts_{}", std::process::id()));
+        let nested = root.join("truetype/dejavu");
+        std::fs::create_dir_all(&nested).expect("create font directories");
+        let (_, files) = &SYSTEM_FALLBACK_FAMILIES[0];
+        for file in &files.all()[..3] {
+            std::fs::write(nested.join(file), b"").expect("write font file");
+        }
+
+        let incomplete = find_family_directory(&root, files, SYSTEM_FONT_SEARCH_DEPTH);
+        std::fs::write(nested.join(files.bold_italic), b"").expect("write font file");
+        let complete = find_family_directory(&root, files, SYSTEM_FONT_SEARCH_DEPTH);
+        let too_shallow = find_family_directory(&root, files, 1);
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(incomplete, None);
+        assert_eq!(complete, Some(nested));
+        assert_eq!(too_shallow, None);
+    }
+
+    #[test]
+    fn installed_family_reports_its_directory_and_name() {
+        let root = env::temp_dir().join(format!("pdf_helper_located_fonts_{}", std::process::id()));
+        let nested = root.join("truetype/liberation");
+        std::fs::create_dir_all(&nested).expect("create font directories");
+        let (name, files) = &SYSTEM_FALLBACK_FAMILIES[1];
+        for file in files.all() {
+            std::fs::write(nested.join(file), b"").expect("write font file");
+        }
+
+        let located = locate_installed_family(SYSTEM_FALLBACK_FAMILIES, &[root.clone()])
+            .map(|((family, _), directory)| (*family, directory));
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(located, Some((*name, nested.clone())));
+        let source = ResolvedFontSource::SystemFallback {
+            directory: nested,
+            family: name,
+        };
+        assert_eq!(source.fallback_family(), Some(*name));
+        assert_eq!(
+            ResolvedFontSource::WindowsFallback(PathBuf::new()).fallback_family(),
+            Some(WINDOWS_FALLBACK_FAMILY_NAME)
+        );
+        assert_eq!(
+            ResolvedFontSource::Bundled(PathBuf::new()).fallback_family(),
+            None
+        );
+        assert_eq!(
+            ResolvedFontSource::default(),
+            ResolvedFontSource::Unavailable
+        );
+    }
+
+    #[test]
+    fn mixed_latin_and_cjk_text_splits_without_dropping_characters() {
+        let text = "Hello 世界! 😀";
+        let runs = split_runs(text, |character| {
+            if character.is_ascii() {
+                None
+            } else if ('\u{4e00}'..='\u{9fff}').contains(&character) {
+                Some(0)
+            } else {
+                Some(1)
+            }
+        });
+
+        let pieces: Vec<_> = runs
+            .iter()
+            .map(|(range, font)| (&text[range.clone()], *font))
+            .collect();
+        assert_eq!(
+            pieces,
+            vec![
+                ("Hello ", None),
+                ("世界", Some(0)),
+                ("! ", None),
+                ("😀", Some(1)),
+            ]
+        );
+        let rejoined: String = pieces.iter().map(|(piece, _)| *piece).collect();
+        assert_eq!(rejoined, text);
+    }
+}
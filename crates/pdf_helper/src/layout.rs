@@ -0,0 +1,513 @@
+//! Page geometry shared between the page decorator and elements while rendering.
+//!
+//! `genpdf` hands every element a render area without revealing where that area sits on the page.
+//! The page decorator therefore records the absolute rectangle of each page body in a
+//! [`LayoutTracker`], which lets elements translate their area into page coordinates.  Elements
+//! use this to report the clickable rectangles of hyperlinked content as [`LinkArea`] values, and the
+//! builder uses it to capture a [`LayoutDump`] describing where every block was placed.
+
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use genpdf::error::Error;
+use genpdf::style::Style;
+use genpdf::{render, Element, Mm, RenderResult, Size};
+
+/// Rectangle on a page in millimetres, measured from the top-left corner of the page.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    /// Distance from the left edge of the page.
+    pub x: f64,
+    /// Distance from the top edge of the page.
+    pub y: f64,
+    /// Horizontal extent of the rectangle.
+    pub width: f64,
+    /// Vertical extent of the rectangle.
+    pub height: f64,
+}
+
+impl Rect {
+    /// Creates a rectangle from its top-left corner and size.
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Destination of a [`LinkArea`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkTarget {
+    /// External URI opened by the viewer.
+    Uri(String),
+    /// One-based page number of the same document.
+    Page(usize),
+}
+
+/// Clickable region that follows `target` when activated in a PDF viewer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkArea {
+    /// One-based page number hosting the link.
+    pub page: usize,
+    /// Position and size of the linked content on the page.
+    pub rect: Rect,
+    /// Destination followed by the link.
+    pub target: LinkTarget,
+}
+
+/// Kind of content block reported in a [`LayoutDump`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElementKind {
+    /// Title, subtitle, or identifier printed on the cover page.
+    CoverText,
+    /// Heading or entry table of the printed table of contents.
+    TableOfContents,
+    /// Section or subsection heading.
+    Heading,
+    /// Body paragraph.
+    Paragraph,
+    /// Image together with its caption.
+    Image,
+    /// Bulleted, numbered, or lettered list.
+    List,
+    /// Color legend.
+    Legend,
+    /// Data table.
+    Table,
+    /// Quotation or callout.
+    Quote,
+    /// Horizontal rule.
+    Rule,
+    /// Vertical whitespace.
+    Spacer,
+    /// Preformatted code.
+    Code,
+    /// Columns placed side by side.
+    Row,
+    /// Blocks kept together on one page.
+    KeepTogether,
+    /// Raw `genpdf` element supplied by the caller.
+    Custom,
+}
+
+impl fmt::Display for ElementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::CoverText => "cover text",
+            Self::TableOfContents => "table of contents",
+            Self::Heading => "heading",
+            Self::Paragraph => "paragraph",
+            Self::Image => "image",
+            Self::List => "list",
+            Self::Legend => "legend",
+            Self::Table => "table",
+            Self::Quote => "quote",
+            Self::Rule => "rule",
+            Self::Spacer => "spacer",
+            Self::Code => "code",
+            Self::Row => "row",
+            Self::KeepTogether => "keep-together group",
+            Self::Custom => "custom element",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Portion of a content block rendered on a single page.
+///
+/// Blocks that continue across a page break are reported once per page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlacedElement {
+    /// Kind of block that was rendered.
+    pub kind: ElementKind,
+    /// One-based page number hosting this portion of the block.
+    pub page: usize,
+    /// Position and size of the rendered portion on the page.
+    pub rect: Rect,
+}
+
+/// Elements placed on a single page.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PageLayout {
+    /// One-based page number.
+    pub page: usize,
+    /// Rectangle left for body content after margins, header, and footer.
+    pub body: Rect,
+    /// Elements in the order they were rendered.
+    pub elements: Vec<PlacedElement>,
+}
+
+/// Structured description of what was placed where during a render.
+///
+/// Produced by [`PdfBuilder::render_with_layout_debug`](crate::builder::PdfBuilder::render_with_layout_debug)
+/// to help diagnose overflow and alignment problems.  The `Display` implementation prints one
+/// line per page and element.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LayoutDump {
+    /// Every rendered page in order.
+    pub pages: Vec<PageLayout>,
+}
+
+impl LayoutDump {
+    /// Iterates over the elements of every page in rendering order.
+    pub fn elements(&self) -> impl Iterator<Item = &PlacedElement> {
+        self.pages.iter().flat_map(|page| page.elements.iter())
+    }
+}
+
+impl fmt::Display for LayoutDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for page in &self.pages {
+            writeln!(
+                f,
+                "page {} (body at {:.1}, {:.1}; {:.1} x {:.1} mm)",
+                page.page, page.body.x, page.body.y, page.body.width, page.body.height
+            )?;
+            for element in &page.elements {
+                writeln!(
+                    f,
+                    "  {} at {:.1}, {:.1}; {:.1} x {:.1} mm",
+                    element.kind,
+                    element.rect.x,
+                    element.rect.y,
+                    element.rect.width,
+                    element.rect.height
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Content block whose placements are collected for tagged output.
+#[cfg(feature = "tagged-pdf")]
+#[derive(Clone, Debug)]
+pub(crate) struct TaggedBlock {
+    pub(crate) kind: ElementKind,
+    /// Outline level of headings; unused for other blocks.
+    pub(crate) level: usize,
+    pub(crate) alt_text: Option<String>,
+    /// One-based page and rectangle of every part of the block, in rendering order.
+    pub(crate) fragments: Vec<(usize, Rect)>,
+}
+
+#[derive(Debug, Default)]
+struct LayoutState {
+    page: usize,
+    body: Rect,
+    links: Vec<LinkArea>,
+    footnotes: Vec<(usize, usize)>,
+    captions: Vec<(ElementKind, usize, usize)>,
+    #[cfg(feature = "tagged-pdf")]
+    tagged: Vec<TaggedBlock>,
+    recording: bool,
+    dump: LayoutDump,
+}
+
+/// Shared record of the current page geometry and the links placed during a render pass.
+///
+/// Cloning the tracker yields another handle to the same state, mirroring
+/// [`WarningSink`](crate::diagnostics::WarningSink).
+#[derive(Clone, Debug, Default)]
+pub struct LayoutTracker {
+    state: Arc<Mutex<LayoutState>>,
+}
+
+impl LayoutTracker {
+    /// Creates an empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Locks the shared state; a panic in another handle leaves it usable.
+    fn state(&self) -> MutexGuard<'_, LayoutState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Records that `page` started and that its body occupies `body`.
+    pub fn begin_page(&self, page: usize, body: Rect) {
+        let mut state = self.state();
+        state.page = page;
+        state.body = body;
+        if state.recording {
+            state.dump.pages.push(PageLayout {
+                page,
+                body,
+                elements: Vec::new(),
+            });
+        }
+    }
+
+    /// Returns the page currently being rendered, or 0 before the first page starts.
+    pub fn page(&self) -> usize {
+        self.state().page
+    }
+
+    /// Returns the body rectangle of the current page.
+    pub fn body(&self) -> Rect {
+        self.state().body
+    }
+
+    /// Returns the current page together with the absolute rectangle covered by `area`.
+    ///
+    /// Areas handed out by `genpdf` layouts always extend to the bottom of the page body, and
+    /// indentation only shrinks them from the left, so the rectangle is derived from the body's
+    /// bottom-right corner.  Areas that are also narrowed from the right, such as the non-final
+    /// columns of a table, are reported too far to the right.
+    pub fn locate(&self, area: &render::Area<'_>) -> (usize, Rect) {
+        let state = self.state();
+        let size = area.size();
+        let (width, height) = (mm_to_f64(size.width), mm_to_f64(size.height));
+        let rect = Rect::new(
+            state.body.x + state.body.width - width,
+            state.body.y + state.body.height - height,
+            width,
+            height,
+        );
+        (state.page, rect)
+    }
+
+    /// Records a link area.
+    pub fn push_link(&self, link: LinkArea) {
+        self.state().links.push(link);
+    }
+
+    /// Returns a copy of the link areas recorded so far.
+    pub fn links(&self) -> Vec<LinkArea> {
+        self.state().links.clone()
+    }
+
+    /// Records that the marker of footnote `number` was printed on the current page.
+    ///
+    /// Only the first placement of each footnote is kept.
+    pub fn place_footnote(&self, number: usize) {
+        let mut state = self.state();
+        if !state.footnotes.iter().any(|&(placed, _)| placed == number) {
+            let page = state.page;
+            state.footnotes.push((number, page));
+        }
+    }
+
+    /// Returns the footnote numbers placed so far together with their one-based pages, ordered by
+    /// number.
+    pub fn footnote_pages(&self) -> Vec<(usize, usize)> {
+        let mut footnotes = self.state().footnotes.clone();
+        footnotes.sort_unstable();
+        footnotes
+    }
+
+    /// Records that numbered figure or table `number` of `kind` started on the current page.
+    ///
+    /// Only the first placement of each number is kept.
+    pub fn place_caption(&self, kind: ElementKind, number: usize) {
+        let mut state = self.state();
+        if !state
+            .captions
+            .iter()
+            .any(|&(placed_kind, placed, _)| placed_kind == kind && placed == number)
+        {
+            let page = state.page;
+            state.captions.push((kind, number, page));
+        }
+    }
+
+    /// Returns the numbers of the figures or tables of `kind` placed so far together with their
+    /// one-based pages, ordered by number.
+    pub fn caption_pages(&self, kind: ElementKind) -> Vec<(usize, usize)> {
+        let mut pages: Vec<_> = self
+            .state()
+            .captions
+            .iter()
+            .filter(|(placed_kind, _, _)| *placed_kind == kind)
+            .map(|&(_, number, page)| (number, page))
+            .collect();
+        pages.sort_unstable();
+        pages
+    }
+
+    /// Registers a block whose placements are collected for tagged output and returns its index.
+    #[cfg(feature = "tagged-pdf")]
+    pub(crate) fn open_tagged(
+        &self,
+        kind: ElementKind,
+        level: usize,
+        alt_text: Option<String>,
+    ) -> usize {
+        let mut state = self.state();
+        state.tagged.push(TaggedBlock {
+            kind,
+            level,
+            alt_text,
+            fragments: Vec::new(),
+        });
+        state.tagged.len() - 1
+    }
+
+    /// Records that part of the tagged block `index` was rendered on `page` within `rect`.
+    #[cfg(feature = "tagged-pdf")]
+    fn place_tagged(&self, index: usize, page: usize, rect: Rect) {
+        if let Some(block) = self.state().tagged.get_mut(index) {
+            block.fragments.push((page, rect));
+        }
+    }
+
+    /// Returns the tagged blocks registered so far in reading order.
+    #[cfg(feature = "tagged-pdf")]
+    pub(crate) fn tagged_blocks(&self) -> Vec<TaggedBlock> {
+        self.state().tagged.clone()
+    }
+
+    /// Enables or disables capturing element placements for a [`LayoutDump`].
+    pub fn set_recording(&self, recording: bool) {
+        self.state().recording = recording;
+    }
+
+    /// Records that a block of `kind` occupying `size` was rendered at the top-left of `origin`.
+    ///
+    /// Nothing is recorded while recording is disabled or when the block took up no space.
+    pub fn record(&self, kind: ElementKind, page: usize, origin: Rect, size: Size) {
+        let mut state = self.state();
+        let (width, height) = (mm_to_f64(size.width), mm_to_f64(size.height));
+        if !state.recording || (width <= 0.0 && height <= 0.0) {
+            return;
+        }
+        let element = PlacedElement {
+            kind,
+            page,
+            rect: Rect::new(origin.x, origin.y, width, height),
+        };
+        match state
+            .dump
+            .pages
+            .iter_mut()
+            .rev()
+            .find(|layout| layout.page == page)
+        {
+            Some(layout) => layout.elements.push(element),
+            None => state.dump.pages.push(PageLayout {
+                page,
+                body: Rect::default(),
+                elements: vec![element],
+            }),
+        }
+    }
+
+    /// Returns a copy of the placements captured so far.
+    pub fn dump(&self) -> LayoutDump {
+        self.state().dump.clone()
+    }
+
+    /// Forgets the recorded geometry, links, footnotes, captions, tagged blocks, and placements so
+    /// the tracker can serve another render pass.  Whether recording is enabled is kept.
+    pub fn reset(&self) {
+        let mut state = self.state();
+        let recording = state.recording;
+        *state = LayoutState {
+            recording,
+            ..LayoutState::default()
+        };
+    }
+}
+
+/// Wrapper that reports where its inner element was rendered to a [`LayoutTracker`].
+pub(crate) struct LayoutProbe<E> {
+    inner: E,
+    kind: ElementKind,
+    layout: LayoutTracker,
+    #[cfg(feature = "tagged-pdf")]
+    tagged: Option<usize>,
+}
+
+impl<E: Element> LayoutProbe<E> {
+    pub(crate) fn new(inner: E, kind: ElementKind, layout: LayoutTracker) -> Self {
+        Self {
+            inner,
+            kind,
+            layout,
+            #[cfg(feature = "tagged-pdf")]
+            tagged: None,
+        }
+    }
+
+    /// Also collects the placements of the element as a tagged block with the given heading
+    /// `level` and alternate text.
+    #[cfg(feature = "tagged-pdf")]
+    pub(crate) fn with_tag(mut self, level: usize, alt_text: Option<String>) -> Self {
+        self.tagged = Some(self.layout.open_tagged(self.kind, level, alt_text));
+        self
+    }
+}
+
+impl<E: Element> Element for LayoutProbe<E> {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let (page, origin) = self.layout.locate(&area);
+        let result = self.inner.render(context, area, style)?;
+        self.layout.record(self.kind, page, origin, result.size);
+        #[cfg(feature = "tagged-pdf")]
+        if let Some(index) = self.tagged {
+            let (width, height) = (mm_to_f64(result.size.width), mm_to_f64(result.size.height));
+            if height > 0.0 {
+                let rect = Rect::new(origin.x, origin.y, width, height);
+                self.layout.place_tagged(index, page, rect);
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn mm_to_f64(value: Mm) -> f64 {
+    let mm: printpdf::Mm = value.into();
+    mm.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_are_grouped_by_page_only_while_recording() {
+        let tracker = LayoutTracker::new();
+        let size = Size::new(100, 10);
+        tracker.begin_page(1, Rect::new(20.0, 20.0, 170.0, 250.0));
+        tracker.record(ElementKind::Paragraph, 1, Rect::default(), size);
+        assert!(tracker.dump().pages.is_empty());
+
+        tracker.set_recording(true);
+        tracker.reset();
+        tracker.begin_page(1, Rect::new(20.0, 20.0, 170.0, 250.0));
+        tracker.record(
+            ElementKind::Heading,
+            1,
+            Rect::new(20.0, 20.0, 0.0, 0.0),
+            size,
+        );
+        tracker.record(
+            ElementKind::Paragraph,
+            1,
+            Rect::new(20.0, 30.0, 0.0, 0.0),
+            Size::new(0, 0),
+        );
+        tracker.begin_page(2, Rect::new(20.0, 20.0, 170.0, 250.0));
+        tracker.record(
+            ElementKind::Paragraph,
+            2,
+            Rect::new(20.0, 20.0, 0.0, 0.0),
+            size,
+        );
+
+        let dump = tracker.dump();
+        assert_eq!(dump.pages.len(), 2);
+        assert_eq!(dump.pages[0].elements.len(), 1);
+        assert_eq!(dump.pages[0].elements[0].kind, ElementKind::Heading);
+        assert_eq!(dump.pages[0].elements[0].rect.width, 100.0);
+        assert_eq!(dump.pages[1].elements[0].page, 2);
+    }
+}
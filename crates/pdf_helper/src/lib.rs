@@ -37,8 +37,23 @@
 //!
 //! Enable the `bookmarks` feature to post-process rendered bytes with hierarchical outlines via
 //! [`PdfBuilder::render_with_bookmarks`](crate::builder::PdfBuilder::render_with_bookmarks).
-//! Enabling the `hyphenation` feature wires an embedded US-English dictionary into the generated
-//! document to improve paragraph flow.
+//! The `serde` feature derives `Serialize` and `Deserialize` for the content model so document
+//! descriptions can be loaded from JSON or other formats; colors are stored as `#RRGGBB` strings.
+//! The `json` and `yaml` features build on it with
+//! [`PdfBuilder::from_json`](crate::builder::PdfBuilder::from_json) and `PdfBuilder::from_yaml`,
+//! which read a whole document description including the builder options (see [`loader`]).
+//! Sections that override the page orientation are rendered as separate runs of pages and joined
+//! with `lopdf`, which requires the `mixed-orientation` feature; the `merge` feature joins
+//! separately rendered documents into one with `merge::merge_documents`.  The `links` feature
+//! turns spans created with [`Span::linked`](crate::richtext::Span::linked) into clickable link
+//! annotations, and the `encryption` feature password-protects the output via
+//! [`PdfBuilder::with_encryption`](crate::builder::PdfBuilder::with_encryption).  The `pdfa`
+//! feature converts the output to PDF/A-1b or PDF/A-2b for archiving via
+//! [`PdfBuilder::pdfa_mode`](crate::builder::PdfBuilder::pdfa_mode), and the `preview` feature
+//! rasterizes the first page to a PNG thumbnail with Pdfium via `PdfBuilder::render_with_preview`.
+//! The `async` feature adds `PdfBuilder::render_async`, which builds and renders a document on
+//! Tokio's blocking thread pool.  Enabling the `hyphenation` feature wires an embedded US-English dictionary into the
+//! generated document to improve paragraph flow.
 //!
 //! ## Fonts
 //!
@@ -53,16 +68,67 @@
 //! checks the `PDF_HELPER_WINDOWS_FONTS_DIR` environment variable first and, on Windows hosts,
 //! falls back to `%WINDIR%\Fonts`.  A warning is emitted through the `log` facade whenever the
 //! fallback is activated so consumers can provision the preferred Roboto family when desired.
+//! [`fonts::resolved_font_source`] reports which family would be used without loading it, and
+//! [`PdfRenderResult::font_source`](crate::builder::PdfRenderResult::font_source) records the one
+//! a render actually used.
 
 pub mod builder;
+pub mod diagnostics;
 pub mod elements;
 pub mod examples;
 pub mod fonts;
+pub mod layout;
 pub mod model;
 pub mod richtext;
+pub mod theme;
 
 #[doc = include_str!("../GUIDE.md")]
 pub mod guide {}
 
 #[cfg(feature = "bookmarks")]
 pub mod bookmarks;
+
+#[cfg(feature = "links")]
+pub mod links;
+
+#[cfg(feature = "merge")]
+pub mod merge;
+
+#[cfg(feature = "encryption")]
+pub mod encryption;
+
+#[cfg(any(feature = "json", feature = "yaml"))]
+pub mod loader;
+
+#[cfg(feature = "pdfa")]
+pub mod pdfa;
+
+#[cfg(feature = "preview")]
+pub mod preview;
+
+#[cfg(any(
+    feature = "bookmarks",
+    feature = "links",
+    feature = "merge",
+    feature = "mixed-orientation",
+    feature = "page-labels",
+    feature = "tagged-pdf",
+    feature = "viewer-preferences"
+))]
+mod pdf_objects;
+
+#[cfg(feature = "tagged-pdf")]
+mod tagging;
+
+mod calendar;
+
+mod markdown;
+
+#[cfg(feature = "fixed-timestamp")]
+mod timestamp;
+
+#[cfg(feature = "transparency")]
+mod transparency;
+
+#[cfg(feature = "serde")]
+mod serde_color;
@@ -0,0 +1,91 @@
+//! Link annotation support built on top of `lopdf`.
+
+use lopdf::{Dictionary, Document, Object};
+
+use crate::layout::{LinkArea, LinkTarget};
+pub use crate::pdf_objects::BookmarkDestination;
+use crate::pdf_objects::{page_height_points, page_top_points, POINTS_PER_MM};
+
+/// Adds a link annotation for every recorded link area.
+///
+/// `genpdf` cannot emit annotations, so the rendered bytes are reopened with `lopdf` and each
+/// page receives `/Link` annotations whose `/Rect` covers the linked content.  URI targets become
+/// `/URI` actions and page targets a `/Dest` showing the target page as `page_destination`
+/// describes.  Link areas on or pointing to pages missing from the document are skipped.
+pub fn apply_link_annotations(
+    pdf_bytes: &[u8],
+    links: &[LinkArea],
+    page_destination: BookmarkDestination,
+) -> Result<Vec<u8>, lopdf::Error> {
+    if links.is_empty() {
+        return Ok(pdf_bytes.to_vec());
+    }
+
+    let mut document = Document::load_mem(pdf_bytes)?;
+    let pages = document.get_pages();
+
+    for link in links {
+        let Some(&page_id) = pages.get(&(link.page as u32)) else {
+            continue;
+        };
+        let page_height = page_height_points(&document, page_id)?;
+
+        let left = link.rect.x * POINTS_PER_MM;
+        let right = (link.rect.x + link.rect.width) * POINTS_PER_MM;
+        let top = page_height - link.rect.y * POINTS_PER_MM;
+        let bottom = page_height - (link.rect.y + link.rect.height) * POINTS_PER_MM;
+
+        let mut annotation = Dictionary::new();
+        annotation.set("Type", Object::Name("Annot".into()));
+        annotation.set("Subtype", Object::Name("Link".into()));
+        annotation.set(
+            "Rect",
+            Object::Array(
+                [left, bottom, right, top]
+                    .iter()
+                    .map(|value| Object::from(*value))
+                    .collect(),
+            ),
+        );
+        annotation.set(
+            "Border",
+            Object::Array(vec![
+                Object::Integer(0),
+                Object::Integer(0),
+                Object::Integer(0),
+            ]),
+        );
+        match &link.target {
+            LinkTarget::Uri(uri) => {
+                let mut action = Dictionary::new();
+                action.set("S", Object::Name("URI".into()));
+                action.set("URI", Object::string_literal(uri.as_str()));
+                annotation.set("A", Object::Dictionary(action));
+            }
+            LinkTarget::Page(target) => {
+                let Some(&target_id) = pages.get(&(*target as u32)) else {
+                    continue;
+                };
+                let page_top = page_top_points(&document, target_id)?;
+                annotation.set(
+                    "Dest",
+                    Object::Array(page_destination.to_array(target_id, page_top)),
+                );
+            }
+        }
+        let annotation_id = document.add_object(annotation);
+
+        let page = document.get_object_mut(page_id)?.as_dict_mut()?;
+        match page.get_mut(b"Annots") {
+            Ok(Object::Array(annotations)) => annotations.push(Object::Reference(annotation_id)),
+            _ => page.set(
+                "Annots",
+                Object::Array(vec![Object::Reference(annotation_id)]),
+            ),
+        }
+    }
+
+    let mut buffer = Vec::new();
+    document.save_to(&mut buffer)?;
+    Ok(buffer)
+}
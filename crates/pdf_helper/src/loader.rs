@@ -0,0 +1,287 @@
+//! Front end that configures a [`PdfBuilder`] from a JSON or YAML document description.
+//!
+//! The description holds an optional `cover`, the `sections`, and an `options` object with the
+//! builder settings; every field may be omitted.  Options that are left out keep the defaults of
+//! [`PdfBuilder::new`].  Unknown fields anywhere in the description are rejected so typos do not go
+//! unnoticed, and errors name where the problem was found by its path, such as
+//! `sections[1].blocks[0].Paragraph.spans[0]` for a span with a misspelled field.
+//!
+//! ```
+//! # #[cfg(feature = "json")]
+//! # fn main() -> Result<(), pdf_helper::loader::LoadError> {
+//! use pdf_helper::builder::PdfBuilder;
+//!
+//! let builder = PdfBuilder::from_json(
+//!     r#"{
+//!         "cover": { "title": "Quarterly Report" },
+//!         "sections": [
+//!             { "title": "Summary",
+//!               "blocks": [{ "Paragraph": { "spans": [{ "text": "Revenue grew." }] } }] }
+//!         ],
+//!         "options": { "show_footer": true, "include_toc": true, "margins_mm": [20, 15, 20, 15] }
+//!     }"#,
+//! )?;
+//! # let _ = builder;
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "json"))]
+//! # fn main() {}
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use genpdf::style::Color;
+use genpdf::Size;
+use serde::Deserialize;
+
+use crate::builder::{FooterStyle, HeaderStyle, PdfBuilder};
+use crate::elements::WatermarkOptions;
+use crate::model::{Cover, HorizontalAlignment, Section, TextDirection};
+
+/// Document description read by [`PdfBuilder::from_json`] and [`PdfBuilder::from_yaml`].
+///
+/// The description can also be deserialized from any other serde format and turned into a builder
+/// with [`DocumentDescription::into_builder`].
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DocumentDescription {
+    cover: Option<Cover>,
+    sections: Vec<Section>,
+    options: DocumentOptions,
+}
+
+/// Builder settings of a [`DocumentDescription`]; each maps to the [`PdfBuilder`] method of the
+/// same name.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DocumentOptions {
+    /// Paper width and height in millimetres.
+    paper_size_mm: Option<[f64; 2]>,
+    landscape: Option<bool>,
+    /// Top, right, bottom, and left margins in millimetres.
+    margins_mm: Option<[f64; 4]>,
+    #[serde(with = "crate::serde_color::option")]
+    page_background: Option<Color>,
+    show_header: Option<bool>,
+    show_footer: Option<bool>,
+    header_on_cover: Option<bool>,
+    footer_on_cover: Option<bool>,
+    header_style: Option<HeaderStyle>,
+    footer_style: Option<FooterStyle>,
+    footer_format: Option<String>,
+    strict_footer_format: Option<bool>,
+    include_toc: Option<bool>,
+    toc_title: Option<String>,
+    toc_depth: Option<usize>,
+    include_list_of_figures: Option<bool>,
+    include_list_of_tables: Option<bool>,
+    number_sections: Option<bool>,
+    number_figures: Option<bool>,
+    render_section_headings: Option<bool>,
+    sections_on_new_pages: Option<bool>,
+    default_alignment: Option<HorizontalAlignment>,
+    text_direction: Option<TextDirection>,
+    enable_hyphenation: Option<bool>,
+    min_lines_before_break: Option<usize>,
+    min_lines_after_break: Option<usize>,
+    /// Text drawn with the default [`WatermarkOptions`] behind every page.
+    watermark: Option<String>,
+}
+
+impl DocumentDescription {
+    /// Returns a builder configured with the cover, sections, and options of the description.
+    pub fn into_builder(self) -> PdfBuilder {
+        let options = self.options;
+        let mut builder = PdfBuilder::new()
+            .with_cover(self.cover)
+            .with_sections(self.sections);
+
+        if let Some([width, height]) = options.paper_size_mm {
+            builder = builder.with_paper_size(Size::new(printpdf::Mm(width), printpdf::Mm(height)));
+        }
+        if let Some(landscape) = options.landscape {
+            builder = builder.landscape(landscape);
+        }
+        if let Some([top, right, bottom, left]) = options.margins_mm {
+            builder = builder.with_margins_mm(top, right, bottom, left);
+        }
+        if let Some(color) = options.page_background {
+            builder = builder.with_page_background(color);
+        }
+        if let Some(show) = options.show_header {
+            builder = builder.show_header(show);
+        }
+        if let Some(show) = options.show_footer {
+            builder = builder.show_footer(show);
+        }
+        if let Some(show) = options.header_on_cover {
+            builder = builder.header_on_cover(show);
+        }
+        if let Some(show) = options.footer_on_cover {
+            builder = builder.footer_on_cover(show);
+        }
+        if let Some(style) = options.header_style {
+            builder = builder.header_style(style);
+        }
+        if let Some(style) = options.footer_style {
+            builder = builder.footer_style(style);
+        }
+        if let Some(format) = options.footer_format {
+            builder = builder.footer_format(format);
+        }
+        if let Some(strict) = options.strict_footer_format {
+            builder = builder.strict_footer_format(strict);
+        }
+        if let Some(include) = options.include_toc {
+            builder = builder.include_printed_toc(include);
+        }
+        if let Some(title) = options.toc_title {
+            builder = builder.with_toc_title(title);
+        }
+        if let Some(depth) = options.toc_depth {
+            builder = builder.with_toc_depth(depth);
+        }
+        if let Some(include) = options.include_list_of_figures {
+            builder = builder.include_list_of_figures(include);
+        }
+        if let Some(include) = options.include_list_of_tables {
+            builder = builder.include_list_of_tables(include);
+        }
+        if let Some(enabled) = options.number_sections {
+            builder = builder.number_sections(enabled);
+        }
+        if let Some(enabled) = options.number_figures {
+            builder = builder.number_figures(enabled);
+        }
+        if let Some(enabled) = options.render_section_headings {
+            builder = builder.render_section_headings(enabled);
+        }
+        if let Some(enabled) = options.sections_on_new_pages {
+            builder = builder.sections_on_new_pages(enabled);
+        }
+        if let Some(alignment) = options.default_alignment {
+            builder = builder.with_default_alignment(alignment);
+        }
+        if let Some(direction) = options.text_direction {
+            builder = builder.with_text_direction(direction);
+        }
+        if let Some(enable) = options.enable_hyphenation {
+            builder = builder.enable_hyphenation(enable);
+        }
+        if let Some(lines) = options.min_lines_before_break {
+            builder = builder.min_lines_before_break(lines);
+        }
+        if let Some(lines) = options.min_lines_after_break {
+            builder = builder.min_lines_after_break(lines);
+        }
+        if let Some(text) = options.watermark {
+            builder = builder.with_watermark(text, WatermarkOptions::default());
+        }
+        builder
+    }
+}
+
+impl PdfBuilder {
+    /// Configures a builder from a JSON [`DocumentDescription`].
+    ///
+    /// Requires the `json` feature.
+    #[cfg(feature = "json")]
+    pub fn from_json(input: &str) -> Result<Self, LoadError> {
+        let mut deserializer = serde_json::Deserializer::from_str(input);
+        let description: DocumentDescription =
+            serde_path_to_error::deserialize(&mut deserializer).map_err(LoadError::from_path)?;
+        deserializer
+            .end()
+            .map_err(|err| LoadError::new(String::new(), err.to_string()))?;
+        Ok(description.into_builder())
+    }
+
+    /// Configures a builder from a YAML [`DocumentDescription`].
+    ///
+    /// Requires the `yaml` feature.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(input: &str) -> Result<Self, LoadError> {
+        let deserializer = serde_yaml::Deserializer::from_str(input);
+        let description: DocumentDescription =
+            serde_path_to_error::deserialize(deserializer).map_err(LoadError::from_path)?;
+        Ok(description.into_builder())
+    }
+}
+
+/// Error returned when a document description cannot be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadError {
+    path: String,
+    message: String,
+}
+
+impl LoadError {
+    fn new(path: String, message: String) -> Self {
+        Self { path, message }
+    }
+
+    fn from_path<E: fmt::Display>(err: serde_path_to_error::Error<E>) -> Self {
+        let path = err.path().to_string();
+        Self::new(path, err.into_inner().to_string())
+    }
+
+    /// Returns the path of the value the error was found in, such as `sections[0].title`, or an
+    /// empty string or `.` when the error concerns the document as a whole.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the parser's description of the problem.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() || self.path == "." {
+            write!(f, "Invalid document description: {}", self.message)
+        } else {
+            write!(
+                f,
+                "Invalid document description at `{}`: {}",
+                self.path, self.message
+            )
+        }
+    }
+}
+
+impl StdError for LoadError {}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_keep_builder_defaults_when_omitted() {
+        let description: DocumentDescription =
+            serde_json::from_str(r#"{ "options": { "show_header": true } }"#).expect("parse");
+        assert_eq!(description.options.show_header, Some(true));
+        assert_eq!(description.options.show_footer, None);
+        assert!(description.sections.is_empty());
+    }
+
+    #[test]
+    fn unknown_fields_are_reported_with_their_path() {
+        let err = PdfBuilder::from_json(
+            r#"{ "sections": [{ "title": "Intro",
+                               "blocks": [{ "Paragraph": { "spans": [{ "txt": "Hi" }] } }] }] }"#,
+        )
+        .expect_err("typo is rejected");
+        assert!(err.path().starts_with("sections[0].blocks[0]"), "{err}");
+        assert!(err.message().contains("unknown field `txt`"), "{err}");
+    }
+
+    #[test]
+    fn invalid_values_name_the_option() {
+        let err = PdfBuilder::from_json(r#"{ "options": { "margins_mm": [10, 10] } }"#)
+            .expect_err("margins need four values");
+        assert_eq!(err.path(), "options.margins_mm");
+    }
+}
@@ -0,0 +1,435 @@
+//! Markdown subset read by [`Section::from_markdown`](crate::model::Section::from_markdown).
+//!
+//! Block constructs are handled here, while inline styling is delegated to [`parse_markup`] after
+//! `[text](target)` links have been cut out.  A link therefore cannot sit inside a bold or italic
+//! span; style the link text instead, as in `[**docs**](https://example.com)`.
+
+use crate::model::{atx_heading, Block, CodeBlock, ListBlock, ListItem, ListStyle, RichParagraph};
+use crate::richtext::{coalesce_spans, parse_markup, ParseError, Span};
+
+/// Parses `input` into blocks, rejecting constructs outside the supported subset.
+pub(crate) fn parse_markdown(input: &str) -> Result<Vec<Block>, ParseError> {
+    let mut parser = Parser::default();
+    let mut lines = lines_with_offsets(input);
+    while let Some((offset, line)) = lines.next() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        let at = offset + indent;
+
+        if trimmed.is_empty() {
+            parser.flush_paragraph();
+            parser.after_blank = true;
+            continue;
+        }
+
+        if let Some(fence) = code_fence(trimmed) {
+            parser.finish_blocks();
+            let language = trimmed[fence.len()..].trim();
+            let mut code = Vec::new();
+            let mut closed = false;
+            for (_, line) in lines.by_ref() {
+                if line.trim_start().starts_with(fence) {
+                    closed = true;
+                    break;
+                }
+                code.push(strip_indent(line, indent));
+            }
+            if !closed {
+                return Err(ParseError::new(
+                    at,
+                    format!("unterminated code fence; close it with `{}`", fence),
+                ));
+            }
+            let language = (!language.is_empty()).then(|| language.to_string());
+            parser.blocks.push(Block::Code(
+                CodeBlock::new(code.join("\n")).with_language(language),
+            ));
+            parser.after_blank = false;
+            continue;
+        }
+
+        if let Some(construct) = unsupported_block(trimmed) {
+            return Err(ParseError::new(
+                at,
+                format!("{} are not supported", construct),
+            ));
+        }
+        if indent >= 4 && parser.list.is_none() && parser.paragraph.is_empty() {
+            return Err(ParseError::new(
+                at,
+                "indented code blocks are not supported; use a ``` fence",
+            ));
+        }
+        if !parser.paragraph.is_empty() && is_setext_underline(trimmed) {
+            return Err(ParseError::new(
+                at,
+                "underlined headings are not supported; start the line with `#` instead",
+            ));
+        }
+
+        if let Some((level, text, start)) = atx_heading(trimmed) {
+            parser.finish_blocks();
+            parser
+                .blocks
+                .push(Block::heading(level, parse_inline(text, at + start)?));
+        } else if is_thematic_break(trimmed) {
+            parser.finish_blocks();
+            parser.blocks.push(Block::rule());
+        } else if let Some((style, start, marker_len)) = list_marker(trimmed) {
+            parser.flush_paragraph();
+            let rest = &trimmed[marker_len..];
+            let text_at = at + marker_len + (rest.len() - rest.trim_start().len());
+            let spans = parse_inline(rest.trim(), text_at)?;
+            parser.push_item(indent, style, start, spans);
+        } else if parser.list.is_some() && (!parser.after_blank || indent >= 2) {
+            // Lines following an item without a blank line, or indented below it, continue it.
+            let spans = parse_inline(trimmed.trim_end(), at)?;
+            if let Some(list) = parser.list.as_mut() {
+                list.append(spans);
+            }
+        } else {
+            parser.finish_list();
+            let spans = parse_inline(trimmed.trim_end(), at)?;
+            if !parser.paragraph.is_empty() {
+                parser.paragraph.push(Span::new(" "));
+            }
+            parser.paragraph.extend(spans);
+        }
+        parser.after_blank = false;
+    }
+    parser.finish_blocks();
+    Ok(parser.blocks)
+}
+
+/// Blocks parsed so far together with the paragraph or list still being read.
+#[derive(Default)]
+struct Parser {
+    blocks: Vec<Block>,
+    paragraph: Vec<Span>,
+    list: Option<PendingList>,
+    after_blank: bool,
+}
+
+impl Parser {
+    fn flush_paragraph(&mut self) {
+        if !self.paragraph.is_empty() {
+            let spans = coalesce_spans(self.paragraph.drain(..));
+            self.blocks.push(Block::paragraph(spans));
+        }
+    }
+
+    fn finish_list(&mut self) {
+        if let Some(list) = self.list.take() {
+            self.blocks.push(Block::List(list.into_block()));
+        }
+    }
+
+    fn finish_blocks(&mut self) {
+        self.flush_paragraph();
+        self.finish_list();
+    }
+
+    /// Adds an item to the open list, or starts a new list when the marker style changes at the
+    /// top level.
+    fn push_item(&mut self, indent: usize, style: ListStyle, start: usize, spans: Vec<Span>) {
+        if let Some(list) = self
+            .list
+            .as_mut()
+            .filter(|list| indent > list.indent + 1 || list.style == style)
+        {
+            list.insert(indent, style, start, spans);
+            return;
+        }
+        self.finish_list();
+        self.list = Some(PendingList::new(indent, style, start, spans));
+    }
+}
+
+/// List whose items are still being read, nested by the indentation of their markers.
+struct PendingList {
+    indent: usize,
+    style: ListStyle,
+    start: usize,
+    items: Vec<PendingItem>,
+}
+
+struct PendingItem {
+    spans: Vec<Span>,
+    sublist: Option<PendingList>,
+}
+
+impl PendingList {
+    fn new(indent: usize, style: ListStyle, start: usize, spans: Vec<Span>) -> Self {
+        Self {
+            indent,
+            style,
+            start,
+            items: vec![PendingItem {
+                spans,
+                sublist: None,
+            }],
+        }
+    }
+
+    /// Adds an item, nesting it below the last item when its marker is indented further.
+    fn insert(&mut self, indent: usize, style: ListStyle, start: usize, spans: Vec<Span>) {
+        if indent > self.indent + 1 {
+            if let Some(last) = self.items.last_mut() {
+                if let Some(sublist) = last.sublist.as_mut() {
+                    sublist.insert(indent, style, start, spans);
+                } else {
+                    last.sublist = Some(Self::new(indent, style, start, spans));
+                }
+                return;
+            }
+        }
+        self.items.push(PendingItem {
+            spans,
+            sublist: None,
+        });
+    }
+
+    /// Appends continuation text to the innermost last item.
+    fn append(&mut self, spans: Vec<Span>) {
+        let Some(last) = self.items.last_mut() else {
+            return;
+        };
+        match last.sublist.as_mut() {
+            Some(sublist) => sublist.append(spans),
+            None => {
+                last.spans.push(Span::new(" "));
+                last.spans.extend(spans);
+            }
+        }
+    }
+
+    fn into_block(self) -> ListBlock {
+        let items = self.items.into_iter().map(|item| {
+            ListItem::new(RichParagraph::new(coalesce_spans(item.spans)))
+                .with_sublist(item.sublist.map(Self::into_block))
+        });
+        ListBlock::new(self.style)
+            .with_start(self.start)
+            .with_items(items)
+    }
+}
+
+/// Splits `input` into lines without their line endings, paired with their byte offsets.
+fn lines_with_offsets(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut offset = 0;
+    input.split_inclusive('\n').map(move |line| {
+        let start = offset;
+        offset += line.len();
+        (start, line.trim_end_matches(['\r', '\n']))
+    })
+}
+
+/// Returns the fence opening a fenced code block.
+fn code_fence(line: &str) -> Option<&'static str> {
+    ["```", "~~~"]
+        .into_iter()
+        .find(|fence| line.starts_with(fence))
+}
+
+/// Removes up to `indent` leading spaces, the indentation of the opening fence, from a code line.
+fn strip_indent(line: &str, indent: usize) -> &str {
+    let spaces = line.len() - line.trim_start_matches(' ').len();
+    &line[spaces.min(indent)..]
+}
+
+/// Names block constructs the parser rejects instead of printing them as text.
+fn unsupported_block(line: &str) -> Option<&'static str> {
+    let mut chars = line.chars();
+    match chars.next()? {
+        '>' => Some("block quotes"),
+        '|' => Some("tables"),
+        '<' if chars
+            .next()
+            .is_some_and(|next| next.is_ascii_alphabetic() || next == '/' || next == '!') =>
+        {
+            Some("raw HTML blocks")
+        }
+        _ => None,
+    }
+}
+
+/// Recognizes `---`, `***`, and `___` lines, optionally with spaces between the characters.
+fn is_thematic_break(line: &str) -> bool {
+    let mut marks = line.chars().filter(|ch| !ch.is_whitespace());
+    let Some(first) = marks.next() else {
+        return false;
+    };
+    let rest: Vec<char> = marks.collect();
+    matches!(first, '-' | '*' | '_') && rest.len() >= 2 && rest.iter().all(|ch| *ch == first)
+}
+
+/// Recognizes the `===` or `---` line that turns the preceding paragraph into a heading.
+fn is_setext_underline(line: &str) -> bool {
+    let line = line.trim_end();
+    ['=', '-']
+        .into_iter()
+        .any(|mark| line.chars().all(|ch| ch == mark))
+}
+
+/// Recognizes a list item marker and returns the list style, the item's number, and the length
+/// of the marker including the space after it.
+fn list_marker(line: &str) -> Option<(ListStyle, usize, usize)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if line.starts_with(bullet) {
+            return Some((ListStyle::Bulleted, 1, bullet.len()));
+        }
+    }
+    let digits = line.len()
+        - line
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .len();
+    let rest = &line[digits..];
+    if (1..=9).contains(&digits) && (rest.starts_with(". ") || rest.starts_with(") ")) {
+        let start = line[..digits].parse().ok()?;
+        return Some((ListStyle::Numbered, start, digits + 2));
+    }
+    None
+}
+
+/// Parses the inline markup of `text`, which starts at byte `offset` of the input.
+///
+/// `[text](target)` links the spans of `text` to `target`; targets starting with `#` link to the
+/// section with that identifier.
+fn parse_inline(text: &str, offset: usize) -> Result<Vec<Span>, ParseError> {
+    let mut spans = Vec::new();
+    let mut plain_start = 0;
+    let mut search = 0;
+    while let Some(found) = text[search..].find('[') {
+        let open = search + found;
+        search = open + 1;
+        if is_escaped(text, open) {
+            continue;
+        }
+        if open > 0 && text[..open].ends_with('!') && !is_escaped(text, open - 1) {
+            return Err(ParseError::new(
+                offset + open - 1,
+                "images are not supported; add an image block to the section instead",
+            ));
+        }
+        let Some((label_end, target, end)) = link_at(text, open) else {
+            continue;
+        };
+
+        spans.extend(markup(&text[plain_start..open], offset + plain_start)?);
+        let label = markup(&text[open + 1..label_end], offset + open + 1)?;
+        spans.extend(
+            label
+                .into_iter()
+                .map(|span| match target.strip_prefix('#') {
+                    Some(identifier) => span.linked_to_section(identifier),
+                    None => span.linked(target),
+                }),
+        );
+        plain_start = end;
+        search = end;
+    }
+    spans.extend(markup(&text[plain_start..], offset + plain_start)?);
+    Ok(spans)
+}
+
+/// Finds the `](target)` completing a link opened at `open` and returns the end of the label,
+/// the target, and the end of the link.
+fn link_at(text: &str, open: usize) -> Option<(usize, &str, usize)> {
+    let mut label_end = None;
+    for (index, ch) in text[open + 1..].char_indices() {
+        let index = open + 1 + index;
+        match ch {
+            '[' if !is_escaped(text, index) => return None,
+            ']' if !is_escaped(text, index) => {
+                label_end = Some(index);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let label_end = label_end?;
+    let target_start = label_end + 1;
+    if !text[target_start..].starts_with('(') {
+        return None;
+    }
+    let close = target_start + text[target_start..].find(')')?;
+    let target = text[target_start + 1..close].trim();
+    (!target.is_empty()).then_some((label_end, target, close + 1))
+}
+
+/// Whether the character at `index` follows an odd number of backslashes.
+fn is_escaped(text: &str, index: usize) -> bool {
+    let backslashes = text[..index].len() - text[..index].trim_end_matches('\\').len();
+    backslashes % 2 == 1
+}
+
+fn markup(text: &str, offset: usize) -> Result<Vec<Span>, ParseError> {
+    parse_markup(text).map_err(|err| err.offset_by(offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supported_constructs_become_blocks() {
+        let blocks = parse_markdown(
+            "Intro with **bold** and a [guide](https://example.com).\n\
+             \n\
+             ## Steps\n\
+             1. Install\n\
+             2. Configure\n  \
+               - see [setup](#setup)\n\
+             \n\
+             ```toml\n\
+             [package]\n\
+             ```\n\
+             ---\n",
+        )
+        .expect("supported markdown");
+
+        assert_eq!(
+            blocks,
+            vec![
+                Block::paragraph(vec![
+                    Span::new("Intro with "),
+                    Span::new("bold").bold(),
+                    Span::new(" and a "),
+                    Span::new("guide").linked("https://example.com"),
+                    Span::new("."),
+                ]),
+                Block::heading(2, vec![Span::new("Steps")]),
+                Block::List(
+                    ListBlock::new(ListStyle::Numbered)
+                        .with_item(RichParagraph::new(vec![Span::new("Install")]))
+                        .with_item(
+                            ListItem::new(RichParagraph::new(vec![Span::new("Configure")]))
+                                .with_sublist(ListBlock::new(ListStyle::Bulleted).with_item(
+                                    RichParagraph::new(vec![
+                                        Span::new("see "),
+                                        Span::new("setup").linked_to_section("setup"),
+                                    ]),
+                                )),
+                        ),
+                ),
+                Block::Code(CodeBlock::new("[package]").with_language("toml".to_string())),
+                Block::rule(),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_constructs_are_reported_where_they_start() {
+        let err = parse_markdown("Text\n\n> quoted").expect_err("block quote");
+        assert_eq!(
+            (err.index(), err.message()),
+            (6, "block quotes are not supported")
+        );
+
+        let err = parse_markdown("See ![chart](chart.png)").expect_err("image");
+        assert_eq!(err.index(), 4);
+
+        let err = parse_markdown("```rust\nfn main() {}\n").expect_err("open fence");
+        assert_eq!(err.index(), 0);
+    }
+}
@@ -0,0 +1,225 @@
+//! Joining separately rendered PDF documents into one book.
+
+use crate::bookmarks::BookmarkError;
+use crate::pdf_objects;
+use lopdf::{Document, Object, ObjectId};
+
+/// Combines the PDF documents in `parts` into one document, in the given order.
+///
+/// The objects of every part are renumbered past those of the parts before it and its pages are
+/// appended to the page tree of the first part.  Pages keep their own media box, so parts with
+/// differing page sizes or orientations can be combined.  The top-level bookmarks of every part
+/// are chained after those of the previous parts, which keeps each part's outline tree intact
+/// below a single combined outline.
+///
+/// The catalog of the first part is kept; the other catalog entries of later parts, such as page
+/// labels, viewer preferences, or a structure tree, are dropped.  The result uses the highest PDF
+/// version of the parts.
+pub fn merge_documents(parts: Vec<Vec<u8>>) -> Result<Vec<u8>, BookmarkError> {
+    let mut parts = parts.into_iter();
+    let first = parts.next().ok_or(BookmarkError::NoDocuments)?;
+    let mut merged = Document::load_mem(&first)?;
+
+    for bytes in parts {
+        let part = Document::load_mem(&bytes)?;
+        if part.version > merged.version {
+            merged.version = part.version.clone();
+        }
+        let catalog = pdf_objects::append_document(&mut merged, part)?;
+        if let Ok(outlines_id) = catalog.get(b"Outlines").and_then(Object::as_reference) {
+            chain_outlines(&mut merged, outlines_id)?;
+        }
+    }
+
+    // The page tree roots and catalogs of the appended parts are no longer referenced.
+    merged.prune_objects();
+
+    let mut buffer = Vec::new();
+    merged.save_to(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Moves the top-level entries of the appended outline root `appended_id` to the end of the
+/// outline of `document` and removes the appended root.
+///
+/// When `document` has no outline yet, the appended root becomes its outline.
+fn chain_outlines(document: &mut Document, appended_id: ObjectId) -> Result<(), BookmarkError> {
+    let appended = document.get_dictionary(appended_id)?.clone();
+    let entry = |key: &[u8]| appended.get(key).and_then(Object::as_reference).ok();
+    let (Some(first), Some(last)) = (entry(b"First"), entry(b"Last")) else {
+        document.objects.remove(&appended_id);
+        return Ok(());
+    };
+    let appended_count = appended.get(b"Count").and_then(Object::as_i64).unwrap_or(0);
+
+    let catalog_id = document
+        .trailer
+        .get(b"Root")
+        .and_then(Object::as_reference)
+        .map_err(|_| BookmarkError::MissingCatalog)?;
+    let catalog = document
+        .get_object_mut(catalog_id)
+        .and_then(Object::as_dict_mut)
+        .map_err(|_| BookmarkError::InvalidCatalog)?;
+    let Ok(root_id) = catalog.get(b"Outlines").and_then(Object::as_reference) else {
+        catalog.set("Outlines", Object::Reference(appended_id));
+        return Ok(());
+    };
+
+    let root = document.get_dictionary(root_id)?;
+    let previous_last = root.get(b"Last").and_then(Object::as_reference).ok();
+    let count = root.get(b"Count").and_then(Object::as_i64).unwrap_or(0);
+
+    let mut sibling = Some(first);
+    while let Some(item_id) = sibling {
+        let item = document.get_object_mut(item_id)?.as_dict_mut()?;
+        item.set("Parent", Object::Reference(root_id));
+        sibling = item.get(b"Next").and_then(Object::as_reference).ok();
+    }
+
+    match previous_last {
+        Some(previous) => {
+            document
+                .get_object_mut(previous)?
+                .as_dict_mut()?
+                .set("Next", Object::Reference(first));
+            document
+                .get_object_mut(first)?
+                .as_dict_mut()?
+                .set("Prev", Object::Reference(previous));
+        }
+        None => {
+            document
+                .get_object_mut(root_id)?
+                .as_dict_mut()?
+                .set("First", Object::Reference(first));
+        }
+    }
+    let root = document.get_object_mut(root_id)?.as_dict_mut()?;
+    root.set("Last", Object::Reference(last));
+    root.set("Count", Object::Integer(count + appended_count));
+    document.objects.remove(&appended_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::Dictionary;
+
+    /// Builds a one-page document whose media box is inherited from the page tree root and
+    /// whose outline has a single entry titled `title`.
+    fn sample_document(title: &str, width: i64, height: i64) -> Vec<u8> {
+        let mut document = Document::with_version("1.3");
+        let pages_id = document.new_object_id();
+        let outlines_id = document.new_object_id();
+
+        let mut page = Dictionary::new();
+        page.set("Type", Object::Name("Page".into()));
+        page.set("Parent", Object::Reference(pages_id));
+        let page_id = document.add_object(page);
+
+        let mut pages = Dictionary::new();
+        pages.set("Type", Object::Name("Pages".into()));
+        pages.set("Kids", Object::Array(vec![Object::Reference(page_id)]));
+        pages.set("Count", Object::Integer(1));
+        pages.set(
+            "MediaBox",
+            Object::Array(vec![0.into(), 0.into(), width.into(), height.into()]),
+        );
+        document.objects.insert(pages_id, Object::Dictionary(pages));
+
+        let mut entry = Dictionary::new();
+        entry.set("Title", Object::string_literal(title));
+        entry.set("Parent", Object::Reference(outlines_id));
+        entry.set(
+            "Dest",
+            Object::Array(vec![Object::Reference(page_id), Object::Name("Fit".into())]),
+        );
+        let entry_id = document.add_object(entry);
+
+        let mut outlines = Dictionary::new();
+        outlines.set("Type", Object::Name("Outlines".into()));
+        outlines.set("First", Object::Reference(entry_id));
+        outlines.set("Last", Object::Reference(entry_id));
+        outlines.set("Count", Object::Integer(1));
+        document
+            .objects
+            .insert(outlines_id, Object::Dictionary(outlines));
+
+        let mut catalog = Dictionary::new();
+        catalog.set("Type", Object::Name("Catalog".into()));
+        catalog.set("Pages", Object::Reference(pages_id));
+        catalog.set("Outlines", Object::Reference(outlines_id));
+        let catalog_id = document.add_object(catalog);
+        document.trailer.set("Root", Object::Reference(catalog_id));
+
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).expect("save sample document");
+        bytes
+    }
+
+    /// Width of a page's media box, which may be inherited from its page tree ancestors.
+    fn page_width(document: &Document, page_id: ObjectId) -> i64 {
+        let mut node = document.get_dictionary(page_id).expect("page");
+        loop {
+            if let Ok(media_box) = node.get(b"MediaBox").and_then(Object::as_array) {
+                return media_box[2].as_i64().expect("width");
+            }
+            let parent = node
+                .get(b"Parent")
+                .and_then(Object::as_reference)
+                .expect("media box");
+            node = document.get_dictionary(parent).expect("page tree node");
+        }
+    }
+
+    #[test]
+    fn merged_documents_keep_page_sizes_and_chain_outlines() {
+        let bytes = merge_documents(vec![
+            sample_document("Platform team", 595, 842),
+            sample_document("Data team", 842, 595),
+        ])
+        .expect("documents merged");
+        let document = Document::load_mem(&bytes).expect("merged PDF parses");
+
+        let widths: Vec<_> = document
+            .get_pages()
+            .into_values()
+            .map(|page_id| page_width(&document, page_id))
+            .collect();
+        assert_eq!(widths, vec![595, 842]);
+
+        let outlines = document
+            .catalog()
+            .and_then(|catalog| catalog.get(b"Outlines"))
+            .and_then(Object::as_reference)
+            .and_then(|id| document.get_dictionary(id))
+            .expect("combined outline");
+        assert_eq!(
+            outlines.get(b"Count").and_then(Object::as_i64).ok(),
+            Some(2)
+        );
+
+        let mut titles = Vec::new();
+        let mut entry = outlines.get(b"First").and_then(Object::as_reference).ok();
+        while let Some(id) = entry {
+            let dictionary = document.get_dictionary(id).expect("outline entry");
+            let title = dictionary
+                .get(b"Title")
+                .and_then(Object::as_str)
+                .expect("title");
+            titles.push(String::from_utf8_lossy(title).into_owned());
+            entry = dictionary.get(b"Next").and_then(Object::as_reference).ok();
+        }
+        assert_eq!(titles, vec!["Platform team", "Data team"]);
+    }
+
+    #[test]
+    fn merging_nothing_is_an_error() {
+        assert!(matches!(
+            merge_documents(Vec::new()),
+            Err(BookmarkError::NoDocuments)
+        ));
+    }
+}
@@ -6,7 +6,14 @@
 //! persisted, or exchanged over the network without pulling in heavy
 //! dependencies.
 
-use crate::richtext::Span;
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+use genpdf::style::Color;
+use genpdf::Element;
+
+use crate::richtext::{coalesce_spans, parse_markup, ParseError, Span};
 
 /// Metadata that controls how textual and visual elements are aligned once
 /// they are converted into [`genpdf::elements`].
@@ -15,6 +22,7 @@ use crate::richtext::Span;
 /// enum so that serialized representations stay compact and easy to
 /// interoperate with.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HorizontalAlignment {
     /// Left aligned content.
     #[default]
@@ -27,6 +35,42 @@ pub enum HorizontalAlignment {
     Justified,
 }
 
+/// Reading direction of a paragraph.
+///
+/// Right-to-left paragraphs, e.g. in Arabic or Hebrew, are set flush right and every line is
+/// reordered for display.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextDirection {
+    /// Left-to-right text.
+    #[default]
+    Ltr,
+    /// Right-to-left text.
+    Rtl,
+}
+
+/// Horizontal placement of the values in a table column.
+///
+/// Besides the usual left/center/right placement, [`ColumnAlignment::Decimal`] lines numeric
+/// values up on their decimal separator, which keeps financial figures readable when the number
+/// of integer or fractional digits varies between rows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColumnAlignment {
+    /// Left aligned cells.
+    #[default]
+    Left,
+    /// Center aligned cells.
+    Center,
+    /// Right aligned cells.
+    Right,
+    /// Cells aligned on their decimal separator.
+    ///
+    /// Decimal-aligned cells are printed as plain text in the style of their first span; the
+    /// styling of later spans is ignored.
+    Decimal,
+}
+
 /// Rich text paragraph carrying inline styling information and alignment
 /// metadata.
 ///
@@ -36,9 +80,18 @@ pub enum HorizontalAlignment {
 /// [`genpdf::Alignment`] while the spans are turned into styled strings via the
 /// helpers in [`crate::richtext`].
 #[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct RichParagraph {
     spans: Vec<Span>,
     alignment: HorizontalAlignment,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_color::option"))]
+    background: Option<Color>,
+    background_alpha: Option<u8>,
+    direction: Option<TextDirection>,
+    word_spacing: Option<f64>,
+    line_spacing: Option<f64>,
 }
 
 impl RichParagraph {
@@ -65,10 +118,91 @@ impl RichParagraph {
         self.alignment = alignment;
         self
     }
+
+    /// Returns the color filled behind the paragraph, if any.
+    pub fn background(&self) -> Option<Color> {
+        self.background
+    }
+
+    /// Fills the full content width behind the paragraph with `color`, e.g. for highlighted
+    /// notes, and returns the updated paragraph.
+    pub fn with_background(mut self, color: impl Into<Option<Color>>) -> Self {
+        self.background = color.into();
+        self
+    }
+
+    /// Returns the opacity of the background from `0` (invisible) to `255` (opaque, the default).
+    pub fn background_alpha(&self) -> u8 {
+        self.background_alpha.unwrap_or(u8::MAX)
+    }
+
+    /// Fills the content width behind the paragraph with a translucent `color` and returns the
+    /// updated paragraph.
+    ///
+    /// With the `transparency` feature the fill is drawn at the given opacity; otherwise the color
+    /// is blended towards white, which only matches true transparency on white pages.
+    pub fn with_background_alpha(mut self, color: Color, alpha: u8) -> Self {
+        self.background = Some(color);
+        self.background_alpha = Some(alpha);
+        self
+    }
+
+    /// Returns the reading direction, or `None` to use the document default.
+    pub fn direction(&self) -> Option<TextDirection> {
+        self.direction
+    }
+
+    /// Sets the reading direction and returns the updated paragraph.
+    ///
+    /// `None` uses the default set with
+    /// [`PdfBuilder::with_text_direction`](crate::builder::PdfBuilder::with_text_direction).
+    pub fn with_direction(mut self, direction: impl Into<Option<TextDirection>>) -> Self {
+        self.direction = direction.into();
+        self
+    }
+
+    /// Returns the extra space in points added to every space between words, `0.0` by default.
+    pub fn word_spacing(&self) -> f64 {
+        self.word_spacing.unwrap_or_default()
+    }
+
+    /// Adds `points` of space to every space character, like the PDF `Tw` operator, and returns
+    /// the updated paragraph; negative values tighten the text.
+    ///
+    /// Paragraphs with word spacing are laid out by the custom text elements, which measure the
+    /// extra space so aligned and wrapped lines stay in place.
+    pub fn with_word_spacing(mut self, points: f64) -> Self {
+        self.word_spacing = (points != 0.0).then_some(points);
+        self
+    }
+
+    /// Returns the line height as a multiple of the font's natural line height, `1.0` by default.
+    pub fn line_spacing(&self) -> f64 {
+        self.line_spacing.unwrap_or(1.0)
+    }
+
+    /// Sets the line height to `multiplier` times the font's natural line height, e.g. `0.9` for
+    /// dense legal text or `1.5` for airy copy, and returns the updated paragraph.
+    ///
+    /// Only this paragraph is affected; headings, captions, and the table of contents keep their
+    /// own spacing.  Values that are not positive restore the natural line height.
+    pub fn with_line_spacing(mut self, multiplier: f64) -> Self {
+        self.line_spacing = (multiplier > 0.0 && multiplier != 1.0).then_some(multiplier);
+        self
+    }
+
+    /// Merges consecutive spans with identical styling and removes empty spans.
+    ///
+    /// The builder applies the same merging when rendering, so calling this is only needed to
+    /// shrink the model itself, e.g. before serializing it.
+    pub fn normalize(&mut self) {
+        self.spans = coalesce_spans(std::mem::take(&mut self.spans));
+    }
 }
 
 /// Representation of image sources supported by the content model.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ImageSource {
     /// Image loaded from raw bytes.
     Bytes(Vec<u8>),
@@ -95,72 +229,1258 @@ impl ImageSource {
 /// the same primitives as text paragraphs, allowing callers to build captions
 /// with the same styling affordances.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
 pub struct ImageBlock {
     source: ImageSource,
+    #[cfg_attr(feature = "serde", serde(default))]
     caption: Option<RichParagraph>,
+    #[cfg_attr(feature = "serde", serde(default))]
     alignment: HorizontalAlignment,
+    #[cfg_attr(feature = "serde", serde(default))]
     width_mm: Option<f64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    width_percent: Option<f64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    max_height_mm: Option<f64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    border: Option<BorderStyle>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    alt_text: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    dpi: Option<f64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    caption_position: CaptionPosition,
+}
+
+impl ImageBlock {
+    /// Creates a new image block using the provided source.
+    pub fn new(source: ImageSource) -> Self {
+        Self {
+            source,
+            caption: None,
+            alignment: HorizontalAlignment::Left,
+            width_mm: None,
+            width_percent: None,
+            max_height_mm: None,
+            border: None,
+            alt_text: None,
+            dpi: None,
+            caption_position: CaptionPosition::Below,
+        }
+    }
+
+    /// Returns the image source.
+    pub fn source(&self) -> &ImageSource {
+        &self.source
+    }
+
+    /// Returns the caption paragraph, if any.
+    pub fn caption(&self) -> Option<&RichParagraph> {
+        self.caption.as_ref()
+    }
+
+    /// Returns the configured alignment.
+    pub fn alignment(&self) -> HorizontalAlignment {
+        self.alignment
+    }
+
+    /// Returns the requested rendered width in millimetres, if any.
+    pub fn width_mm(&self) -> Option<f64> {
+        self.width_mm
+    }
+
+    /// Returns the requested rendered width as a percentage of the content width, if any.
+    pub fn width_percent(&self) -> Option<f64> {
+        self.width_percent
+    }
+
+    /// Returns the maximum height of the image and its caption in millimetres, if any.
+    pub fn max_height_mm(&self) -> Option<f64> {
+        self.max_height_mm
+    }
+
+    /// Returns the frame drawn around the image, if any.
+    pub fn border(&self) -> Option<BorderStyle> {
+        self.border
+    }
+
+    /// Returns the alternate text describing the image to screen readers, if any.
+    pub fn alt_text(&self) -> Option<&str> {
+        self.alt_text.as_deref()
+    }
+
+    /// Returns the resolution the image was prepared for in dots per inch, if any.
+    pub fn dpi(&self) -> Option<f64> {
+        self.dpi
+    }
+
+    /// Returns whether the caption is placed above or below the image.
+    pub fn caption_position(&self) -> CaptionPosition {
+        self.caption_position
+    }
+
+    /// Sets the caption and returns the updated image block.
+    pub fn with_caption(mut self, caption: impl Into<Option<RichParagraph>>) -> Self {
+        self.caption = caption.into();
+        self
+    }
+
+    /// Sets the alignment and returns the updated image block.
+    pub fn with_alignment(mut self, alignment: HorizontalAlignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Constrains the rendered width (in millimetres) and returns the updated block.
+    ///
+    /// An absolute width takes precedence over a percentage width.
+    pub fn with_width_mm(mut self, width_mm: impl Into<Option<f64>>) -> Self {
+        self.width_mm = width_mm.into();
+        self
+    }
+
+    /// Scales the image to `percent` of the content width, e.g. `50.0` for half the width, and
+    /// returns the updated block.
+    ///
+    /// Unlike [`ImageBlock::with_width_mm`] the width follows the paper size and margins.
+    pub fn with_width_percent(mut self, percent: impl Into<Option<f64>>) -> Self {
+        self.width_percent = percent.into();
+        self
+    }
+
+    /// Limits the height of the image together with its caption (in millimetres) and returns the
+    /// updated block.
+    ///
+    /// The image shrinks to fit, keeping its aspect ratio; when a width is requested as well, the
+    /// smaller of both scales is used.
+    pub fn with_max_height_mm(mut self, height_mm: impl Into<Option<f64>>) -> Self {
+        self.max_height_mm = height_mm.into();
+        self
+    }
+
+    /// Draws a frame around the image and returns the updated block.
+    pub fn with_border(mut self, border: impl Into<Option<BorderStyle>>) -> Self {
+        self.border = border.into();
+        self
+    }
+
+    /// Sets the alternate text read out in place of the image and returns the updated block.
+    ///
+    /// With the `tagged-pdf` feature the text is written as an `/Alt` entry around the image
+    /// drawing even when the document is not tagged.  Tagged output falls back to the plain text
+    /// of the caption when no alternate text is set.
+    pub fn with_alt_text(mut self, alt_text: impl Into<String>) -> Self {
+        self.alt_text = Some(alt_text.into());
+        self
+    }
+
+    /// Sets the resolution of the image in dots per inch and returns the updated block.
+    ///
+    /// The resolution determines the natural size of the image, which is used when no width is
+    /// requested; for example, a 96 DPI screenshot comes out about three times as large as with
+    /// the default of 300 DPI.  Values that are not positive are ignored.
+    pub fn with_dpi(mut self, dpi: impl Into<Option<f64>>) -> Self {
+        self.dpi = dpi.into();
+        self
+    }
+
+    /// Places the caption above or below the image and returns the updated block.
+    pub fn with_caption_position(mut self, position: CaptionPosition) -> Self {
+        self.caption_position = position;
+        self
+    }
+}
+
+/// Placement of a caption relative to the image it describes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaptionPosition {
+    /// The caption precedes the image, as is customary for tables and some scientific figures.
+    Above,
+    /// The caption follows the image.
+    #[default]
+    Below,
+}
+
+/// Frame drawn around an image, optionally separated from it by some padding.
+///
+/// The frame wraps the scaled image, so it follows width and height constraints; the caption is
+/// placed outside it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct BorderStyle {
+    thickness_mm: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_color"))]
+    color: Color,
+    padding_mm: f64,
+}
+
+impl Default for BorderStyle {
+    fn default() -> Self {
+        Self {
+            thickness_mm: 0.3,
+            color: Color::Greyscale(0),
+            padding_mm: 0.0,
+        }
+    }
+}
+
+impl BorderStyle {
+    /// Creates a frame with the given line thickness (in millimetres) and color.
+    pub fn new(thickness_mm: f64, color: Color) -> Self {
+        Self {
+            thickness_mm,
+            color,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the line thickness in millimetres.
+    pub fn thickness_mm(&self) -> f64 {
+        self.thickness_mm
+    }
+
+    /// Returns the line color.
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Returns the gap between the image and the frame in millimetres.
+    pub fn padding_mm(&self) -> f64 {
+        self.padding_mm
+    }
+
+    /// Sets the gap between the image and the frame (in millimetres) and returns the updated style.
+    pub fn with_padding_mm(mut self, padding_mm: f64) -> Self {
+        self.padding_mm = padding_mm;
+        self
+    }
+
+    /// Distance from the outer edge of the frame to the image.
+    pub(crate) fn inset_mm(&self) -> f64 {
+        self.thickness_mm.max(0.0) + self.padding_mm.max(0.0)
+    }
+}
+
+/// Marker style used to prefix the items of a [`ListBlock`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ListStyle {
+    /// Items prefixed with a bullet.
+    #[default]
+    Bulleted,
+    /// Items prefixed with arabic numbers (`1.`, `2.`, ...).
+    Numbered,
+    /// Items prefixed with lowercase letters (`a.`, `b.`, ..., `z.`, `aa.`, ...).
+    Lettered,
+}
+
+impl ListStyle {
+    /// Returns the marker printed before the item with the given 1-based `number`.
+    pub fn marker(self, number: usize) -> String {
+        match self {
+            Self::Bulleted => "\u{2022}".to_string(),
+            Self::Numbered => format!("{}.", number),
+            Self::Lettered => {
+                let mut letters = Vec::new();
+                let mut remaining = number.max(1);
+                while remaining > 0 {
+                    remaining -= 1;
+                    letters.push(char::from(b'a' + (remaining % 26) as u8));
+                    remaining /= 26;
+                }
+                letters.iter().rev().chain(std::iter::once(&'.')).collect()
+            }
+        }
+    }
+}
+
+/// Single entry of a [`ListBlock`], optionally followed by a nested list.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ListItem {
+    content: RichParagraph,
+    sublist: Option<ListBlock>,
+}
+
+impl ListItem {
+    /// Creates a list item from the provided paragraph.
+    pub fn new(content: RichParagraph) -> Self {
+        Self {
+            content,
+            sublist: None,
+        }
+    }
+
+    /// Returns the paragraph rendered next to the item marker.
+    pub fn content(&self) -> &RichParagraph {
+        &self.content
+    }
+
+    /// Returns the nested list rendered below the item, if any.
+    pub fn sublist(&self) -> Option<&ListBlock> {
+        self.sublist.as_ref()
+    }
+
+    /// Sets the nested list and returns the updated item.
+    pub fn with_sublist(mut self, sublist: impl Into<Option<ListBlock>>) -> Self {
+        self.sublist = sublist.into();
+        self
+    }
+}
+
+impl From<RichParagraph> for ListItem {
+    fn from(content: RichParagraph) -> Self {
+        Self::new(content)
+    }
+}
+
+/// Bulleted, numbered, or lettered list of paragraphs.
+///
+/// Items are rendered indented with their marker in the gutter, so wrapped lines align with the
+/// item text rather than the marker.  Numbering runs continuously across the list starting at
+/// [`ListBlock::start`], and every item may carry a nested list with its own style.
+///
+/// A marker always stays on the same page as the first line of its item.  Use
+/// [`ListBlock::with_min_items_together`] to move the whole list to the next page when fewer than
+/// the requested number of items would fit at the bottom of the current one.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ListBlock {
+    items: Vec<ListItem>,
+    style: ListStyle,
+    start: usize,
+    min_items_together: usize,
+}
+
+impl Default for ListBlock {
+    fn default() -> Self {
+        Self::new(ListStyle::default())
+    }
+}
+
+impl ListBlock {
+    /// Creates an empty list using the provided marker style.
+    pub fn new(style: ListStyle) -> Self {
+        Self {
+            items: Vec::new(),
+            style,
+            start: 1,
+            min_items_together: 1,
+        }
+    }
+
+    /// Returns the list items.
+    pub fn items(&self) -> &[ListItem] {
+        &self.items
+    }
+
+    /// Returns the marker style.
+    pub fn style(&self) -> ListStyle {
+        self.style
+    }
+
+    /// Returns the number assigned to the first item.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the minimum number of leading items that must fit before the list may start.
+    pub fn min_items_together(&self) -> usize {
+        self.min_items_together
+    }
+
+    /// Appends an item and returns the updated list.
+    pub fn with_item(mut self, item: impl Into<ListItem>) -> Self {
+        self.items.push(item.into());
+        self
+    }
+
+    /// Extends the list with multiple items and returns the updated list.
+    pub fn with_items<I>(mut self, items: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<ListItem>,
+    {
+        self.items.extend(items.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the number assigned to the first item and returns the updated list.
+    pub fn with_start(mut self, start: usize) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Requires at least `count` leading items (including nested ones) to fit on the page where
+    /// the list starts; otherwise the list moves to the next page.  A count of 1 disables the check.
+    pub fn with_min_items_together(mut self, count: usize) -> Self {
+        self.min_items_together = count.max(1);
+        self
+    }
+}
+
+/// Layout direction of the entries in a [`LegendBlock`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LegendOrientation {
+    /// Entries flow left to right and wrap onto additional rows when needed.
+    #[default]
+    Horizontal,
+    /// Entries are stacked one per row.
+    Vertical,
+}
+
+/// Color swatch paired with the label that explains it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct LegendEntry {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_color"))]
+    color: Color,
+    #[cfg_attr(feature = "serde", serde(default))]
+    label: Vec<Span>,
+}
+
+impl LegendEntry {
+    /// Creates a legend entry for the given color and label spans.
+    pub fn new(color: Color, label: impl Into<Vec<Span>>) -> Self {
+        Self {
+            color,
+            label: label.into(),
+        }
+    }
+
+    /// Returns the swatch color.
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Returns the label spans.
+    pub fn label(&self) -> &[Span] {
+        &self.label
+    }
+}
+
+impl From<(Color, Vec<Span>)> for LegendEntry {
+    fn from((color, label): (Color, Vec<Span>)) -> Self {
+        Self::new(color, label)
+    }
+}
+
+/// Legend mapping colored swatches to labels, used alongside charts and color-coded content.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct LegendBlock {
+    entries: Vec<LegendEntry>,
+    orientation: LegendOrientation,
+}
+
+impl LegendBlock {
+    /// Creates an empty legend with horizontal orientation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the legend entries.
+    pub fn entries(&self) -> &[LegendEntry] {
+        &self.entries
+    }
+
+    /// Returns the configured orientation.
+    pub fn orientation(&self) -> LegendOrientation {
+        self.orientation
+    }
+
+    /// Appends an entry and returns the updated legend.
+    pub fn with_entry(mut self, entry: impl Into<LegendEntry>) -> Self {
+        self.entries.push(entry.into());
+        self
+    }
+
+    /// Extends the legend with multiple entries and returns the updated legend.
+    pub fn with_entries<I>(mut self, entries: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<LegendEntry>,
+    {
+        self.entries.extend(entries.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the orientation and returns the updated legend.
+    pub fn with_orientation(mut self, orientation: LegendOrientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+}
+
+/// Lines drawn around and between the cells of a [`TableBlock`].
+///
+/// Every kind of line has its own [`BorderStyle`], whose padding is ignored, so a table can pair a
+/// heavy frame with hairline rules.  The top and bottom lines close the part of the table on every
+/// page it spans.  The default draws no lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TableBorders {
+    top: Option<BorderStyle>,
+    bottom: Option<BorderStyle>,
+    sides: Option<BorderStyle>,
+    header: Option<BorderStyle>,
+    rows: Option<BorderStyle>,
+    columns: Option<BorderStyle>,
+}
+
+impl TableBorders {
+    /// Frames the table without lines between its cells.
+    pub fn outer(line: BorderStyle) -> Self {
+        Self {
+            top: Some(line),
+            bottom: Some(line),
+            sides: Some(line),
+            ..Self::default()
+        }
+    }
+
+    /// Frames the table and separates every row and column.
+    pub fn grid(line: BorderStyle) -> Self {
+        Self {
+            header: Some(line),
+            rows: Some(line),
+            columns: Some(line),
+            ..Self::outer(line)
+        }
+    }
+
+    /// Draws horizontal rules above the table, below it, and between its rows, but no vertical
+    /// lines.
+    pub fn horizontal(line: BorderStyle) -> Self {
+        Self {
+            top: Some(line),
+            bottom: Some(line),
+            header: Some(line),
+            rows: Some(line),
+            ..Self::default()
+        }
+    }
+
+    /// Underlines the header row and closes the table with a rule below the last row, as common
+    /// in report typography.
+    pub fn minimal(line: BorderStyle) -> Self {
+        Self {
+            bottom: Some(line),
+            header: Some(line),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the line above the table.
+    pub fn top(&self) -> Option<BorderStyle> {
+        self.top
+    }
+
+    /// Returns the line below the table.
+    pub fn bottom(&self) -> Option<BorderStyle> {
+        self.bottom
+    }
+
+    /// Returns the lines along the left and right edges of the table.
+    pub fn sides(&self) -> Option<BorderStyle> {
+        self.sides
+    }
+
+    /// Returns the line below the header row.
+    pub fn header(&self) -> Option<BorderStyle> {
+        self.header
+    }
+
+    /// Returns the lines between body rows, also drawn below the header without a header line.
+    pub fn rows(&self) -> Option<BorderStyle> {
+        self.rows
+    }
+
+    /// Returns the lines between columns.
+    pub fn columns(&self) -> Option<BorderStyle> {
+        self.columns
+    }
+
+    /// Sets the line above the table and returns the updated borders.
+    pub fn with_top(mut self, line: impl Into<Option<BorderStyle>>) -> Self {
+        self.top = line.into();
+        self
+    }
+
+    /// Sets the line below the table and returns the updated borders.
+    pub fn with_bottom(mut self, line: impl Into<Option<BorderStyle>>) -> Self {
+        self.bottom = line.into();
+        self
+    }
+
+    /// Sets the lines along the left and right edges and returns the updated borders.
+    pub fn with_sides(mut self, line: impl Into<Option<BorderStyle>>) -> Self {
+        self.sides = line.into();
+        self
+    }
+
+    /// Sets the line below the header row and returns the updated borders.
+    pub fn with_header(mut self, line: impl Into<Option<BorderStyle>>) -> Self {
+        self.header = line.into();
+        self
+    }
+
+    /// Sets the lines between body rows and returns the updated borders.
+    pub fn with_rows(mut self, line: impl Into<Option<BorderStyle>>) -> Self {
+        self.rows = line.into();
+        self
+    }
+
+    /// Sets the lines between columns and returns the updated borders.
+    pub fn with_columns(mut self, line: impl Into<Option<BorderStyle>>) -> Self {
+        self.columns = line.into();
+        self
+    }
+}
+
+/// Body cell of a [`TableBlock`] with an optional background fill and fixed height.
+///
+/// Plain paragraphs convert into cells without a fill.  With the `serde` feature a cell without a
+/// fill or fixed height is stored as its paragraph, and any other cell as
+/// `{ "content": ..., "fill": "#RRGGBB", "height_mm": 12.0 }` with either option omitted.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(from = "TableCellRepr", into = "TableCellRepr")
+)]
+pub struct TableCell {
+    content: RichParagraph,
+    fill: Option<Color>,
+    height_mm: Option<f64>,
+}
+
+impl TableCell {
+    /// Creates a cell holding `content` without a fill or fixed height.
+    pub fn new(content: impl Into<RichParagraph>) -> Self {
+        Self {
+            content: content.into(),
+            fill: None,
+            height_mm: None,
+        }
+    }
+
+    /// Returns the paragraph printed in the cell.
+    pub fn content(&self) -> &RichParagraph {
+        &self.content
+    }
+
+    /// Returns the color filled behind the cell, if any.
+    pub fn fill(&self) -> Option<Color> {
+        self.fill
+    }
+
+    /// Fills the whole cell, across every line of its row, with `color` and returns the updated
+    /// cell.  The fill takes precedence over the zebra stripe of its row.
+    pub fn with_fill(mut self, color: impl Into<Option<Color>>) -> Self {
+        self.fill = color.into();
+        self
+    }
+
+    /// Returns the fixed height of the cell text in millimetres, if any.
+    pub fn height_mm(&self) -> Option<f64> {
+        self.height_mm
+    }
+
+    /// Gives the cell text a fixed height (in millimetres, without the cell padding) and returns
+    /// the updated cell.
+    ///
+    /// The cell keeps that height however short its text is.  Lines that do not fit are dropped
+    /// and reported as a
+    /// [`RenderWarning::ContentClipped`](crate::diagnostics::RenderWarning::ContentClipped) in
+    /// the render result.  Cells of decimal-aligned columns print on a single line and ignore the
+    /// height.
+    pub fn with_height_mm(mut self, height_mm: impl Into<Option<f64>>) -> Self {
+        self.height_mm = height_mm.into();
+        self
+    }
+}
+
+impl From<RichParagraph> for TableCell {
+    fn from(content: RichParagraph) -> Self {
+        Self::new(content)
+    }
+}
+
+/// Serialized form of a [`TableCell`], which keeps unfilled cells as plain paragraphs.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum TableCellRepr {
+    #[serde(deny_unknown_fields)]
+    Detailed {
+        content: RichParagraph,
+        #[serde(
+            default,
+            with = "crate::serde_color::option",
+            skip_serializing_if = "Option::is_none"
+        )]
+        fill: Option<Color>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        height_mm: Option<f64>,
+    },
+    Plain(RichParagraph),
+}
+
+#[cfg(feature = "serde")]
+impl From<TableCellRepr> for TableCell {
+    fn from(repr: TableCellRepr) -> Self {
+        match repr {
+            TableCellRepr::Detailed {
+                content,
+                fill,
+                height_mm,
+            } => Self::new(content).with_fill(fill).with_height_mm(height_mm),
+            TableCellRepr::Plain(content) => Self::new(content),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<TableCell> for TableCellRepr {
+    fn from(cell: TableCell) -> Self {
+        if cell.fill.is_none() && cell.height_mm.is_none() {
+            Self::Plain(cell.content)
+        } else {
+            Self::Detailed {
+                content: cell.content,
+                fill: cell.fill,
+                height_mm: cell.height_mm,
+            }
+        }
+    }
+}
+
+/// Data table with a header row, body rows, and relative column widths.
+///
+/// The number of columns is the length of the longest of the header and body rows; shorter rows
+/// are padded with empty cells when rendered.  Column weights work like the ratios passed to
+/// [`genpdf::elements::TableLayout`]: weights of `[6, 1]` give the first column six times the width
+/// of the second.  Columns without an explicit [`ColumnAlignment`] keep the alignment of their
+/// cell paragraphs.  Body rows can be striped with [`with_zebra`](Self::with_zebra) and single
+/// cells filled through [`TableCell::with_fill`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TableBlock {
+    headers: Vec<RichParagraph>,
+    rows: Vec<Vec<TableCell>>,
+    column_weights: Vec<usize>,
+    column_alignments: Vec<ColumnAlignment>,
+    caption: Option<RichParagraph>,
+    repeat_header: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_color::option"))]
+    zebra: Option<Color>,
+    borders: TableBorders,
+}
+
+impl TableBlock {
+    /// Creates a table with the provided header cells and no body rows.
+    pub fn new<I>(headers: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<RichParagraph>,
+    {
+        Self {
+            headers: headers.into_iter().map(Into::into).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the header cells.
+    pub fn headers(&self) -> &[RichParagraph] {
+        &self.headers
+    }
+
+    /// Returns the body rows.
+    pub fn rows(&self) -> &[Vec<TableCell>] {
+        &self.rows
+    }
+
+    /// Returns the number of columns spanned by the header and body rows.
+    pub fn column_count(&self) -> usize {
+        self.rows
+            .iter()
+            .map(Vec::len)
+            .chain(std::iter::once(self.headers.len()))
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Returns the weight of every column.
+    ///
+    /// Explicit weights are used when one non-zero weight was supplied per column; otherwise every
+    /// column receives the same share of the available width.
+    pub fn column_weights(&self) -> Vec<usize> {
+        let columns = self.column_count();
+        if self.column_weights.len() == columns && self.column_weights.iter().all(|w| *w > 0) {
+            self.column_weights.clone()
+        } else {
+            vec![1; columns]
+        }
+    }
+
+    /// Returns the explicit alignment of the column at `index`, if one was configured.
+    pub fn column_alignment(&self, index: usize) -> Option<ColumnAlignment> {
+        self.column_alignments.get(index).copied()
+    }
+
+    /// Returns the caption printed above the table, if any.
+    pub fn caption(&self) -> Option<&RichParagraph> {
+        self.caption.as_ref()
+    }
+
+    /// Returns `true` when the header row is printed again on every page the table continues on.
+    pub fn repeat_header(&self) -> bool {
+        self.repeat_header
+    }
+
+    /// Returns the color filled behind every second body row, if any.
+    pub fn zebra(&self) -> Option<Color> {
+        self.zebra
+    }
+
+    /// Returns the lines drawn around and between the cells.
+    pub fn borders(&self) -> TableBorders {
+        self.borders
+    }
+
+    /// Returns the color filled behind the body cell at `row` and `column`: the fill of the cell
+    /// itself, or the zebra stripe on every second row.
+    pub fn cell_fill(&self, row: usize, column: usize) -> Option<Color> {
+        self.rows
+            .get(row)
+            .and_then(|cells| cells.get(column))
+            .and_then(TableCell::fill)
+            .or(self.zebra.filter(|_| row % 2 == 1))
+    }
+
+    /// Sets the caption printed above the table and returns the updated table.
+    pub fn with_caption(mut self, caption: impl Into<Option<RichParagraph>>) -> Self {
+        self.caption = caption.into();
+        self
+    }
+
+    /// Appends a body row and returns the updated table.
+    pub fn with_row<I>(mut self, row: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<TableCell>,
+    {
+        self.rows.push(row.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Appends multiple body rows and returns the updated table.
+    pub fn with_rows<R, I>(mut self, rows: R) -> Self
+    where
+        R: IntoIterator<Item = I>,
+        I: IntoIterator,
+        I::Item: Into<TableCell>,
+    {
+        for row in rows {
+            self = self.with_row(row);
+        }
+        self
+    }
+
+    /// Sets the relative column widths and returns the updated table.
+    pub fn with_column_weights(mut self, weights: impl Into<Vec<usize>>) -> Self {
+        self.column_weights = weights.into();
+        self
+    }
+
+    /// Sets the alignment of the leading columns and returns the updated table.
+    pub fn with_column_alignments(mut self, alignments: impl Into<Vec<ColumnAlignment>>) -> Self {
+        self.column_alignments = alignments.into();
+        self
+    }
+
+    /// Controls whether the header row is printed again at the top of every page a long table
+    /// continues on and returns the updated table.
+    ///
+    /// Rows are split across pages as usual; a row that cannot print anything below the repeated
+    /// header continues on the next page without it.
+    pub fn with_repeat_header(mut self, repeat: bool) -> Self {
+        self.repeat_header = repeat;
+        self
+    }
+
+    /// Fills every second body row, starting with the second, with `color` and returns the
+    /// updated table.
+    pub fn with_zebra(mut self, color: impl Into<Option<Color>>) -> Self {
+        self.zebra = color.into();
+        self
+    }
+
+    /// Sets the lines drawn around and between the cells, such as
+    /// [`TableBorders::minimal`], and returns the updated table.
+    pub fn with_borders(mut self, borders: TableBorders) -> Self {
+        self.borders = borders;
+        self
+    }
+}
+
+/// Quotation or callout whose blocks are indented behind a colored bar along the left edge.
+///
+/// The bar and the optional background follow the quote across page breaks.  Distinct bar and
+/// background colors let warnings stand apart from neutral quotes.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct QuoteBlock {
+    blocks: Vec<Block>,
+    attribution: Option<String>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_color"))]
+    bar_color: Color,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_color::option"))]
+    background_color: Option<Color>,
+}
+
+impl Default for QuoteBlock {
+    fn default() -> Self {
+        Self {
+            blocks: Vec::new(),
+            attribution: None,
+            bar_color: Color::Greyscale(160),
+            background_color: None,
+        }
+    }
+}
+
+impl QuoteBlock {
+    /// Creates a neutral quote with a gray bar around the provided blocks.
+    pub fn new(blocks: impl Into<Vec<Block>>) -> Self {
+        Self {
+            blocks: blocks.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the quoted blocks.
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Returns the attribution printed below the quoted blocks, if any.
+    pub fn attribution(&self) -> Option<&str> {
+        self.attribution.as_deref()
+    }
+
+    /// Returns the color of the bar drawn along the left edge.
+    pub fn bar_color(&self) -> Color {
+        self.bar_color
+    }
+
+    /// Returns the color filling the area behind the quote, if any.
+    pub fn background_color(&self) -> Option<Color> {
+        self.background_color
+    }
+
+    /// Sets the attribution, e.g. the author or source, and returns the updated quote.
+    pub fn with_attribution(mut self, attribution: impl Into<Option<String>>) -> Self {
+        self.attribution = attribution.into();
+        self
+    }
+
+    /// Sets the color of the left bar and returns the updated quote.
+    pub fn with_bar_color(mut self, color: Color) -> Self {
+        self.bar_color = color;
+        self
+    }
+
+    /// Sets the background color and returns the updated quote.
+    pub fn with_background_color(mut self, color: impl Into<Option<Color>>) -> Self {
+        self.background_color = color.into();
+        self
+    }
+}
+
+/// Column of a [`Block::Row`] with its own blocks and a relative width.
+///
+/// The blocks of every column wrap within the column's share of the content width.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ColumnBlock {
+    weight: usize,
+    blocks: Vec<Block>,
+}
+
+impl Default for ColumnBlock {
+    fn default() -> Self {
+        Self {
+            weight: 1,
+            blocks: Vec::new(),
+        }
+    }
+}
+
+impl ColumnBlock {
+    /// Creates a column with weight 1 holding the provided blocks.
+    pub fn new(blocks: impl Into<Vec<Block>>) -> Self {
+        Self {
+            blocks: blocks.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the weight of the column relative to the other columns of the row.
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    /// Returns the blocks rendered in the column.
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Sets the relative width of the column, where a weight of 0 counts as 1, and returns the
+    /// updated column.
+    pub fn with_weight(mut self, weight: usize) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Appends a block to the column and returns the updated column.
+    pub fn with_block(mut self, block: Block) -> Self {
+        self.blocks.push(block);
+        self
+    }
+}
+
+/// Preformatted source code, log output, or configuration printed in a monospace font.
+///
+/// Whitespace is preserved, tabs expand to four spaces, and lines wider than the page wrap at the
+/// last character that fits instead of at word boundaries.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct CodeBlock {
+    code: String,
+    language: Option<String>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_color"))]
+    background_color: Color,
+}
+
+impl Default for CodeBlock {
+    fn default() -> Self {
+        Self {
+            code: String::new(),
+            language: None,
+            background_color: Color::Greyscale(245),
+        }
+    }
+}
+
+impl CodeBlock {
+    /// Creates a code block with a light gray background around `code`.
+    pub fn new(code: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the raw code.
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Returns the language tag, such as `rust` or `toml`, if any.
+    ///
+    /// The tag is informational; code is not syntax highlighted.
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Returns the color filled behind the code.
+    pub fn background_color(&self) -> Color {
+        self.background_color
+    }
+
+    /// Sets the language tag and returns the updated block.
+    pub fn with_language(mut self, language: impl Into<Option<String>>) -> Self {
+        self.language = language.into();
+        self
+    }
+
+    /// Sets the background color and returns the updated block.
+    pub fn with_background_color(mut self, color: Color) -> Self {
+        self.background_color = color;
+        self
+    }
+}
+
+/// Appearance of a [`Block::HorizontalRule`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RuleStyle {
+    thickness_mm: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_color::option"))]
+    color: Option<Color>,
+    margin_mm: f64,
 }
 
-impl ImageBlock {
-    /// Creates a new image block using the provided source.
-    pub fn new(source: ImageSource) -> Self {
+impl Default for RuleStyle {
+    fn default() -> Self {
         Self {
-            source,
-            caption: None,
-            alignment: HorizontalAlignment::Left,
-            width_mm: None,
+            thickness_mm: 0.3,
+            color: None,
+            margin_mm: 3.0,
         }
     }
+}
 
-    /// Returns the image source.
-    pub fn source(&self) -> &ImageSource {
-        &self.source
+impl RuleStyle {
+    /// Creates a thin rule in the text color with 3 mm of space above and below.
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    /// Returns the caption paragraph, if any.
-    pub fn caption(&self) -> Option<&RichParagraph> {
-        self.caption.as_ref()
+    /// Returns the stroke thickness in millimetres.
+    pub fn thickness_mm(&self) -> f64 {
+        self.thickness_mm
     }
 
-    /// Returns the configured alignment.
-    pub fn alignment(&self) -> HorizontalAlignment {
-        self.alignment
+    /// Returns the stroke color, or `None` to use the text color.
+    pub fn color(&self) -> Option<Color> {
+        self.color
     }
 
-    /// Returns the requested rendered width in millimetres, if any.
-    pub fn width_mm(&self) -> Option<f64> {
-        self.width_mm
+    /// Returns the space kept above and below the stroke in millimetres.
+    pub fn margin_mm(&self) -> f64 {
+        self.margin_mm
     }
 
-    /// Sets the caption and returns the updated image block.
-    pub fn with_caption(mut self, caption: impl Into<Option<RichParagraph>>) -> Self {
-        self.caption = caption.into();
+    /// Sets the stroke thickness and returns the updated style.
+    pub fn with_thickness_mm(mut self, thickness_mm: f64) -> Self {
+        self.thickness_mm = thickness_mm;
         self
     }
 
-    /// Sets the alignment and returns the updated image block.
-    pub fn with_alignment(mut self, alignment: HorizontalAlignment) -> Self {
-        self.alignment = alignment;
+    /// Sets the stroke color and returns the updated style.
+    pub fn with_color(mut self, color: impl Into<Option<Color>>) -> Self {
+        self.color = color.into();
         self
     }
 
-    /// Constrains the rendered width (in millimetres) and returns the updated block.
-    pub fn with_width_mm(mut self, width_mm: impl Into<Option<f64>>) -> Self {
-        self.width_mm = width_mm.into();
+    /// Sets the space above and below the stroke and returns the updated style.
+    pub fn with_margin_mm(mut self, margin_mm: f64) -> Self {
+        self.margin_mm = margin_mm;
         self
     }
 }
 
+/// Raw `genpdf` element placed by [`Block::Custom`], for content the model cannot express.
+///
+/// Documents are laid out more than once, for example to find the pages of a printed table of
+/// contents, so the block stores a factory that builds a fresh element for every pass instead of
+/// the element itself.  The factory is shared when the block is cloned, and two custom elements
+/// are only equal when they share the same factory.  Custom elements cannot be serialized.
+#[derive(Clone)]
+pub struct CustomElement {
+    factory: Arc<dyn Fn() -> Box<dyn Element>>,
+}
+
+impl CustomElement {
+    /// Creates a custom element that calls `factory` whenever the block is rendered.
+    pub fn new<F, E>(factory: F) -> Self
+    where
+        F: Fn() -> E + 'static,
+        E: Element + 'static,
+    {
+        Self {
+            factory: Arc::new(move || Box::new(factory()) as Box<dyn Element>),
+        }
+    }
+
+    /// Builds a new instance of the element.
+    pub fn build(&self) -> Box<dyn Element> {
+        (self.factory)()
+    }
+}
+
+impl fmt::Debug for CustomElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomElement").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for CustomElement {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.factory, &other.factory)
+    }
+}
+
 /// Individual content blocks that make up sections and the cover.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Block {
     /// Styled paragraph content.
     Paragraph(RichParagraph),
     /// Captioned image content.
     Image(ImageBlock),
+    /// Bulleted, numbered, or lettered list.
+    List(ListBlock),
+    /// Color legend for charts and color-coded content.
+    Legend(LegendBlock),
+    /// Data table with a bold header row.
+    Table(TableBlock),
+    /// Indented quotation or callout with a colored left bar.
+    Quote(QuoteBlock),
+    /// Divider line spanning the content width.
+    HorizontalRule(RuleStyle),
+    /// Vertical whitespace of the given height in millimetres.
+    Spacer(f64),
+    /// Preformatted text in a monospace font.
+    Code(CodeBlock),
+    /// Columns placed side by side; the row is as tall as its tallest column.
+    Row(Vec<ColumnBlock>),
+    /// Blocks that start on the next page together rather than being split across a page
+    /// boundary; groups taller than a full page are split anyway and page breaks are skipped.
+    KeepTogether(Vec<Block>),
+    /// Raw `genpdf` element pushed into the document as is; cannot be serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(CustomElement),
     /// Explicit page break request.
     PageBreak,
+    /// Subheading inside a section body, where level 1 sits one level below the section title.
+    ///
+    /// Headings at the top level of a section body are numbered and listed in the table of
+    /// contents and the bookmark outline like subsections; headings nested in other blocks or
+    /// placed on the cover only take the style of their level.
+    Heading {
+        /// Depth below the enclosing section, starting at 1.
+        level: u8,
+        /// Text of the heading.
+        paragraph: RichParagraph,
+    },
 }
 
 impl Block {
@@ -169,11 +1489,83 @@ impl Block {
         Self::Paragraph(RichParagraph::new(spans))
     }
 
+    /// Convenience helper for building a subheading `level` levels below the enclosing section.
+    pub fn heading(level: u8, spans: impl Into<Vec<Span>>) -> Self {
+        Self::Heading {
+            level,
+            paragraph: RichParagraph::new(spans),
+        }
+    }
+
     /// Convenience helper for building an image block.
     pub fn image(source: ImageSource) -> Self {
         Self::Image(ImageBlock::new(source))
     }
 
+    /// Convenience helper for building a list block from paragraphs or list items.
+    pub fn list<I>(style: ListStyle, items: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<ListItem>,
+    {
+        Self::List(ListBlock::new(style).with_items(items))
+    }
+
+    /// Convenience helper for building a horizontal legend from color/label pairs.
+    pub fn legend<I>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = (Color, Vec<Span>)>,
+    {
+        Self::Legend(LegendBlock::new().with_entries(entries))
+    }
+
+    /// Convenience helper for building a table from header cells and body rows.
+    pub fn table<H, R, I>(headers: H, rows: R) -> Self
+    where
+        H: IntoIterator,
+        H::Item: Into<RichParagraph>,
+        R: IntoIterator<Item = I>,
+        I: IntoIterator,
+        I::Item: Into<TableCell>,
+    {
+        Self::Table(TableBlock::new(headers).with_rows(rows))
+    }
+
+    /// Convenience helper for building a neutral quote around the provided blocks.
+    pub fn quote(blocks: impl Into<Vec<Block>>) -> Self {
+        Self::Quote(QuoteBlock::new(blocks))
+    }
+
+    /// Convenience helper for placing columns side by side.
+    pub fn row(columns: impl Into<Vec<ColumnBlock>>) -> Self {
+        Self::Row(columns.into())
+    }
+
+    /// Convenience helper for keeping blocks, such as a paragraph and the table it introduces, on
+    /// the same page.
+    pub fn keep_together(blocks: impl Into<Vec<Block>>) -> Self {
+        Self::KeepTogether(blocks.into())
+    }
+
+    /// Convenience helper for a raw `genpdf` element built by `factory` on every render pass.
+    pub fn custom<F, E>(factory: F) -> Self
+    where
+        F: Fn() -> E + 'static,
+        E: Element + 'static,
+    {
+        Self::Custom(CustomElement::new(factory))
+    }
+
+    /// Convenience helper for a code block without language tag.
+    pub fn code(code: impl Into<String>) -> Self {
+        Self::Code(CodeBlock::new(code))
+    }
+
+    /// Convenience helper for a horizontal rule with the default [`RuleStyle`].
+    pub fn rule() -> Self {
+        Self::HorizontalRule(RuleStyle::default())
+    }
+
     /// Convenience helper that yields an explicit page break block.
     pub fn page_break() -> Self {
         Self::PageBreak
@@ -186,11 +1578,18 @@ impl Block {
 /// mix paragraphs, images with captions, and explicit page breaks to provide a
 /// flexible layout while remaining easy to serialize.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
 pub struct Cover {
     title: String,
+    #[cfg_attr(feature = "serde", serde(default))]
     subtitle: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
     identifier: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
     blocks: Vec<Block>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    logo: Option<ImageBlock>,
 }
 
 impl Cover {
@@ -201,6 +1600,7 @@ impl Cover {
             subtitle: None,
             identifier: None,
             blocks: Vec::new(),
+            logo: None,
         }
     }
 
@@ -224,6 +1624,11 @@ impl Cover {
         &self.blocks
     }
 
+    /// Returns the logo printed above the title, if any.
+    pub fn logo(&self) -> Option<&ImageBlock> {
+        self.logo.as_ref()
+    }
+
     /// Sets the subtitle and returns the updated cover.
     pub fn with_subtitle(mut self, subtitle: impl Into<Option<String>>) -> Self {
         self.subtitle = subtitle.into();
@@ -236,6 +1641,19 @@ impl Cover {
         self
     }
 
+    /// Places a logo `width_mm` wide, centered above the title, and returns the updated cover.
+    ///
+    /// A logo that cannot be loaded is left out and reported as a
+    /// [`RenderWarning::ImageSkipped`](crate::diagnostics::RenderWarning::ImageSkipped).
+    pub fn with_logo(mut self, source: ImageSource, width_mm: f64) -> Self {
+        self.logo = Some(
+            ImageBlock::new(source)
+                .with_alignment(HorizontalAlignment::Center)
+                .with_width_mm(width_mm),
+        );
+        self
+    }
+
     /// Appends a block to the cover and returns the updated instance.
     pub fn with_block(mut self, block: Block) -> Self {
         self.blocks.push(block);
@@ -252,12 +1670,105 @@ impl Cover {
     }
 }
 
+/// Orientation of the pages hosting a section.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Orientation {
+    /// Pages taller than they are wide.
+    #[default]
+    Portrait,
+    /// Pages wider than they are tall.
+    Landscape,
+}
+
+/// Full-page divider printed before a top-level section, such as "Part II: Operations".
+///
+/// The divider title is centered on a page of its own and gets its own entry in the outline, the
+/// bookmarks, and the printed table of contents, listed above the sections that follow it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct Divider {
+    title: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    subtitle: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    restart_numbering: bool,
+}
+
+impl Divider {
+    /// Creates a divider with the given title that continues the section numbering.
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            subtitle: None,
+            restart_numbering: false,
+        }
+    }
+
+    /// Returns the title printed on the divider page.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Returns the subtitle printed below the title, if any.
+    pub fn subtitle(&self) -> Option<&str> {
+        self.subtitle.as_deref()
+    }
+
+    /// Returns whether the sections after the divider are numbered from "1." again.
+    pub fn restarts_numbering(&self) -> bool {
+        self.restart_numbering
+    }
+
+    /// Sets the subtitle and returns the updated divider.
+    pub fn with_subtitle(mut self, subtitle: impl Into<Option<String>>) -> Self {
+        self.subtitle = subtitle.into();
+        self
+    }
+
+    /// Restarts the section numbering after the divider when `restart` is `true`.
+    ///
+    /// Only affects documents rendered with
+    /// [`PdfBuilder::number_sections`](crate::builder::PdfBuilder::number_sections).
+    pub fn with_restart_numbering(mut self, restart: bool) -> Self {
+        self.restart_numbering = restart;
+        self
+    }
+}
+
+impl From<&str> for Divider {
+    fn from(title: &str) -> Self {
+        Self::new(title)
+    }
+}
+
+impl From<String> for Divider {
+    fn from(title: String) -> Self {
+        Self::new(title)
+    }
+}
+
 /// Logical representation of a document section.
+///
+/// Sections nest recursively: each section may carry subsections that are rendered after its own
+/// blocks with a heading one level deeper.  A top-level section may override the page orientation
+/// of the document; its subsections share its pages.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
 pub struct Section {
+    #[cfg_attr(feature = "serde", serde(default))]
     identifier: Option<String>,
     title: String,
+    #[cfg_attr(feature = "serde", serde(default))]
     blocks: Vec<Block>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    subsections: Vec<Section>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    orientation: Option<Orientation>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    divider: Option<Divider>,
 }
 
 impl Section {
@@ -267,9 +1778,26 @@ impl Section {
             identifier: None,
             title: title.into(),
             blocks: Vec::new(),
+            subsections: Vec::new(),
+            orientation: None,
+            divider: None,
         }
     }
 
+    /// Creates a section whose blocks are read from a Markdown subset.
+    ///
+    /// Paragraphs, `#` headings, bulleted and numbered lists nested by indentation, fenced code
+    /// blocks, horizontal rules, `**bold**` and `*italic*` text, and `[text](url)` links are
+    /// supported; a link target starting with `#` points at the section with that identifier.
+    /// Other inline styling follows [`parse_markup`].  Block quotes, tables, images, raw HTML,
+    /// underlined headings, and indented code blocks are rejected with a [`ParseError`] naming
+    /// the construct and its byte index in `markdown`, rather than being printed as text.
+    pub fn from_markdown(title: impl Into<String>, markdown: &str) -> Result<Self, ParseError> {
+        let mut section = Self::new(title);
+        section.blocks = crate::markdown::parse_markdown(markdown)?;
+        Ok(section)
+    }
+
     /// Returns the section identifier used for bookmarks or cross references.
     pub fn identifier(&self) -> Option<&str> {
         self.identifier.as_deref()
@@ -285,6 +1813,21 @@ impl Section {
         &self.blocks
     }
 
+    /// Returns the nested subsections rendered after the section blocks.
+    pub fn subsections(&self) -> &[Section] {
+        &self.subsections
+    }
+
+    /// Returns the page orientation requested for the section, if it overrides the document's.
+    pub fn orientation(&self) -> Option<Orientation> {
+        self.orientation
+    }
+
+    /// Returns the divider page printed before the section, if any.
+    pub fn divider(&self) -> Option<&Divider> {
+        self.divider.as_ref()
+    }
+
     /// Sets the identifier and returns the updated section.
     pub fn with_identifier(mut self, identifier: impl Into<Option<String>>) -> Self {
         self.identifier = identifier.into();
@@ -306,6 +1849,39 @@ impl Section {
         self
     }
 
+    /// Appends a nested subsection and returns the updated section.
+    pub fn with_subsection(mut self, subsection: Section) -> Self {
+        self.subsections.push(subsection);
+        self
+    }
+
+    /// Extends the section with multiple subsections and returns the updated instance.
+    pub fn with_subsections<I>(mut self, subsections: I) -> Self
+    where
+        I: IntoIterator<Item = Section>,
+    {
+        self.subsections.extend(subsections);
+        self
+    }
+
+    /// Overrides the page orientation for the section and returns the updated instance.
+    ///
+    /// Only honoured on top-level sections.  A section whose orientation differs from the
+    /// preceding content starts on a new page.
+    pub fn with_orientation(mut self, orientation: impl Into<Option<Orientation>>) -> Self {
+        self.orientation = orientation.into();
+        self
+    }
+
+    /// Prints a full-page divider before the section and returns the updated instance.
+    ///
+    /// Only honoured on top-level sections.  The divider starts on a new page and the section
+    /// starts on the page after it.
+    pub fn with_divider(mut self, divider: impl Into<Option<Divider>>) -> Self {
+        self.divider = divider.into();
+        self
+    }
+
     /// Creates a builder that can inject an initial page break.
     pub fn builder(title: impl Into<String>) -> SectionBuilder {
         SectionBuilder::new(title)
@@ -322,6 +1898,7 @@ pub struct SectionBuilder {
     identifier: Option<String>,
     title: String,
     blocks: Vec<Block>,
+    subsections: Vec<Section>,
     start_on_new_page: bool,
 }
 
@@ -361,6 +1938,12 @@ impl SectionBuilder {
         self
     }
 
+    /// Pushes a nested subsection into the section.
+    pub fn push_subsection(mut self, subsection: Section) -> Self {
+        self.subsections.push(subsection);
+        self
+    }
+
     /// Builds the final section, injecting a leading page break when requested.
     pub fn build(mut self) -> Section {
         if self.start_on_new_page {
@@ -373,13 +1956,248 @@ impl SectionBuilder {
         let mut section = Section::new(self.title);
         section.identifier = self.identifier;
         section.blocks = self.blocks;
+        section.subsections = self.subsections;
         section
     }
 }
 
+/// Parses text with one block per paragraph and markdown-style headings into blocks.
+///
+/// Lines starting with one to six `#` characters followed by a space become
+/// [`Block::Heading`]s of that level, so `## Results` is a heading two levels below the section.
+/// Other lines are grouped into paragraphs separated by blank lines, and the lines of a paragraph
+/// are joined with a space.  Inline styling uses the syntax of [`parse_markup`]; errors report the
+/// byte index in `input`.
+pub fn parse_block_markup(input: &str) -> Result<Vec<Block>, ParseError> {
+    fn flush(paragraph: &mut Vec<Span>, blocks: &mut Vec<Block>) {
+        if !paragraph.is_empty() {
+            blocks.push(Block::paragraph(coalesce_spans(paragraph.drain(..))));
+        }
+    }
+
+    let mut blocks = Vec::new();
+    let mut paragraph = Vec::new();
+
+    let mut start = 0;
+    for line in input.split_inclusive('\n') {
+        let offset = start;
+        start += line.len();
+        let line = line.trim_end_matches(['\r', '\n']);
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        if trimmed.is_empty() {
+            flush(&mut paragraph, &mut blocks);
+            continue;
+        }
+
+        if let Some((level, text, start)) = atx_heading(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            let spans = parse_markup(text).map_err(|err| err.offset_by(offset + indent + start))?;
+            blocks.push(Block::heading(level, spans));
+            continue;
+        }
+
+        let spans = parse_markup(trimmed).map_err(|err| err.offset_by(offset + indent))?;
+        if !paragraph.is_empty() {
+            paragraph.push(Span::new(" "));
+        }
+        paragraph.extend(spans);
+    }
+    flush(&mut paragraph, &mut blocks);
+    Ok(blocks)
+}
+
+/// Splits a line starting with one to six `#` characters and a space into the heading level, the
+/// heading text, and the byte position of the text in `line`.
+pub(crate) fn atx_heading(line: &str) -> Option<(u8, &str, usize)> {
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    let rest = &line[hashes..];
+    if !(1..=6).contains(&hashes) || !rest.starts_with(' ') {
+        return None;
+    }
+    let start = hashes + rest.len() - rest.trim_start().len();
+    Some((hashes as u8, rest.trim(), start))
+}
+
+/// Entry of the document outline listed by [`flatten_outline`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutlineItem<'a> {
+    /// Title of a section or subsection.
+    Section(&'a Section),
+    /// [`Block::Heading`] at the top level of a section body.
+    Heading(&'a RichParagraph),
+    /// [`Divider`] printed before a top-level section.
+    Divider(&'a Divider),
+}
+
+impl<'a> OutlineItem<'a> {
+    /// Returns the plain text of the entry.
+    pub fn title(&self) -> Cow<'a, str> {
+        match self {
+            Self::Section(section) => Cow::Borrowed(section.title()),
+            Self::Heading(paragraph) => paragraph.spans().iter().map(Span::text).collect(),
+            Self::Divider(divider) => Cow::Borrowed(divider.title()),
+        }
+    }
+
+    /// Returns the identifier of a section entry.
+    pub fn identifier(&self) -> Option<&'a str> {
+        match self {
+            Self::Section(section) => section.identifier(),
+            Self::Heading(_) | Self::Divider(_) => None,
+        }
+    }
+}
+
+/// Flattens a section tree in reading order.
+///
+/// Each entry pairs a section with its nesting level, where top-level sections are level 1 and
+/// every subsection is one level deeper than its parent.  Headings inside section bodies are left
+/// out; see [`flatten_outline`] for the entries the builder records pages for.
+pub fn flatten_sections(sections: &[Section]) -> Vec<(usize, &Section)> {
+    fn visit<'a>(sections: &'a [Section], level: usize, out: &mut Vec<(usize, &'a Section)>) {
+        for section in sections {
+            out.push((level, section));
+            visit(section.subsections(), level + 1, out);
+        }
+    }
+
+    let mut flattened = Vec::new();
+    visit(sections, 1, &mut flattened);
+    flattened
+}
+
+/// Flattens a section tree into the entries of the document outline in reading order.
+///
+/// Like [`flatten_sections`], but every section is followed by the [`Block::Heading`]s at the top
+/// level of its body, before its subsections.  A heading's level is the section's level plus the
+/// heading's own level.  The [`Divider`] of a top-level section precedes it at level 0, so the
+/// sections after a divider nest below it.  The order matches the order in which the builder
+/// renders headings, so indices into the returned vector line up with recorded pages.
+pub fn flatten_outline(sections: &[Section]) -> Vec<(usize, OutlineItem<'_>)> {
+    fn visit<'a>(sections: &'a [Section], level: usize, out: &mut Vec<(usize, OutlineItem<'a>)>) {
+        for section in sections {
+            if let Some(divider) = section.divider().filter(|_| level == 1) {
+                out.push((0, OutlineItem::Divider(divider)));
+            }
+            out.push((level, OutlineItem::Section(section)));
+            for block in section.blocks() {
+                if let Block::Heading {
+                    level: depth,
+                    paragraph,
+                } = block
+                {
+                    out.push((
+                        level + usize::from(*depth).max(1),
+                        OutlineItem::Heading(paragraph),
+                    ));
+                }
+            }
+            visit(section.subsections(), level + 1, out);
+        }
+    }
+
+    let mut flattened = Vec::new();
+    visit(sections, 1, &mut flattened);
+    flattened
+}
+
+/// Computes the outline number of every section in the reading order of [`flatten_sections`].
+///
+/// Top-level sections are numbered "1.", "2.", and so on, while subsections join the positions
+/// of all their ancestors, as in "1.1" or "1.2.3".
+pub fn outline_numbers(sections: &[Section]) -> Vec<String> {
+    number_levels(
+        flatten_sections(sections)
+            .into_iter()
+            .map(|(level, _)| level),
+    )
+}
+
+/// Numbers outline entries from their levels in reading order, as [`outline_numbers`] does.
+///
+/// An entry more than one level below its predecessor gets a 0 for every skipped level, as in
+/// "1.0.1".
+pub(crate) fn number_levels(levels: impl IntoIterator<Item = usize>) -> Vec<String> {
+    let mut path: Vec<usize> = Vec::new();
+    levels
+        .into_iter()
+        .map(|level| next_number(&mut path, level))
+        .collect()
+}
+
+/// Outline numbers of the entries of [`flatten_outline`], leaving dividers unnumbered.
+///
+/// A divider that restarts the numbering numbers the sections after it from "1." again;
+/// otherwise the numbering continues across dividers.
+pub(crate) fn number_outline(outline: &[(usize, OutlineItem<'_>)]) -> Vec<Option<String>> {
+    continue_numbering(&mut Vec::new(), outline)
+}
+
+/// Numbers `outline` like [`number_outline`], continuing from the numbering `path` left by the
+/// entries before it, so sections can be numbered one at a time.
+pub(crate) fn continue_numbering(
+    path: &mut Vec<usize>,
+    outline: &[(usize, OutlineItem<'_>)],
+) -> Vec<Option<String>> {
+    outline
+        .iter()
+        .map(|(level, item)| match item {
+            OutlineItem::Divider(divider) => {
+                if divider.restarts_numbering() {
+                    path.clear();
+                }
+                None
+            }
+            _ => Some(next_number(path, *level)),
+        })
+        .collect()
+}
+
+/// Advances the numbering `path` by an entry at `level` and returns the entry's number.
+fn next_number(path: &mut Vec<usize>, level: usize) -> String {
+    let level = level.max(1);
+    path.resize(level, 0);
+    path[level - 1] += 1;
+    let number = path
+        .iter()
+        .map(usize::to_string)
+        .collect::<Vec<_>>()
+        .join(".");
+    if level == 1 {
+        format!("{}.", number)
+    } else {
+        number
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Block, Section};
+    use super::{
+        flatten_outline, flatten_sections, number_levels, number_outline, outline_numbers,
+        parse_block_markup, Block, BorderStyle, Divider, ListStyle, RichParagraph, Section, Span,
+        TableBlock, TableBorders, TableCell,
+    };
+    use genpdf::style::Color;
+
+    #[test]
+    fn normalize_merges_spans_with_identical_styling() {
+        let mut paragraph = RichParagraph::new(vec![
+            Span::new("Total: ").bold(),
+            Span::new("42").bold(),
+            Span::new(""),
+            Span::new(" units, see ").bold(),
+            Span::new("docs").linked("https://a.example"),
+            Span::new(" and ").linked("https://a.example"),
+            Span::new("more").linked("https://b.example"),
+        ]);
+
+        paragraph.normalize();
+
+        let texts: Vec<_> = paragraph.spans().iter().map(Span::text).collect();
+        assert_eq!(texts, vec!["Total: 42 units, see ", "docs and ", "more"]);
+        assert!(paragraph.spans()[0].is_bold());
+    }
 
     #[test]
     fn builder_inserts_page_break() {
@@ -401,4 +2219,228 @@ mod tests {
         assert!(matches!(section.blocks().first(), Some(Block::PageBreak)));
         assert_eq!(section.blocks().len(), 1);
     }
+
+    #[test]
+    fn flatten_sections_reports_levels_in_reading_order() {
+        let sections = vec![
+            Section::new("Chapter")
+                .with_subsection(Section::new("First"))
+                .with_subsection(Section::new("Second")),
+            Section::new("Appendix"),
+        ];
+
+        let flattened: Vec<_> = flatten_sections(&sections)
+            .into_iter()
+            .map(|(level, section)| (level, section.title()))
+            .collect();
+
+        assert_eq!(
+            flattened,
+            vec![(1, "Chapter"), (2, "First"), (2, "Second"), (1, "Appendix")]
+        );
+    }
+
+    #[test]
+    fn list_markers_follow_style() {
+        assert_eq!(ListStyle::Bulleted.marker(3), "\u{2022}");
+        assert_eq!(ListStyle::Numbered.marker(12), "12.");
+        assert_eq!(ListStyle::Lettered.marker(1), "a.");
+        assert_eq!(ListStyle::Lettered.marker(26), "z.");
+        assert_eq!(ListStyle::Lettered.marker(28), "ab.");
+    }
+
+    #[test]
+    fn table_weights_fall_back_to_equal_columns() {
+        let cell = |text: &str| RichParagraph::new(vec![Span::new(text)]);
+        let table = TableBlock::new(vec![cell("Item")])
+            .with_row(vec![cell("Total"), cell("4.50")])
+            .with_column_weights(vec![6]);
+
+        assert_eq!(table.column_count(), 2);
+        assert_eq!(table.column_weights(), vec![1, 1]);
+        assert_eq!(
+            table.with_column_weights(vec![6, 1]).column_weights(),
+            vec![6, 1]
+        );
+    }
+
+    #[test]
+    fn outline_numbers_follow_the_section_tree() {
+        let sections = vec![
+            Section::new("Intro"),
+            Section::new("Body")
+                .with_subsection(Section::new("Scope"))
+                .with_subsection(Section::new("Method").with_subsection(Section::new("Sampling"))),
+            Section::new("Appendix"),
+        ];
+
+        assert_eq!(
+            outline_numbers(&sections),
+            vec!["1.", "2.", "2.1", "2.2", "2.2.1", "3."]
+        );
+        assert_eq!(
+            outline_numbers(&sections).len(),
+            flatten_sections(&sections).len()
+        );
+    }
+
+    #[test]
+    fn custom_blocks_compare_by_shared_factory() {
+        let block = Block::custom(|| genpdf::elements::Break::new(1));
+        assert_eq!(block, block.clone());
+        assert_ne!(block, Block::custom(|| genpdf::elements::Break::new(1)));
+    }
+
+    #[test]
+    fn block_markup_splits_headings_and_paragraphs() {
+        let blocks = parse_block_markup(
+            "# Overview\nFirst **line**\nsecond line\n\n### Detail\n#hashtag stays text\n",
+        )
+        .expect("valid markup");
+
+        assert_eq!(
+            blocks,
+            vec![
+                Block::heading(1, vec![Span::new("Overview")]),
+                Block::paragraph(vec![
+                    Span::new("First "),
+                    Span::new("line").bold(),
+                    Span::new(" second line"),
+                ]),
+                Block::heading(3, vec![Span::new("Detail")]),
+                Block::paragraph(vec![Span::new("#hashtag stays text")]),
+            ]
+        );
+
+        let err = parse_block_markup("Intro\n##  bad \\").expect_err("trailing backslash");
+        assert_eq!(err.index(), 14);
+    }
+
+    #[test]
+    fn body_headings_join_the_outline_below_their_section() {
+        let sections = vec![
+            Section::new("Chapter")
+                .with_block(Block::heading(1, vec![Span::new("Part")]))
+                .with_block(Block::quote(vec![Block::heading(
+                    1,
+                    vec![Span::new("Quoted")],
+                )]))
+                .with_subsection(Section::new("Background")),
+            Section::new("Appendix"),
+        ];
+
+        let outline: Vec<_> = flatten_outline(&sections)
+            .into_iter()
+            .map(|(level, item)| (level, item.title().into_owned()))
+            .collect();
+        assert_eq!(
+            outline,
+            vec![
+                (1, "Chapter".to_string()),
+                (2, "Part".to_string()),
+                (2, "Background".to_string()),
+                (1, "Appendix".to_string()),
+            ]
+        );
+        assert_eq!(
+            number_levels(vec![1, 2, 2, 1, 3]),
+            vec!["1.", "1.1", "1.2", "2.", "2.0.1"]
+        );
+    }
+
+    #[test]
+    fn cell_fills_override_zebra_stripes() {
+        let cell = |text: &str| RichParagraph::new(vec![Span::new(text)]);
+        let highlight = Color::Rgb(255, 230, 150);
+        let stripe = Color::Greyscale(240);
+        let table = TableBlock::new(vec![cell("Item"), cell("Amount")])
+            .with_row(vec![cell("Rent"), cell("900")])
+            .with_row(vec![
+                TableCell::new(cell("Travel")),
+                TableCell::new(cell("1,250")).with_fill(highlight),
+            ])
+            .with_row(vec![cell("Meals"), cell("80")])
+            .with_zebra(stripe);
+
+        assert_eq!(table.cell_fill(0, 0), None);
+        assert_eq!(table.cell_fill(1, 0), Some(stripe));
+        assert_eq!(table.cell_fill(1, 1), Some(highlight));
+        assert_eq!(table.cell_fill(2, 1), None);
+        // Cells missing from short rows still get the stripe of their row.
+        assert_eq!(table.cell_fill(1, 5), Some(stripe));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn unfilled_table_cells_serialize_as_paragraphs() {
+        let plain = TableCell::new(RichParagraph::new(vec![Span::new("Rent")]));
+        let filled = plain.clone().with_fill(Color::Rgb(255, 230, 150));
+
+        let json = serde_json::to_value(&plain).expect("serialize plain cell");
+        assert!(json.get("spans").is_some(), "{json}");
+        let json = serde_json::to_value(&filled).expect("serialize filled cell");
+        assert_eq!(json["fill"], "#FFE696");
+
+        let parsed: TableCell = serde_json::from_value(json).expect("parse filled cell");
+        assert_eq!(parsed, filled);
+
+        let limited = plain.clone().with_height_mm(8.0);
+        let json = serde_json::to_value(&limited).expect("serialize limited cell");
+        assert_eq!(json["height_mm"], 8.0);
+        assert!(json.get("fill").is_none(), "{json}");
+        let parsed: TableCell = serde_json::from_value(json).expect("parse limited cell");
+        assert_eq!(parsed, limited);
+    }
+
+    #[test]
+    fn border_presets_select_their_lines() {
+        let line = BorderStyle::new(0.2, Color::Greyscale(0));
+
+        let minimal = TableBorders::minimal(line);
+        assert_eq!(
+            (minimal.header(), minimal.bottom()),
+            (Some(line), Some(line))
+        );
+        assert_eq!((minimal.top(), minimal.rows()), (None, None));
+
+        let horizontal = TableBorders::horizontal(line);
+        assert_eq!(horizontal.rows(), Some(line));
+        assert_eq!((horizontal.sides(), horizontal.columns()), (None, None));
+
+        let heavy = BorderStyle::new(0.6, Color::Greyscale(0));
+        let grid = TableBorders::grid(line).with_sides(heavy);
+        assert_eq!((grid.sides(), grid.columns()), (Some(heavy), Some(line)));
+        assert_eq!(TableBorders::outer(line).rows(), None);
+    }
+
+    #[test]
+    fn dividers_precede_their_sections_without_numbers() {
+        let sections = vec![
+            Section::new("Overview")
+                .with_divider(Divider::new("Part I"))
+                .with_subsection(Section::new("Scope").with_divider(Divider::new("Ignored"))),
+            Section::new("Runbooks").with_divider(Divider::new("Part II")),
+            Section::new("Appendix")
+                .with_divider(Divider::new("Part III").with_restart_numbering(true)),
+        ];
+
+        let outline = flatten_outline(&sections);
+        let entries: Vec<_> = outline
+            .iter()
+            .zip(number_outline(&outline))
+            .map(|((level, item), number)| (*level, item.title().into_owned(), number))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (0, "Part I".to_string(), None),
+                (1, "Overview".to_string(), Some("1.".to_string())),
+                (2, "Scope".to_string(), Some("1.1".to_string())),
+                (0, "Part II".to_string(), None),
+                (1, "Runbooks".to_string(), Some("2.".to_string())),
+                (0, "Part III".to_string(), None),
+                (1, "Appendix".to_string(), Some("1.".to_string())),
+            ]
+        );
+    }
 }
@@ -0,0 +1,291 @@
+//! Small `lopdf` helpers shared by the post-processing passes.
+
+#[cfg(any(
+    feature = "mixed-orientation",
+    feature = "merge",
+    feature = "page-labels",
+    feature = "viewer-preferences"
+))]
+use lopdf::Dictionary;
+use lopdf::{Document, Object, ObjectId};
+
+/// Number of PDF points in one millimetre.
+#[cfg(any(feature = "bookmarks", feature = "links", feature = "tagged-pdf"))]
+pub(crate) const POINTS_PER_MM: f64 = 72.0 / 25.4;
+
+/// Reads the height of a page's media box in points.
+#[cfg(any(feature = "links", feature = "tagged-pdf"))]
+pub(crate) fn page_height_points(
+    document: &Document,
+    page_id: ObjectId,
+) -> Result<f64, lopdf::Error> {
+    let [_, bottom, _, top] = media_box(document, page_id)?;
+    Ok(top - bottom)
+}
+
+/// View a PDF reader switches to when an outline entry or a link to a page is activated.
+#[cfg(any(feature = "bookmarks", feature = "links"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BookmarkDestination {
+    /// Fit the whole target page into the window (`/Fit`).
+    #[default]
+    Fit,
+    /// Fit the page width and scroll to `top` millimetres below the top edge of the page
+    /// (`/FitH`).  `None` keeps the current vertical position.
+    FitH(Option<f64>),
+    /// Scroll to `top` millimetres below the top edge of the page and zoom to `zoom`, where `1.0`
+    /// is 100% (`/XYZ`).  `None` keeps the corresponding value of the current view, so
+    /// `XYZ { top: Some(0.0), zoom: None }` jumps to the top of the page without changing the
+    /// reader's zoom.
+    XYZ {
+        /// Distance from the top edge of the page in millimetres.
+        top: Option<f64>,
+        /// Zoom factor, where `1.0` is 100%.
+        zoom: Option<f64>,
+    },
+}
+
+#[cfg(any(feature = "bookmarks", feature = "links"))]
+impl BookmarkDestination {
+    /// Builds the destination array for `page_ref`, whose media box has its upper edge at
+    /// `page_top` points.
+    pub(crate) fn to_array(self, page_ref: ObjectId, page_top: f64) -> Vec<Object> {
+        let top = |top: Option<f64>| {
+            top.map_or(Object::Null, |top| {
+                Object::from(page_top - top * POINTS_PER_MM)
+            })
+        };
+        let mut array = vec![Object::Reference(page_ref)];
+        match self {
+            Self::Fit => array.push(Object::Name("Fit".into())),
+            Self::FitH(offset) => {
+                array.push(Object::Name("FitH".into()));
+                array.push(top(offset));
+            }
+            Self::XYZ { top: offset, zoom } => {
+                array.push(Object::Name("XYZ".into()));
+                array.push(Object::Null);
+                array.push(top(offset));
+                array.push(zoom.map_or(Object::Null, Object::from));
+            }
+        }
+        array
+    }
+}
+
+/// Reads the y coordinate of the upper edge of a page's media box in points.
+///
+/// Media boxes usually start at the origin, but documents from other tools may place the lower
+/// left corner elsewhere, which moves every position on the page by the same amount.
+#[cfg(any(feature = "bookmarks", feature = "links"))]
+pub(crate) fn page_top_points(document: &Document, page_id: ObjectId) -> Result<f64, lopdf::Error> {
+    let [_, _, _, top] = media_box(document, page_id)?;
+    Ok(top)
+}
+
+/// Reads the `[left bottom right top]` coordinates of a page's media box in points.
+#[cfg(any(feature = "bookmarks", feature = "links", feature = "tagged-pdf"))]
+fn media_box(document: &Document, page_id: ObjectId) -> Result<[f64; 4], lopdf::Error> {
+    let media_box = document
+        .get_dictionary(page_id)?
+        .get(b"MediaBox")?
+        .as_array()?;
+    let coordinate = |index: usize| -> Result<f64, lopdf::Error> {
+        match media_box.get(index) {
+            Some(Object::Integer(value)) => Ok(*value as f64),
+            Some(Object::Real(value)) => Ok(f64::from(*value)),
+            _ => Err(lopdf::Error::Type),
+        }
+    };
+    Ok([
+        coordinate(0)?,
+        coordinate(1)?,
+        coordinate(2)?,
+        coordinate(3)?,
+    ])
+}
+
+/// Replaces the `/PageLabels` number tree of the catalog.
+///
+/// `ranges` lists the zero-based index of the first page of every range together with the
+/// numbering style written to its `/S` entry; each range starts counting at 1.
+#[cfg(feature = "page-labels")]
+pub(crate) fn set_page_labels(
+    pdf_bytes: &[u8],
+    ranges: &[(usize, &str)],
+) -> Result<Vec<u8>, lopdf::Error> {
+    let mut document = Document::load_mem(pdf_bytes)?;
+    let mut numbers = Vec::with_capacity(ranges.len() * 2);
+    for &(start, style) in ranges {
+        let mut label = Dictionary::new();
+        label.set("S", Object::Name(style.into()));
+        numbers.push(Object::Integer(start as i64));
+        numbers.push(Object::Dictionary(label));
+    }
+    let mut tree = Dictionary::new();
+    tree.set("Nums", Object::Array(numbers));
+
+    let catalog_id = document.trailer.get(b"Root")?.as_reference()?;
+    document
+        .get_object_mut(catalog_id)?
+        .as_dict_mut()?
+        .set("PageLabels", Object::Dictionary(tree));
+
+    let mut buffer = Vec::new();
+    document.save_to(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Sets the `/PageMode` and `/PageLayout` of the catalog and, when `hide_toolbar` is set, adds
+/// `/HideToolbar` to its `/ViewerPreferences`.
+///
+/// Entries passed as `None` are left as they are.
+#[cfg(feature = "viewer-preferences")]
+pub(crate) fn set_viewer_preferences(
+    pdf_bytes: &[u8],
+    page_mode: Option<&str>,
+    page_layout: Option<&str>,
+    hide_toolbar: bool,
+) -> Result<Vec<u8>, lopdf::Error> {
+    let mut document = Document::load_mem(pdf_bytes)?;
+    let catalog_id = document.trailer.get(b"Root")?.as_reference()?;
+    let catalog = document.get_object_mut(catalog_id)?.as_dict_mut()?;
+    if let Some(mode) = page_mode {
+        catalog.set("PageMode", Object::Name(mode.into()));
+    }
+    if let Some(layout) = page_layout {
+        catalog.set("PageLayout", Object::Name(layout.into()));
+    }
+    if hide_toolbar {
+        let mut preferences = Dictionary::new();
+        preferences.set("HideToolbar", Object::Boolean(true));
+        catalog.set("ViewerPreferences", Object::Dictionary(preferences));
+    }
+
+    let mut buffer = Vec::new();
+    document.save_to(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Appends the pages of `other` to the end of `base`.
+///
+/// The objects of `other` are renumbered past those of `base`, its catalog and page tree root are
+/// dropped, and its pages are re-parented under the page tree of `base`.  Attributes the pages
+/// inherited from the dropped page tree, such as their media box, are copied onto the pages.  The
+/// optional content groups `printpdf` creates for page layers are registered with the catalog of
+/// `base` so viewers keep showing the appended content.
+///
+/// Returns the renumbered catalog of `other`, whose other entries are left to the caller.
+#[cfg(any(feature = "mixed-orientation", feature = "merge"))]
+pub(crate) fn append_document(
+    base: &mut Document,
+    mut other: Document,
+) -> Result<Dictionary, lopdf::Error> {
+    other.renumber_objects_with(base.max_id + 1);
+    base.max_id = base.max_id.max(other.max_id);
+
+    let other_catalog_id = other.trailer.get(b"Root")?.as_reference()?;
+    let other_catalog = other.get_dictionary(other_catalog_id)?.clone();
+    let other_pages_id = other_catalog.get(b"Pages")?.as_reference()?;
+    let other_groups = optional_content_groups(&other_catalog);
+    let pages: Vec<ObjectId> = other.get_pages().into_values().collect();
+    for &page_id in &pages {
+        let inherited = inherited_attributes(&other, page_id)?;
+        let page = other.get_object_mut(page_id)?.as_dict_mut()?;
+        for (key, value) in inherited {
+            page.set(key, value);
+        }
+    }
+
+    let base_catalog_id = base.trailer.get(b"Root")?.as_reference()?;
+    let base_pages_id = base
+        .get_dictionary(base_catalog_id)?
+        .get(b"Pages")?
+        .as_reference()?;
+
+    for (id, object) in other.objects {
+        if id != other_catalog_id && id != other_pages_id {
+            base.objects.insert(id, object);
+        }
+    }
+    for &page_id in &pages {
+        base.get_object_mut(page_id)?
+            .as_dict_mut()?
+            .set("Parent", Object::Reference(base_pages_id));
+    }
+
+    let page_tree = base.get_object_mut(base_pages_id)?.as_dict_mut()?;
+    let count = page_tree
+        .get(b"Count")
+        .and_then(Object::as_i64)
+        .unwrap_or(0);
+    page_tree.set("Count", Object::Integer(count + pages.len() as i64));
+    let kids = pages.iter().map(|&id| Object::Reference(id));
+    append_to_array(page_tree, "Kids", kids);
+
+    if !other_groups.is_empty() {
+        let catalog = base.get_object_mut(base_catalog_id)?.as_dict_mut()?;
+        if let Ok(Object::Dictionary(properties)) = catalog.get_mut(b"OCProperties") {
+            append_to_array(properties, "OCGs", other_groups.iter().cloned());
+            if let Ok(Object::Dictionary(config)) = properties.get_mut(b"D") {
+                append_to_array(config, "Order", other_groups.iter().cloned());
+                append_to_array(config, "ON", other_groups.iter().cloned());
+            }
+        }
+    }
+
+    Ok(other_catalog)
+}
+
+/// Collects the inheritable attributes a page lacks from the closest page tree node that sets them.
+#[cfg(any(feature = "mixed-orientation", feature = "merge"))]
+fn inherited_attributes(
+    document: &Document,
+    page_id: ObjectId,
+) -> Result<Vec<(&'static str, Object)>, lopdf::Error> {
+    const INHERITABLE: [&str; 4] = ["Resources", "MediaBox", "CropBox", "Rotate"];
+    let page = document.get_dictionary(page_id)?;
+    let mut missing: Vec<_> = INHERITABLE
+        .into_iter()
+        .filter(|key| !page.has(key.as_bytes()))
+        .collect();
+    let mut inherited = Vec::new();
+    let mut parent = page.get(b"Parent").and_then(Object::as_reference).ok();
+    while let Some(node_id) = parent.filter(|_| !missing.is_empty()) {
+        let node = document.get_dictionary(node_id)?;
+        missing.retain(|&key| match node.get(key.as_bytes()) {
+            Ok(value) => {
+                inherited.push((key, value.clone()));
+                false
+            }
+            Err(_) => true,
+        });
+        parent = node.get(b"Parent").and_then(Object::as_reference).ok();
+    }
+    Ok(inherited)
+}
+
+/// Lists the optional content groups registered in a catalog.
+#[cfg(any(feature = "mixed-orientation", feature = "merge"))]
+fn optional_content_groups(catalog: &Dictionary) -> Vec<Object> {
+    catalog
+        .get(b"OCProperties")
+        .and_then(Object::as_dict)
+        .and_then(|properties| properties.get(b"OCGs"))
+        .and_then(Object::as_array)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Appends `items` to the array stored under `key`, creating the array when it is missing.
+#[cfg(any(feature = "mixed-orientation", feature = "merge"))]
+fn append_to_array(
+    dictionary: &mut Dictionary,
+    key: &str,
+    items: impl IntoIterator<Item = Object>,
+) {
+    match dictionary.get_mut(key.as_bytes()) {
+        Ok(Object::Array(array)) => array.extend(items),
+        _ => dictionary.set(key, Object::Array(items.into_iter().collect())),
+    }
+}
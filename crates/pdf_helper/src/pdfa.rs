@@ -0,0 +1,706 @@
+//! PDF/A conversion built on top of `lopdf`.
+//!
+//! The rendered document is rewritten to meet the structural requirements of PDF/A-1b or
+//! PDF/A-2b: XMP metadata mirroring the document information dictionary, an sRGB output intent,
+//! embedded fonts, and no transparency or hidden annotations.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use lopdf::{Dictionary, Document, Object, Stream, StringFormat};
+
+/// Name of the output condition embedded with the sRGB profile.
+const SRGB_CONDITION: &str = "sRGB IEC61966-2.1";
+
+/// Part of the PDF/A standard the output conforms to, both at conformance level B (visual
+/// appearance).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PdfaLevel {
+    /// PDF/A-1b (ISO 19005-1), based on PDF 1.4.
+    #[default]
+    A1b,
+    /// PDF/A-2b (ISO 19005-2), based on PDF 1.7.
+    A2b,
+}
+
+impl PdfaLevel {
+    fn part(self) -> u8 {
+        match self {
+            Self::A1b => 1,
+            Self::A2b => 2,
+        }
+    }
+
+    fn pdf_version(self) -> &'static str {
+        match self {
+            Self::A1b => "1.4",
+            Self::A2b => "1.7",
+        }
+    }
+}
+
+/// Errors that can occur while converting a rendered PDF document to PDF/A.
+#[derive(Debug)]
+pub enum PdfaError {
+    /// The PDF bytes could not be parsed or written by `lopdf`.
+    Parse(lopdf::Error),
+    /// The document trailer does not reference a catalog dictionary.
+    MissingCatalog,
+    /// A font is referenced without embedding its font program.
+    FontNotEmbedded {
+        /// Base name of the font.
+        font: String,
+    },
+    /// The document is encrypted, which PDF/A forbids.
+    Encrypted,
+}
+
+impl From<lopdf::Error> for PdfaError {
+    fn from(err: lopdf::Error) -> Self {
+        Self::Parse(err)
+    }
+}
+
+impl From<std::io::Error> for PdfaError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Parse(err.into())
+    }
+}
+
+impl std::fmt::Display for PdfaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(err) => write!(f, "Failed to parse PDF bytes: {err}"),
+            Self::MissingCatalog => write!(f, "PDF catalog entry is missing"),
+            Self::FontNotEmbedded { font } => write!(f, "Font {font} is not embedded"),
+            Self::Encrypted => write!(f, "PDF/A documents cannot be encrypted"),
+        }
+    }
+}
+
+impl std::error::Error for PdfaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Parse(err) => Some(err),
+            Self::MissingCatalog | Self::FontNotEmbedded { .. } | Self::Encrypted => None,
+        }
+    }
+}
+
+/// Rewrites a rendered document so it conforms to `level`.
+///
+/// The conversion:
+///
+/// * replaces the document information dictionary with its standard text entries and embeds
+///   matching XMP metadata that identifies the PDF/A part and conformance level,
+/// * adds an output intent with a built-in sRGB ICC profile for the device colours `genpdf` uses,
+/// * checks that every font program is embedded,
+/// * marks annotations as printable and disables image interpolation,
+/// * for PDF/A-1b, removes soft masks, transparency, and optional content, and for PDF/A-2b names
+///   the optional content configuration,
+/// * and writes the PDF version and binary header comment required by `level`.
+///
+/// Colours given as CMYK are not converted and keep the output from validating.
+pub fn apply_pdfa(pdf_bytes: &[u8], level: PdfaLevel) -> Result<Vec<u8>, PdfaError> {
+    let mut document = Document::load_mem(pdf_bytes)?;
+    if document.trailer.has(b"Encrypt") {
+        return Err(PdfaError::Encrypted);
+    }
+    let catalog_id = document
+        .trailer
+        .get(b"Root")
+        .and_then(Object::as_reference)
+        .map_err(|_| PdfaError::MissingCatalog)?;
+
+    if document.trailer.get(b"ID").is_err() {
+        let id = Object::String(file_identifier(pdf_bytes), StringFormat::Hexadecimal);
+        document
+            .trailer
+            .set("ID", Object::Array(vec![id.clone(), id]));
+    }
+
+    let info = document_info(&document);
+    let metadata = Stream::new(metadata_dictionary(), xmp_packet(level, &info).into_bytes());
+    let metadata_id = document.add_object(metadata);
+    let info_dictionary = Object::Dictionary(info_dictionary(&info));
+    match document.trailer.get(b"Info").and_then(Object::as_reference) {
+        Ok(info_id) => {
+            document.objects.insert(info_id, info_dictionary);
+        }
+        Err(_) => {
+            let info_id = document.add_object(info_dictionary);
+            document.trailer.set("Info", Object::Reference(info_id));
+        }
+    }
+
+    let mut profile = Dictionary::new();
+    profile.set("N", Object::Integer(3));
+    let profile_id = document.add_object(Stream::new(profile, srgb_profile()));
+    let mut intent = Dictionary::new();
+    intent.set("Type", Object::Name("OutputIntent".into()));
+    intent.set("S", Object::Name("GTS_PDFA1".into()));
+    intent.set(
+        "OutputConditionIdentifier",
+        Object::string_literal(SRGB_CONDITION),
+    );
+    intent.set("Info", Object::string_literal(SRGB_CONDITION));
+    intent.set("DestOutputProfile", Object::Reference(profile_id));
+
+    for object in document.objects.values_mut() {
+        let dictionary = match object {
+            Object::Dictionary(dictionary) => dictionary,
+            Object::Stream(stream) => &mut stream.dict,
+            _ => continue,
+        };
+        clean_dictionary(dictionary, level)?;
+    }
+
+    let catalog = document
+        .get_object_mut(catalog_id)
+        .and_then(Object::as_dict_mut)
+        .map_err(|_| PdfaError::MissingCatalog)?;
+    catalog.set("Metadata", Object::Reference(metadata_id));
+    catalog.set(
+        "OutputIntents",
+        Object::Array(vec![Object::Dictionary(intent)]),
+    );
+    match level {
+        PdfaLevel::A1b => {
+            catalog.remove(b"OCProperties");
+        }
+        PdfaLevel::A2b => {
+            if let Ok(Object::Dictionary(properties)) = catalog.get_mut(b"OCProperties") {
+                if let Ok(Object::Dictionary(config)) = properties.get_mut(b"D") {
+                    config.remove(b"AS");
+                    if !config.has(b"Name") {
+                        config.set("Name", Object::string_literal("Default"));
+                    }
+                }
+            }
+        }
+    }
+
+    // `lopdf` writes the version right after `%PDF-`, so the binary comment PDF/A requires on the
+    // second line is appended to it.  Each of these characters takes two bytes above 127 in
+    // UTF-8.
+    document.version = format!("{}\n%\u{e2}\u{e3}\u{cf}\u{d3}", level.pdf_version());
+
+    let mut buffer = Vec::new();
+    document.save_to(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Applies the per-object restrictions of `level` to a dictionary or stream dictionary.
+fn clean_dictionary(dictionary: &mut Dictionary, level: PdfaLevel) -> Result<(), PdfaError> {
+    let name = |key: &[u8]| {
+        dictionary
+            .get(key)
+            .and_then(Object::as_name)
+            .ok()
+            .map(<[u8]>::to_vec)
+    };
+    let type_name = name(b"Type");
+    let subtype = name(b"Subtype");
+
+    if type_name.as_deref() == Some(b"FontDescriptor".as_slice())
+        && !["FontFile", "FontFile2", "FontFile3"]
+            .iter()
+            .any(|key| dictionary.has(key.as_bytes()))
+    {
+        let font = dictionary
+            .get(b"FontName")
+            .and_then(Object::as_name_str)
+            .unwrap_or("unnamed")
+            .to_string();
+        return Err(PdfaError::FontNotEmbedded { font });
+    }
+
+    if type_name.as_deref() == Some(b"Annot".as_slice()) {
+        // Annotations have to be printed and may not be hidden.
+        dictionary.set("F", Object::Integer(4));
+    }
+
+    if subtype.as_deref() == Some(b"Image".as_slice()) && dictionary.has(b"Interpolate") {
+        dictionary.set("Interpolate", Object::Boolean(false));
+    }
+
+    if level == PdfaLevel::A1b {
+        let masked = dictionary.get(b"SMask").map_or(false, |mask| {
+            mask.as_name().ok() != Some(b"None".as_slice())
+        });
+        if masked {
+            dictionary.remove(b"SMask");
+        }
+        if type_name.as_deref() == Some(b"ExtGState".as_slice()) {
+            for key in ["CA", "ca"] {
+                if dictionary.has(key.as_bytes()) {
+                    dictionary.set(key, Object::Real(1.0));
+                }
+            }
+            if dictionary.has(b"BM") {
+                dictionary.set("BM", Object::Name("Normal".into()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Text entries of the document information dictionary that PDF/A mirrors in XMP.
+#[derive(Debug, Default)]
+struct DocumentInfo {
+    title: Option<String>,
+    author: Option<String>,
+    subject: Option<String>,
+    keywords: Option<String>,
+    creator: Option<String>,
+    producer: Option<String>,
+    creation_date: Option<(String, String)>,
+    modification_date: Option<(String, String)>,
+}
+
+/// Reads the standard entries of the information dictionary, dropping dates that cannot be
+/// expressed in XMP.
+fn document_info(document: &Document) -> DocumentInfo {
+    let info = document
+        .trailer
+        .get(b"Info")
+        .and_then(Object::as_reference)
+        .and_then(|id| document.get_dictionary(id))
+        .ok();
+    let text = |key: &[u8]| {
+        info.and_then(|info| info.get(key).and_then(Object::as_str).ok())
+            .map(decode_text_string)
+    };
+    let date = |key: &[u8]| text(key).and_then(|date| Some((xmp_date(&date)?, date)));
+
+    DocumentInfo {
+        title: text(b"Title"),
+        author: text(b"Author"),
+        subject: text(b"Subject"),
+        keywords: text(b"Keywords"),
+        creator: text(b"Creator"),
+        producer: text(b"Producer"),
+        creation_date: date(b"CreationDate"),
+        modification_date: date(b"ModDate"),
+    }
+}
+
+fn info_dictionary(info: &DocumentInfo) -> Dictionary {
+    let mut dictionary = Dictionary::new();
+    let entries = [
+        ("Title", &info.title),
+        ("Author", &info.author),
+        ("Subject", &info.subject),
+        ("Keywords", &info.keywords),
+        ("Creator", &info.creator),
+        ("Producer", &info.producer),
+    ];
+    for (key, value) in entries {
+        if let Some(value) = value {
+            dictionary.set(key, encode_text_string(value));
+        }
+    }
+    for (key, value) in [
+        ("CreationDate", &info.creation_date),
+        ("ModDate", &info.modification_date),
+    ] {
+        if let Some((_, date)) = value {
+            dictionary.set(key, Object::string_literal(date.as_str()));
+        }
+    }
+    dictionary
+}
+
+fn metadata_dictionary() -> Dictionary {
+    let mut dictionary = Dictionary::new();
+    dictionary.set("Type", Object::Name("Metadata".into()));
+    dictionary.set("Subtype", Object::Name("XML".into()));
+    dictionary
+}
+
+/// Builds the XMP packet identifying the PDF/A level and repeating the document information.
+fn xmp_packet(level: PdfaLevel, info: &DocumentInfo) -> String {
+    let mut description = String::new();
+    let mut property = |name: &str, value: String| {
+        description.push_str(&format!("   <{name}>{value}</{name}>\n"));
+    };
+    let alternative = |value: &str| {
+        format!(
+            "<rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt>",
+            escape_xml(value)
+        )
+    };
+
+    property("pdfaid:part", level.part().to_string());
+    property("pdfaid:conformance", "B".to_string());
+    if let Some(title) = &info.title {
+        property("dc:title", alternative(title));
+    }
+    if let Some(author) = &info.author {
+        let author = escape_xml(author);
+        property(
+            "dc:creator",
+            format!("<rdf:Seq><rdf:li>{author}</rdf:li></rdf:Seq>"),
+        );
+    }
+    if let Some(subject) = &info.subject {
+        property("dc:description", alternative(subject));
+    }
+    if let Some(keywords) = &info.keywords {
+        property("pdf:Keywords", escape_xml(keywords));
+    }
+    if let Some(producer) = &info.producer {
+        property("pdf:Producer", escape_xml(producer));
+    }
+    if let Some(creator) = &info.creator {
+        property("xmp:CreatorTool", escape_xml(creator));
+    }
+    if let Some((date, _)) = &info.creation_date {
+        property("xmp:CreateDate", date.clone());
+    }
+    if let Some((date, _)) = &info.modification_date {
+        property("xmp:ModifyDate", date.clone());
+    }
+
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n\
+         \x20<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n\
+         \x20 <rdf:Description rdf:about=\"\"\n\
+         \x20   xmlns:pdfaid=\"http://www.aiim.org/pdfa/ns/id/\"\n\
+         \x20   xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n\
+         \x20   xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\"\n\
+         \x20   xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\">\n\
+         {description}\
+         \x20 </rdf:Description>\n\
+         \x20</rdf:RDF>\n\
+         </x:xmpmeta>\n\
+         <?xpacket end=\"w\"?>"
+    )
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Decodes a PDF text string, which is either UTF-16BE with a byte order mark or
+/// PDFDocEncoding, approximated here by Latin-1.
+fn decode_text_string(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xFE, 0xFF]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => bytes.iter().map(|&byte| char::from(byte)).collect(),
+    }
+}
+
+fn encode_text_string(value: &str) -> Object {
+    if value.is_ascii() {
+        Object::string_literal(value)
+    } else {
+        let mut bytes = vec![0xFE, 0xFF];
+        bytes.extend(value.encode_utf16().flat_map(u16::to_be_bytes));
+        Object::String(bytes, StringFormat::Hexadecimal)
+    }
+}
+
+/// Converts a PDF date such as `D:20240131093000+01'00'` into the ISO 8601 form used by XMP.
+///
+/// Missing fields default to the start of the period, and dates without a time zone stay local.
+fn xmp_date(date: &str) -> Option<String> {
+    let date = date.strip_prefix("D:").unwrap_or(date);
+    let digits = date.chars().take_while(char::is_ascii_digit).count();
+    if !(4..=14).contains(&digits) || digits % 2 != 0 {
+        return None;
+    }
+    let field = |start: usize, default: &'static str| {
+        date.get(start..start + 2)
+            .filter(|_| start < digits)
+            .unwrap_or(default)
+    };
+    let mut iso = format!(
+        "{}-{}-{}T{}:{}:{}",
+        &date[..4],
+        field(4, "01"),
+        field(6, "01"),
+        field(8, "00"),
+        field(10, "00"),
+        field(12, "00"),
+    );
+
+    let zone = &date[digits..];
+    match zone.chars().next() {
+        None => {}
+        Some('Z') => iso.push('Z'),
+        Some(sign @ ('+' | '-')) => {
+            let mut parts = zone[1..].split('\'').filter(|part| !part.is_empty());
+            let hours = parts.next().unwrap_or("00");
+            let minutes = parts.next().unwrap_or("00");
+            if hours.len() != 2 || minutes.len() != 2 {
+                return None;
+            }
+            iso.push_str(&format!("{sign}{hours}:{minutes}"));
+        }
+        Some(_) => return None,
+    }
+    Some(iso)
+}
+
+/// Derives a file identifier from the document bytes for documents written without one.
+fn file_identifier(pdf_bytes: &[u8]) -> Vec<u8> {
+    let mut identifier = Vec::with_capacity(16);
+    for seed in 0u8..2 {
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        pdf_bytes.hash(&mut hasher);
+        identifier.extend(hasher.finish().to_be_bytes());
+    }
+    identifier
+}
+
+/// Builds an ICC version 2 display profile describing the sRGB colour space.
+///
+/// The profile holds the D50-adapted sRGB primaries and the sRGB tone curve sampled at 1024
+/// points, which is all PDF/A needs to interpret `DeviceRGB` colours.
+fn srgb_profile() -> Vec<u8> {
+    fn s15_fixed16(value: f64) -> [u8; 4] {
+        ((value * 65536.0).round() as i32).to_be_bytes()
+    }
+    fn xyz(values: [f64; 3]) -> Vec<u8> {
+        let mut tag = b"XYZ \0\0\0\0".to_vec();
+        for value in values {
+            tag.extend(s15_fixed16(value));
+        }
+        tag
+    }
+
+    const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+    let description = {
+        let mut tag = b"desc\0\0\0\0".to_vec();
+        tag.extend(((SRGB_CONDITION.len() + 1) as u32).to_be_bytes());
+        tag.extend(SRGB_CONDITION.as_bytes());
+        // Terminator, empty Unicode and ScriptCode descriptions.
+        tag.extend([0; 1 + 8 + 3 + 67]);
+        tag
+    };
+    let copyright = {
+        let mut tag = b"text\0\0\0\0".to_vec();
+        tag.extend(b"No copyright, use freely\0");
+        tag
+    };
+    let curve = {
+        const POINTS: u32 = 1024;
+        let mut tag = b"curv\0\0\0\0".to_vec();
+        tag.extend(POINTS.to_be_bytes());
+        for index in 0..POINTS {
+            let encoded = f64::from(index) / f64::from(POINTS - 1);
+            let linear = if encoded <= 0.04045 {
+                encoded / 12.92
+            } else {
+                ((encoded + 0.055) / 1.055).powf(2.4)
+            };
+            tag.extend(((linear * 65535.0).round() as u16).to_be_bytes());
+        }
+        tag
+    };
+
+    let tags: [(&[u8; 4], Vec<u8>); 7] = [
+        (b"desc", description),
+        (b"cprt", copyright),
+        (b"wtpt", xyz(D50)),
+        (b"rXYZ", xyz([0.4361, 0.2225, 0.0139])),
+        (b"gXYZ", xyz([0.3851, 0.7169, 0.0971])),
+        (b"bXYZ", xyz([0.1431, 0.0606, 0.7141])),
+        (b"rTRC", curve),
+    ];
+
+    // The three tone curves share one tag, so the table lists nine entries for seven tags.
+    let table_size = 4 + 12 * (tags.len() + 2);
+    let mut data = Vec::new();
+    let mut table = Vec::with_capacity(table_size);
+    table.extend(((tags.len() + 2) as u32).to_be_bytes());
+    for (signature, tag) in &tags {
+        let offset = (128 + table_size + data.len()) as u32;
+        let entry = |signature: &[u8; 4]| {
+            let mut entry = signature.to_vec();
+            entry.extend(offset.to_be_bytes());
+            entry.extend((tag.len() as u32).to_be_bytes());
+            entry
+        };
+        table.extend(entry(*signature));
+        if *signature == b"rTRC" {
+            table.extend(entry(b"gTRC"));
+            table.extend(entry(b"bTRC"));
+        }
+        data.extend(tag);
+        data.resize((data.len() + 3) / 4 * 4, 0);
+    }
+
+    let size = (128 + table.len() + data.len()) as u32;
+    let mut profile = Vec::with_capacity(size as usize);
+    profile.extend(size.to_be_bytes());
+    profile.extend([0; 4]);
+    profile.extend([0x02, 0x10, 0, 0]);
+    profile.extend(b"mntrRGB XYZ ");
+    // Creation date: 2024-01-01 00:00:00.
+    for field in [2024u16, 1, 1, 0, 0, 0] {
+        profile.extend(field.to_be_bytes());
+    }
+    profile.extend(b"acsp");
+    profile.extend([0; 24]);
+    // Perceptual rendering intent.
+    profile.extend([0; 4]);
+    for value in D50 {
+        profile.extend(s15_fixed16(value));
+    }
+    profile.resize(128, 0);
+    profile.extend(table);
+    profile.extend(data);
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_document(embed_font: bool) -> Vec<u8> {
+        let mut document = Document::with_version("1.3");
+        let pages_id = document.new_object_id();
+
+        let mut descriptor = Dictionary::new();
+        descriptor.set("Type", Object::Name("FontDescriptor".into()));
+        descriptor.set("FontName", Object::Name("Roboto".into()));
+        if embed_font {
+            let program = document.add_object(Stream::new(Dictionary::new(), vec![0; 16]));
+            descriptor.set("FontFile2", Object::Reference(program));
+        }
+        document.add_object(descriptor);
+
+        let mut page = Dictionary::new();
+        page.set("Type", Object::Name("Page".into()));
+        page.set("Parent", Object::Reference(pages_id));
+        let page_id = document.add_object(page);
+        let mut pages = Dictionary::new();
+        pages.set("Type", Object::Name("Pages".into()));
+        pages.set("Kids", Object::Array(vec![Object::Reference(page_id)]));
+        pages.set("Count", Object::Integer(1));
+        document.objects.insert(pages_id, Object::Dictionary(pages));
+
+        let mut catalog = Dictionary::new();
+        catalog.set("Type", Object::Name("Catalog".into()));
+        catalog.set("Pages", Object::Reference(pages_id));
+        catalog.set("OCProperties", Object::Dictionary(Dictionary::new()));
+        let catalog_id = document.add_object(catalog);
+        document.trailer.set("Root", Object::Reference(catalog_id));
+
+        let mut info = Dictionary::new();
+        info.set("Title", Object::string_literal("Audit & Review"));
+        info.set("Producer", Object::string_literal("pdf_helper"));
+        info.set(
+            "CreationDate",
+            Object::string_literal("D:20240131093000+01'00'"),
+        );
+        info.set("Trapped", Object::Name("False".into()));
+        let info_id = document.add_object(info);
+        document.trailer.set("Info", Object::Reference(info_id));
+
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).expect("save sample document");
+        bytes
+    }
+
+    #[test]
+    fn catalog_references_metadata_and_an_srgb_output_intent() {
+        let bytes = apply_pdfa(&sample_document(true), PdfaLevel::A1b).expect("convert");
+        assert!(bytes.starts_with(b"%PDF-1.4\n%"));
+        assert!(bytes[10..14].iter().all(|&byte| byte > 127));
+
+        let document = Document::load_mem(&bytes).expect("converted PDF parses");
+        let catalog = document
+            .trailer
+            .get(b"Root")
+            .and_then(Object::as_reference)
+            .and_then(|id| document.get_dictionary(id))
+            .expect("catalog");
+        assert!(!catalog.has(b"OCProperties"));
+
+        let intents = catalog
+            .get(b"OutputIntents")
+            .and_then(Object::as_array)
+            .expect("output intents");
+        let intent = intents[0].as_dict().expect("output intent");
+        assert_eq!(
+            intent.get(b"S").and_then(Object::as_name).ok(),
+            Some(b"GTS_PDFA1".as_slice())
+        );
+        let profile_id = intent
+            .get(b"DestOutputProfile")
+            .and_then(Object::as_reference)
+            .expect("profile reference");
+        let profile = document
+            .get_object(profile_id)
+            .and_then(Object::as_stream)
+            .expect("profile stream");
+        assert_eq!(
+            u32::from_be_bytes(profile.content[..4].try_into().unwrap()) as usize,
+            profile.content.len()
+        );
+        assert_eq!(&profile.content[36..40], b"acsp");
+
+        let metadata_id = catalog
+            .get(b"Metadata")
+            .and_then(Object::as_reference)
+            .expect("metadata reference");
+        let metadata = document
+            .get_object(metadata_id)
+            .and_then(Object::as_stream)
+            .expect("metadata stream");
+        let xmp = String::from_utf8(metadata.content.clone()).expect("UTF-8 XMP");
+        assert!(xmp.contains("<pdfaid:part>1</pdfaid:part>"), "{xmp}");
+        assert!(xmp.contains(">Audit &amp; Review</rdf:li>"), "{xmp}");
+        assert!(xmp.contains("<xmp:CreateDate>2024-01-31T09:30:00+01:00</xmp:CreateDate>"));
+
+        let info = document
+            .trailer
+            .get(b"Info")
+            .and_then(Object::as_reference)
+            .and_then(|id| document.get_dictionary(id))
+            .expect("info dictionary");
+        assert!(!info.has(b"Trapped"));
+        assert!(document.trailer.has(b"ID"));
+    }
+
+    #[test]
+    fn fonts_without_an_embedded_program_are_rejected() {
+        match apply_pdfa(&sample_document(false), PdfaLevel::A2b) {
+            Err(PdfaError::FontNotEmbedded { font }) => assert_eq!(font, "Roboto"),
+            other => panic!("expected a missing font error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn pdf_dates_convert_to_xmp_dates() {
+        assert_eq!(
+            xmp_date("D:20240131093000+01'00'").as_deref(),
+            Some("2024-01-31T09:30:00+01:00")
+        );
+        assert_eq!(xmp_date("D:2024Z").as_deref(), Some("2024-01-01T00:00:00Z"));
+        assert_eq!(xmp_date("D:202401311").as_deref(), None);
+        assert_eq!(xmp_date("yesterday"), None);
+    }
+}
@@ -0,0 +1,135 @@
+//! PNG previews of rendered documents built on top of `pdfium-render`.
+//!
+//! Pdfium is loaded at runtime: the `PDF_HELPER_PDFIUM_DIR` environment variable may name the
+//! directory holding the platform library (`libpdfium.so`, `libpdfium.dylib`, or `pdfium.dll`),
+//! otherwise the library is looked up on the system library path.
+
+use std::env;
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageOutputFormat, RgbaImage};
+use pdfium_render::prelude::{PdfRenderConfig, Pdfium, PdfiumError, PdfiumLibraryBindings};
+
+/// Resolution assumed by PDF page sizes, in dots per inch.
+const POINTS_PER_INCH: f32 = 72.0;
+
+/// Errors produced while rasterizing a page preview.
+#[derive(Debug)]
+pub enum PreviewError {
+    /// The requested resolution is not a positive number.
+    InvalidDpi(f32),
+    /// Pdfium could not be loaded or failed to open or draw the document.
+    Pdfium(PdfiumError),
+    /// Pdfium returned pixel data that does not hold four bytes for each pixel of the bitmap.
+    Bitmap { width: u32, height: u32, len: usize },
+    /// The drawn page could not be encoded as PNG.
+    Encode(image::ImageError),
+}
+
+impl From<PdfiumError> for PreviewError {
+    fn from(err: PdfiumError) -> Self {
+        Self::Pdfium(err)
+    }
+}
+
+impl From<image::ImageError> for PreviewError {
+    fn from(err: image::ImageError) -> Self {
+        Self::Encode(err)
+    }
+}
+
+impl std::fmt::Display for PreviewError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidDpi(dpi) => write!(f, "Preview resolution {dpi} dpi is not positive"),
+            Self::Pdfium(err) => write!(f, "Failed to rasterize PDF page: {err}"),
+            Self::Bitmap { width, height, len } => write!(
+                f,
+                "Rasterized page of {width}x{height} pixels came with {len} bytes of RGBA data"
+            ),
+            Self::Encode(err) => write!(f, "Failed to encode preview PNG: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for PreviewError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidDpi(_) | Self::Bitmap { .. } => None,
+            Self::Pdfium(err) => Some(err),
+            Self::Encode(err) => Some(err),
+        }
+    }
+}
+
+/// Rasterizes the first page of `pdf_bytes` at `dpi` and returns it encoded as PNG.
+///
+/// `password` opens encrypted documents and is ignored otherwise.
+pub fn first_page_png(
+    pdf_bytes: &[u8],
+    dpi: f32,
+    password: Option<&str>,
+) -> Result<Vec<u8>, PreviewError> {
+    if !(dpi.is_finite() && dpi > 0.0) {
+        return Err(PreviewError::InvalidDpi(dpi));
+    }
+
+    let pdfium = Pdfium::new(bind_pdfium()?);
+    let document = pdfium.load_pdf_from_byte_slice(pdf_bytes, password)?;
+    let page = document.pages().get(0)?;
+    let config = PdfRenderConfig::new().scale_page_by_factor(dpi / POINTS_PER_INCH);
+    let bitmap = page.render_with_config(&config)?;
+
+    let pixels = rgba_image(
+        bitmap.width() as u32,
+        bitmap.height() as u32,
+        bitmap.as_rgba_bytes(),
+    )?;
+    let mut bytes = Vec::new();
+    DynamicImage::ImageRgba8(pixels)
+        .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)?;
+    Ok(bytes)
+}
+
+/// Wraps the RGBA bytes of a `width` by `height` bitmap in an image.
+fn rgba_image(width: u32, height: u32, pixels: Vec<u8>) -> Result<RgbaImage, PreviewError> {
+    let len = pixels.len();
+    RgbaImage::from_raw(width, height, pixels).ok_or(PreviewError::Bitmap { width, height, len })
+}
+
+fn bind_pdfium() -> Result<Box<dyn PdfiumLibraryBindings>, PdfiumError> {
+    if let Some(dir) = env::var_os("PDF_HELPER_PDFIUM_DIR") {
+        return Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(&dir));
+    }
+    Pdfium::bind_to_system_library()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_positive_resolutions_are_rejected() {
+        assert!(matches!(
+            first_page_png(b"%PDF-1.4", 0.0, None),
+            Err(PreviewError::InvalidDpi(dpi)) if dpi == 0.0
+        ));
+        assert!(matches!(
+            first_page_png(b"%PDF-1.4", f32::NAN, None),
+            Err(PreviewError::InvalidDpi(_))
+        ));
+    }
+
+    #[test]
+    fn truncated_bitmaps_are_reported() {
+        assert!(rgba_image(2, 1, vec![0; 8]).is_ok());
+        assert!(matches!(
+            rgba_image(2, 1, vec![0; 6]),
+            Err(PreviewError::Bitmap {
+                width: 2,
+                height: 1,
+                len: 6
+            })
+        ));
+    }
+}
@@ -8,22 +8,103 @@
 //! [genpdf]: https://docs.rs/genpdf/
 
 use std::fmt;
+use std::ops::Range;
 
 use genpdf::style::{Color, Style, StyledString};
+use genpdf::Mm;
+
+/// Millimetres per typographic point.
+const MM_PER_POINT: f64 = 25.4 / 72.0;
+
+/// Converts a length in points, as used for font sizes and spacing, to millimetres.
+pub(crate) fn points_to_mm(points: f64) -> Mm {
+    Mm::from(printpdf::Mm(points * MM_PER_POINT))
+}
+
+/// Length in thousandths of a point, which keeps [`Span`] comparable with `Eq`.
+///
+/// Serialized as a number of points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "f64", into = "f64"))]
+struct MilliPoints(i64);
+
+impl MilliPoints {
+    /// Rounds `points` to the nearest thousandth of a point, or `None` when that is zero.
+    fn nonzero(points: f64) -> Option<Self> {
+        Some(Self::from(points)).filter(|length| length.0 != 0)
+    }
+}
+
+impl From<f64> for MilliPoints {
+    fn from(points: f64) -> Self {
+        Self((points * 1000.0).round() as i64)
+    }
+}
+
+impl From<MilliPoints> for f64 {
+    fn from(length: MilliPoints) -> Self {
+        length.0 as f64 / 1000.0
+    }
+}
+
+/// Vertical placement of a span relative to the baseline of the surrounding text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VerticalOffset {
+    /// Text sits on the baseline.
+    #[default]
+    Normal,
+    /// Smaller text raised above the baseline, e.g. footnote markers or exponents.
+    Super,
+    /// Smaller text lowered below the baseline, e.g. chemical formulas.
+    Sub,
+}
+
+/// Stroke pattern of the underline drawn below a [`Span`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnderlineStyle {
+    /// One solid stroke.
+    #[default]
+    Single,
+    /// Two parallel solid strokes.
+    Double,
+    /// A row of short dashes.
+    Dotted,
+    /// A zigzag wave, e.g. to flag questionable wording.
+    Wavy,
+}
 
 /// A slice of text together with inline style attributes.
 ///
 /// The `Span` type mirrors the most common inline text decorations supported by the PDF renderer
-/// (bold, italic and color).  In addition, it exposes an `underline` flag.  The underline effect is
-/// not natively supported by `genpdf`'s [`StyledString`], so the conversion helpers in this module
-/// keep track of it separately and defer the actual rendering to custom element implementations.
+/// (bold, italic and color).  In addition, it exposes `underline` and `strikethrough` flags.  These
+/// effects are not natively supported by `genpdf`'s [`StyledString`], so the conversion helpers in
+/// this module keep track of them separately and defer the actual rendering to custom element
+/// implementations.  Hyperlink targets and links to other sections of the document are carried the
+/// same way.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Span {
     text: String,
     bold: bool,
     italic: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_color::option"))]
     color: Option<Color>,
+    alpha: Option<u8>,
     underline: bool,
+    underline_style: UnderlineStyle,
+    underline_thickness: Option<MilliPoints>,
+    strikethrough: bool,
+    font_size: Option<u8>,
+    link: Option<String>,
+    internal_link: Option<String>,
+    vertical_offset: VerticalOffset,
+    footnote: Option<String>,
+    letter_spacing: Option<MilliPoints>,
 }
 
 impl Span {
@@ -35,6 +116,18 @@ impl Span {
         }
     }
 
+    /// Creates a footnote marker whose note `text` is printed at the bottom of the page the marker
+    /// lands on.
+    ///
+    /// The marker is rendered as a superscript number in place of the span text.  Footnotes are
+    /// numbered from 1 in reading order across the whole document.
+    pub fn footnote(text: impl Into<String>) -> Self {
+        Self {
+            footnote: Some(text.into()),
+            ..Self::default()
+        }
+    }
+
     /// Returns the raw text contained in this span.
     pub fn text(&self) -> &str {
         &self.text
@@ -55,11 +148,61 @@ impl Span {
         self.color
     }
 
+    /// Returns the opacity of the span color from `0` (invisible) to `255` (opaque, the default).
+    pub fn alpha(&self) -> u8 {
+        self.alpha.unwrap_or(u8::MAX)
+    }
+
     /// Returns whether the span is marked as underlined.
     pub fn is_underlined(&self) -> bool {
         self.underline
     }
 
+    /// Returns the stroke pattern of the underline.
+    pub fn underline_style(&self) -> UnderlineStyle {
+        self.underline_style
+    }
+
+    /// Returns the thickness of the underline in points, if set.
+    pub fn underline_thickness(&self) -> Option<f64> {
+        self.underline_thickness.map(f64::from)
+    }
+
+    /// Returns whether the span is marked as struck through.
+    pub fn is_strikethrough(&self) -> bool {
+        self.strikethrough
+    }
+
+    /// Returns the font size in points requested for the span, if any.
+    pub fn font_size(&self) -> Option<u8> {
+        self.font_size
+    }
+
+    /// Returns the hyperlink target of the span, if any.
+    pub fn link(&self) -> Option<&str> {
+        self.link.as_deref()
+    }
+
+    /// Returns the identifier of the section the span links to, if any.
+    pub fn internal_link(&self) -> Option<&str> {
+        self.internal_link.as_deref()
+    }
+
+    /// Returns the note text when the span is a footnote marker.
+    pub fn footnote_text(&self) -> Option<&str> {
+        self.footnote.as_deref()
+    }
+
+    /// Returns whether the span is printed as superscript, subscript, or on the baseline.
+    pub fn vertical_offset(&self) -> VerticalOffset {
+        self.vertical_offset
+    }
+
+    /// Returns the extra space in points added after every character, `0.0` by default.
+    pub fn letter_spacing(&self) -> f64 {
+        self.letter_spacing.map_or(0.0, f64::from)
+    }
+
     /// Sets the bold flag and returns the updated span.
     pub fn with_bold(mut self, bold: bool) -> Self {
         self.bold = bold;
@@ -78,12 +221,83 @@ impl Span {
         self
     }
 
+    /// Underlines the span with the given stroke pattern and returns the updated span.
+    pub fn with_underline_style(mut self, style: UnderlineStyle) -> Self {
+        self.underline = true;
+        self.underline_style = style;
+        self
+    }
+
+    /// Sets the thickness of the underline strokes in points and returns the updated span.
+    ///
+    /// Spans without a thickness use the hairline of the plain underline.  The thickness is kept
+    /// to a thousandth of a point.
+    pub fn with_underline_thickness(mut self, points: Option<f64>) -> Self {
+        self.underline_thickness = points.map(MilliPoints::from);
+        self
+    }
+
+    /// Sets the strikethrough flag and returns the updated span.
+    pub fn with_strikethrough(mut self, strikethrough: bool) -> Self {
+        self.strikethrough = strikethrough;
+        self
+    }
+
     /// Sets the span color and returns the updated span.
     pub fn with_color(mut self, color: Option<Color>) -> Self {
         self.color = color;
         self
     }
 
+    /// Sets the opacity of the span color and returns the updated span; `None` draws it opaque.
+    pub fn with_alpha(mut self, alpha: Option<u8>) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sets a translucent span color, e.g. for highlighted or de-emphasised text, and returns the
+    /// updated span.
+    ///
+    /// With the `transparency` feature the text is drawn at the given opacity; otherwise the color
+    /// is blended towards white, which only matches true transparency on white pages.
+    pub fn with_color_alpha(self, color: Color, alpha: u8) -> Self {
+        self.with_color(Some(color)).with_alpha(Some(alpha))
+    }
+
+    /// Sets the font size in points and returns the updated span.
+    ///
+    /// Spans without a size inherit the size of the surrounding element.
+    pub fn with_font_size(mut self, font_size: Option<u8>) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// Sets the hyperlink target and returns the updated span.
+    pub fn with_link(mut self, link: Option<String>) -> Self {
+        self.link = link;
+        self
+    }
+
+    /// Sets the vertical offset and returns the updated span.
+    ///
+    /// Raised and lowered spans are printed in a smaller font by the custom text elements without
+    /// changing the height of the line they appear on.
+    pub fn with_vertical_offset(mut self, offset: VerticalOffset) -> Self {
+        self.vertical_offset = offset;
+        self
+    }
+
+    /// Adds `points` of space after every character, like the PDF `Tc` operator, and returns the
+    /// updated span; negative values tighten the text.
+    ///
+    /// Tracking is applied by the custom text elements, which measure the extra space so aligned
+    /// and wrapped lines stay in place.  Headings and paragraphs holding tracked spans are laid
+    /// out by those elements.  The spacing is kept to a thousandth of a point.
+    pub fn with_letter_spacing(mut self, points: f64) -> Self {
+        self.letter_spacing = MilliPoints::nonzero(points);
+        self
+    }
+
     /// Convenience shorthand that marks the span as bold.
     pub fn bold(self) -> Self {
         self.with_bold(true)
@@ -99,11 +313,51 @@ impl Span {
         self.with_underline(true)
     }
 
+    /// Convenience shorthand that marks the span as struck through.
+    pub fn strikethrough(self) -> Self {
+        self.with_strikethrough(true)
+    }
+
     /// Convenience shorthand that assigns a color to the span.
     pub fn colored(self, color: Color) -> Self {
         self.with_color(Some(color))
     }
 
+    /// Convenience shorthand that sets the font size of the span in points.
+    pub fn sized(self, font_size: u8) -> Self {
+        self.with_font_size(Some(font_size))
+    }
+
+    /// Links the span to the section whose identifier is `identifier` and returns the updated span.
+    ///
+    /// The link jumps to the page the section starts on.  Rendering fails when no section carries
+    /// the identifier.
+    pub fn with_internal_link(mut self, identifier: Option<String>) -> Self {
+        self.internal_link = identifier;
+        self
+    }
+
+    /// Convenience shorthand that links the span to the section set up with
+    /// [`Section::with_identifier`](crate::model::Section::with_identifier).
+    pub fn linked_to_section(self, identifier: impl Into<String>) -> Self {
+        self.with_internal_link(Some(identifier.into()))
+    }
+
+    /// Convenience shorthand that turns the span into a hyperlink to `target`.
+    pub fn linked(self, target: impl Into<String>) -> Self {
+        self.with_link(Some(target.into()))
+    }
+
+    /// Convenience shorthand that raises the span above the baseline.
+    pub fn superscript(self) -> Self {
+        self.with_vertical_offset(VerticalOffset::Super)
+    }
+
+    /// Convenience shorthand that lowers the span below the baseline.
+    pub fn subscript(self) -> Self {
+        self.with_vertical_offset(VerticalOffset::Sub)
+    }
+
     /// Builds a [`Style`] representation for the span.
     fn to_style(&self) -> Style {
         let mut style = Style::new();
@@ -116,17 +370,40 @@ impl Span {
         if self.italic {
             style.set_italic();
         }
+        if let Some(font_size) = self.font_size {
+            style.set_font_size(font_size);
+        }
         style
     }
 
-    /// Converts the span to a [`StyledString`] while ignoring the underline attribute.
+    /// Converts the span to a [`StyledString`] while ignoring the underline and strikethrough
+    /// attributes.
     ///
-    /// The underline information is intentionally dropped at this layer.  Consumers that need to
-    /// render underline effects should use [`StyledSpan`] so that the flag is preserved for the
-    /// element layer.
+    /// The decoration flags are intentionally dropped at this layer.  Consumers that need to render
+    /// them should use [`StyledSpan`] so that the flags are preserved for the element layer.
     pub fn to_styled_string(&self) -> StyledString {
         StyledString::new(self.text.clone(), self.to_style())
     }
+
+    /// Returns whether both spans render their text identically, including decorations and the
+    /// link target.  Footnote markers never match another span.
+    pub fn has_same_style(&self, other: &Span) -> bool {
+        self.bold == other.bold
+            && self.italic == other.italic
+            && self.color == other.color
+            && self.alpha() == other.alpha()
+            && self.underline == other.underline
+            && self.underline_style == other.underline_style
+            && self.underline_thickness == other.underline_thickness
+            && self.strikethrough == other.strikethrough
+            && self.font_size == other.font_size
+            && self.link == other.link
+            && self.internal_link == other.internal_link
+            && self.vertical_offset == other.vertical_offset
+            && self.letter_spacing == other.letter_spacing
+            && self.footnote.is_none()
+            && other.footnote.is_none()
+    }
 }
 
 impl From<&Span> for StyledString {
@@ -141,25 +418,116 @@ impl From<Span> for StyledString {
     }
 }
 
-/// A styled span ready to be consumed by `genpdf` elements together with the underline flag.
+/// A styled span ready to be consumed by `genpdf` elements together with its decorations,
+/// hyperlink target, page link, vertical offset, footnote number, and spacing.
 #[derive(Clone, Debug)]
 pub struct StyledSpan {
     /// The styled text fragment.
     pub string: StyledString,
     /// Whether the fragment should be rendered with an underline.
     pub underline: bool,
+    /// Stroke pattern of the underline.
+    pub underline_style: UnderlineStyle,
+    /// Thickness of the underline strokes; `None` keeps the default hairline.
+    pub underline_thickness: Option<Mm>,
+    /// Whether the fragment should be rendered with a stroke through its middle.
+    pub strikethrough: bool,
+    /// Hyperlink target opened when the fragment is clicked.
+    pub link: Option<String>,
+    /// One-based page of the same document shown when the fragment is clicked.
+    pub page_link: Option<usize>,
+    /// Whether the fragment is raised or lowered relative to the baseline.
+    pub vertical_offset: VerticalOffset,
+    /// Number of the footnote whose marker this fragment prints.
+    pub footnote: Option<usize>,
+    /// Extra space added after every character.
+    pub letter_spacing: Mm,
+    /// Extra space added to every space character.
+    pub word_spacing: Mm,
 }
 
 impl StyledSpan {
-    /// Creates a new styled span.
+    /// Creates a new styled span on the baseline without strikethrough or a hyperlink.
     pub fn new(string: StyledString, underline: bool) -> Self {
-        Self { string, underline }
+        Self {
+            string,
+            underline,
+            underline_style: UnderlineStyle::Single,
+            underline_thickness: None,
+            strikethrough: false,
+            link: None,
+            page_link: None,
+            vertical_offset: VerticalOffset::Normal,
+            footnote: None,
+            letter_spacing: Mm::default(),
+            word_spacing: Mm::default(),
+        }
+    }
+
+    /// Sets the stroke pattern of the underline and returns the updated span.
+    pub fn with_underline_style(mut self, style: UnderlineStyle) -> Self {
+        self.underline_style = style;
+        self
+    }
+
+    /// Sets the thickness of the underline strokes and returns the updated span.
+    pub fn with_underline_thickness(mut self, thickness: Option<Mm>) -> Self {
+        self.underline_thickness = thickness;
+        self
+    }
+
+    /// Sets the strikethrough flag and returns the updated span.
+    pub fn with_strikethrough(mut self, strikethrough: bool) -> Self {
+        self.strikethrough = strikethrough;
+        self
+    }
+
+    /// Sets the hyperlink target and returns the updated span.
+    pub fn with_link(mut self, link: Option<String>) -> Self {
+        self.link = link;
+        self
+    }
+
+    /// Sets the page the fragment links to and returns the updated span.
+    pub fn with_page_link(mut self, page: Option<usize>) -> Self {
+        self.page_link = page;
+        self
+    }
+
+    /// Sets the vertical offset and returns the updated span.
+    pub fn with_vertical_offset(mut self, offset: VerticalOffset) -> Self {
+        self.vertical_offset = offset;
+        self
+    }
+
+    /// Sets the footnote number and returns the updated span.
+    pub fn with_footnote(mut self, number: Option<usize>) -> Self {
+        self.footnote = number;
+        self
+    }
+
+    /// Sets the space added after every character and returns the updated span.
+    pub fn with_letter_spacing(mut self, spacing: Mm) -> Self {
+        self.letter_spacing = spacing;
+        self
+    }
+
+    /// Sets the space added to every space character and returns the updated span.
+    pub fn with_word_spacing(mut self, spacing: Mm) -> Self {
+        self.word_spacing = spacing;
+        self
     }
 }
 
 impl From<&Span> for StyledSpan {
     fn from(span: &Span) -> Self {
         StyledSpan::new(span.to_styled_string(), span.underline)
+            .with_underline_style(span.underline_style)
+            .with_underline_thickness(span.underline_thickness().map(points_to_mm))
+            .with_strikethrough(span.strikethrough)
+            .with_link(span.link.clone())
+            .with_vertical_offset(span.vertical_offset)
+            .with_letter_spacing(points_to_mm(span.letter_spacing()))
     }
 }
 
@@ -169,7 +537,29 @@ impl From<Span> for StyledSpan {
     }
 }
 
-/// Converts a sequence of [`Span`] values into styled strings while keeping underline flags.
+/// Merges consecutive spans that share the same style into one span and drops empty spans.
+///
+/// Programmatically built paragraphs often consist of many small spans; merging them keeps the
+/// PDF content stream small.  Linked spans only merge when their targets match.  Footnote markers
+/// are kept as they are.
+pub fn coalesce_spans<I>(spans: I) -> Vec<Span>
+where
+    I: IntoIterator<Item = Span>,
+{
+    let mut merged: Vec<Span> = Vec::new();
+    for span in spans {
+        if span.text.is_empty() && span.footnote.is_none() {
+            continue;
+        }
+        match merged.last_mut() {
+            Some(previous) if previous.has_same_style(&span) => previous.text.push_str(&span.text),
+            _ => merged.push(span),
+        }
+    }
+    merged
+}
+
+/// Converts a sequence of [`Span`] values into styled strings while keeping decoration flags.
 pub fn spans_to_styled_strings<'a, I>(spans: I) -> Vec<StyledSpan>
 where
     I: IntoIterator<Item = &'a Span>,
@@ -185,7 +575,7 @@ pub struct ParseError {
 }
 
 impl ParseError {
-    fn new(index: usize, message: impl Into<String>) -> Self {
+    pub(crate) fn new(index: usize, message: impl Into<String>) -> Self {
         Self {
             index,
             message: message.into(),
@@ -201,6 +591,12 @@ impl ParseError {
     pub fn message(&self) -> &str {
         &self.message
     }
+
+    /// Shifts the index by `offset`, for errors in markup taken from a larger input.
+    pub(crate) fn offset_by(mut self, offset: usize) -> Self {
+        self.index += offset;
+        self
+    }
 }
 
 impl fmt::Display for ParseError {
@@ -217,6 +613,9 @@ struct StyleState {
     italic: bool,
     color: Option<Color>,
     underline: bool,
+    strikethrough: bool,
+    font_size: Option<u8>,
+    vertical_offset: VerticalOffset,
 }
 
 impl StyleState {
@@ -226,16 +625,33 @@ impl StyleState {
             bold: self.bold,
             italic: self.italic,
             color: self.color,
+            alpha: None,
             underline: self.underline,
+            underline_style: UnderlineStyle::Single,
+            underline_thickness: None,
+            strikethrough: self.strikethrough,
+            font_size: self.font_size,
+            link: None,
+            internal_link: None,
+            vertical_offset: self.vertical_offset,
+            footnote: None,
+            letter_spacing: None,
         }
     }
 }
 
+/// Characters that can be printed literally by prefixing them with a backslash.
+const ESCAPABLE: &[char] = &['*', '[', ']', '{', '}', '~', '^', '_', '\\'];
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Marker {
     Bold,
     Italic,
+    Strikethrough,
     Color,
+    Size,
+    Super,
+    Sub,
 }
 
 impl Marker {
@@ -243,7 +659,8 @@ impl Marker {
         match self {
             Marker::Bold => "**",
             Marker::Italic => "*",
-            Marker::Color => "}",
+            Marker::Strikethrough => "~~",
+            Marker::Color | Marker::Size | Marker::Super | Marker::Sub => "}",
         }
     }
 
@@ -251,7 +668,11 @@ impl Marker {
         match self {
             Marker::Bold => "bold span",
             Marker::Italic => "italic span",
+            Marker::Strikethrough => "strikethrough span",
             Marker::Color => "color span",
+            Marker::Size => "size span",
+            Marker::Super => "superscript span",
+            Marker::Sub => "subscript span",
         }
     }
 }
@@ -262,12 +683,31 @@ impl Marker {
 ///
 /// - `**bold**` for bold text
 /// - `*italic*` for italic text
+/// - `~~struck~~` for struck-through text
 /// - `[color=#RRGGBB]{text}` for colored text, where `RRGGBB` is a hexadecimal RGB value
+/// - `[color=name]{text}` for colored text using a CSS color name such as `red`, `navy`, or `gray`
+/// - `[size=14]{text}` for text set in the given font size in points
+/// - `^{text}` for superscript and `_{text}` for subscript text
+///
+/// A backslash prints the following marker character literally: `\*`, `\[`, `\]`, `\{`, `\}`,
+/// `\~`, `\^`, `\_`, and `\\` for the backslash itself.  A backslash before any other character
+/// is kept as is, while a backslash ending the input is an error.
 ///
 /// The parser performs strict validation and returns [`ParseError`] with positional information for
 /// malformed inputs.  The underline flag is not exposed through this syntax, but callers may set it
 /// on the returned spans if required.
 pub fn parse_markup(input: &str) -> Result<Vec<Span>, ParseError> {
+    let spans = parse_markup_spanned(input)?;
+    Ok(spans.into_iter().map(|(span, _)| span).collect())
+}
+
+/// Parses markup like [`parse_markup`] and pairs every span with the byte range of `input` its
+/// text was read from.
+///
+/// The range covers the source of the text only, without the surrounding markers, but includes
+/// the backslashes of escaped characters.  Editors can use the ranges to map rendered text back
+/// to the markup, e.g. to keep a cursor in sync with a preview.
+pub fn parse_markup_spanned(input: &str) -> Result<Vec<(Span, Range<usize>)>, ParseError> {
     let (spans, idx) = parse_inner(input, 0, StyleState::default(), None)?;
     debug_assert_eq!(idx, input.len());
     Ok(spans)
@@ -278,21 +718,46 @@ fn parse_inner(
     mut index: usize,
     state: StyleState,
     closing_marker: Option<Marker>,
-) -> Result<(Vec<Span>, usize), ParseError> {
+) -> Result<(Vec<(Span, Range<usize>)>, usize), ParseError> {
     let mut spans = Vec::new();
     let mut buffer = String::new();
+    let mut buffer_start = index;
 
     while index < input.len() {
+        if buffer.is_empty() {
+            buffer_start = index;
+        }
+
+        if input[index..].starts_with('\\') {
+            match input[index + 1..].chars().next() {
+                Some(escaped) if ESCAPABLE.contains(&escaped) => {
+                    buffer.push(escaped);
+                    index += 1 + escaped.len_utf8();
+                }
+                Some(_) => {
+                    buffer.push('\\');
+                    index += 1;
+                }
+                None => {
+                    return Err(ParseError::new(
+                        index,
+                        "trailing backslash; write `\\\\` for a literal backslash",
+                    ));
+                }
+            }
+            continue;
+        }
+
         if let Some(marker) = closing_marker {
             if input[index..].starts_with(marker.closing_token()) {
-                flush_buffer(&mut buffer, &mut spans, state);
+                flush_buffer(&mut buffer, &mut spans, state, buffer_start..index);
                 index += marker.closing_token().len();
                 return Ok((spans, index));
             }
         }
 
         if input[index..].starts_with("**") {
-            flush_buffer(&mut buffer, &mut spans, state);
+            flush_buffer(&mut buffer, &mut spans, state, buffer_start..index);
             index += 2;
             let mut nested_state = state;
             nested_state.bold = true;
@@ -303,7 +768,7 @@ fn parse_inner(
         }
 
         if input[index..].starts_with('*') {
-            flush_buffer(&mut buffer, &mut spans, state);
+            flush_buffer(&mut buffer, &mut spans, state, buffer_start..index);
             index += 1;
             let mut nested_state = state;
             nested_state.italic = true;
@@ -314,9 +779,21 @@ fn parse_inner(
             continue;
         }
 
+        if input[index..].starts_with("~~") {
+            flush_buffer(&mut buffer, &mut spans, state, buffer_start..index);
+            index += 2;
+            let mut nested_state = state;
+            nested_state.strikethrough = true;
+            let (nested, new_index) =
+                parse_inner(input, index, nested_state, Some(Marker::Strikethrough))?;
+            spans.extend(nested);
+            index = new_index;
+            continue;
+        }
+
         if input[index..].starts_with("[color=") {
             let (color, after_directive) = parse_color_directive(input, index)?;
-            flush_buffer(&mut buffer, &mut spans, state);
+            flush_buffer(&mut buffer, &mut spans, state, buffer_start..index);
             let mut nested_state = state;
             nested_state.color = Some(color);
             index = after_directive;
@@ -326,10 +803,40 @@ fn parse_inner(
             continue;
         }
 
+        if input[index..].starts_with("[size=") {
+            let (font_size, after_directive) = parse_size_directive(input, index)?;
+            flush_buffer(&mut buffer, &mut spans, state, buffer_start..index);
+            let mut nested_state = state;
+            nested_state.font_size = Some(font_size);
+            index = after_directive;
+            let (nested, new_index) = parse_inner(input, index, nested_state, Some(Marker::Size))?;
+            spans.extend(nested);
+            index = new_index;
+            continue;
+        }
+
+        let script = if input[index..].starts_with("^{") {
+            Some((VerticalOffset::Super, Marker::Super))
+        } else if input[index..].starts_with("_{") {
+            Some((VerticalOffset::Sub, Marker::Sub))
+        } else {
+            None
+        };
+        if let Some((offset, marker)) = script {
+            flush_buffer(&mut buffer, &mut spans, state, buffer_start..index);
+            let mut nested_state = state;
+            nested_state.vertical_offset = offset;
+            index += 2;
+            let (nested, new_index) = parse_inner(input, index, nested_state, Some(marker))?;
+            spans.extend(nested);
+            index = new_index;
+            continue;
+        }
+
         if input[index..].starts_with('}') {
             return Err(ParseError::new(
                 index,
-                "unexpected closing token `}` without matching opening `[color=...]`",
+                "unexpected closing token `}` without matching opening `[color=...]`, `[size=...]`, `^{`, or `_{`",
             ));
         }
 
@@ -340,7 +847,7 @@ fn parse_inner(
         if input[index..].starts_with('[') {
             return Err(ParseError::new(
                 index,
-                "unsupported directive; expected `[color=#RRGGBB]{...}`",
+                "unsupported directive; expected `[color=#RRGGBB]{...}` or `[size=N]{...}`",
             ));
         }
 
@@ -358,26 +865,62 @@ fn parse_inner(
             format!("unterminated {}", marker.description()),
         ))
     } else {
-        flush_buffer(&mut buffer, &mut spans, state);
+        flush_buffer(&mut buffer, &mut spans, state, buffer_start..index);
         Ok((spans, index))
     }
 }
 
-fn flush_buffer(buffer: &mut String, spans: &mut Vec<Span>, state: StyleState) {
+fn flush_buffer(
+    buffer: &mut String,
+    spans: &mut Vec<(Span, Range<usize>)>,
+    state: StyleState,
+    range: Range<usize>,
+) {
     if buffer.is_empty() {
         return;
     }
-    spans.push(state.to_span(std::mem::take(buffer)));
+    spans.push((state.to_span(std::mem::take(buffer)), range));
+}
+
+/// CSS color names accepted by `[color=name]` directives.
+const NAMED_COLORS: &[(&str, (u8, u8, u8))] = &[
+    ("black", (0x00, 0x00, 0x00)),
+    ("white", (0xFF, 0xFF, 0xFF)),
+    ("gray", (0x80, 0x80, 0x80)),
+    ("grey", (0x80, 0x80, 0x80)),
+    ("silver", (0xC0, 0xC0, 0xC0)),
+    ("red", (0xFF, 0x00, 0x00)),
+    ("maroon", (0x80, 0x00, 0x00)),
+    ("orange", (0xFF, 0xA5, 0x00)),
+    ("yellow", (0xFF, 0xFF, 0x00)),
+    ("olive", (0x80, 0x80, 0x00)),
+    ("lime", (0x00, 0xFF, 0x00)),
+    ("green", (0x00, 0x80, 0x00)),
+    ("teal", (0x00, 0x80, 0x80)),
+    ("cyan", (0x00, 0xFF, 0xFF)),
+    ("aqua", (0x00, 0xFF, 0xFF)),
+    ("blue", (0x00, 0x00, 0xFF)),
+    ("navy", (0x00, 0x00, 0x80)),
+    ("purple", (0x80, 0x00, 0x80)),
+    ("magenta", (0xFF, 0x00, 0xFF)),
+    ("fuchsia", (0xFF, 0x00, 0xFF)),
+    ("pink", (0xFF, 0xC0, 0xCB)),
+    ("brown", (0xA5, 0x2A, 0x2A)),
+];
+
+/// Resolves a CSS color name, ignoring ASCII case.
+fn named_color(name: &str) -> Option<Color> {
+    NAMED_COLORS
+        .iter()
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(name))
+        .map(|&(_, (r, g, b))| Color::Rgb(r, g, b))
 }
 
 fn parse_color_directive(input: &str, index: usize) -> Result<(Color, usize), ParseError> {
     const PREFIX: &str = "[color=";
     let start_hex = index + PREFIX.len();
     if !input[start_hex..].starts_with('#') {
-        return Err(ParseError::new(
-            start_hex,
-            "expected `#` followed by a hexadecimal RGB value",
-        ));
+        return parse_named_color_directive(input, start_hex);
     }
 
     let hex_start = start_hex + 1;
@@ -420,6 +963,87 @@ fn parse_color_directive(input: &str, index: usize) -> Result<(Color, usize), Pa
     Ok((Color::Rgb(r, g, b), brace_index + 1))
 }
 
+fn parse_named_color_directive(
+    input: &str,
+    name_start: usize,
+) -> Result<(Color, usize), ParseError> {
+    let name_len = input[name_start..]
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(input.len() - name_start);
+    if name_len == 0 {
+        return Err(ParseError::new(
+            name_start,
+            "expected a color name or `#` followed by a hexadecimal RGB value",
+        ));
+    }
+
+    let name_end = name_start + name_len;
+    let name = &input[name_start..name_end];
+    let color = named_color(name).ok_or_else(|| {
+        ParseError::new(
+            name_start,
+            format!("unrecognized color name `{name}`; use a CSS color name or `#RRGGBB`"),
+        )
+    })?;
+
+    if !input[name_end..].starts_with(']') {
+        return Err(ParseError::new(
+            name_end,
+            "expected `]` to close color directive",
+        ));
+    }
+
+    let brace_index = name_end + 1;
+    if !input[brace_index..].starts_with('{') {
+        return Err(ParseError::new(
+            brace_index,
+            "expected `{` to start the colored text",
+        ));
+    }
+
+    Ok((color, brace_index + 1))
+}
+
+fn parse_size_directive(input: &str, index: usize) -> Result<(u8, usize), ParseError> {
+    const PREFIX: &str = "[size=";
+    let digits_start = index + PREFIX.len();
+    let digits_len = input[digits_start..]
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len() - digits_start);
+    if digits_len == 0 {
+        return Err(ParseError::new(
+            digits_start,
+            "expected a font size in points",
+        ));
+    }
+
+    let digits_end = digits_start + digits_len;
+    let font_size = input[digits_start..digits_end]
+        .parse::<u8>()
+        .ok()
+        .filter(|size| *size > 0)
+        .ok_or_else(|| {
+            ParseError::new(digits_start, "font size must be between 1 and 255 points")
+        })?;
+
+    if !input[digits_end..].starts_with(']') {
+        return Err(ParseError::new(
+            digits_end,
+            "expected `]` to close size directive",
+        ));
+    }
+
+    let brace_index = digits_end + 1;
+    if !input[brace_index..].starts_with('{') {
+        return Err(ParseError::new(
+            brace_index,
+            "expected `{` to start the sized text",
+        ));
+    }
+
+    Ok((font_size, brace_index + 1))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -488,4 +1112,207 @@ mod tests {
         let err = parse_markup("[color=#12FG34]{x}").unwrap_err();
         assert!(err.message().contains("invalid RGB"));
     }
+
+    #[test]
+    fn styled_span_keeps_link_target() {
+        let span = Span::new("Reports").linked("https://intranet.example.com/reports");
+        let styled = StyledSpan::from(&span);
+
+        assert_eq!(span.link(), Some("https://intranet.example.com/reports"));
+        assert_eq!(
+            styled.link.as_deref(),
+            Some("https://intranet.example.com/reports")
+        );
+    }
+
+    #[test]
+    fn parse_strikethrough() {
+        let spans = parse_markup("Was ~~**10**~~ now 12").expect("parse succeeds");
+        assert_eq!(spans.len(), 3);
+        assert!(spans[1].is_strikethrough());
+        assert!(spans[1].is_bold());
+        assert!(!spans[2].is_strikethrough());
+        assert!(StyledSpan::from(&spans[1]).strikethrough);
+
+        let err = parse_markup("~~open").unwrap_err();
+        assert!(err.message().contains("unterminated strikethrough"));
+        assert_eq!(err.index(), 6);
+    }
+
+    #[test]
+    fn color_directives_accept_css_color_names() {
+        let spans = parse_markup("[color=Orange]{Warm} text").expect("parse succeeds");
+        assert_eq!(spans[0].text(), "Warm");
+        assert_eq!(spans[0].color(), Some(Color::Rgb(0xFF, 0xA5, 0x00)));
+    }
+
+    #[test]
+    fn error_on_unknown_color_name() {
+        let err = parse_markup("Hi [color=reddish]{x}").unwrap_err();
+        assert_eq!(err.index(), 10);
+        assert!(err.message().contains("unrecognized color name `reddish`"));
+    }
+
+    #[test]
+    fn parse_size_directive() {
+        let spans = parse_markup("Note [size=8]{*fine* print}").expect("parse succeeds");
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[0].font_size(), None);
+        assert_eq!(spans[1].font_size(), Some(8));
+        assert!(spans[1].is_italic());
+        assert_eq!(spans[2].text(), " print");
+        assert_eq!(spans[2].to_styled_string().style.font_size(), 8);
+
+        let err = parse_markup("[size=0]{x}").unwrap_err();
+        assert_eq!(err.index(), 6);
+        assert!(err.message().contains("between 1 and 255"));
+    }
+
+    #[test]
+    fn parse_superscript_and_subscript() {
+        let spans = parse_markup("E = mc^{2} and H_{**2**}O").expect("parse succeeds");
+        assert_eq!(spans.len(), 5);
+        assert_eq!(spans[1].text(), "2");
+        assert_eq!(spans[1].vertical_offset(), VerticalOffset::Super);
+        assert_eq!(spans[3].text(), "2");
+        assert_eq!(spans[3].vertical_offset(), VerticalOffset::Sub);
+        assert!(spans[3].is_bold());
+        assert_eq!(spans[4].vertical_offset(), VerticalOffset::Normal);
+        assert_eq!(
+            StyledSpan::from(&spans[1]).vertical_offset,
+            VerticalOffset::Super
+        );
+
+        let plain = parse_markup("a_b ^ c").expect("parse succeeds");
+        assert_eq!(plain.len(), 1);
+
+        let err = parse_markup("x^{2").unwrap_err();
+        assert!(err.message().contains("unterminated superscript"));
+    }
+
+    #[test]
+    fn footnote_markers_survive_coalescing() {
+        let spans = coalesce_spans(vec![
+            Span::new("Revenue grew"),
+            Span::footnote("Annual report, p. 4"),
+            Span::footnote("Audited figures"),
+            Span::new("."),
+        ]);
+
+        assert_eq!(spans.len(), 4);
+        assert_eq!(spans[1].footnote_text(), Some("Annual report, p. 4"));
+        assert_eq!(spans[2].footnote_text(), Some("Audited figures"));
+        assert_eq!(spans[3].footnote_text(), None);
+    }
+
+    #[test]
+    fn translucent_spans_do_not_merge_with_opaque_ones() {
+        let red = Color::Rgb(200, 0, 0);
+        let spans = coalesce_spans(vec![
+            Span::new("solid ").colored(red),
+            Span::new("faint").with_color_alpha(red, 96),
+        ]);
+
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].alpha(), u8::MAX);
+        assert_eq!(spans[1].alpha(), 96);
+    }
+
+    #[test]
+    fn parse_escaped_markers() {
+        let spans =
+            parse_markup(r"\*not italic\* costs \[5\] \{x\} in C:\temp\\").expect("parse succeeds");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].text(), r"*not italic* costs [5] {x} in C:\temp\");
+        assert!(!spans[0].is_italic());
+
+        let spans = parse_markup(r"**a \*\* b**").expect("parse succeeds");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].text(), "a ** b");
+        assert!(spans[0].is_bold());
+    }
+
+    #[test]
+    fn error_on_trailing_backslash() {
+        let err = parse_markup(r"ends with \").unwrap_err();
+        assert_eq!(err.index(), 10);
+        assert!(err.message().contains("trailing backslash"));
+    }
+
+    #[test]
+    fn parse_markup_spanned_reports_source_ranges() {
+        let input = "Hi **bold** [color=red]{red \\*} ^{2}";
+        let spans = parse_markup_spanned(input).expect("parse succeeds");
+        let sources: Vec<_> = spans
+            .iter()
+            .map(|(span, range)| (span.text(), &input[range.clone()]))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                ("Hi ", "Hi "),
+                ("bold", "bold"),
+                (" ", " "),
+                ("red *", "red \\*"),
+                (" ", " "),
+                ("2", "2"),
+            ]
+        );
+        assert_eq!(
+            parse_markup(input).expect("parse succeeds"),
+            spans.into_iter().map(|(span, _)| span).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn letter_spacing_is_kept_in_points_and_converted_for_rendering() {
+        let span = Span::new("Tracked").with_letter_spacing(1.5);
+        assert_eq!(span.letter_spacing(), 1.5);
+        assert!(!span.has_same_style(&Span::new("Plain")));
+        assert_eq!(
+            Span::new("Plain").with_letter_spacing(0.0),
+            Span::new("Plain")
+        );
+
+        assert_eq!(
+            Span::new("Plain").with_letter_spacing(0.0001),
+            Span::new("Plain")
+        );
+        assert_eq!(
+            Span::new("Tracked")
+                .with_letter_spacing(0.1234)
+                .letter_spacing(),
+            0.123
+        );
+
+        let styled = StyledSpan::from(&span);
+        let spacing: printpdf::Mm = styled.letter_spacing.into();
+        assert!((spacing.0 - 1.5 * 25.4 / 72.0).abs() < 1e-9);
+        assert_eq!(styled.word_spacing, Mm::default());
+    }
+
+    #[test]
+    fn spans_stay_comparable_with_eq() {
+        fn assert_eq_impl<T: Eq>() {}
+        assert_eq_impl::<Span>();
+    }
+
+    #[test]
+    fn underline_style_and_thickness_reach_the_styled_span() {
+        let span = Span::new("Check")
+            .with_underline_style(UnderlineStyle::Wavy)
+            .with_underline_thickness(Some(1.0));
+        assert!(span.is_underlined());
+        assert!(!span.has_same_style(&Span::new("Check").underline()));
+
+        let styled = StyledSpan::from(&span);
+        assert!(styled.underline);
+        assert_eq!(styled.underline_style, UnderlineStyle::Wavy);
+        let thickness: printpdf::Mm = styled.underline_thickness.expect("thickness").into();
+        assert!((thickness.0 - 25.4 / 72.0).abs() < 1e-9);
+
+        let plain = StyledSpan::from(&Span::new("Plain").underline());
+        assert_eq!(plain.underline_style, UnderlineStyle::Single);
+        assert!(plain.underline_thickness.is_none());
+    }
 }
@@ -0,0 +1,88 @@
+//! `serde` adapters that store [`genpdf::style::Color`] values as `#RRGGBB` strings.
+//!
+//! Greyscale and CMYK colors are converted to their RGB equivalent when serialized, so they come
+//! back as [`Color::Rgb`] after a round trip.
+
+use genpdf::style::Color;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serializer};
+
+pub(crate) fn serialize<S>(color: &Color, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&to_hex(*color))
+}
+
+pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Color, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    parse_hex(&value).ok_or_else(|| D::Error::custom(format!("invalid color '{value}'")))
+}
+
+/// Adapter for optional colors, which are stored as `null` when absent.
+pub(crate) mod option {
+    use super::*;
+
+    pub(crate) fn serialize<S>(color: &Option<Color>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match color {
+            Some(color) => serializer.serialize_some(&to_hex(*color)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Option<Color>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|value| {
+                parse_hex(&value)
+                    .ok_or_else(|| D::Error::custom(format!("invalid color '{value}'")))
+            })
+            .transpose()
+    }
+}
+
+fn to_hex(color: Color) -> String {
+    let (r, g, b) = match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Greyscale(value) => (value, value, value),
+        Color::Cmyk(c, m, y, k) => {
+            let channel = |value: u8| {
+                let remaining = (255 - u16::from(value)) * (255 - u16::from(k));
+                (remaining / 255) as u8
+            };
+            (channel(c), channel(m), channel(y))
+        }
+    };
+    format!("#{r:02X}{g:02X}{b:02X}")
+}
+
+fn parse_hex(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+    Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_round_trip_through_hex() {
+        assert_eq!(to_hex(Color::Rgb(36, 92, 160)), "#245CA0");
+        assert_eq!(parse_hex("#245ca0"), Some(Color::Rgb(36, 92, 160)));
+        assert_eq!(to_hex(Color::Greyscale(200)), "#C8C8C8");
+        assert_eq!(parse_hex("245CA0"), None);
+        assert_eq!(parse_hex("#24"), None);
+    }
+}
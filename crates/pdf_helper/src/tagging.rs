@@ -0,0 +1,418 @@
+//! Logical structure for tagged PDF output.
+//!
+//! `genpdf` writes untagged page content, so [`apply_structure`] marks it up afterwards: every text
+//! object (`BT` … `ET`) and every saved graphics state (`q` … `Q`), which is how images are drawn,
+//! is matched against the placements the [`LayoutTracker`](crate::layout::LayoutTracker) recorded
+//! for the blocks of the content model.  Content inside a block is wrapped in marked content with
+//! an `MCID` and referenced from a structure element for the block; anything else, such as headers,
+//! footers, and watermarks, is marked as an artifact.  The structure tree holds a `/Document`
+//! element with one child per block in reading order.
+//!
+//! Untagged documents still describe images with alternate text: [`apply_alt_text`] wraps the
+//! drawing of each such image in a `/Span` marked-content sequence carrying an `/Alt` entry.
+
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+
+use crate::layout::{ElementKind, Rect, TaggedBlock};
+use crate::pdf_objects::{self, POINTS_PER_MM};
+
+/// Distance in millimetres a content anchor may lie outside the rectangle of a block.
+const ANCHOR_TOLERANCE_MM: f64 = 0.5;
+
+/// Returns the structure type of a block, or `None` for blocks whose children are tagged instead
+/// and for blocks without content of their own.
+pub(crate) fn structure_type(kind: ElementKind, level: usize) -> Option<String> {
+    let name = match kind {
+        ElementKind::Heading => return Some(format!("H{}", level.clamp(1, 6))),
+        ElementKind::CoverText | ElementKind::Paragraph => "P",
+        ElementKind::Image => "Figure",
+        ElementKind::Table => "Table",
+        ElementKind::List => "L",
+        ElementKind::Code => "Code",
+        ElementKind::TableOfContents => "TOC",
+        ElementKind::Legend | ElementKind::Custom => "Div",
+        ElementKind::Quote
+        | ElementKind::Row
+        | ElementKind::KeepTogether
+        | ElementKind::Rule
+        | ElementKind::Spacer => return None,
+    };
+    Some(name.to_string())
+}
+
+/// Adds a structure tree built from the placed `blocks` to the rendered document.
+pub(crate) fn apply_structure(
+    pdf_bytes: &[u8],
+    blocks: &[TaggedBlock],
+) -> Result<Vec<u8>, lopdf::Error> {
+    let mut document = Document::load_mem(pdf_bytes)?;
+    let pages: Vec<ObjectId> = document.get_pages().into_values().collect();
+
+    // Marked content of every block as (page index, MCID) pairs, and the block of every MCID by
+    // page for the parent tree.
+    let mut marked: Vec<Vec<(usize, i64)>> = vec![Vec::new(); blocks.len()];
+    let mut parents: Vec<Vec<usize>> = Vec::with_capacity(pages.len());
+    for (index, &page_id) in pages.iter().enumerate() {
+        let height = pdf_objects::page_height_points(&document, page_id)?;
+        let placed = page_placements(blocks, index + 1, |tagged| {
+            structure_type(tagged.kind, tagged.level).is_some()
+        });
+
+        let content = Content::decode(&document.get_page_content(page_id)?)?;
+        let mut page_parents = Vec::new();
+        let operations = mark_operations(content.operations, |group| {
+            let block = block_at(&placed, height, group);
+            let role =
+                block.and_then(|block| structure_type(blocks[block].kind, blocks[block].level));
+            let (Some(block), Some(role)) = (block, role) else {
+                return Some(Operation::new(
+                    "BMC",
+                    vec![Object::Name(b"Artifact".to_vec())],
+                ));
+            };
+            let mcid = page_parents.len() as i64;
+            page_parents.push(block);
+            marked[block].push((index, mcid));
+            let mut properties = Dictionary::new();
+            properties.set("MCID", Object::Integer(mcid));
+            Some(begin_marked_content(role, properties))
+        });
+        document.change_page_content(page_id, Content { operations }.encode()?)?;
+        document
+            .get_object_mut(page_id)?
+            .as_dict_mut()?
+            .set("StructParents", Object::Integer(index as i64));
+        parents.push(page_parents);
+    }
+
+    let root_id = document.new_object_id();
+    let document_id = document.new_object_id();
+    let mut element_ids = vec![None; blocks.len()];
+    let mut children = Vec::new();
+    for (block, tagged) in blocks.iter().enumerate() {
+        let (Some(role), Some(&(first_page, _))) = (
+            structure_type(tagged.kind, tagged.level),
+            marked[block].first(),
+        ) else {
+            continue;
+        };
+        let mut element = Dictionary::new();
+        element.set("Type", Object::Name(b"StructElem".to_vec()));
+        element.set("S", Object::Name(role.into_bytes()));
+        element.set("P", Object::Reference(document_id));
+        element.set("Pg", Object::Reference(pages[first_page]));
+        let references = marked[block]
+            .iter()
+            .map(|&(page, mcid)| {
+                let mut reference = Dictionary::new();
+                reference.set("Type", Object::Name(b"MCR".to_vec()));
+                reference.set("Pg", Object::Reference(pages[page]));
+                reference.set("MCID", Object::Integer(mcid));
+                Object::Dictionary(reference)
+            })
+            .collect();
+        element.set("K", Object::Array(references));
+        if let Some(alt_text) = &tagged.alt_text {
+            element.set("Alt", text_string(alt_text));
+        }
+        let id = document.add_object(element);
+        element_ids[block] = Some(id);
+        children.push(Object::Reference(id));
+    }
+
+    let mut document_element = Dictionary::new();
+    document_element.set("Type", Object::Name(b"StructElem".to_vec()));
+    document_element.set("S", Object::Name(b"Document".to_vec()));
+    document_element.set("P", Object::Reference(root_id));
+    document_element.set("K", Object::Array(children));
+    document
+        .objects
+        .insert(document_id, Object::Dictionary(document_element));
+
+    let mut numbers = Vec::with_capacity(parents.len() * 2);
+    for (index, page_parents) in parents.iter().enumerate() {
+        let elements = page_parents
+            .iter()
+            .map(|&block| element_ids[block].map_or(Object::Null, Object::Reference))
+            .collect();
+        numbers.push(Object::Integer(index as i64));
+        numbers.push(Object::Array(elements));
+    }
+    let mut parent_tree = Dictionary::new();
+    parent_tree.set("Nums", Object::Array(numbers));
+
+    let mut root = Dictionary::new();
+    root.set("Type", Object::Name(b"StructTreeRoot".to_vec()));
+    root.set("K", Object::Reference(document_id));
+    root.set("ParentTree", Object::Dictionary(parent_tree));
+    root.set("ParentTreeNextKey", Object::Integer(pages.len() as i64));
+    document.objects.insert(root_id, Object::Dictionary(root));
+
+    let mut mark_info = Dictionary::new();
+    mark_info.set("Marked", Object::Boolean(true));
+    let catalog_id = document.trailer.get(b"Root")?.as_reference()?;
+    let catalog = document.get_object_mut(catalog_id)?.as_dict_mut()?;
+    catalog.set("MarkInfo", Object::Dictionary(mark_info));
+    catalog.set("StructTreeRoot", Object::Reference(root_id));
+
+    let mut buffer = Vec::new();
+    document.save_to(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Wraps the drawing of every image with alternate text in a `/Span` carrying an `/Alt` entry.
+pub(crate) fn apply_alt_text(
+    pdf_bytes: &[u8],
+    blocks: &[TaggedBlock],
+) -> Result<Vec<u8>, lopdf::Error> {
+    let mut document = Document::load_mem(pdf_bytes)?;
+    let pages: Vec<ObjectId> = document.get_pages().into_values().collect();
+    for (index, &page_id) in pages.iter().enumerate() {
+        let placed = page_placements(blocks, index + 1, |tagged| {
+            tagged.kind == ElementKind::Image && tagged.alt_text.is_some()
+        });
+        if placed.is_empty() {
+            continue;
+        }
+
+        let height = pdf_objects::page_height_points(&document, page_id)?;
+        let content = Content::decode(&document.get_page_content(page_id)?)?;
+        let operations = mark_operations(content.operations, |group| {
+            if !group.iter().any(|operation| operation.operator == "Do") {
+                return None;
+            }
+            let alt_text = blocks[block_at(&placed, height, group)?]
+                .alt_text
+                .as_ref()?;
+            let mut properties = Dictionary::new();
+            properties.set("Alt", text_string(alt_text));
+            Some(begin_marked_content("Span".to_string(), properties))
+        });
+        document.change_page_content(page_id, Content { operations }.encode()?)?;
+    }
+
+    let mut buffer = Vec::new();
+    document.save_to(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Lists the rectangles of the `blocks` accepted by `filter` that were placed on the one-based
+/// `page`.
+fn page_placements(
+    blocks: &[TaggedBlock],
+    page: usize,
+    filter: impl Fn(&TaggedBlock) -> bool,
+) -> Vec<(usize, Rect)> {
+    blocks
+        .iter()
+        .enumerate()
+        .filter(|(_, tagged)| filter(tagged))
+        .flat_map(|(block, tagged)| {
+            tagged
+                .fragments
+                .iter()
+                .filter(move |(placed, _)| *placed == page)
+                .map(move |(_, rect)| (block, *rect))
+        })
+        .collect()
+}
+
+/// Returns the block whose placement holds the anchor of `group` on a page `height` points tall.
+fn block_at(placed: &[(usize, Rect)], height: f64, group: &[Operation]) -> Option<usize> {
+    let (x, y) = group_anchor(group)?;
+    let (x, y) = (x / POINTS_PER_MM, (height - y) / POINTS_PER_MM);
+    placed
+        .iter()
+        .find(|(_, rect)| contains(rect, x, y))
+        .map(|&(block, _)| block)
+}
+
+fn begin_marked_content(tag: String, properties: Dictionary) -> Operation {
+    Operation::new(
+        "BDC",
+        vec![
+            Object::Name(tag.into_bytes()),
+            Object::Dictionary(properties),
+        ],
+    )
+}
+
+/// Wraps text objects and saved graphics states of a content stream in marked content.
+///
+/// `tag` receives the operations of each group and returns the operator that opens its marked
+/// content, or `None` to leave the group as it is.
+fn mark_operations(
+    operations: Vec<Operation>,
+    mut tag: impl FnMut(&[Operation]) -> Option<Operation>,
+) -> Vec<Operation> {
+    let mut marked = Vec::with_capacity(operations.len());
+    let mut group: Vec<Operation> = Vec::new();
+    let mut depth = 0usize;
+    for operation in operations {
+        let operator = operation.operator.as_str();
+        let opens = operator == "BT" || operator == "q";
+        let closes = operator == "ET" || operator == "Q";
+        if depth == 0 && !opens {
+            marked.push(operation);
+            continue;
+        }
+        if opens {
+            depth += 1;
+        } else if closes {
+            depth = depth.saturating_sub(1);
+        }
+        group.push(operation);
+        if depth == 0 {
+            match tag(&group) {
+                Some(begin) => {
+                    marked.push(begin);
+                    marked.append(&mut group);
+                    marked.push(Operation::new("EMC", vec![]));
+                }
+                None => marked.append(&mut group),
+            }
+        }
+    }
+    marked.append(&mut group);
+    marked
+}
+
+/// Returns the position set by the first text positioning or transformation operator of a group.
+fn group_anchor(group: &[Operation]) -> Option<(f64, f64)> {
+    group.iter().find_map(|operation| {
+        let offset = match operation.operator.as_str() {
+            "Td" | "TD" => 0,
+            "Tm" | "cm" => 4,
+            _ => return None,
+        };
+        let number = |index: usize| match operation.operands.get(index) {
+            Some(Object::Integer(value)) => Some(*value as f64),
+            Some(Object::Real(value)) => Some(f64::from(*value)),
+            _ => None,
+        };
+        Some((number(offset)?, number(offset + 1)?))
+    })
+}
+
+fn contains(rect: &Rect, x: f64, y: f64) -> bool {
+    x >= rect.x - ANCHOR_TOLERANCE_MM
+        && x <= rect.x + rect.width + ANCHOR_TOLERANCE_MM
+        && y >= rect.y - ANCHOR_TOLERANCE_MM
+        && y <= rect.y + rect.height + ANCHOR_TOLERANCE_MM
+}
+
+/// Encodes `text` as a PDF text string, in UTF-16 when it is not plain ASCII.
+fn text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::String(text.as_bytes().to_vec(), StringFormat::Literal);
+    }
+    let mut bytes = vec![0xFE, 0xFF];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operation(operator: &str, operands: Vec<Object>) -> Operation {
+        Operation::new(operator, operands)
+    }
+
+    #[test]
+    fn text_objects_and_graphics_states_are_marked_by_anchor() {
+        let operations = vec![
+            operation("rg", vec![0.into(), 0.into(), 0.into()]),
+            operation("BT", vec![]),
+            operation("Td", vec![10.into(), 700.into()]),
+            operation("Tj", vec![Object::string_literal("Body")]),
+            operation("ET", vec![]),
+            operation("q", vec![]),
+            operation(
+                "cm",
+                vec![1.into(), 0.into(), 0.into(), 1.into(), 5.into(), 5.into()],
+            ),
+            operation("Do", vec![Object::Name(b"X0".to_vec())]),
+            operation("Q", vec![]),
+        ];
+        let mut anchors = Vec::new();
+        let marked = mark_operations(operations, |group| {
+            let anchor = group_anchor(group);
+            anchors.push(anchor);
+            (anchor == Some((10.0, 700.0))).then(|| Operation::new("BMC", vec![]))
+        });
+
+        assert_eq!(anchors, vec![Some((10.0, 700.0)), Some((5.0, 5.0))]);
+        let operators: Vec<&str> = marked.iter().map(|op| op.operator.as_str()).collect();
+        assert_eq!(
+            operators,
+            vec!["rg", "BMC", "BT", "Td", "Tj", "ET", "EMC", "q", "cm", "Do", "Q"]
+        );
+    }
+
+    #[test]
+    fn structure_types_follow_the_content_model() {
+        assert_eq!(
+            structure_type(ElementKind::Heading, 2).as_deref(),
+            Some("H2")
+        );
+        assert_eq!(
+            structure_type(ElementKind::Heading, 9).as_deref(),
+            Some("H6")
+        );
+        assert_eq!(
+            structure_type(ElementKind::Image, 0).as_deref(),
+            Some("Figure")
+        );
+        assert_eq!(structure_type(ElementKind::Quote, 0), None);
+    }
+
+    #[test]
+    fn non_ascii_text_is_encoded_as_utf16() {
+        assert!(matches!(
+            text_string("Chart"),
+            Object::String(bytes, StringFormat::Literal) if bytes == b"Chart"
+        ));
+        assert!(matches!(
+            text_string("\u{e9}"),
+            Object::String(bytes, StringFormat::Hexadecimal) if bytes == [0xFE, 0xFF, 0x00, 0xE9]
+        ));
+    }
+
+    #[test]
+    fn images_are_found_by_the_placement_of_their_drawing() {
+        let image = TaggedBlock {
+            kind: ElementKind::Image,
+            level: 0,
+            alt_text: Some("Chart".to_string()),
+            fragments: vec![(2, Rect::new(20.0, 30.0, 100.0, 50.0))],
+        };
+        let placed = page_placements(std::slice::from_ref(&image), 2, |_| true);
+        assert!(page_placements(std::slice::from_ref(&image), 1, |_| true).is_empty());
+
+        let drawing = |x: f64, y: f64| {
+            vec![
+                operation("q", vec![]),
+                operation(
+                    "cm",
+                    vec![
+                        1.into(),
+                        0.into(),
+                        0.into(),
+                        1.into(),
+                        Object::Real(x as f32),
+                        Object::Real(y as f32),
+                    ],
+                ),
+                operation("Do", vec![Object::Name(b"X0".to_vec())]),
+                operation("Q", vec![]),
+            ]
+        };
+        let height = 297.0 * POINTS_PER_MM;
+        let inside = drawing(30.0 * POINTS_PER_MM, height - 60.0 * POINTS_PER_MM);
+        let outside = drawing(30.0 * POINTS_PER_MM, height - 90.0 * POINTS_PER_MM);
+        assert_eq!(block_at(&placed, height, &inside), Some(0));
+        assert_eq!(block_at(&placed, height, &outside), None);
+    }
+}
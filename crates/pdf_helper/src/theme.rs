@@ -0,0 +1,240 @@
+//! Presets that bundle the look of a document for [`PdfBuilder::with_theme`].
+//!
+//! A [`Theme`] collects heading styles, the default paragraph alignment, the table of contents
+//! title, page margins, and the built-in header and footer.  Applying it sets all of them at once;
+//! builder calls made afterwards still override individual settings:
+//!
+//! ```no_run
+//! use pdf_helper::builder::PdfBuilder;
+//! use pdf_helper::theme::Theme;
+//!
+//! let builder = PdfBuilder::new()
+//!     .with_theme(Theme::corporate().with_toc_title("Overview"))
+//!     .show_header(false);
+//! # let _ = builder;
+//! ```
+
+use std::collections::BTreeMap;
+
+use genpdf::style::Color;
+
+use crate::builder::{FooterStyle, HeaderStyle, HeadingLevel, HeadingStyle, PdfBuilder};
+use crate::fonts::FontSource;
+use crate::model::HorizontalAlignment;
+
+/// Bundle of layout and typography settings applied by [`PdfBuilder::with_theme`].
+///
+/// The default theme matches the settings of [`PdfBuilder::new`].
+#[derive(Clone, Default)]
+pub struct Theme {
+    heading_styles: BTreeMap<HeadingLevel, HeadingStyle>,
+    default_alignment: HorizontalAlignment,
+    toc_title: Option<String>,
+    margins_mm: Option<[f64; 4]>,
+    show_header: bool,
+    show_footer: bool,
+    header_style: HeaderStyle,
+    footer_style: FooterStyle,
+    font_source: Option<FontSource>,
+}
+
+impl Theme {
+    /// Navy headings, a running document title, and "Page 3 of 12" footers for business reports.
+    pub fn corporate() -> Self {
+        let navy = Color::Rgb(0, 51, 102);
+        Self::default()
+            .with_heading_style(
+                HeadingLevel::CoverTitle,
+                HeadingStyle::new(30).with_color(navy).with_space_after(1.5),
+            )
+            .with_heading_style(
+                HeadingLevel::CoverSubtitle,
+                HeadingStyle::new(18).with_color(navy).with_space_after(1.0),
+            )
+            .with_heading_style(1, HeadingStyle::new(20).with_color(navy))
+            .with_heading_style(2, HeadingStyle::new(15).with_color(navy))
+            .with_heading_style(3, HeadingStyle::new(12).with_color(navy))
+            .with_toc_title("Contents")
+            .with_margins_mm(25.0, 20.0, 25.0, 20.0)
+            .show_header(true)
+            .show_footer(true)
+            .footer_style(FooterStyle::PageOfTotal)
+    }
+
+    /// Small grey headings with generous spacing, no header, and page numbers in the footer.
+    pub fn minimal() -> Self {
+        let grey = Color::Greyscale(64);
+        Self::default()
+            .with_heading_style(
+                HeadingLevel::CoverTitle,
+                HeadingStyle::new(24)
+                    .with_bold(false)
+                    .with_color(grey)
+                    .with_space_after(2.0),
+            )
+            .with_heading_style(
+                HeadingLevel::CoverSubtitle,
+                HeadingStyle::new(14)
+                    .with_bold(false)
+                    .with_color(grey)
+                    .with_space_after(1.5),
+            )
+            .with_heading_style(
+                1,
+                HeadingStyle::new(16).with_color(grey).with_space_after(1.0),
+            )
+            .with_heading_style(
+                2,
+                HeadingStyle::new(13).with_color(grey).with_space_after(1.0),
+            )
+            .with_heading_style(
+                3,
+                HeadingStyle::new(11).with_color(grey).with_space_after(1.0),
+            )
+            .with_toc_title("Contents")
+            .with_margins_mm(20.0, 20.0, 20.0, 20.0)
+            .show_footer(true)
+    }
+
+    /// Justified text, one-inch margins, and running section titles in the header, in the style of
+    /// a paper or thesis.
+    pub fn academic() -> Self {
+        Self::default()
+            .with_heading_style(
+                HeadingLevel::CoverTitle,
+                HeadingStyle::new(24).with_space_after(1.5),
+            )
+            .with_heading_style(
+                HeadingLevel::CoverSubtitle,
+                HeadingStyle::default_for(HeadingLevel::CoverSubtitle).with_font_size(16),
+            )
+            .with_heading_style(1, HeadingStyle::new(16).with_space_before(0.5))
+            .with_heading_style(2, HeadingStyle::new(13).with_space_before(0.5))
+            .with_heading_style(3, HeadingStyle::new(12).with_italic(true))
+            .with_default_alignment(HorizontalAlignment::Justified)
+            .with_toc_title("Table of Contents")
+            .with_margins_mm(25.4, 25.4, 25.4, 25.4)
+            .show_header(true)
+            .header_style(HeaderStyle::RunningHeading)
+            .show_footer(true)
+    }
+
+    /// Returns the configured style of the heading at `level`, if any.
+    pub fn heading_style(&self, level: impl Into<HeadingLevel>) -> Option<HeadingStyle> {
+        self.heading_styles.get(&level.into()).copied()
+    }
+
+    /// Returns the alignment of paragraphs that do not choose one themselves.
+    pub fn default_alignment(&self) -> HorizontalAlignment {
+        self.default_alignment
+    }
+
+    /// Returns the title of the table of contents, or `None` for the builder's default.
+    pub fn toc_title(&self) -> Option<&str> {
+        self.toc_title.as_deref()
+    }
+
+    /// Returns the top, right, bottom, and left margins in millimetres, if the theme sets them.
+    pub fn margins_mm(&self) -> Option<[f64; 4]> {
+        self.margins_mm
+    }
+
+    /// Sets the style of the heading at `level` and returns the updated theme.
+    pub fn with_heading_style(
+        mut self,
+        level: impl Into<HeadingLevel>,
+        style: HeadingStyle,
+    ) -> Self {
+        self.heading_styles.insert(level.into(), style);
+        self
+    }
+
+    /// Sets the alignment of paragraphs that do not choose one and returns the updated theme.
+    pub fn with_default_alignment(mut self, alignment: HorizontalAlignment) -> Self {
+        self.default_alignment = alignment;
+        self
+    }
+
+    /// Sets the title of the table of contents and returns the updated theme.
+    pub fn with_toc_title(mut self, title: impl Into<String>) -> Self {
+        self.toc_title = Some(title.into());
+        self
+    }
+
+    /// Sets the top, right, bottom, and left margins in millimetres and returns the updated theme.
+    pub fn with_margins_mm(mut self, top: f64, right: f64, bottom: f64, left: f64) -> Self {
+        self.margins_mm = Some([top, right, bottom, left]);
+        self
+    }
+
+    /// Controls whether the built-in header is printed and returns the updated theme.
+    pub fn show_header(mut self, show: bool) -> Self {
+        self.show_header = show;
+        self
+    }
+
+    /// Controls whether the built-in footer is printed and returns the updated theme.
+    pub fn show_footer(mut self, show: bool) -> Self {
+        self.show_footer = show;
+        self
+    }
+
+    /// Selects the text of the built-in header and returns the updated theme.
+    pub fn header_style(mut self, style: HeaderStyle) -> Self {
+        self.header_style = style;
+        self
+    }
+
+    /// Selects the text of the built-in footer and returns the updated theme.
+    pub fn footer_style(mut self, style: FooterStyle) -> Self {
+        self.footer_style = style;
+        self
+    }
+
+    /// Selects the font family and returns the updated theme; themes keep the builder's font
+    /// otherwise.
+    pub fn with_font_source(mut self, source: impl Into<FontSource>) -> Self {
+        self.font_source = Some(source.into());
+        self
+    }
+
+    /// Applies every setting of the theme to `builder`.
+    pub(crate) fn apply(self, mut builder: PdfBuilder) -> PdfBuilder {
+        for (level, style) in self.heading_styles {
+            builder = builder.with_heading_style(level, style);
+        }
+        if let Some([top, right, bottom, left]) = self.margins_mm {
+            builder = builder.with_margins_mm(top, right, bottom, left);
+        }
+        if let Some(source) = self.font_source {
+            builder = builder.with_font_source(source);
+        }
+        builder
+            .with_default_alignment(self.default_alignment)
+            .with_toc_title(self.toc_title)
+            .show_header(self.show_header)
+            .show_footer(self.show_footer)
+            .header_style(self.header_style)
+            .footer_style(self.footer_style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_configure_every_heading_level() {
+        for theme in [Theme::corporate(), Theme::minimal(), Theme::academic()] {
+            assert!(theme.heading_style(HeadingLevel::CoverTitle).is_some());
+            assert!(theme.heading_style(HeadingLevel::CoverSubtitle).is_some());
+            assert!((1..=3).all(|level| theme.heading_style(level).is_some()));
+            assert!(theme.margins_mm().is_some());
+        }
+        assert_eq!(
+            Theme::academic().default_alignment(),
+            HorizontalAlignment::Justified
+        );
+        assert_eq!(Theme::corporate().toc_title(), Some("Contents"));
+    }
+}
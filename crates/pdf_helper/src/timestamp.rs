@@ -0,0 +1,204 @@
+//! Deterministic document dates and identifiers.
+//!
+//! `printpdf` stamps every document with the current time and random identifiers, both in the
+//! document information dictionary and in the XMP metadata, so no two renders produce the same
+//! bytes.  [`fix_timestamp`] replaces them with a caller-supplied time and identifiers derived from
+//! that time and the page content.
+
+use std::time::SystemTime;
+
+use lopdf::{Document, Object, StringFormat};
+use md5::{Digest, Md5};
+
+use crate::calendar::{utc_fields, DateFields};
+
+/// Sets the creation and modification dates of the document information dictionary and the XMP
+/// metadata to `time`, and replaces the file and XMP identifiers with stable values.
+pub(crate) fn fix_timestamp(pdf_bytes: &[u8], time: SystemTime) -> Result<Vec<u8>, lopdf::Error> {
+    let mut document = Document::load_mem(pdf_bytes)?;
+    let fields = utc_fields(time);
+    let pdf_date = pdf_date(fields);
+    let identifier = document_identifier(&document, &pdf_date)?;
+
+    let info_id = document
+        .trailer
+        .get(b"Info")
+        .and_then(Object::as_reference)
+        .ok();
+    if let Some(info_id) = info_id {
+        let info = document.get_object_mut(info_id)?.as_dict_mut()?;
+        info.set("CreationDate", Object::string_literal(pdf_date.as_str()));
+        info.set("ModDate", Object::string_literal(pdf_date.as_str()));
+    }
+    let id = Object::String(identifier.to_vec(), StringFormat::Hexadecimal);
+    document
+        .trailer
+        .set("ID", Object::Array(vec![id.clone(), id]));
+
+    let catalog_id = document.trailer.get(b"Root")?.as_reference()?;
+    let metadata_id = document
+        .get_dictionary(catalog_id)?
+        .get(b"Metadata")
+        .and_then(Object::as_reference)
+        .ok();
+    if let Some(metadata_id) = metadata_id {
+        let stream = document.get_object_mut(metadata_id)?.as_stream_mut()?;
+        let content = stream
+            .decompressed_content()
+            .unwrap_or_else(|_| stream.content.clone());
+        let date = xmp_date(fields);
+        let uuid = format!("uuid:{}", uuid(&identifier));
+        let xml = replace_elements(
+            &String::from_utf8_lossy(&content),
+            &[
+                ("xmp:CreateDate", &date),
+                ("xmp:ModifyDate", &date),
+                ("xmp:MetadataDate", &date),
+                ("xmpMM:DocumentID", &uuid),
+                ("xmpMM:InstanceID", &uuid),
+                ("xmpMM:VersionID", "1"),
+            ],
+        );
+        stream.set_plain_content(xml.into_bytes());
+    }
+
+    let mut buffer = Vec::new();
+    document.save_to(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn pdf_date((year, month, day, hour, minute, second): DateFields) -> String {
+    format!("D:{year:04}{month:02}{day:02}{hour:02}{minute:02}{second:02}+00'00'")
+}
+
+fn xmp_date((year, month, day, hour, minute, second): DateFields) -> String {
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}+00:00")
+}
+
+/// Derives a 16-byte identifier from the date and the content of every page.
+///
+/// The PDF specification suggests MD5 for file identifiers, and unlike the standard library
+/// hashers its output is the same across Rust releases and platforms.
+fn document_identifier(document: &Document, date: &str) -> Result<[u8; 16], lopdf::Error> {
+    let mut hasher = Md5::new();
+    hasher.update(date.as_bytes());
+    for page_id in document.get_pages().into_values() {
+        let content = document.get_page_content(page_id)?;
+        // Length prefixes keep the boundaries between pages part of the identifier.
+        hasher.update((content.len() as u64).to_be_bytes());
+        hasher.update(&content);
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// Formats 16 bytes in the hyphenated form of a UUID.
+fn uuid(bytes: &[u8; 16]) -> String {
+    let hex = |range: std::ops::Range<usize>| -> String {
+        bytes[range]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    };
+    format!(
+        "{}-{}-{}-{}-{}",
+        hex(0..4),
+        hex(4..6),
+        hex(6..8),
+        hex(8..10),
+        hex(10..16)
+    )
+}
+
+/// Replaces the text of every `<tag>...</tag>` element named in `replacements`.
+fn replace_elements(xml: &str, replacements: &[(&str, &str)]) -> String {
+    let mut xml = xml.to_string();
+    for (tag, value) in replacements {
+        let open = format!("<{tag}>");
+        let close = format!("</{tag}>");
+        let mut offset = 0;
+        while let Some(start) = xml[offset..]
+            .find(&open)
+            .map(|index| offset + index + open.len())
+        {
+            let Some(end) = xml[start..].find(&close).map(|index| start + index) else {
+                break;
+            };
+            xml.replace_range(start..end, value);
+            offset = start + value.len() + close.len();
+        }
+    }
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    fn document_with_pages(contents: &[&str]) -> Document {
+        let mut document = Document::with_version("1.3");
+        let pages_id = document.new_object_id();
+        let mut kids = Vec::new();
+        for content in contents {
+            let content_id = document.add_object(lopdf::Stream::new(
+                lopdf::Dictionary::new(),
+                content.as_bytes().to_vec(),
+            ));
+            let mut page = lopdf::Dictionary::new();
+            page.set("Type", Object::Name("Page".into()));
+            page.set("Parent", Object::Reference(pages_id));
+            page.set("Contents", Object::Reference(content_id));
+            kids.push(Object::Reference(document.add_object(page)));
+        }
+        let mut pages = lopdf::Dictionary::new();
+        pages.set("Type", Object::Name("Pages".into()));
+        pages.set("Count", Object::Integer(kids.len() as i64));
+        pages.set("Kids", Object::Array(kids));
+        document.objects.insert(pages_id, Object::Dictionary(pages));
+        let mut catalog = lopdf::Dictionary::new();
+        catalog.set("Type", Object::Name("Catalog".into()));
+        catalog.set("Pages", Object::Reference(pages_id));
+        let catalog_id = document.add_object(catalog);
+        document.trailer.set("Root", Object::Reference(catalog_id));
+        document
+    }
+
+    #[test]
+    fn identifiers_are_stable_md5_digests_of_date_and_pages() {
+        let date = pdf_date(utc_fields(at(1_700_000_000)));
+        let identifier =
+            document_identifier(&document_with_pages(&["BT ET", "q Q"]), &date).expect("hashes");
+        assert_eq!(uuid(&identifier), "cac9324e-b618-65ed-ef49-3fb86591b2f6");
+
+        // Moving bytes from one page to the next changes the identifier.
+        let shifted =
+            document_identifier(&document_with_pages(&["BT", " ETq Q"]), &date).expect("hashes");
+        assert_ne!(identifier, shifted);
+        let later = pdf_date(utc_fields(at(1_700_000_001)));
+        let redated =
+            document_identifier(&document_with_pages(&["BT ET", "q Q"]), &later).expect("hashes");
+        assert_ne!(identifier, redated);
+    }
+
+    #[test]
+    fn dates_use_the_pdf_and_xmp_formats() {
+        let fields = utc_fields(at(1_700_000_000));
+        assert_eq!(pdf_date(fields), "D:20231114221320+00'00'");
+        assert_eq!(xmp_date(fields), "2023-11-14T22:13:20+00:00");
+    }
+
+    #[test]
+    fn xmp_elements_are_replaced_in_place() {
+        let xml = "<x><xmp:CreateDate>2020</xmp:CreateDate><xmpMM:VersionID>7</xmpMM:VersionID>\
+                   <xmp:CreateDate></xmp:CreateDate></x>";
+        assert_eq!(
+            replace_elements(xml, &[("xmp:CreateDate", "2024"), ("xmpMM:VersionID", "1")]),
+            "<x><xmp:CreateDate>2024</xmp:CreateDate><xmpMM:VersionID>1</xmpMM:VersionID>\
+             <xmp:CreateDate>2024</xmp:CreateDate></x>"
+        );
+    }
+}
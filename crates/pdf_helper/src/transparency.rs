@@ -0,0 +1,429 @@
+//! Translucent colors drawn through PDF graphics states.
+//!
+//! `genpdf` paints everything opaque and offers no access to graphics states, so translucent colors
+//! are rendered with a placeholder CMYK color whose cyan, magenta, and yellow components encode an
+//! index into [`TranslucentColors`] and whose black component is [`PLACEHOLDER_KEY`].  When a
+//! document may use the very same CMYK color on purpose, the final page is rendered a second time
+//! with [`ALTERNATE_KEY`] as the black component: only the operators that differ between the two
+//! renders are placeholders.  [`apply_transparency`] then rewrites the page content streams: every
+//! placeholder is replaced by the real color preceded by an `ExtGState` that sets the stroke (`CA`)
+//! and fill (`ca`) alpha, and the alpha is reset once the translucent text or stroke is painted.
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use genpdf::style::Color;
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+/// Black component of placeholder colors, in 1/255 steps.
+const PLACEHOLDER_KEY: u8 = 1;
+
+/// Black component of placeholder colors in the render that tells them apart from document colors.
+const ALTERNATE_KEY: u8 = 2;
+
+/// Operators that set a stroke or fill color.
+const COLOR_OPERATORS: [&str; 12] = [
+    "CS", "cs", "SC", "sc", "SCN", "scn", "G", "g", "RG", "rg", "K", "k",
+];
+
+/// Operators that end a text object or paint a path.
+const PAINT_OPERATORS: [&str; 11] = ["ET", "S", "s", "f", "F", "f*", "B", "B*", "b", "b*", "n"];
+
+/// Translucent colors used by a render, shared between its passes.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TranslucentColors(Arc<Mutex<Palette>>);
+
+#[derive(Debug, Default)]
+struct Palette {
+    colors: Vec<(Color, u8)>,
+    /// Black component of the placeholders handed out, `None` before the alternate render.
+    key: Option<u8>,
+    /// Set for single-pass renders, which cannot tell placeholders apart from document colors.
+    disabled: bool,
+}
+
+impl TranslucentColors {
+    /// Returns the placeholder color drawn in place of `color` at the given `alpha`, or `None`
+    /// once placeholders were disabled.
+    pub(crate) fn placeholder(&self, color: Color, alpha: u8) -> Option<Color> {
+        let mut palette = self.lock();
+        if palette.disabled {
+            return None;
+        }
+        let index = match palette
+            .colors
+            .iter()
+            .position(|entry| *entry == (color, alpha))
+        {
+            Some(index) => index,
+            None => {
+                palette.colors.push((color, alpha));
+                palette.colors.len() - 1
+            }
+        };
+        Some(Color::Cmyk(
+            (index >> 16) as u8,
+            (index >> 8) as u8,
+            index as u8,
+            palette.key.unwrap_or(PLACEHOLDER_KEY),
+        ))
+    }
+
+    /// Hands out placeholders keyed with [`ALTERNATE_KEY`] from now on, for the render passed to
+    /// [`apply_transparency`] as `alternate_bytes`.
+    pub(crate) fn use_alternate_key(&self) {
+        self.lock().key = Some(ALTERNATE_KEY);
+    }
+
+    /// Stops handing out placeholders, for renders that cannot be repeated.
+    pub(crate) fn disable(&self) {
+        self.lock().disabled = true;
+    }
+
+    /// Returns `true` when no translucent color was drawn.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.lock().colors.is_empty()
+    }
+
+    /// Lists the registered colors and alphas by placeholder index.
+    pub(crate) fn colors(&self) -> Vec<(Color, u8)> {
+        self.lock().colors.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Palette> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Returns `true` when `color` is keyed like a placeholder, so a document drawing it on purpose
+/// needs the alternate render of [`apply_transparency`].
+pub(crate) fn matches_placeholder_key(color: Color) -> bool {
+    matches!(color, Color::Cmyk(_, _, _, PLACEHOLDER_KEY))
+}
+
+/// Replaces the placeholder colors of [`TranslucentColors`] with the real colors drawn at their
+/// alpha.
+///
+/// `alternate_bytes` holds the same document rendered after
+/// [`TranslucentColors::use_alternate_key`]; a color operator is only treated as a placeholder when
+/// it changed between the two renders.  Without it every color keyed with [`PLACEHOLDER_KEY`] is a
+/// placeholder, which is only correct when the document draws no such color itself.
+pub(crate) fn apply_transparency(
+    pdf_bytes: &[u8],
+    alternate_bytes: Option<&[u8]>,
+    colors: &[(Color, u8)],
+) -> Result<Vec<u8>, lopdf::Error> {
+    let mut document = Document::load_mem(pdf_bytes)?;
+    let pages: Vec<ObjectId> = document.get_pages().into_values().collect();
+    let alternate = match alternate_bytes {
+        Some(bytes) => {
+            let alternate = Document::load_mem(bytes)?;
+            let alternate_pages: Vec<ObjectId> = alternate.get_pages().into_values().collect();
+            if pages.len() != alternate_pages.len() {
+                return Err(mismatched_renders());
+            }
+            Some((alternate, alternate_pages))
+        }
+        None => None,
+    };
+    for (position, page_id) in pages.into_iter().enumerate() {
+        let content = Content::decode(&document.get_page_content(page_id)?)?;
+        let alternate_operations = match &alternate {
+            Some((alternate, alternate_pages)) => {
+                let page_content = alternate.get_page_content(alternate_pages[position])?;
+                let operations = Content::decode(&page_content)?.operations;
+                if content.operations.len() != operations.len() {
+                    return Err(mismatched_renders());
+                }
+                Some(operations)
+            }
+            None => None,
+        };
+        let mut alphas = BTreeSet::new();
+        let operations = rewrite_operations(
+            content.operations,
+            alternate_operations.as_deref(),
+            colors,
+            &mut alphas,
+        );
+        if alphas.is_empty() {
+            continue;
+        }
+        document.change_page_content(page_id, Content { operations }.encode()?)?;
+        let states = graphics_states_mut(&mut document, page_id)?;
+        for alpha in alphas {
+            let value = f32::from(alpha) / 255.0;
+            let mut state = Dictionary::new();
+            state.set("Type", Object::Name(b"ExtGState".to_vec()));
+            state.set("CA", Object::Real(value));
+            state.set("ca", Object::Real(value));
+            states.set(state_name(alpha), Object::Dictionary(state));
+        }
+    }
+
+    let mut buffer = Vec::new();
+    document.save_to(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn mismatched_renders() -> lopdf::Error {
+    lopdf::Error::Invalid("the alternate render has a different page content".to_string())
+}
+
+/// Swaps placeholder colors for graphics state changes and the real colors, collecting the alphas
+/// whose graphics states the page needs.
+///
+/// `alternate` holds the operations of the same page in the alternate render, if there is one.
+fn rewrite_operations(
+    operations: Vec<Operation>,
+    alternate: Option<&[Operation]>,
+    colors: &[(Color, u8)],
+    alphas: &mut BTreeSet<u8>,
+) -> Vec<Operation> {
+    let mut rewritten = Vec::with_capacity(operations.len());
+    let mut translucent = false;
+    for (position, operation) in operations.into_iter().enumerate() {
+        let operator = operation.operator.as_str();
+        let stroke = operator == "K";
+        let placeholder = (stroke || operator == "k")
+            .then(|| placeholder_index(&operation.operands, PLACEHOLDER_KEY))
+            .flatten()
+            .filter(|index| {
+                alternate.map_or(true, |alternate| {
+                    let alternate = &alternate[position];
+                    alternate.operator == operation.operator
+                        && placeholder_index(&alternate.operands, ALTERNATE_KEY) == Some(*index)
+                })
+            })
+            .and_then(|index| colors.get(index));
+        if let Some(&(color, alpha)) = placeholder {
+            alphas.insert(alpha);
+            rewritten.push(set_alpha(alpha));
+            rewritten.push(color_operation(color, stroke));
+            translucent = true;
+            continue;
+        }
+
+        if translucent && COLOR_OPERATORS.contains(&operator) {
+            alphas.insert(u8::MAX);
+            rewritten.push(set_alpha(u8::MAX));
+            translucent = false;
+        }
+        let paints = PAINT_OPERATORS.contains(&operator);
+        rewritten.push(operation);
+        if translucent && paints {
+            alphas.insert(u8::MAX);
+            rewritten.push(set_alpha(u8::MAX));
+            translucent = false;
+        }
+    }
+    rewritten
+}
+
+/// Decodes the placeholder index from the operands of a CMYK color operator whose black component
+/// is `key`.
+fn placeholder_index(operands: &[Object], key: u8) -> Option<usize> {
+    let components = operands
+        .iter()
+        .map(|operand| match operand {
+            Object::Integer(value) => Some(*value as f64),
+            Object::Real(value) => Some(f64::from(*value)),
+            _ => None,
+        })
+        .map(|value| value.map(|value| (value * 255.0).round() as usize))
+        .collect::<Option<Vec<_>>>()?;
+    match components[..] {
+        [c, m, y, black] if black == usize::from(key) => Some(c << 16 | m << 8 | y),
+        _ => None,
+    }
+}
+
+fn state_name(alpha: u8) -> String {
+    format!("PdfHelperAlpha{}", alpha)
+}
+
+fn set_alpha(alpha: u8) -> Operation {
+    Operation::new("gs", vec![Object::Name(state_name(alpha).into_bytes())])
+}
+
+/// Builds the operator that sets `color` as the stroke or fill color.
+fn color_operation(color: Color, stroke: bool) -> Operation {
+    let (operator, components) = match color {
+        Color::Rgb(r, g, b) => ("rg", vec![r, g, b]),
+        Color::Greyscale(value) => ("g", vec![value]),
+        Color::Cmyk(c, m, y, k) => ("k", vec![c, m, y, k]),
+    };
+    let operator = if stroke {
+        operator.to_uppercase()
+    } else {
+        operator.to_string()
+    };
+    let operands = components
+        .into_iter()
+        .map(|component| Object::Real(f32::from(component) / 255.0))
+        .collect();
+    Operation::new(&operator, operands)
+}
+
+/// Returns the `ExtGState` resource dictionary of a page, creating it when missing.
+fn graphics_states_mut(
+    document: &mut Document,
+    page_id: ObjectId,
+) -> Result<&mut Dictionary, lopdf::Error> {
+    let states_id = page_resources_mut(document, page_id)?
+        .get(b"ExtGState")
+        .and_then(Object::as_reference)
+        .ok();
+    if let Some(id) = states_id {
+        return document.get_object_mut(id)?.as_dict_mut();
+    }
+    let resources = page_resources_mut(document, page_id)?;
+    if !resources.has(b"ExtGState") {
+        resources.set("ExtGState", Dictionary::new());
+    }
+    resources.get_mut(b"ExtGState")?.as_dict_mut()
+}
+
+/// Returns the resource dictionary of a page, creating it when missing.
+fn page_resources_mut(
+    document: &mut Document,
+    page_id: ObjectId,
+) -> Result<&mut Dictionary, lopdf::Error> {
+    let resources_id = document
+        .get_dictionary(page_id)?
+        .get(b"Resources")
+        .and_then(Object::as_reference)
+        .ok();
+    if let Some(id) = resources_id {
+        return document.get_object_mut(id)?.as_dict_mut();
+    }
+    let page = document.get_object_mut(page_id)?.as_dict_mut()?;
+    if !page.has(b"Resources") {
+        page.set("Resources", Dictionary::new());
+    }
+    page.get_mut(b"Resources")?.as_dict_mut()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operation(operator: &str, operands: &[f32]) -> Operation {
+        Operation::new(
+            operator,
+            operands.iter().map(|value| Object::Real(*value)).collect(),
+        )
+    }
+
+    fn operators(operations: &[Operation]) -> Vec<&str> {
+        operations
+            .iter()
+            .map(|operation| operation.operator.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn placeholders_round_trip_through_the_content_stream() {
+        let colors = TranslucentColors::default();
+        colors.placeholder(Color::Rgb(0, 0, 255), 200);
+        let Some(Color::Cmyk(c, m, y, k)) = colors.placeholder(Color::Rgb(255, 0, 0), 128) else {
+            panic!("placeholders are CMYK colors");
+        };
+        let operands = [c, m, y, k].map(|value| f32::from(value) / 255.0);
+        assert_eq!(
+            placeholder_index(&operation("k", &operands).operands, PLACEHOLDER_KEY),
+            Some(1)
+        );
+        assert_eq!(
+            colors.placeholder(Color::Rgb(255, 0, 0), 128),
+            Some(Color::Cmyk(c, m, y, k))
+        );
+        assert_eq!(colors.colors().len(), 2);
+
+        colors.use_alternate_key();
+        assert_eq!(
+            colors.placeholder(Color::Rgb(255, 0, 0), 128),
+            Some(Color::Cmyk(c, m, y, ALTERNATE_KEY))
+        );
+        colors.disable();
+        assert_eq!(colors.placeholder(Color::Rgb(255, 0, 0), 128), None);
+    }
+
+    #[test]
+    fn translucent_strokes_reset_the_alpha_once_painted() {
+        let colors = [(Color::Greyscale(0), 64)];
+        let operations = vec![
+            operation("K", &[0.0, 0.0, 0.0, 1.0 / 255.0]),
+            operation("m", &[0.0, 0.0]),
+            operation("l", &[10.0, 0.0]),
+            operation("S", &[]),
+            operation("RG", &[1.0, 0.0, 0.0]),
+        ];
+        let mut alternate = operations.clone();
+        alternate[0] = operation("K", &[0.0, 0.0, 0.0, 2.0 / 255.0]);
+        let mut alphas = BTreeSet::new();
+        let rewritten = rewrite_operations(operations, Some(&alternate), &colors, &mut alphas);
+
+        assert_eq!(
+            operators(&rewritten),
+            ["gs", "G", "m", "l", "S", "gs", "RG"]
+        );
+        assert_eq!(alphas.into_iter().collect::<Vec<_>>(), [64, 255]);
+    }
+
+    #[test]
+    fn opaque_colors_are_left_untouched() {
+        let colors = [(Color::Greyscale(0), 64)];
+        let operations = vec![
+            operation("k", &[0.0, 0.0, 0.0, 1.0]),
+            operation("rg", &[1.0, 0.0, 0.0]),
+        ];
+        let mut alphas = BTreeSet::new();
+        let rewritten =
+            rewrite_operations(operations.clone(), Some(&operations), &colors, &mut alphas);
+
+        assert_eq!(operators(&rewritten), operators(&operations));
+        assert!(alphas.is_empty());
+    }
+
+    #[test]
+    fn document_colors_matching_a_placeholder_are_left_untouched() {
+        let colors = [(Color::Greyscale(0), 64)];
+        // The document draws the placeholder color itself; it does not change between renders.
+        let operations = vec![
+            operation("k", &[0.0, 0.0, 0.0, 1.0 / 255.0]),
+            operation("ET", &[]),
+            operation("k", &[0.0, 0.0, 0.0, 1.0 / 255.0]),
+            operation("ET", &[]),
+        ];
+        let mut alternate = operations.clone();
+        alternate[2] = operation("k", &[0.0, 0.0, 0.0, 2.0 / 255.0]);
+        let mut alphas = BTreeSet::new();
+        let rewritten = rewrite_operations(operations, Some(&alternate), &colors, &mut alphas);
+
+        assert_eq!(operators(&rewritten), ["k", "ET", "gs", "g", "ET", "gs"]);
+        assert_eq!(
+            placeholder_index(&rewritten[0].operands, PLACEHOLDER_KEY),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn without_an_alternate_render_every_keyed_color_is_a_placeholder() {
+        let colors = [(Color::Greyscale(0), 64)];
+        let operations = vec![
+            operation("k", &[0.0, 0.0, 0.0, 1.0 / 255.0]),
+            operation("ET", &[]),
+            operation("k", &[0.0, 0.0, 0.0, 1.0]),
+            operation("ET", &[]),
+        ];
+        let mut alphas = BTreeSet::new();
+        let rewritten = rewrite_operations(operations, None, &colors, &mut alphas);
+
+        assert_eq!(operators(&rewritten), ["gs", "g", "ET", "gs", "k", "ET"]);
+        let keyed = Color::Cmyk(0, 0, 0, PLACEHOLDER_KEY);
+        assert!(matches_placeholder_key(keyed));
+        assert!(!matches_placeholder_key(Color::Cmyk(0, 0, 0, 255)));
+    }
+}
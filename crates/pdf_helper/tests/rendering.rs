@@ -1,8 +1,20 @@
-use pdf_helper::builder::{PdfBuildError, PdfBuilder};
-use pdf_helper::fonts::{self, bundled_fonts_source_dir};
-use pdf_helper::model::{Block, Section};
-use pdf_helper::richtext::Span;
+use genpdf::style::Color;
+use pdf_helper::builder::{PdfBuildError, PdfBuilder, PdfRenderResult, RenderMode, TocLeader};
+use pdf_helper::diagnostics::RenderWarning;
+use pdf_helper::elements::{measure_paragraph, measure_span, FixedRegion, WatermarkOptions};
+use pdf_helper::examples::shared::build_sample_report_builder;
+use pdf_helper::fonts::{self, bundled_fonts_source_dir, FontSource};
+use pdf_helper::layout::{ElementKind, LayoutDump};
+#[cfg(feature = "mixed-orientation")]
+use pdf_helper::model::Orientation;
+use pdf_helper::model::{
+    Block, BorderStyle, CaptionPosition, CodeBlock, ColumnAlignment, ColumnBlock,
+    HorizontalAlignment, ImageBlock, ImageSource, QuoteBlock, RichParagraph, RuleStyle, Section,
+    TableBlock, TextDirection,
+};
+use pdf_helper::richtext::{self, Span};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::env;
 use std::ffi::OsString;
 use std::fs;
@@ -14,16 +26,14 @@ static FONT_ENV_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
 struct FontSearchGuard {
     original_env: Option<OsString>,
     original_windows_env: Option<OsString>,
+    original_system_env: Option<OsString>,
     renamed_dir: Option<(PathBuf, PathBuf)>,
     lock: Option<MutexGuard<'static, ()>>,
 }
 
 impl FontSearchGuard {
     fn isolate() -> Self {
-        let lock = FONT_ENV_LOCK
-            .get_or_init(|| Mutex::new(()))
-            .lock()
-            .expect("font isolation mutex poisoned");
+        let lock = lock_font_env();
         let original_env = env::var_os("PDF_HELPER_FONTS_DIR");
         env::set_var("PDF_HELPER_FONTS_DIR", "/__pdf_helper_missing_fonts__");
 
@@ -33,6 +43,11 @@ impl FontSearchGuard {
             "/__pdf_helper_missing_windows_fonts__",
         );
 
+        // The system font fallback is part of what these tests exercise, so search the default
+        // system directories rather than a directory configured for the whole test run.
+        let original_system_env = env::var_os("PDF_HELPER_SYSTEM_FONTS_DIR");
+        env::remove_var("PDF_HELPER_SYSTEM_FONTS_DIR");
+
         let manifest_fonts = bundled_fonts_source_dir();
         let renamed_dir = if manifest_fonts.exists() {
             let backup = manifest_fonts.with_file_name("fonts.test-backup");
@@ -52,6 +67,7 @@ impl FontSearchGuard {
         Self {
             original_env,
             original_windows_env,
+            original_system_env,
             renamed_dir,
             lock: Some(lock),
         }
@@ -76,6 +92,10 @@ impl Drop for FontSearchGuard {
             None => env::remove_var("PDF_HELPER_WINDOWS_FONTS_DIR"),
         }
 
+        if let Some(value) = self.original_system_env.take() {
+            env::set_var("PDF_HELPER_SYSTEM_FONTS_DIR", value);
+        }
+
         self.lock.take();
     }
 }
@@ -87,22 +107,50 @@ fn render_sample_pdf() -> Option<Vec<u8>> {
         "Bundled fonts unexpectedly available; the fallback path is not exercised"
     );
 
-    match PdfBuilder::new()
-        .add_section(
-            Section::new("Sample")
-                .with_block(Block::paragraph(vec![Span::new("Hello, PDF!").bold()])),
-        )
-        .render()
-    {
-        Ok(result) => Some(result.bytes),
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Sample").with_block(Block::paragraph(vec![Span::new("Hello, PDF!").bold()])),
+    );
+    skip_without_fonts(builder.render()).map(|result| result.bytes)
+}
+
+/// Keeps [`FontSearchGuard`] from moving the bundled fonts away while the lock is held.
+fn lock_font_env() -> MutexGuard<'static, ()> {
+    FONT_ENV_LOCK
+        .get_or_init(|| Mutex::new(()))
+        .lock()
+        .expect("font isolation mutex poisoned")
+}
+
+/// Returns the output of a render, or `None` when no fonts are available.
+fn skip_without_fonts<T>(rendered: Result<T, PdfBuildError>) -> Option<T> {
+    match rendered {
+        Ok(output) => Some(output),
         Err(PdfBuildError::FontLoad(err)) => {
-            eprintln!("Skipping rendering fallback assertions: {}", err);
+            eprintln!("Skipping rendering assertions: {}", err);
             None
         }
-        Err(other) => panic!("render sample pdf: {other}"),
+        Err(other) => panic!("render pdf: {other}"),
     }
 }
 
+/// Runs `render` with whatever fonts are installed, returning `None` when none are available.
+fn with_available_fonts<T>(render: impl FnOnce() -> Result<T, PdfBuildError>) -> Option<T> {
+    let _lock = lock_font_env();
+    skip_without_fonts(render())
+}
+
+/// Renders `builder` with whatever fonts are installed, returning `None` when none are available.
+fn render_with_available_fonts(builder: PdfBuilder) -> Option<PdfRenderResult> {
+    with_available_fonts(|| builder.render())
+}
+
+/// Renders `builder` like [`render_with_available_fonts`] and returns the layout dump as well.
+fn render_layout_with_available_fonts(
+    builder: PdfBuilder,
+) -> Option<(PdfRenderResult, LayoutDump)> {
+    with_available_fonts(|| builder.render_with_layout_debug())
+}
+
 fn scrub_pdf(bytes: &[u8]) -> Vec<u8> {
     fn scrub_segment(data: &mut [u8], tag: &[u8], terminator: u8) {
         let mut index = 0;
@@ -187,6 +235,167 @@ fn normalized_hash(bytes: &[u8]) -> [u8; 32] {
     digest.into()
 }
 
+/// Text object (`BT` to `ET`) printed on a page.
+#[derive(Debug, Default)]
+struct PrintedText {
+    /// Horizontal position of the text object in points from the left page edge.
+    x: f64,
+    /// Baseline of the text object in points from the bottom page edge.
+    y: f64,
+    text: String,
+}
+
+/// Decodes the text printed on the first page of `bytes` like [`page_text`].
+fn first_page_text(bytes: &[u8]) -> Vec<PrintedText> {
+    page_text(bytes, 1)
+}
+
+/// Decodes the text printed on the one-based `page` of `bytes`, one entry per text object in
+/// content stream order, through the `/ToUnicode` maps of the embedded fonts.
+fn page_text(bytes: &[u8], page: u32) -> Vec<PrintedText> {
+    use lopdf::content::Content;
+    use lopdf::{Dictionary, Object};
+
+    let document = lopdf::Document::load_mem(bytes).expect("rendered PDF parses");
+    let page_id = document.get_pages()[&page];
+    // printpdf stores the resource and font dictionaries as indirect objects, which
+    // `Document::get_page_fonts` does not follow.
+    fn resolve<'a>(document: &'a lopdf::Document, object: &'a Object) -> Option<&'a Dictionary> {
+        let (_, object) = document.dereference(object).ok()?;
+        object.as_dict().ok()
+    }
+    let page = document.get_dictionary(page_id).expect("page dictionary");
+    let fonts = page
+        .get(b"Resources")
+        .ok()
+        .and_then(|resources| resolve(&document, resources))
+        .and_then(|resources| resources.get(b"Font").ok())
+        .and_then(|fonts| resolve(&document, fonts))
+        .expect("page fonts");
+    let maps: HashMap<&[u8], HashMap<u16, char>> = fonts
+        .iter()
+        .map(|(name, font)| {
+            let cmap = resolve(&document, font)
+                .and_then(|font| font.get(b"ToUnicode").ok())
+                .and_then(|cmap| document.dereference(cmap).ok())
+                .and_then(|(_, cmap)| cmap.as_stream().ok())
+                .map(|stream| {
+                    stream
+                        .decompressed_content()
+                        .unwrap_or_else(|_| stream.content.clone())
+                })
+                .unwrap_or_default();
+            (
+                name.as_slice(),
+                parse_to_unicode(&String::from_utf8_lossy(&cmap)),
+            )
+        })
+        .collect();
+
+    let content = document.get_page_content(page_id).expect("page content");
+    let content = Content::decode(&content).expect("content stream decodes");
+    let mut texts = Vec::new();
+    let mut font = None;
+    for operation in &content.operations {
+        match operation.operator.as_str() {
+            "BT" => texts.push(PrintedText::default()),
+            "Td" => {
+                if let (Some(text), [x, y]) = (texts.last_mut(), operation.operands.as_slice()) {
+                    text.x += f64::from(x.as_float().expect("horizontal offset"));
+                    text.y += f64::from(y.as_float().expect("vertical offset"));
+                }
+            }
+            "Tf" => {
+                font = operation
+                    .operands
+                    .first()
+                    .and_then(|name| name.as_name().ok())
+                    .and_then(|name| maps.get(name));
+            }
+            "Tj" | "TJ" => {
+                let (Some(text), Some(font)) = (texts.last_mut(), font) else {
+                    continue;
+                };
+                let strings = operation.operands.iter().flat_map(|operand| match operand {
+                    Object::Array(items) => items.iter().collect(),
+                    other => vec![other],
+                });
+                for string in strings {
+                    if let Object::String(glyphs, _) = string {
+                        text.text.extend(glyphs.chunks(2).map(|glyph| {
+                            let glyph = u16::from_be_bytes([glyph[0], glyph[1]]);
+                            font.get(&glyph)
+                                .copied()
+                                .unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    texts
+}
+
+/// Decodes the straight paths stroked on the first page of `bytes`, as lists of points in PDF
+/// coordinates.
+fn first_page_strokes(bytes: &[u8]) -> Vec<Vec<(f64, f64)>> {
+    use lopdf::content::{Content, Operation};
+
+    let document = lopdf::Document::load_mem(bytes).expect("rendered PDF parses");
+    let page_id = *document.get_pages().values().next().expect("first page");
+    let content = document.get_page_content(page_id).expect("page content");
+    let content = Content::decode(&content).expect("content stream decodes");
+    let point = |operation: &Operation| match operation.operands.as_slice() {
+        [x, y] => (
+            f64::from(x.as_float().expect("x coordinate")),
+            f64::from(y.as_float().expect("y coordinate")),
+        ),
+        operands => panic!("unexpected path operands {operands:?}"),
+    };
+    let mut strokes = Vec::new();
+    let mut path = Vec::new();
+    for operation in &content.operations {
+        match operation.operator.as_str() {
+            "m" => path = vec![point(operation)],
+            "l" => path.push(point(operation)),
+            "S" => strokes.push(std::mem::take(&mut path)),
+            _ => {}
+        }
+    }
+    strokes
+}
+
+/// Reads the `bfchar` entries of a `/ToUnicode` CMap into a glyph-to-character map.
+fn parse_to_unicode(cmap: &str) -> HashMap<u16, char> {
+    let mut map = HashMap::new();
+    let mut in_block = false;
+    for line in cmap.lines() {
+        let line = line.trim();
+        if line.ends_with("beginbfchar") {
+            in_block = true;
+        } else if line == "endbfchar" {
+            in_block = false;
+        } else if in_block {
+            let mut codes = line
+                .split(['<', '>'])
+                .map(str::trim)
+                .filter(|code| !code.is_empty());
+            let glyph = codes
+                .next()
+                .and_then(|code| u16::from_str_radix(code, 16).ok());
+            let unicode = codes
+                .next()
+                .and_then(|code| u32::from_str_radix(code, 16).ok())
+                .and_then(char::from_u32);
+            if let (Some(glyph), Some(unicode)) = (glyph, unicode) {
+                map.insert(glyph, unicode);
+            }
+        }
+    }
+    map
+}
+
 #[test]
 fn renders_non_empty_output() {
     let Some(bytes) = render_sample_pdf() else {
@@ -217,3 +426,2168 @@ fn rendering_is_deterministic() {
         "PDF renders must be deterministic after metadata normalization"
     );
 }
+
+#[cfg(feature = "fixed-timestamp")]
+#[test]
+fn fixed_timestamps_make_renders_byte_identical() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let render = || {
+        let builder = PdfBuilder::new()
+            .with_fixed_timestamp(UNIX_EPOCH + Duration::from_secs(1_704_067_200))
+            .add_section(
+                Section::new("Stable").with_block(Block::paragraph(vec![Span::new("Same bytes.")])),
+            );
+        render_with_available_fonts(builder)
+    };
+    let (Some(first), Some(second)) = (render(), render()) else {
+        return;
+    };
+
+    assert_eq!(first.bytes, second.bytes);
+    let needle = b"D:20240101000000+00'00'";
+    assert!(first
+        .bytes
+        .windows(needle.len())
+        .any(|window| window == needle));
+}
+
+#[test]
+fn clipped_fixed_region_reports_warning() {
+    const SENTENCE: &str = "Overflowing footer text that cannot possibly fit. ";
+    let builder = PdfBuilder::new()
+        .add_section(Section::new("Sample").with_block(Block::paragraph(vec![Span::new("Body")])));
+    let sink = builder.warning_sink();
+    let builder = builder.with_footer(12.0, move |_| {
+        let spans = richtext::spans_to_styled_strings(&[Span::new(SENTENCE.repeat(20))]);
+        FixedRegion::new(6, spans)
+            .with_location("footer")
+            .with_warnings(sink.clone())
+    });
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+
+    let [RenderWarning::ContentClipped {
+        location,
+        dropped_preview,
+    }] = result.warnings.as_slice()
+    else {
+        panic!(
+            "expected one clipped-content warning, got {:?}",
+            result.warnings
+        );
+    };
+    assert_eq!(location, "footer");
+    let first_word = dropped_preview
+        .split_whitespace()
+        .next()
+        .expect("preview contains dropped text");
+    assert!(SENTENCE.split_whitespace().any(|word| word == first_word));
+}
+
+#[test]
+fn tables_with_missing_or_short_rows_render() {
+    let cell = |text: &str| RichParagraph::new(vec![Span::new(text)]);
+    let empty = TableBlock::new(vec![cell("Item"), cell("Amount")]).with_column_weights(vec![6, 1]);
+    let ragged = TableBlock::new(vec![cell("Item"), cell("Amount")])
+        .with_row(vec![cell("Consulting"), cell("1,250.5")])
+        .with_row(vec![cell("Travel")])
+        .with_row(Vec::<RichParagraph>::new())
+        .with_column_alignments(vec![ColumnAlignment::Left, ColumnAlignment::Decimal]);
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Tables")
+            .with_block(Block::Table(empty))
+            .with_block(Block::Table(ragged))
+            .with_block(Block::Table(TableBlock::default())),
+    );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    assert!(result.bytes.starts_with(b"%PDF"));
+}
+
+#[test]
+fn repeated_table_headers_take_space_on_continuation_pages() {
+    let cell = |text: String| RichParagraph::new(vec![Span::new(text)]);
+    let table = |repeat: bool| {
+        TableBlock::new(vec![cell("Item".into()), cell("Amount".into())])
+            .with_rows((1..=120).map(|row| vec![cell(format!("Row {row}")), cell(row.to_string())]))
+            .with_repeat_header(repeat)
+    };
+    let table_height = |repeat: bool| {
+        let builder = PdfBuilder::new()
+            .add_section(Section::new("Ledger").with_block(Block::Table(table(repeat))));
+        let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+            return None;
+        };
+        let fragments: Vec<_> = dump
+            .pages
+            .iter()
+            .flat_map(|page| &page.elements)
+            .filter(|element| element.kind == ElementKind::Table)
+            .map(|element| element.rect.height)
+            .collect();
+        assert!(fragments.len() > 1, "{dump}");
+        Some(fragments.iter().sum::<f64>())
+    };
+
+    let (Some(plain), Some(repeated)) = (table_height(false), table_height(true)) else {
+        return;
+    };
+    assert!(repeated > plain, "{repeated} <= {plain}");
+}
+
+#[test]
+fn striped_tables_with_filled_cells_render() {
+    use pdf_helper::model::TableCell;
+
+    let cell = |text: &str| RichParagraph::new(vec![Span::new(text)]);
+    let table = TableBlock::new(vec![cell("Item"), cell("Notes")])
+        .with_row(vec![cell("Rent"), cell("Due on the first of every month.")])
+        .with_row(vec![
+            TableCell::new(cell("Travel")),
+            TableCell::new(cell(
+                "Over budget; flights were booked late and a second trip was added.",
+            ))
+            .with_fill(Color::Rgb(255, 230, 150)),
+        ])
+        .with_row(vec![cell("Meals")])
+        .with_zebra(Color::Greyscale(235));
+    let builder =
+        PdfBuilder::new().add_section(Section::new("Expenses").with_block(Block::Table(table)));
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    assert!(result.bytes.starts_with(b"%PDF"));
+}
+
+#[test]
+fn fixed_height_table_cells_report_clipped_text() {
+    use pdf_helper::model::TableCell;
+
+    const SENTENCE: &str = "Notes that run far past the height of their cell. ";
+    let cell = |text: &str| RichParagraph::new(vec![Span::new(text)]);
+    let table = TableBlock::new(vec![cell("Item"), cell("Notes")])
+        .with_row(vec![
+            TableCell::new(cell("Travel")),
+            TableCell::new(cell(&SENTENCE.repeat(10))).with_height_mm(6.0),
+        ])
+        .with_row(vec![
+            TableCell::new(cell("Meals")),
+            TableCell::new(cell("Short")).with_height_mm(6.0),
+        ]);
+    let builder =
+        PdfBuilder::new().add_section(Section::new("Expenses").with_block(Block::Table(table)));
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+
+    let [RenderWarning::ContentClipped {
+        location,
+        dropped_preview,
+    }] = result.warnings.as_slice()
+    else {
+        panic!(
+            "expected one clipped-content warning, got {:?}",
+            result.warnings
+        );
+    };
+    assert_eq!(location, "table row 1 column 2");
+    assert!(!dropped_preview.is_empty());
+}
+
+#[test]
+fn bordered_tables_render_across_pages() {
+    use pdf_helper::model::TableBorders;
+
+    let cell = |text: String| RichParagraph::new(vec![Span::new(text)]);
+    let line = BorderStyle::new(0.2, Color::Greyscale(0));
+    let table = |borders: TableBorders| {
+        Block::Table(
+            TableBlock::new(vec![cell("Item".into()), cell("Amount".into())])
+                .with_rows(
+                    (1..=60).map(|row| vec![cell(format!("Row {row}")), cell(row.to_string())]),
+                )
+                .with_repeat_header(true)
+                .with_borders(borders),
+        )
+    };
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Borders")
+            .with_block(table(TableBorders::grid(line)))
+            .with_block(table(TableBorders::horizontal(line)))
+            .with_block(table(TableBorders::minimal(line))),
+    );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    assert!(result.bytes.starts_with(b"%PDF"));
+}
+
+fn stamped_builder() -> PdfBuilder {
+    PdfBuilder::new()
+        .add_section(Section::new("Sample").with_block(Block::paragraph(vec![Span::new("Body")])))
+        .stamp_if_warnings("PROVISIONAL")
+}
+
+#[test]
+fn provisional_stamp_applies_only_when_warnings_exist() {
+    let bundled = bundled_fonts_source_dir();
+    let variants = ["Regular", "Bold", "Italic", "BoldItalic"];
+    let Ok(font_bytes) = variants
+        .iter()
+        .map(|variant| fs::read(bundled.join(format!("Roboto-{variant}.ttf"))))
+        .collect::<Result<Vec<_>, _>>()
+    else {
+        eprintln!("Skipping provisional stamp assertions: bundled fonts are not available");
+        return;
+    };
+
+    let clean = render_with_available_fonts(stamped_builder()).expect("bundled fonts render");
+    assert!(clean.warnings.is_empty(), "{:?}", clean.warnings);
+    assert_eq!(clean.provisional_stamp, None);
+
+    // Serve the bundled fonts under the Windows file names so the fallback family loads.
+    let fallback_dir = env::temp_dir().join(format!("pdf_helper_fallback_{}", std::process::id()));
+    fs::create_dir_all(&fallback_dir).expect("create fallback font directory");
+    for (name, bytes) in ["arial.ttf", "arialbd.ttf", "ariali.ttf", "arialbi.ttf"]
+        .iter()
+        .zip(&font_bytes)
+    {
+        fs::write(fallback_dir.join(name), bytes).expect("write fallback font");
+    }
+
+    let stamped = {
+        let _guard = FontSearchGuard::isolate();
+        env::set_var("PDF_HELPER_WINDOWS_FONTS_DIR", &fallback_dir);
+        stamped_builder().render()
+    };
+    let _ = fs::remove_dir_all(&fallback_dir);
+
+    let stamped = stamped.expect("render with fallback fonts");
+    assert!(stamped
+        .warnings
+        .iter()
+        .any(|warning| matches!(warning, RenderWarning::FontFallback { .. })));
+    assert_eq!(stamped.provisional_stamp.as_deref(), Some("PROVISIONAL"));
+}
+
+#[test]
+fn watermark_does_not_move_content() {
+    let Some(plain) = render_with_available_fonts(build_sample_report_builder().expect("sample"))
+    else {
+        return;
+    };
+    let options = WatermarkOptions::new()
+        .with_angle(30.0)
+        .with_opacity(0.4)
+        .with_font_size(64)
+        .with_color(Color::Rgb(200, 30, 30));
+    let watermarked = build_sample_report_builder()
+        .expect("sample")
+        .with_watermark("CONFIDENTIAL", options);
+    let watermarked =
+        render_with_available_fonts(watermarked).expect("fonts available for the plain render");
+
+    assert_eq!(watermarked.headings, plain.headings);
+    assert_ne!(
+        normalized_hash(&watermarked.bytes),
+        normalized_hash(&plain.bytes)
+    );
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn encrypted_output_references_an_encryption_dictionary() {
+    use pdf_helper::encryption::{EncryptionAlgorithm, EncryptionOptions};
+
+    let options = EncryptionOptions::new("reader", "author")
+        .with_algorithm(EncryptionAlgorithm::Rc4)
+        .allow_copy(false);
+    let builder = PdfBuilder::new().with_encryption(options).add_section(
+        Section::new("Confidential").with_block(Block::paragraph(vec![Span::new("Salary bands.")])),
+    );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    assert!(result.bytes.windows(8).any(|window| window == b"/Encrypt"));
+    assert!(result.bytes.windows(9).any(|window| window == b"/Standard"));
+}
+
+#[cfg(feature = "pdfa")]
+#[test]
+fn pdfa_output_declares_its_conformance_in_the_catalog() {
+    use pdf_helper::pdfa::PdfaLevel;
+
+    let builder = PdfBuilder::new().pdfa_mode(PdfaLevel::A2b).add_section(
+        Section::new("Archive")
+            .with_block(Block::paragraph(vec![Span::new("Retained for ten years.")])),
+    );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    assert!(result.bytes.starts_with(b"%PDF-1.7\n%"));
+    let document = lopdf::Document::load_mem(&result.bytes).expect("PDF/A output parses");
+    let catalog = document
+        .trailer
+        .get(b"Root")
+        .and_then(lopdf::Object::as_reference)
+        .and_then(|id| document.get_dictionary(id))
+        .expect("catalog");
+    assert!(catalog.has(b"OutputIntents"));
+    let metadata = catalog
+        .get(b"Metadata")
+        .and_then(lopdf::Object::as_reference)
+        .and_then(|id| document.get_object(id))
+        .and_then(lopdf::Object::as_stream)
+        .expect("metadata stream");
+    let xmp = String::from_utf8_lossy(&metadata.content);
+    assert!(xmp.contains("<pdfaid:part>2</pdfaid:part>"), "{xmp}");
+    assert!(
+        xmp.contains("<pdfaid:conformance>B</pdfaid:conformance>"),
+        "{xmp}"
+    );
+}
+
+#[cfg(feature = "transparency")]
+#[test]
+fn translucent_watermarks_set_a_graphics_state_alpha() {
+    let builder = PdfBuilder::new()
+        .with_watermark("DRAFT", WatermarkOptions::new().with_opacity(0.5))
+        .add_section(
+            Section::new("Draft").with_block(Block::paragraph(vec![Span::new("Under review.")])),
+        );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    let document = lopdf::Document::load_mem(&result.bytes).expect("translucent output parses");
+    let page_id = *document.get_pages().values().next().expect("first page");
+    let content = document.get_page_content(page_id).expect("page content");
+    let content = String::from_utf8_lossy(&content);
+    assert!(content.contains("/PdfHelperAlpha128 gs"), "{content}");
+    assert!(content.contains("/PdfHelperAlpha255 gs"), "{content}");
+}
+
+#[cfg(feature = "transparency")]
+#[test]
+fn document_colors_matching_a_placeholder_stay_opaque() {
+    // The watermark is the first translucent color, so its placeholder is this very color.
+    let placeholder = Color::Cmyk(0, 0, 0, 1);
+    let builder = PdfBuilder::new()
+        .with_watermark("DRAFT", WatermarkOptions::new().with_opacity(0.5))
+        .add_section(Section::new("Draft").with_block(Block::paragraph(vec![
+            Span::new("Nearly white.").with_color(Some(placeholder)),
+        ])));
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    let document = lopdf::Document::load_mem(&result.bytes).expect("translucent output parses");
+    let page_id = *document.get_pages().values().next().expect("first page");
+    let content = document.get_page_content(page_id).expect("page content");
+    let operations = lopdf::content::Content::decode(&content)
+        .expect("page content decodes")
+        .operations;
+    let black = |operation: &lopdf::content::Operation| match operation.operands.last() {
+        Some(lopdf::Object::Real(value)) => Some((f64::from(*value) * 255.0).round()),
+        Some(lopdf::Object::Integer(value)) => Some(*value as f64 * 255.0),
+        _ => None,
+    };
+    assert!(operations
+        .iter()
+        .any(|operation| operation.operator == "k" && black(operation) == Some(1.0)));
+    assert!(operations
+        .iter()
+        .any(|operation| operation.operator == "gs"));
+}
+
+#[cfg(feature = "tagged-pdf")]
+#[test]
+fn tagged_output_describes_headings_and_paragraphs() {
+    let builder = PdfBuilder::new()
+        .tagged(true)
+        .show_footer(true)
+        .add_section(
+            Section::new("Findings")
+                .with_block(Block::paragraph(vec![Span::new("First finding.")]))
+                .with_subsection(
+                    Section::new("Details")
+                        .with_block(Block::paragraph(vec![Span::new("Second finding.")])),
+                ),
+        );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    let document = lopdf::Document::load_mem(&result.bytes).expect("tagged output parses");
+    let catalog = document.catalog().expect("catalog");
+    let root = catalog
+        .get(b"StructTreeRoot")
+        .and_then(|root| document.dereference(root))
+        .and_then(|(_, root)| root.as_dict())
+        .expect("structure tree root");
+    let (_, top) = document
+        .dereference(root.get(b"K").expect("document element"))
+        .expect("document element resolves");
+    let roles: Vec<String> = top
+        .as_dict()
+        .and_then(|element| element.get(b"K"))
+        .and_then(lopdf::Object::as_array)
+        .expect("document children")
+        .iter()
+        .map(|child| {
+            let (_, child) = document.dereference(child).expect("child resolves");
+            let role = child.as_dict().and_then(|child| child.get(b"S"));
+            String::from_utf8_lossy(role.and_then(lopdf::Object::as_name).expect("role"))
+                .into_owned()
+        })
+        .collect();
+    assert_eq!(roles, vec!["H1", "P", "H2", "P"]);
+
+    let page_id = *document.get_pages().values().next().expect("first page");
+    let content = document.get_page_content(page_id).expect("page content");
+    let content = String::from_utf8_lossy(&content);
+    // The page-number footer is not part of the content model.
+    assert!(content.contains("/Artifact BMC"), "{content}");
+}
+
+#[cfg(feature = "viewer-preferences")]
+#[test]
+fn viewer_preferences_are_written_to_the_catalog() {
+    use pdf_helper::builder::{PageMode, ViewerLayout, ViewerPrefs};
+
+    let builder = PdfBuilder::new()
+        .viewer_preferences(
+            ViewerPrefs::new()
+                .with_page_mode(PageMode::UseOutlines)
+                .with_page_layout(ViewerLayout::TwoColumnLeft)
+                .with_hide_toolbar(true),
+        )
+        .add_section(
+            Section::new("Summary").with_block(Block::paragraph(vec![Span::new("Revenue grew.")])),
+        );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    let document = lopdf::Document::load_mem(&result.bytes).expect("output parses");
+    let catalog = document.catalog().expect("catalog");
+    let name = |key: &[u8]| {
+        catalog
+            .get(key)
+            .and_then(lopdf::Object::as_name_str)
+            .map(str::to_string)
+            .ok()
+    };
+    assert_eq!(name(b"PageMode").as_deref(), Some("UseOutlines"));
+    assert_eq!(name(b"PageLayout").as_deref(), Some("TwoColumnLeft"));
+    let hide_toolbar = catalog
+        .get(b"ViewerPreferences")
+        .and_then(lopdf::Object::as_dict)
+        .and_then(|preferences| preferences.get(b"HideToolbar"))
+        .and_then(lopdf::Object::as_bool)
+        .expect("toolbar preference");
+    assert!(hide_toolbar);
+}
+
+#[cfg(feature = "page-labels")]
+#[test]
+fn page_labels_restart_numbering_at_the_first_section() {
+    use pdf_helper::builder::PageLabelScheme;
+    use pdf_helper::model::Cover;
+
+    let builder = PdfBuilder::new()
+        .with_cover(Cover::new("Annual Report"))
+        .include_printed_toc(true)
+        .with_page_labels(PageLabelScheme::new().with_footer_labels(true))
+        .add_section(
+            Section::new("Summary").with_block(Block::paragraph(vec![Span::new("Revenue grew.")])),
+        );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    let body_start = result.section_start_pages[0].expect("section page tracked");
+    let document = lopdf::Document::load_mem(&result.bytes).expect("labelled output parses");
+    let numbers = document
+        .trailer
+        .get(b"Root")
+        .and_then(lopdf::Object::as_reference)
+        .and_then(|id| document.get_dictionary(id))
+        .and_then(|catalog| catalog.get(b"PageLabels"))
+        .and_then(lopdf::Object::as_dict)
+        .and_then(|tree| tree.get(b"Nums"))
+        .and_then(lopdf::Object::as_array)
+        .expect("page label number tree");
+    let ranges: Vec<_> = numbers
+        .chunks(2)
+        .map(|range| {
+            let start = range[0].as_i64().expect("range start");
+            let style = range[1]
+                .as_dict()
+                .and_then(|label| label.get(b"S"))
+                .and_then(lopdf::Object::as_name_str)
+                .expect("numbering style");
+            (start, style.to_string())
+        })
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            (0, "r".to_string()),
+            (body_start as i64 - 1, "D".to_string())
+        ]
+    );
+}
+
+#[cfg(feature = "links")]
+#[test]
+fn linked_spans_become_link_annotations() {
+    const TARGET: &str = "https://intranet.example.com/reports";
+    let builder =
+        PdfBuilder::new().add_section(Section::new("Links").with_block(Block::paragraph(vec![
+            Span::new("Reports live at "),
+            Span::new(TARGET).underline().linked(TARGET),
+        ])));
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    assert!(result
+        .bytes
+        .windows(TARGET.len())
+        .any(|window| window == TARGET.as_bytes()));
+}
+
+#[cfg(feature = "links")]
+#[test]
+fn internal_links_jump_to_the_identified_section() {
+    let summary = Section::new("Summary")
+        .with_block(Block::paragraph(vec![
+            Span::new("See "),
+            Span::new("the methodology")
+                .underline()
+                .linked_to_section("methodology"),
+            Span::new(" for details."),
+        ]))
+        .with_block(Block::page_break());
+    let methodology = Section::new("Methodology")
+        .with_identifier("methodology".to_string())
+        .with_block(Block::paragraph(vec![Span::new("Sampling rules.")]));
+    let builder = PdfBuilder::new()
+        .add_section(summary)
+        .add_section(methodology);
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    let document = lopdf::Document::load_mem(&result.bytes).expect("linked PDF parses");
+    let pages = document.get_pages();
+    let first_page = document.get_dictionary(pages[&1]).expect("first page");
+    let annotations = first_page
+        .get(b"Annots")
+        .and_then(lopdf::Object::as_array)
+        .expect("link annotations");
+    let annotation = document
+        .get_dictionary(annotations[0].as_reference().expect("annotation reference"))
+        .expect("annotation dictionary");
+    let destination = annotation
+        .get(b"Dest")
+        .and_then(lopdf::Object::as_array)
+        .expect("internal destination");
+
+    let methodology_page = result
+        .headings
+        .iter()
+        .find(|heading| heading.identifier.as_deref() == Some("methodology"))
+        .and_then(|heading| heading.page)
+        .expect("methodology page");
+    assert!(methodology_page > 1);
+    assert_eq!(
+        destination[0].as_reference().expect("destination page"),
+        pages[&(methodology_page as u32)]
+    );
+}
+
+#[test]
+fn unknown_link_anchors_are_reported() {
+    let builder =
+        PdfBuilder::new().add_section(Section::new("Summary").with_block(Block::paragraph(vec![
+            Span::new("See appendix").linked_to_section("appendix"),
+        ])));
+
+    match builder.render() {
+        Err(PdfBuildError::Content { message, .. }) => assert!(message.contains("appendix")),
+        other => panic!("expected a missing anchor error, got {other:?}"),
+    }
+}
+
+#[cfg(feature = "links")]
+#[test]
+fn toc_rows_link_to_their_section_pages() {
+    let builder = PdfBuilder::new()
+        .include_printed_toc(true)
+        .add_section(
+            Section::new("First").with_block(Block::paragraph(vec![Span::new("First body.")])),
+        )
+        .add_section(
+            Section::new("Second").with_block(Block::paragraph(vec![Span::new("Second body.")])),
+        );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    let document = lopdf::Document::load_mem(&result.bytes).expect("linked PDF parses");
+    let pages = document.get_pages();
+    let toc_page = document.get_dictionary(pages[&1]).expect("toc page");
+    let annotations = toc_page
+        .get(b"Annots")
+        .and_then(lopdf::Object::as_array)
+        .expect("toc annotations");
+
+    let destinations: Vec<lopdf::ObjectId> = annotations
+        .iter()
+        .map(|annotation| {
+            let annotation = document
+                .get_dictionary(annotation.as_reference().expect("annotation reference"))
+                .expect("annotation dictionary");
+            let destination = annotation
+                .get(b"Dest")
+                .and_then(lopdf::Object::as_array)
+                .expect("internal destination");
+            destination[0].as_reference().expect("destination page")
+        })
+        .collect();
+    let expected: Vec<lopdf::ObjectId> = result
+        .section_start_pages
+        .iter()
+        .map(|page| pages[&(page.expect("section page") as u32)])
+        .collect();
+    assert_eq!(destinations, expected);
+}
+
+#[cfg(all(feature = "links", feature = "bookmarks"))]
+#[test]
+fn toc_rows_open_the_configured_bookmark_destination() {
+    use pdf_helper::bookmarks::BookmarkDestination;
+
+    let builder = PdfBuilder::new()
+        .include_printed_toc(true)
+        .with_bookmark_destination(BookmarkDestination::XYZ {
+            top: Some(0.0),
+            zoom: None,
+        })
+        .add_section(
+            Section::new("First").with_block(Block::paragraph(vec![Span::new("First body.")])),
+        );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    let document = lopdf::Document::load_mem(&result.bytes).expect("linked PDF parses");
+    let toc_page = document
+        .get_dictionary(document.get_pages()[&1])
+        .expect("toc page");
+    let annotation = toc_page
+        .get(b"Annots")
+        .and_then(lopdf::Object::as_array)
+        .expect("toc annotations")[0]
+        .as_reference()
+        .and_then(|id| document.get_dictionary(id))
+        .expect("annotation dictionary");
+    let destination = annotation
+        .get(b"Dest")
+        .and_then(lopdf::Object::as_array)
+        .expect("internal destination");
+    assert_eq!(
+        destination[1].as_name_str().expect("destination kind"),
+        "XYZ"
+    );
+    assert_eq!(destination.len(), 5);
+}
+
+#[cfg(feature = "links")]
+#[test]
+fn toc_links_cover_the_printed_row_without_its_indent() {
+    let builder = PdfBuilder::new().include_printed_toc(true).add_section(
+        Section::new("First").with_subsection(
+            Section::new("Nested").with_block(Block::paragraph(vec![Span::new("Body.")])),
+        ),
+    );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    let document = lopdf::Document::load_mem(&result.bytes).expect("linked PDF parses");
+    let toc_page = document
+        .get_dictionary(document.get_pages()[&1])
+        .expect("toc page");
+    let rects: Vec<Vec<f32>> = toc_page
+        .get(b"Annots")
+        .and_then(lopdf::Object::as_array)
+        .expect("toc annotations")
+        .iter()
+        .map(|annotation| {
+            annotation
+                .as_reference()
+                .and_then(|id| document.get_dictionary(id))
+                .and_then(|annotation| annotation.get(b"Rect"))
+                .and_then(lopdf::Object::as_array)
+                .expect("annotation rectangle")
+                .iter()
+                .map(|value| value.as_float().expect("rectangle coordinate"))
+                .collect()
+        })
+        .collect();
+
+    let [first, nested] = rects.as_slice() else {
+        panic!("expected one link per toc row, got {rects:?}");
+    };
+    // The nested row is indented by 6 mm, which its link leaves out.
+    let indent = 6.0 * 72.0 / 25.4;
+    assert!((nested[0] - first[0] - indent).abs() < 0.1, "{rects:?}");
+    assert!((nested[2] - first[2]).abs() < 0.1, "{rects:?}");
+}
+
+#[test]
+fn compute_layout_matches_the_rendered_section_pages() {
+    let builder = PdfBuilder::new()
+        .include_printed_toc(true)
+        .add_section(Section::new("First").with_block(Block::paragraph(vec![Span::new("One")])))
+        .add_section(Section::new("Second").with_subsection(
+            Section::new("Nested").with_block(Block::paragraph(vec![Span::new("Two")])),
+        ));
+
+    let Some(pages) = with_available_fonts(|| builder.compute_layout()) else {
+        return;
+    };
+    assert_eq!(pages.len(), 2);
+    assert!(builder.warning_sink().warnings().is_empty());
+
+    let result = render_with_available_fonts(builder).expect("fonts available for the render");
+    assert_eq!(pages, result.section_start_pages);
+    assert!(pages.iter().all(Option::is_some), "{pages:?}");
+}
+
+#[test]
+fn estimated_page_count_matches_the_rendered_pages() {
+    use pdf_helper::model::Cover;
+
+    let builder = PdfBuilder::new()
+        .with_cover(Cover::new("Estimate"))
+        .include_printed_toc(true)
+        .sections_on_new_pages(true)
+        .add_section(Section::new("First").with_block(Block::paragraph(vec![Span::new("One")])))
+        .add_section(Section::new("Second").with_block(Block::paragraph(vec![Span::new("Two")])));
+
+    let Some(estimate) = with_available_fonts(|| builder.estimate_page_count()) else {
+        return;
+    };
+
+    let (result, dump) =
+        render_layout_with_available_fonts(builder).expect("fonts available for the render");
+    assert_eq!(estimate, dump.pages.len(), "{dump}");
+    assert!(result
+        .section_start_pages
+        .iter()
+        .all(|page| page.is_some_and(|page| page <= estimate)));
+}
+
+#[test]
+fn section_dividers_get_their_own_pages_and_outline_entries() {
+    use pdf_helper::model::Divider;
+
+    let paragraph = |text: &str| Block::paragraph(vec![Span::new(text)]);
+    let builder = PdfBuilder::new()
+        .include_printed_toc(true)
+        .number_sections(true)
+        .add_divider("Part I: Planning")
+        .add_section(Section::new("Overview").with_block(paragraph("Scope")))
+        .add_divider(Divider::new("Part II: Operations").with_restart_numbering(true))
+        .add_section(Section::new("Runbooks").with_block(paragraph("Steps")));
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+
+    let headings: Vec<_> = result
+        .headings
+        .iter()
+        .map(|heading| {
+            (
+                heading.level,
+                heading.title.as_str(),
+                heading.number.as_deref(),
+                heading.page,
+            )
+        })
+        .collect();
+    assert_eq!(
+        headings,
+        vec![
+            (0, "Part I: Planning", None, Some(2)),
+            (1, "Overview", Some("1."), Some(3)),
+            (0, "Part II: Operations", None, Some(4)),
+            (1, "Runbooks", Some("1."), Some(5)),
+        ]
+    );
+    assert_eq!(result.section_start_pages, vec![Some(3), Some(5)]);
+}
+
+#[test]
+fn layout_dump_reports_paragraph_positions() {
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Layout")
+            .with_block(Block::paragraph(vec![Span::new("First paragraph.")]))
+            .with_block(Block::paragraph(vec![Span::new("Second paragraph.")])),
+    );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let paragraphs: Vec<_> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::Paragraph)
+        .collect();
+    assert_eq!(paragraphs.len(), 2, "{dump}");
+    assert!(paragraphs.iter().all(|element| element.page == 1));
+    assert!(paragraphs[0].rect.y < paragraphs[1].rect.y);
+}
+
+#[test]
+fn right_to_left_paragraphs_print_their_words_in_display_order() {
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Notes").with_block(Block::Paragraph(
+            RichParagraph::new(vec![Span::new("alpha beta gamma")])
+                .with_direction(TextDirection::Rtl),
+        )),
+    );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    let texts = first_page_text(&result.bytes);
+    let line = texts
+        .iter()
+        .find(|printed| printed.text.contains("alpha"))
+        .unwrap_or_else(|| panic!("paragraph text in {texts:?}"));
+    // Words swap places, while the letters of Latin words keep their order.
+    assert_eq!(
+        line.text.split_whitespace().collect::<Vec<_>>(),
+        ["gamma", "beta", "alpha"]
+    );
+}
+
+#[test]
+fn mixed_font_sizes_share_a_baseline_lowered_by_the_tallest_span() {
+    let render = |headline_size: u8| {
+        let builder = PdfBuilder::new().add_section(
+            Section::new("Sizes")
+                .with_block(Block::paragraph(vec![
+                    Span::new("Fine print ").with_font_size(Some(8)),
+                    Span::new("Headline").with_font_size(Some(headline_size)),
+                    Span::new(" fine print").with_font_size(Some(8)),
+                ]))
+                .with_block(Block::paragraph(vec![Span::new("Next")])),
+        );
+        let result = render_with_available_fonts(builder)?;
+        let texts = first_page_text(&result.bytes);
+        let baseline = |needle: &str| {
+            texts
+                .iter()
+                .find(|printed| printed.text.contains(needle))
+                .unwrap_or_else(|| panic!("{needle:?} in {texts:?}"))
+        };
+        let line = baseline("Headline");
+        // Every span of the line is printed in the same text object, on one baseline.
+        assert_eq!(line.text, "Fine print Headline fine print", "{texts:?}");
+        Some((line.y, baseline("Next").y))
+    };
+
+    let Some((uniform, uniform_next)) = render(8) else {
+        return;
+    };
+    let (mixed, mixed_next) = render(24).expect("fonts available for the second render");
+
+    // The baseline moves down to make room for the ascent of the taller span, but by less than
+    // the size difference of the spans.
+    let drop = uniform - mixed;
+    assert!(drop > 16.0 * 0.5 && drop < 16.0, "{uniform} vs {mixed}");
+    // The following paragraph clears the descent of the taller span as well.
+    assert!(
+        mixed - mixed_next > uniform - uniform_next,
+        "{mixed}/{mixed_next} vs {uniform}/{uniform_next}"
+    );
+}
+
+#[test]
+fn page_of_total_footers_print_the_rendered_page_count() {
+    use pdf_helper::builder::FooterStyle;
+
+    let text = "Paragraph text that fills the page. ".repeat(400);
+    let builder = PdfBuilder::new()
+        .footer_style(FooterStyle::PageOfTotal)
+        .add_section(Section::new("Long").with_block(Block::paragraph(vec![Span::new(text)])));
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    let pages = lopdf::Document::load_mem(&result.bytes)
+        .expect("rendered PDF parses")
+        .get_pages()
+        .len() as u32;
+    assert!(pages > 1, "expected several pages, got {pages}");
+    for page in [1, pages] {
+        let footer = format!("Page {page} of {pages}");
+        let texts = page_text(&result.bytes, page);
+        assert!(
+            texts.iter().any(|printed| printed.text.contains(&footer)),
+            "{footer:?} in {texts:?}"
+        );
+    }
+}
+
+/// Locates a font with CJK glyphs: `PDF_HELPER_CJK_FONT` when set, otherwise one of the common
+/// system fonts on Linux and macOS.
+fn cjk_font_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("PDF_HELPER_CJK_FONT") {
+        return Some(PathBuf::from(path));
+    }
+    [
+        "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+        "/usr/share/fonts/truetype/arphic/uming.ttc",
+        "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+        "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+        "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .find(|path| path.is_file())
+}
+
+#[test]
+fn mixed_latin_and_cjk_text_prints_with_the_fallback_family() {
+    use genpdf::fonts::{FontData, FontFamily};
+
+    let Some(path) = cjk_font_path() else {
+        eprintln!("Skipping CJK fallback assertions: no CJK font found; set PDF_HELPER_CJK_FONT");
+        return;
+    };
+    let bytes = fs::read(&path).expect("read CJK font");
+    let face = || FontData::new(bytes.clone(), None).expect("parse CJK font");
+    let family = FontFamily {
+        regular: face(),
+        bold: face(),
+        italic: face(),
+        bold_italic: face(),
+    };
+    let builder = PdfBuilder::new().with_fallback_fonts([family]).add_section(
+        Section::new("Glossary")
+            .with_block(Block::paragraph(vec![Span::new("Tokyo 東京 station")])),
+    );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    let texts = first_page_text(&result.bytes);
+    // The CJK run switches to the fallback font inside the line, and both fonts map their glyphs
+    // back to the original characters.
+    assert!(
+        texts
+            .iter()
+            .any(|printed| printed.text == "Tokyo 東京 station"),
+        "{texts:?}"
+    );
+}
+
+#[test]
+fn underlines_run_below_the_baseline_across_their_text() {
+    use pdf_helper::elements::UnderlinedText;
+    use pdf_helper::richtext::StyledSpan;
+
+    let span = Span::new("Underlined").underline().with_font_size(Some(14));
+    let underline_offset = 1.5;
+    let element_span = span.clone();
+    let builder = PdfBuilder::new().add_section(Section::new("Decorations").with_block(
+        Block::custom(move || {
+            UnderlinedText::new(vec![StyledSpan::from(&element_span)])
+                .with_underline_offset(printpdf::Mm(underline_offset).into())
+        }),
+    ));
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    let width = {
+        let _lock = lock_font_env();
+        let family = fonts::default_font_family().expect("fonts used for the render");
+        let width: printpdf::Mm =
+            measure_span(&span, 12, &genpdf::fonts::FontCache::new(family)).into();
+        width.0 * 72.0 / 25.4
+    };
+
+    let texts = first_page_text(&result.bytes);
+    let text = texts
+        .iter()
+        .find(|printed| printed.text == "Underlined")
+        .unwrap_or_else(|| panic!("underlined text in {texts:?}"));
+    let strokes = first_page_strokes(&result.bytes);
+    let underline = strokes
+        .iter()
+        .find(|points| points.len() == 2 && (points[0].0 - text.x).abs() < 0.01)
+        .unwrap_or_else(|| panic!("underline starting at {} in {strokes:?}", text.x));
+    let ((start_x, start_y), (end_x, end_y)) = (underline[0], underline[1]);
+    assert!(
+        (end_x - start_x - width).abs() < 0.01,
+        "{underline:?} vs {width}"
+    );
+    assert!((start_y - end_y).abs() < 0.01, "{underline:?}");
+    // The stroke lies below the baseline, at least by the configured offset, within the line.
+    let drop = text.y - start_y;
+    assert!(
+        drop >= underline_offset * 72.0 / 25.4 - 0.01 && drop < 14.0,
+        "{drop}"
+    );
+}
+
+#[test]
+fn letter_and_word_spacing_widen_the_measured_lines() {
+    let text = "Spaced out";
+    let paragraph = |span: Span| Block::Paragraph(RichParagraph::new(vec![span.underline()]));
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Spacing")
+            .with_block(paragraph(Span::new(text)))
+            .with_block(paragraph(Span::new(text).with_letter_spacing(2.0)))
+            .with_block(Block::Paragraph(
+                RichParagraph::new(vec![Span::new(text).underline()]).with_word_spacing(6.0),
+            )),
+    );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let widths: Vec<f64> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::Paragraph)
+        .map(|element| element.rect.width)
+        .collect();
+    assert_eq!(widths.len(), 3, "{dump}");
+    let point = 25.4 / 72.0;
+    let characters = text.chars().count() as f64;
+    assert!((widths[1] - widths[0] - characters * 2.0 * point).abs() < 1e-6);
+    assert!((widths[2] - widths[0] - 6.0 * point).abs() < 1e-6);
+}
+
+#[test]
+fn quote_content_is_indented_on_every_page() {
+    let long_text = "Postmortem finding that keeps the quote running. ".repeat(200);
+    let quote = QuoteBlock::new(vec![Block::paragraph(vec![Span::new(long_text)])])
+        .with_attribution("Incident review".to_string())
+        .with_bar_color(Color::Rgb(200, 60, 40))
+        .with_background_color(Color::Rgb(253, 236, 234));
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Quotes")
+            .with_block(Block::paragraph(vec![Span::new("Before the quote.")]))
+            .with_block(Block::Quote(quote)),
+    );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let quotes: Vec<_> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::Quote)
+        .collect();
+    assert!(quotes.len() > 1, "quote should span pages: {dump}");
+    let outside = dump
+        .elements()
+        .find(|element| element.kind == ElementKind::Paragraph)
+        .expect("leading paragraph");
+    for quote in &quotes {
+        let inner = dump
+            .elements()
+            .find(|element| {
+                element.kind == ElementKind::Paragraph
+                    && element.page == quote.page
+                    && element.rect.y >= quote.rect.y
+            })
+            .expect("quoted paragraph on every quote page");
+        assert!(inner.rect.x > outside.rect.x, "{dump}");
+    }
+}
+
+#[test]
+fn dotted_toc_rows_span_the_body_width() {
+    let long_title =
+        "A section title long enough to wrap before it reaches the page number column \
+                      of the table of contents";
+    let builder = PdfBuilder::new()
+        .include_printed_toc(true)
+        .toc_leader(TocLeader::Dots)
+        .add_section(Section::new("Overview").with_subsection(Section::new("Scope")))
+        .add_section(Section::new(long_title));
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let toc: Vec<_> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::TableOfContents)
+        .collect();
+    assert_eq!(toc.len(), 2, "{dump}");
+    assert!(toc.iter().all(|element| element.page == 1));
+    let body = dump.pages[0].body;
+    assert!((toc[1].rect.width - body.width).abs() < 0.01, "{dump}");
+}
+
+#[test]
+fn undecodable_cover_logo_is_skipped_with_a_warning() {
+    use pdf_helper::model::Cover;
+
+    let cover = Cover::new("Report").with_logo(ImageSource::Bytes(b"not an image".to_vec()), 40.0);
+    let builder = PdfBuilder::new()
+        .with_cover(cover)
+        .add_section(Section::new("Body"));
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    assert!(result.bytes.starts_with(b"%PDF"));
+    let skipped = result.warnings.iter().any(|warning| {
+        matches!(warning, RenderWarning::ImageSkipped { location, .. } if location == "cover logo")
+    });
+    assert!(skipped, "{:?}", result.warnings);
+}
+
+#[test]
+fn undecodable_section_images_fail_with_their_location() {
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Broken")
+            .with_block(Block::paragraph(vec![Span::new("Intro")]))
+            .with_block(Block::image(ImageSource::from_bytes(
+                b"not an image".to_vec(),
+            ))),
+    );
+
+    let _lock = lock_font_env();
+    // Without the `rayon` feature images are decoded while the blocks are laid out rather than
+    // up front, and must still report the block they belong to.
+    match builder.render() {
+        Err(PdfBuildError::ImageDecode {
+            section,
+            block_index,
+            ..
+        }) => {
+            assert_eq!(section.as_deref(), Some("Broken"));
+            assert_eq!(block_index, 1);
+        }
+        Err(PdfBuildError::FontLoad(err)) => {
+            eprintln!("Skipping image decode assertions: {}", err);
+        }
+        other => panic!("expected an image decode error, got {other:?}"),
+    }
+}
+
+#[test]
+fn cover_page_skips_the_footer_when_disabled() {
+    use pdf_helper::model::Cover;
+
+    let builder = PdfBuilder::new()
+        .with_cover(Cover::new("Cover Title"))
+        .show_header(true)
+        .show_footer(true)
+        .footer_on_cover(false)
+        .add_section(
+            Section::new("Body").with_block(Block::paragraph(vec![Span::new("First section.")])),
+        );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    assert_eq!(dump.pages.len(), 2, "{dump}");
+    let (cover, body) = (dump.pages[0].body, dump.pages[1].body);
+    // Without header and footer the cover body starts at the top edge and reaches the bottom.
+    assert!(cover.y < body.y, "{dump}");
+    assert!(
+        cover.y + cover.height > body.y + body.height + 11.9,
+        "{dump}"
+    );
+}
+
+#[test]
+fn cover_renderer_replaces_the_default_cover() {
+    use genpdf::elements::{LinearLayout, Paragraph};
+    use genpdf::Element as _;
+    use pdf_helper::model::Cover;
+
+    let builder = PdfBuilder::new()
+        .with_cover(
+            Cover::new("Annual Review").with_block(Block::paragraph(vec![Span::new(
+                "Left out by the renderer.",
+            )])),
+        )
+        .with_cover_renderer(|cover| {
+            let mut layout = LinearLayout::vertical();
+            layout.push(Paragraph::new(cover.title()).padded(10));
+            layout.push(Paragraph::new("October 2026"));
+            layout
+        })
+        .add_section(Section::new("Body").with_block(Block::paragraph(vec![Span::new("Text.")])));
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    assert_eq!(dump.pages.len(), 2, "{dump}");
+    let cover_kinds: Vec<_> = dump.pages[0]
+        .elements
+        .iter()
+        .map(|element| element.kind)
+        .collect();
+    assert_eq!(cover_kinds, vec![ElementKind::CoverText], "{dump}");
+}
+
+#[test]
+fn cover_and_body_pages_use_their_own_footers() {
+    use genpdf::elements::Paragraph;
+    use pdf_helper::model::Cover;
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+
+    let cover_pages = Arc::new(Mutex::new(BTreeSet::new()));
+    let body_pages = Arc::new(Mutex::new(BTreeSet::new()));
+    let (cover_seen, body_seen) = (Arc::clone(&cover_pages), Arc::clone(&body_pages));
+    let builder = PdfBuilder::new()
+        .with_cover(Cover::new("Cover Title"))
+        .with_cover_footer(30.0, move |page| {
+            cover_seen.lock().unwrap().insert(page);
+            Paragraph::new("UNCLASSIFIED")
+        })
+        .with_body_footer(10.0, move |page| {
+            body_seen.lock().unwrap().insert(page);
+            Paragraph::new(format!("Page {page}"))
+        })
+        .add_section(
+            Section::new("Body").with_block(Block::paragraph(vec![Span::new("First section.")])),
+        );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    assert_eq!(dump.pages.len(), 2, "{dump}");
+    assert_eq!(*cover_pages.lock().unwrap(), BTreeSet::from([1]));
+    assert_eq!(*body_pages.lock().unwrap(), BTreeSet::from([2]));
+    let (cover, body) = (dump.pages[0].body, dump.pages[1].body);
+    // The taller cover footer leaves 20 mm less room for the cover body.
+    assert!(
+        cover.y + cover.height + 19.9 < body.y + body.height,
+        "{dump}"
+    );
+}
+
+#[test]
+fn asymmetric_margins_stack_with_the_footer_reservation() {
+    let builder = PdfBuilder::new()
+        .with_margins_mm(10.0, 15.0, 40.0, 25.0)
+        .show_footer(true)
+        .add_section(
+            Section::new("Margins").with_block(Block::paragraph(vec![Span::new(
+                "Body text inside the margins.",
+            )])),
+        );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    // A4 is 210 x 297 mm; the 12 mm footer sits directly above the 40 mm bottom margin.
+    let body = dump.pages[0].body;
+    assert!((body.x - 25.0).abs() < 0.01, "{dump}");
+    assert!((body.y - 10.0).abs() < 0.01, "{dump}");
+    assert!((body.width - 170.0).abs() < 0.01, "{dump}");
+    assert!((body.height - 235.0).abs() < 0.01, "{dump}");
+}
+
+#[test]
+fn long_code_blocks_continue_on_the_next_page() {
+    let mut code = String::from("fn main() {\n");
+    for line in 0..150 {
+        code.push_str(&format!("\tprintln!(\"line {line:>3}\");\n"));
+    }
+    code.push_str(&format!("    // {}\n}}\n", "x".repeat(400)));
+    let builder = PdfBuilder::new().add_section(Section::new("Code").with_block(Block::Code(
+        CodeBlock::new(code).with_language("rust".to_string()),
+    )));
+
+    let Some((result, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let code_parts: Vec<_> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::Code)
+        .collect();
+    assert!(code_parts.len() > 1, "code should span pages: {dump}");
+    for part in &code_parts {
+        let body = dump.pages[part.page - 1].body;
+        assert!((part.rect.width - body.width).abs() < 0.01, "{dump}");
+        assert!(part.rect.y + part.rect.height <= body.y + body.height + 0.01);
+    }
+    // Without any monospace family installed the code falls back to the document font.
+    let monospace_missing = result.warnings.iter().any(|warning| {
+        matches!(warning, RenderWarning::FontFallback { requested, .. } if requested == "monospace")
+    });
+    let _lock = lock_font_env();
+    assert_eq!(monospace_missing, fonts::monospace_font_family().is_err());
+}
+
+#[test]
+fn custom_elements_are_rebuilt_for_every_pass() {
+    use pdf_helper::elements::Spacer;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let built = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&built);
+    let builder =
+        PdfBuilder::new()
+            .include_printed_toc(true)
+            .add_section(Section::new("Chart").with_block(Block::custom(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Spacer::new(25)
+            })));
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let custom: Vec<_> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::Custom)
+        .collect();
+    assert_eq!(custom.len(), 1, "{dump}");
+    assert!((custom[0].rect.height - 25.0).abs() < 0.01, "{dump}");
+    // The discovery pass for the table of contents and the final pass each build an element.
+    assert!(built.load(Ordering::SeqCst) >= 2);
+}
+
+#[test]
+fn keep_together_groups_move_to_the_next_page_whole() {
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Grouped")
+            .with_block(Block::Spacer(230.0))
+            .with_block(Block::keep_together(vec![
+                Block::paragraph(vec![Span::new(
+                    "The figures below belong to this paragraph.",
+                )]),
+                Block::Spacer(40.0),
+            ])),
+    );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let pages_of = |kind: ElementKind| -> Vec<usize> {
+        dump.elements()
+            .filter(|element| element.kind == kind && element.rect.height > 0.0)
+            .map(|element| element.page)
+            .collect()
+    };
+    assert_eq!(pages_of(ElementKind::KeepTogether), vec![2], "{dump}");
+    assert_eq!(pages_of(ElementKind::Paragraph), vec![2], "{dump}");
+}
+
+#[test]
+fn orphan_control_moves_a_paragraph_start_to_the_next_page() {
+    let layout = |spacer: f64, min_lines: usize, text: &str| {
+        let builder = PdfBuilder::new()
+            .min_lines_before_break(min_lines)
+            .add_section(
+                Section::new("Orphans")
+                    .with_block(Block::Spacer(spacer))
+                    .with_block(Block::paragraph(vec![Span::new(text)])),
+            );
+        skip_without_fonts(builder.render_with_layout_debug()).map(|(_, dump)| dump)
+    };
+    let first_page = |dump: &LayoutDump| {
+        dump.elements()
+            .find(|element| element.kind == ElementKind::Paragraph && element.rect.height > 0.0)
+            .map(|element| element.page)
+    };
+
+    let _lock = lock_font_env();
+    let Some(probe) = layout(0.0, 1, "One line.") else {
+        return;
+    };
+    let line = probe
+        .elements()
+        .find(|element| element.kind == ElementKind::Paragraph)
+        .expect("paragraph placed")
+        .rect;
+    let body = probe.pages[0].body;
+    // Leave room for one and a half lines below the spacer.
+    let spacer = body.y + body.height - line.y - line.height * 1.5;
+    let text = "Long paragraph text that wraps over many lines. ".repeat(20);
+
+    let greedy = layout(spacer, 1, &text).expect("fonts loaded before");
+    assert_eq!(first_page(&greedy), Some(1), "{greedy}");
+    let controlled = layout(spacer, 2, &text).expect("fonts loaded before");
+    assert_eq!(first_page(&controlled), Some(2), "{controlled}");
+}
+
+#[test]
+fn row_columns_share_the_width_and_the_tallest_sets_the_height() {
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Row")
+            .with_block(Block::row(vec![
+                ColumnBlock::new(vec![Block::Spacer(30.0)]).with_weight(2),
+                ColumnBlock::new(vec![Block::Spacer(10.0)]),
+            ]))
+            .with_block(Block::rule()),
+    );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let find = |kind: ElementKind| {
+        dump.elements()
+            .filter(move |element| element.kind == kind)
+            .collect::<Vec<_>>()
+    };
+    let row = find(ElementKind::Row)[0];
+    let spacers = find(ElementKind::Spacer);
+    let rule = find(ElementKind::Rule)[0];
+    let body = dump.pages[0].body;
+
+    assert!((row.rect.height - 30.0).abs() < 0.01, "{dump}");
+    assert_eq!(spacers.len(), 2, "{dump}");
+    assert!(
+        (spacers[0].rect.y - spacers[1].rect.y).abs() < 0.01,
+        "{dump}"
+    );
+    // Two thirds of the width, less half of the 4 mm gap between the columns.
+    assert!((spacers[0].rect.x - body.x).abs() < 0.01, "{dump}");
+    assert!(
+        (spacers[0].rect.width - (body.width * 2.0 / 3.0 - 2.0)).abs() < 0.01,
+        "{dump}"
+    );
+    assert!(
+        (spacers[1].rect.x - (body.x + body.width * 2.0 / 3.0 + 2.0)).abs() < 0.01,
+        "{dump}"
+    );
+    assert!(rule.rect.y >= row.rect.y + row.rect.height - 0.01, "{dump}");
+}
+
+#[test]
+fn horizontal_rule_reserves_its_margins_between_paragraphs() {
+    let rule = RuleStyle::new()
+        .with_thickness_mm(0.5)
+        .with_margin_mm(4.0)
+        .with_color(Color::Rgb(120, 120, 120));
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Rules")
+            .with_block(Block::paragraph(vec![Span::new("Above the rule.")]))
+            .with_block(Block::HorizontalRule(rule))
+            .with_block(Block::paragraph(vec![Span::new("Below the rule.")])),
+    );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let rule = dump
+        .elements()
+        .find(|element| element.kind == ElementKind::Rule)
+        .expect("rule element");
+    assert!((rule.rect.height - 8.5).abs() < 0.01, "{dump}");
+    let paragraphs: Vec<_> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::Paragraph)
+        .collect();
+    assert_eq!(paragraphs.len(), 2, "{dump}");
+    assert!(paragraphs[0].rect.y < rule.rect.y);
+    assert!(rule.rect.y + rule.rect.height <= paragraphs[1].rect.y + 0.01);
+}
+
+#[test]
+fn horizontal_rules_taller_than_a_page_shrink_their_margins() {
+    let rule = RuleStyle::new().with_margin_mm(400.0);
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Rules")
+            .with_block(Block::paragraph(vec![Span::new("Above the rule.")]))
+            .with_block(Block::HorizontalRule(rule)),
+    );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let rule = dump
+        .elements()
+        .find(|element| element.kind == ElementKind::Rule && element.rect.height > 0.0)
+        .expect("rule element");
+    let body = dump.pages[rule.page - 1].body;
+    assert_eq!(rule.page, 2, "{dump}");
+    assert!(rule.rect.height <= body.height + 0.01, "{dump}");
+}
+
+#[test]
+fn percentage_image_widths_follow_the_content_width() {
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::new_rgb8(200, 100)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .expect("encode png");
+    let image = |percent: f64| {
+        Block::Image(
+            ImageBlock::new(ImageSource::from_bytes(png.get_ref().clone()))
+                .with_width_percent(percent),
+        )
+    };
+    let builder = PdfBuilder::new()
+        .with_margins_mm(20.0, 30.0, 20.0, 30.0)
+        .add_section(
+            Section::new("Figures")
+                .with_block(image(50.0))
+                .with_block(image(25.0)),
+        );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let body_width = dump.pages[0].body.width;
+    let images: Vec<_> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::Image)
+        .collect();
+    assert_eq!(images.len(), 2, "{dump}");
+    // Both images share the caption spacing, so only the image heights differ: a quarter of
+    // the body width at the 2:1 aspect ratio.
+    let expected = body_width * 0.25 / 2.0;
+    assert!(
+        (images[0].rect.height - images[1].rect.height - expected).abs() < 0.1,
+        "{dump}"
+    );
+}
+
+#[test]
+fn list_of_figures_follows_the_table_of_contents() {
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::new_rgb8(200, 100)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .expect("encode png");
+    let figure = |caption: &str| {
+        Block::Image(
+            ImageBlock::new(ImageSource::from_bytes(png.get_ref().clone()))
+                .with_caption(RichParagraph::new(vec![Span::new(caption)])),
+        )
+    };
+    let builder = PdfBuilder::new()
+        .include_printed_toc(true)
+        .include_list_of_figures(true)
+        .add_section(Section::new("Charts").with_block(figure("Revenue by region")))
+        .add_section(Section::new("Maps").with_block(figure("Store locations")));
+
+    let Some((result, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    // The table of contents and the list of figures each take a page before the sections.
+    assert_eq!(result.section_start_pages[0], Some(3), "{dump}");
+    let listed = |page: usize| {
+        dump.elements()
+            .filter(|element| element.page == page && element.kind == ElementKind::TableOfContents)
+            .count()
+    };
+    assert!(listed(1) > 0, "{dump}");
+    assert!(listed(2) > 0, "{dump}");
+}
+
+#[test]
+fn image_borders_surround_the_scaled_image() {
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::new_rgb8(200, 100)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .expect("encode png");
+    let image = |border: Option<BorderStyle>| {
+        Block::Image(
+            ImageBlock::new(ImageSource::from_bytes(png.get_ref().clone()))
+                .with_width_mm(40.0)
+                .with_border(border),
+        )
+    };
+    let border = BorderStyle::new(0.5, Color::Rgb(200, 0, 0)).with_padding_mm(1.5);
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Figures")
+            .with_block(image(None))
+            .with_block(image(Some(border))),
+    );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let images: Vec<_> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::Image)
+        .collect();
+    assert_eq!(images.len(), 2, "{dump}");
+    // The 40 mm wide image keeps its size and the frame adds its thickness and padding on top
+    // and bottom.
+    assert!(
+        (images[1].rect.height - images[0].rect.height - 4.0).abs() < 0.1,
+        "{dump}"
+    );
+}
+
+#[test]
+fn max_height_keeps_tall_captioned_images_within_the_limit() {
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::new_rgb8(100, 400)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .expect("encode png");
+    let figure = ImageBlock::new(ImageSource::from_bytes(png.into_inner()))
+        .with_width_percent(100.0)
+        .with_max_height_mm(120.0)
+        .with_caption(RichParagraph::new(vec![Span::new(
+            "A tall portrait figure whose caption counts towards the height limit.",
+        )]));
+    let builder =
+        PdfBuilder::new().add_section(Section::new("Tall").with_block(Block::Image(figure)));
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let images: Vec<_> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::Image)
+        .collect();
+    assert_eq!(images.len(), 1, "{dump}");
+    assert!(images[0].rect.height <= 120.5, "{dump}");
+    assert!(images[0].rect.height > 100.0, "{dump}");
+}
+
+#[test]
+fn captions_above_take_the_same_space_as_captions_below() {
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::new_rgb8(200, 100)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .expect("encode png");
+    let figure = |position: CaptionPosition| {
+        Block::Image(
+            ImageBlock::new(ImageSource::from_bytes(png.get_ref().clone()))
+                .with_width_mm(60.0)
+                .with_alignment(HorizontalAlignment::Center)
+                .with_caption(RichParagraph::new(vec![Span::new("Quarterly revenue")]))
+                .with_caption_position(position),
+        )
+    };
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Figures")
+            .with_block(figure(CaptionPosition::Below))
+            .with_block(figure(CaptionPosition::Above)),
+    );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let images: Vec<_> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::Image)
+        .collect();
+    assert_eq!(images.len(), 2, "{dump}");
+    // The caption and the spacing are counted once whichever side of the image they are on.
+    assert!(
+        (images[0].rect.height - images[1].rect.height).abs() < 0.01,
+        "{dump}"
+    );
+    assert!(images[0].rect.height > 30.0 + 3.0, "{dump}");
+}
+
+#[test]
+fn measured_spans_follow_their_size_style_and_spacing() {
+    let _lock = lock_font_env();
+    let family = match fonts::default_font_family() {
+        Ok(family) => family,
+        Err(err) => {
+            eprintln!("Skipping measurement assertions: {}", err);
+            return;
+        }
+    };
+    let font_cache = genpdf::fonts::FontCache::new(family);
+    let width = |span: &Span, size: u8| {
+        let mm: printpdf::Mm = measure_span(span, size, &font_cache).into();
+        mm.0
+    };
+
+    let plain = Span::new("Width");
+    assert!(width(&plain, 10) > 0.0);
+    assert!((width(&plain, 20) - 2.0 * width(&plain, 10)).abs() < 1e-6);
+    // A font size on the span wins over the size passed in.
+    let sized = Span::new("Width").with_font_size(Some(20));
+    assert!((width(&sized, 10) - width(&plain, 20)).abs() < 1e-6);
+    // Letter spacing follows each of the five characters.
+    let tracked = Span::new("Width").with_letter_spacing(2.0);
+    assert!((width(&tracked, 10) - width(&plain, 10) - 5.0 * 2.0 * 25.4 / 72.0).abs() < 1e-6);
+    assert!(width(&Span::new("Width").superscript(), 10) < width(&plain, 10));
+
+    let paragraph = RichParagraph::new(vec![plain.clone(), Span::new(" and more").bold()]);
+    let total: printpdf::Mm = measure_paragraph(&paragraph, 10, &font_cache).into();
+    assert!((total.0 - width(&plain, 10) - width(&Span::new(" and more").bold(), 10)).abs() < 1e-6);
+    // Word spacing follows each of the two spaces.
+    let spaced: printpdf::Mm =
+        measure_paragraph(&paragraph.with_word_spacing(3.0), 10, &font_cache).into();
+    assert!((spaced.0 - total.0 - 2.0 * 3.0 * 25.4 / 72.0).abs() < 1e-6);
+}
+
+#[test]
+fn line_spacing_scales_the_height_of_a_paragraph() {
+    let text = "Dense contractual wording that wraps over several lines. ".repeat(12);
+    let paragraph = |spacing: f64| {
+        Block::Paragraph(
+            RichParagraph::new(vec![Span::new(text.clone())]).with_line_spacing(spacing),
+        )
+    };
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Terms")
+            .with_block(paragraph(1.5))
+            .with_block(paragraph(3.0)),
+    );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let paragraphs: Vec<_> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::Paragraph)
+        .collect();
+    assert_eq!(paragraphs.len(), 2, "{dump}");
+    // Both paragraphs wrap into the same lines, so doubling the spacing doubles the height.
+    assert!(
+        (paragraphs[1].rect.height - 2.0 * paragraphs[0].rect.height).abs() < 0.1,
+        "{dump}"
+    );
+    let headings: Vec<_> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::Heading)
+        .collect();
+    assert_eq!(headings.len(), 1, "{dump}");
+    assert!(
+        headings[0].rect.height < paragraphs[0].rect.height,
+        "{dump}"
+    );
+}
+
+#[test]
+fn footnotes_reserve_space_on_the_page_of_their_marker() {
+    // The paragraph spans two pages, so its first marker lands on page 1 and its last on page 2.
+    let mut spans = vec![Span::new("Opening claim"), Span::footnote("First source.")];
+    spans.push(Span::new(" Supporting detail.".repeat(300)));
+    spans.push(Span::footnote("Second source, cited after the page break."));
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Sources")
+            .with_block(Block::paragraph(spans))
+            .with_block(Block::page_break())
+            .with_block(Block::paragraph(vec![Span::new("No notes here.")])),
+    );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    assert_eq!(dump.pages.len(), 3, "{dump}");
+    let plain_height = dump.pages[2].body.height;
+    assert!(dump.pages[0].body.height < plain_height, "{dump}");
+    assert!(dump.pages[1].body.height < plain_height, "{dump}");
+    for page in &dump.pages[..2] {
+        let bottom = page.body.y + page.body.height;
+        assert!(page
+            .elements
+            .iter()
+            .all(|element| element.rect.y + element.rect.height <= bottom + 0.01));
+    }
+}
+
+#[test]
+fn footnotes_in_headings_reserve_space_for_their_note() {
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Sources")
+            .with_block(Block::heading(
+                1,
+                vec![
+                    Span::new("Background"),
+                    Span::footnote("Adapted from the 2023 survey."),
+                ],
+            ))
+            .with_block(Block::paragraph(vec![Span::new("Survey details.")]))
+            .with_block(Block::page_break())
+            .with_block(Block::paragraph(vec![Span::new("No notes here.")])),
+    );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    assert_eq!(dump.pages.len(), 2, "{dump}");
+    assert!(
+        dump.pages[0].body.height < dump.pages[1].body.height,
+        "{dump}"
+    );
+}
+
+#[test]
+fn superscripts_do_not_change_the_line_height() {
+    // Underlining routes the plain paragraph through the same text element as the scripts.
+    let plain = Span::new("Area in m2 of H2O samples").underline();
+    let spans = richtext::parse_markup("Area in m^{2} of H_{2}O samples").expect("valid markup");
+    let builder = PdfBuilder::new().add_section(
+        Section::new("Scripts")
+            .with_block(Block::paragraph(vec![plain]))
+            .with_block(Block::paragraph(spans)),
+    );
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let paragraphs: Vec<_> = dump
+        .elements()
+        .filter(|element| element.kind == ElementKind::Paragraph)
+        .collect();
+    assert_eq!(paragraphs.len(), 2, "{dump}");
+    assert!(
+        (paragraphs[0].rect.height - paragraphs[1].rect.height).abs() < 0.01,
+        "{dump}"
+    );
+    assert!(
+        paragraphs[1].rect.width < paragraphs[0].rect.width,
+        "{dump}"
+    );
+}
+
+#[test]
+fn paragraph_background_spans_the_body_width_on_every_line() {
+    let text = "Shaded paragraphs keep their background behind every wrapped line. ".repeat(8);
+    let paragraph =
+        RichParagraph::new(vec![Span::new(text)]).with_background(Color::Rgb(255, 250, 205));
+    let builder = PdfBuilder::new()
+        .with_page_background(Color::Greyscale(250))
+        .add_section(Section::new("Shading").with_block(Block::Paragraph(paragraph)));
+
+    let Some((_, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    let body = dump.pages[0].body;
+    let paragraph = dump
+        .elements()
+        .find(|element| element.kind == ElementKind::Paragraph)
+        .expect("paragraph element");
+    assert!((paragraph.rect.width - body.width).abs() < 0.01, "{dump}");
+    assert!((paragraph.rect.x - body.x).abs() < 0.01, "{dump}");
+    assert!(paragraph.rect.height > 15.0, "{dump}");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_document_round_trips_and_renders() {
+    use pdf_helper::model::Cover;
+
+    const COVER: &str = r##"{
+        "title": "Quarterly Report",
+        "subtitle": "Operations",
+        "blocks": [
+            { "Paragraph": { "spans": [{ "text": "Prepared by ", "italic": true },
+                                       { "text": "Finance", "color": "#245CA0" }],
+                             "alignment": "Center" } }
+        ]
+    }"##;
+    const SECTIONS: &str = r##"[
+        { "title": "Summary",
+          "blocks": [
+              { "Paragraph": { "spans": [{ "text": "Revenue grew.", "bold": true }] } },
+              { "List": { "style": "Numbered",
+                          "items": [{ "content": { "spans": [{ "text": "First" }] } }] } },
+              { "Legend": { "entries": [{ "color": "#C0392B", "label": [{ "text": "Actual" }] }] } },
+              "PageBreak",
+              { "Table": { "headers": [{ "spans": [{ "text": "Item" }] }],
+                           "rows": [[{ "spans": [{ "text": "Travel" }] }]] } }
+          ],
+          "subsections": [{ "title": "Details" }] }
+    ]"##;
+
+    let cover: Cover = serde_json::from_str(COVER).expect("parse cover");
+    let sections: Vec<Section> = serde_json::from_str(SECTIONS).expect("parse sections");
+
+    let cover_json = serde_json::to_string(&cover).expect("serialize cover");
+    assert!(cover_json.contains("\"#245CA0\""), "{cover_json}");
+    assert_eq!(
+        serde_json::from_str::<Cover>(&cover_json).expect("reparse cover"),
+        cover
+    );
+    let sections_json = serde_json::to_string(&sections).expect("serialize sections");
+    assert_eq!(
+        serde_json::from_str::<Vec<Section>>(&sections_json).expect("reparse sections"),
+        sections
+    );
+
+    let builder = PdfBuilder::new().with_cover(cover).with_sections(sections);
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    assert!(result.bytes.starts_with(b"%PDF"));
+}
+
+#[test]
+fn render_to_writer_matches_buffered_render() {
+    let builder = || {
+        PdfBuilder::new().include_printed_toc(true).add_section(
+            Section::new("Streaming").with_block(Block::paragraph(vec![Span::new("Body")])),
+        )
+    };
+    let Some(buffered) = render_with_available_fonts(builder()) else {
+        return;
+    };
+
+    let _lock = lock_font_env();
+    let mut streamed = Vec::new();
+    let metadata = builder()
+        .render_to(&mut streamed)
+        .expect("render to writer");
+
+    assert_eq!(normalized_hash(&streamed), normalized_hash(&buffered.bytes));
+    assert_eq!(metadata.section_start_pages, buffered.section_start_pages);
+    assert_eq!(metadata.headings, buffered.headings);
+}
+
+#[test]
+fn streamed_sections_match_the_buffered_headings() {
+    use pdf_helper::model::Cover;
+
+    let sections = || {
+        (1..=3).map(|batch| {
+            Section::new(format!("Batch {batch}"))
+                .with_block(Block::paragraph(vec![Span::new("Rows of the batch.")]))
+                .with_subsection(Section::new("Totals"))
+        })
+    };
+    let builder = || {
+        PdfBuilder::new()
+            .with_cover(Cover::new("Data dump"))
+            .sections_on_new_pages(true)
+            .number_sections(true)
+    };
+    let Some(buffered) = render_with_available_fonts(builder().with_sections(sections())) else {
+        return;
+    };
+
+    let _lock = lock_font_env();
+    let mut streamed = Vec::new();
+    let metadata = builder()
+        .render_streaming(sections(), &mut streamed)
+        .expect("render streamed sections");
+
+    assert!(streamed.starts_with(b"%PDF"));
+    assert_eq!(
+        metadata.section_start_pages,
+        vec![Some(2), Some(3), Some(4)]
+    );
+    // The buffered render only records pages when something needs them.
+    let outline = |headings: &[pdf_helper::builder::SectionHeading]| -> Vec<_> {
+        headings
+            .iter()
+            .map(|heading| (heading.level, heading.title.clone(), heading.number.clone()))
+            .collect()
+    };
+    assert_eq!(outline(&metadata.headings), outline(&buffered.headings));
+    assert_eq!(metadata.headings[1].number.as_deref(), Some("1.1"));
+}
+
+#[cfg(feature = "fixed-timestamp")]
+#[test]
+fn streamed_sections_render_the_same_bytes_as_render() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    // Without footers, links, or a table of contents, nothing depends on the second pass.
+    let sections = || {
+        (1..=3).map(|batch| {
+            Section::new(format!("Batch {batch}"))
+                .with_block(Block::paragraph(vec![Span::new("Rows of the batch.")]))
+        })
+    };
+    let builder = || {
+        PdfBuilder::new()
+            .show_footer(false)
+            .with_fixed_timestamp(UNIX_EPOCH + Duration::from_secs(1_704_067_200))
+    };
+    let Some(buffered) = render_with_available_fonts(builder().with_sections(sections())) else {
+        return;
+    };
+
+    let _lock = lock_font_env();
+    let mut streamed = Vec::new();
+    builder()
+        .render_streaming(sections(), &mut streamed)
+        .expect("render streamed sections");
+
+    assert_eq!(streamed, buffered.bytes);
+}
+
+#[test]
+fn streamed_sections_are_pulled_from_thread_local_iterators() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let pulled = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&pulled);
+    let sections = (1..=3).map(move |batch| {
+        counter.set(counter.get() + 1);
+        Section::new(format!("Batch {batch}"))
+    });
+
+    let mut streamed = Vec::new();
+    let Some(metadata) =
+        with_available_fonts(|| PdfBuilder::new().render_streaming(sections, &mut streamed))
+    else {
+        return;
+    };
+
+    assert_eq!(pulled.get(), 3);
+    assert_eq!(metadata.headings.len(), 3);
+}
+
+#[test]
+fn fast_render_mode_matches_exact_page_numbers() {
+    let exact = build_sample_report_builder().expect("sample report");
+    let Some(exact) = render_with_available_fonts(exact) else {
+        return;
+    };
+    let fast = build_sample_report_builder()
+        .expect("sample report")
+        .with_render_mode(RenderMode::Fast);
+    let fast = render_with_available_fonts(fast).expect("fonts available for the exact render");
+
+    assert_eq!(fast.headings, exact.headings);
+    assert_eq!(normalized_hash(&fast.bytes), normalized_hash(&exact.bytes));
+}
+
+#[test]
+fn sections_on_new_pages_record_the_page_after_the_break() {
+    let section = |title: &str| {
+        Section::new(title).with_block(Block::paragraph(vec![Span::new("Short body.")]))
+    };
+    let builder = PdfBuilder::new()
+        .sections_on_new_pages(true)
+        .collect_section_pages(true)
+        .add_section(section("Introduction"))
+        .add_section(section("Findings"))
+        .add_section(
+            Section::builder("Appendix")
+                .start_on_new_page(true)
+                .push_block(Block::paragraph(vec![Span::new("Tables.")]))
+                .build(),
+        );
+
+    let Some((result, dump)) = render_layout_with_available_fonts(builder) else {
+        return;
+    };
+
+    assert_eq!(
+        result.section_start_pages,
+        vec![Some(1), Some(2), Some(3)],
+        "{dump}"
+    );
+    // The explicit break of the appendix does not add a blank page.
+    assert_eq!(dump.pages.len(), 3, "{dump}");
+}
+
+#[cfg(feature = "mixed-orientation")]
+#[test]
+fn landscape_sections_get_their_own_pages() {
+    let builder = PdfBuilder::new()
+        .show_footer(true)
+        .add_section(Section::new("Body").with_block(Block::paragraph(vec![Span::new("Text")])))
+        .add_section(
+            Section::new("Appendix")
+                .with_orientation(Orientation::Landscape)
+                .with_block(Block::paragraph(vec![Span::new("Wide tables")])),
+        );
+
+    let Some(result) = render_with_available_fonts(builder) else {
+        return;
+    };
+    assert_eq!(result.section_start_pages, vec![Some(1), Some(2)]);
+
+    let document = lopdf::Document::load_mem(&result.bytes).expect("merged PDF parses");
+    let pages = document.get_pages();
+    assert_eq!(pages.len(), 2);
+    let widths: Vec<f64> = pages
+        .values()
+        .map(|&page_id| {
+            let media_box = document
+                .get_dictionary(page_id)
+                .and_then(|page| page.get(b"MediaBox"))
+                .and_then(|media_box| media_box.as_array())
+                .expect("page has a media box");
+            match media_box[2] {
+                lopdf::Object::Integer(width) => width as f64,
+                lopdf::Object::Real(width) => f64::from(width),
+                _ => panic!("media box width is not a number"),
+            }
+        })
+        .collect();
+    assert!(widths[1] > widths[0]);
+}
+
+#[test]
+fn font_directory_source_replaces_bundled_discovery() {
+    let bundled = bundled_fonts_source_dir();
+    let builder = || {
+        PdfBuilder::new().add_section(
+            Section::new("Brand").with_block(Block::paragraph(vec![Span::new("Body")])),
+        )
+    };
+
+    // Hold the lock for every render: a concurrent `FontSearchGuard` moves the bundled fonts away.
+    let _lock = lock_font_env();
+    let Some(default) = skip_without_fonts(builder().render()) else {
+        return;
+    };
+    if !default.warnings.is_empty() {
+        eprintln!("Skipping font source assertions: bundled fonts are not available");
+        return;
+    }
+    let custom = builder()
+        .with_font_source(FontSource::directory(&bundled, "Roboto"))
+        .render()
+        .expect("render with explicit font directory");
+    assert_eq!(
+        normalized_hash(&custom.bytes),
+        normalized_hash(&default.bytes)
+    );
+
+    let missing = builder()
+        .with_font_source(FontSource::directory(bundled.join("missing"), "Brand"))
+        .render();
+    assert!(matches!(missing, Err(PdfBuildError::FontLoad(_))));
+}